@@ -0,0 +1,88 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use minicaldav::ical::{Ical, LineIterator, Property};
+
+/// A calendar with the given number of events, similar to what a large sync returns.
+fn calendar(events: usize) -> String {
+    let mut ics =
+        String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//minicaldav//bench//EN\r\n");
+    for i in 0..events {
+        ics.push_str(&format!(
+            "BEGIN:VEVENT\r\n\
+             UID:{i}@example.com\r\n\
+             DTSTAMP:20240101T120000Z\r\n\
+             DTSTART;TZID=Europe/Berlin:20240102T100000\r\n\
+             DTEND;TZID=Europe/Berlin:20240102T110000\r\n\
+             SUMMARY:Weekly meeting number {i}\r\n\
+             DESCRIPTION:A long description of the meeting that is folded over more than\r\n \
+             one line because it exceeds the maximum line length of seventy-five octets\r\n\
+             RRULE:FREQ=WEEKLY;BYDAY=TU;COUNT=10\r\n\
+             ORGANIZER;CN=\"Doe, Jane\":mailto:jane@example.com\r\n\
+             ATTENDEE;ROLE=REQ-PARTICIPANT;PARTSTAT=ACCEPTED:mailto:john@example.com\r\n\
+             END:VEVENT\r\n"
+        ));
+    }
+    ics.push_str("END:VCALENDAR\r\n");
+    ics
+}
+
+fn parse_calendar(c: &mut Criterion) {
+    let ics = calendar(1000);
+    c.bench_function("Ical::parse 1000 events", |b| {
+        b.iter(|| Ical::parse(&mut LineIterator::new(black_box(&ics))).unwrap())
+    });
+}
+
+/// `Property::parse` before the memchr fast path, scanning every line char by char.
+/// Kept as the baseline the current parser is compared with.
+fn parse_property_baseline(input: &str) -> Property {
+    let mut name = String::new();
+    let mut value = String::new();
+    let mut read_value = false;
+    let mut ignore_colon = false;
+    for c in input.chars() {
+        if c == '"' {
+            ignore_colon = !ignore_colon;
+        } else if c == ':' && !ignore_colon && !read_value {
+            read_value = true;
+            continue;
+        }
+        if read_value {
+            value.push(c);
+        } else {
+            name.push(c);
+        }
+    }
+    let mut parts = name.split(';');
+    let mut property = Property::new(parts.next().unwrap_or_default().trim(), &value);
+    for part in parts {
+        if let Some((k, v)) = part.split_once('=') {
+            property.attributes.insert(k.into(), v.into());
+        }
+    }
+    property
+}
+
+fn parse_property(c: &mut Criterion) {
+    for (name, line) in [
+        (
+            "unquoted",
+            "ATTENDEE;ROLE=REQ-PARTICIPANT;PARTSTAT=ACCEPTED:mailto:john@example.com",
+        ),
+        (
+            "quoted",
+            "ORGANIZER;CN=\"Doe, Jane\":mailto:jane@example.com",
+        ),
+    ] {
+        let mut group = c.benchmark_group(format!("Property::parse {}", name));
+        group.bench_function("baseline", |b| {
+            b.iter(|| parse_property_baseline(black_box(line)))
+        });
+        group.bench_function("current", |b| {
+            b.iter(|| Property::parse(black_box(line)).unwrap())
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, parse_calendar, parse_property);
+criterion_main!(benches);
@@ -0,0 +1,202 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Reminder daemon: watches all calendars of a user and shows a desktop notification
+//! (with `notify-send`) when an alarm of an event fires.
+//!
+//! ```sh
+//! URL=https://mycaldav.com/ EMAIL=foo PASSWORD=s3cret! TZ=Europe/Berlin \
+//!     cargo run --example minicaldav-notify --features blocking,tz
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use minicaldav::ical::Property;
+use minicaldav::recurrence::{parse_date_time, parse_duration};
+use minicaldav::watch::{WatchEventKind, Watcher};
+use minicaldav::{Alarm, AlarmTrigger, Client, Credentials, Event};
+use url::Url;
+
+/// How often the calendars are checked for changes and alarms.
+const POLL_INTERVAL: StdDuration = StdDuration::from_secs(60);
+
+/// How far ahead of an alarm the instances of an event are expanded.
+/// Alarms triggered earlier than this before the start of an instance are missed.
+const LOOKAHEAD_DAYS: i64 = 8;
+
+/// For how many days the alarms are computed in advance. The alarms of an event are computed
+/// when it changes and the alarms of all events once a day, not on every poll.
+const SCHEDULE_DAYS: i64 = 2;
+
+fn main() {
+    let url = Url::parse(&env("URL")).expect("URL is not a valid url");
+    let credentials = Credentials::Basic(env("EMAIL"), env("PASSWORD"));
+    let floating: Tz = std::env::var("TZ")
+        .ok()
+        .and_then(|tz| tz.parse().ok())
+        .unwrap_or(Tz::UTC);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("Could not create runtime");
+    let client = Client::new();
+    let calendars = runtime
+        .block_on(minicaldav::get_calendars(&client, &credentials, url))
+        .expect("Could not get calendars");
+
+    let mut watcher = Watcher::new();
+    for calendar in calendars {
+        println!("Watching {}", calendar.name());
+        watcher.add_calendar(calendar, None);
+    }
+
+    let mut events: HashMap<Url, Event> = HashMap::new();
+    let mut alarms: HashMap<Url, Vec<(DateTime<Utc>, String)>> = HashMap::new();
+    let mut fired: HashSet<(Url, DateTime<Utc>)> = HashSet::new();
+    let mut last_check = Utc::now();
+    let mut scheduled_until = last_check + Duration::days(SCHEDULE_DAYS);
+    loop {
+        for change in runtime.block_on(watcher.poll(&client, &credentials)) {
+            match change.kind {
+                WatchEventKind::Updated(event) => {
+                    let due = due_alarms(&event, floating, last_check, scheduled_until);
+                    alarms.insert(event.url().clone(), due);
+                    events.insert(event.url().clone(), event);
+                }
+                WatchEventKind::Cancelled(event) => {
+                    alarms.remove(event.url());
+                    events.remove(event.url());
+                }
+                WatchEventKind::Deleted(url) => {
+                    alarms.remove(&url);
+                    events.remove(&url);
+                }
+                WatchEventKind::Error(e) => {
+                    eprintln!("Sync of {} failed: {:?}", change.calendar, e)
+                }
+            }
+        }
+
+        let now = Utc::now();
+        if now + Duration::days(SCHEDULE_DAYS - 1) > scheduled_until {
+            scheduled_until = now + Duration::days(SCHEDULE_DAYS);
+            for (url, event) in &events {
+                let due = due_alarms(event, floating, last_check, scheduled_until);
+                alarms.insert(url.clone(), due);
+            }
+        }
+        for (url, due) in &alarms {
+            for (time, message) in due {
+                if last_check < *time && *time <= now && fired.insert((url.clone(), *time)) {
+                    notify(message);
+                }
+            }
+        }
+        fired.retain(|(_, time)| *time > last_check);
+        last_check = now;
+
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn env(name: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| panic!("{} is not set", name))
+}
+
+fn notify(message: &str) {
+    let shown = Command::new("notify-send")
+        .args(["--app-name", "minicaldav", "Reminder", message])
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+    if !shown {
+        println!("Reminder: {}", message);
+    }
+}
+
+/// The alarms of the event that fire in `(from, to]`, with the message to show.
+/// Overridden instances use their own alarms, cancelled instances have none.
+fn due_alarms(
+    event: &Event,
+    floating: Tz,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, String)> {
+    let lookahead = Duration::days(LOOKAHEAD_DAYS);
+    let mut due = Vec::new();
+    for occurrence in event.occurrences(from - lookahead, to + lookahead, floating) {
+        let component = &occurrence.component;
+        let value = |name: &str| component.get_first_property(name).map(|p| p.value.as_str());
+        if component.name != "VEVENT" || value("STATUS") == Some("CANCELLED") {
+            continue;
+        }
+        let summary = value("SUMMARY").unwrap_or("Event");
+        let length = occurrence.end - occurrence.start;
+        let alarms = component
+            .children
+            .iter()
+            .filter(|c| c.name == "VALARM")
+            .filter_map(Alarm::from_ical);
+        for alarm in alarms {
+            for time in alarm_times(&alarm, occurrence.start, length, floating) {
+                if from < time && time <= to {
+                    let local = occurrence
+                        .start
+                        .with_timezone(&floating)
+                        .format("%a %d %b %H:%M");
+                    due.push((time, format!("{} at {}", summary, local)));
+                }
+            }
+        }
+    }
+    due
+}
+
+/// When the alarm fires for the instance starting at `start`, including its repetitions.
+fn alarm_times(
+    alarm: &Alarm,
+    start: DateTime<Utc>,
+    length: Duration,
+    floating: Tz,
+) -> Vec<DateTime<Utc>> {
+    let first = match &alarm.trigger {
+        AlarmTrigger::Absolute(time) => parse_date_time(&Property::new("TRIGGER", time), floating)
+            .map(|t| t.with_timezone(&Utc)),
+        AlarmTrigger::Relative {
+            duration,
+            related_to_end,
+        } => parse_duration(duration).map(|offset| {
+            if *related_to_end {
+                start + length + offset
+            } else {
+                start + offset
+            }
+        }),
+    };
+    let Some(first) = first else {
+        return Vec::new();
+    };
+    let interval = alarm.duration.as_deref().and_then(parse_duration);
+    let repeat = interval.map(|_| alarm.repeat.unwrap_or(0)).unwrap_or(0);
+    (0..=repeat)
+        .map(|n| first + interval.unwrap_or_else(Duration::zero) * n as i32)
+        .collect()
+}
@@ -16,17 +16,20 @@
 
 //! Main api of minicaldav.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::caldav;
-use crate::errors::{MiniCaldavError, MiniCaldavError::*};
+use crate::client::{join_bounded, Client};
+use crate::color::CalendarColor;
+use crate::errors::{MiniCaldavError, MiniCaldavError::*, ResourceError};
 use crate::ical;
 use crate::ical::Ical;
-use reqwest::Client;
+use crate::privileges::Privileges;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+pub use crate::caldav::{CalendarDiscovery, DavPropertyName, Progress};
 pub use crate::credentials::Credentials;
 
 /// Simple connection check to the DAV server
@@ -44,7 +47,22 @@ pub async fn get_calendars(
     credentials: &Credentials,
     base_url: Url,
 ) -> Result<Vec<Calendar>, MiniCaldavError> {
-    let calendar_refs = caldav::get_calendars(client, credentials, base_url.clone()).await?;
+    get_calendars_with_discovery(client, credentials, base_url, CalendarDiscovery::Both)
+        .await
+        .map(|(calendars, _)| calendars)
+}
+
+/// Get all calendars from the given CalDAV endpoint, listing them with the given strategy.
+/// Returns the strategy that succeeded with the calendars, see `CalendarDiscovery`.
+pub async fn get_calendars_with_discovery(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: Url,
+    discovery: CalendarDiscovery,
+) -> Result<(Vec<Calendar>, CalendarDiscovery), MiniCaldavError> {
+    let (calendar_refs, used) =
+        caldav::get_calendars_with_discovery(client, credentials, base_url.clone(), discovery)
+            .await?;
     let mut calendars = Vec::new();
     for calendar_ref in calendar_refs {
         calendars.push(Calendar {
@@ -52,39 +70,112 @@ pub async fn get_calendars(
             inner: calendar_ref,
         });
     }
-    Ok(calendars)
+    Ok((calendars, used))
 }
 
-/// Get all todos in the given `Calendar`.
+/// Get the todos in the given `Calendar` matching `filter`, which is applied by the server if possible.
 /// This function returns a tuple of all todos that could be parsed and all todos that couldn't.
+/// Todos the server reported with an error status (e.g. 404 or 403) are returned as `Resource` errors.
 /// If anything besides parsing the todo data fails, an Err will be returned.
 pub async fn get_todos(
     client: &Client,
     credentials: &Credentials,
     calendar: &Calendar,
-) -> Result<(Vec<Event>, Vec<MiniCaldavError>), MiniCaldavError> {
-    let todo_refs =
-        caldav::get_todos(client, credentials, &calendar.base_url, &calendar.inner).await?;
-    let mut todos = Vec::new();
-    let mut errors = Vec::new();
-    for todo_ref in todo_refs {
-        let lines = ical::LineIterator::new(&todo_ref.data);
-
-        match ical::Ical::parse(&lines) {
-            Ok(ical) => todos.push(Event {
-                url: todo_ref.url.clone(),
-                etag: todo_ref.etag.clone(),
-                ical,
-            }),
-            Err(e) => errors.push(CouldNotParseTodo(todo_ref.data, format!("{:?}", e))),
+    filter: TodoFilter,
+) -> Result<(Vec<Todo>, Vec<MiniCaldavError>), MiniCaldavError> {
+    let (todo_refs, resource_errors) = match filter.query() {
+        Some(query) => {
+            caldav::query(
+                client,
+                credentials,
+                &calendar.base_url,
+                calendar.url(),
+                &query,
+            )
+            .await?
         }
-    }
+        None => caldav::get_todos(client, credentials, &calendar.base_url, &calendar.inner).await?,
+    };
+    let (events, errors) = parse_event_refs(client, todo_refs, resource_errors, CouldNotParseTodo);
+    let todos = events
+        .into_iter()
+        .map(Todo::from)
+        .filter(|todo| filter.matches(todo))
+        .collect();
     Ok((todos, errors))
 }
 
+/// Mark the todo as done, or advance a recurring todo to its next instance, and save it,
+/// see `Todo::complete`.
+pub async fn complete_todo(
+    client: &Client,
+    credentials: &Credentials,
+    mut todo: Todo,
+) -> Result<Todo, MiniCaldavError> {
+    todo.complete();
+    save_event(client, credentials, todo.into_event())
+        .await
+        .map(Todo::from)
+}
+
+/// Mark the todo as not done and save it, see `Todo::reopen`.
+pub async fn reopen_todo(
+    client: &Client,
+    credentials: &Credentials,
+    mut todo: Todo,
+) -> Result<Todo, MiniCaldavError> {
+    todo.reopen();
+    save_event(client, credentials, todo.into_event())
+        .await
+        .map(Todo::from)
+}
+
+/// Get all journal entries in the given `Calendar`.
+/// Entries that could not be parsed or that the server reported with an error status are
+/// returned as errors, as with `get_events`.
+pub async fn get_journals(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+) -> Result<(Vec<Journal>, Vec<MiniCaldavError>), MiniCaldavError> {
+    let (journal_refs, resource_errors) = caldav::query(
+        client,
+        credentials,
+        &calendar.base_url,
+        calendar.url(),
+        &caldav::CalendarQuery::new("VJOURNAL"),
+    )
+    .await?;
+    let (events, errors) =
+        parse_event_refs(client, journal_refs, resource_errors, CouldNotParseJournal);
+    Ok((events.into_iter().map(Journal::from).collect(), errors))
+}
+
+/// Save the given journal entry, creating it if it does not exist, see `save_event`.
+pub async fn save_journal(
+    client: &Client,
+    credentials: &Credentials,
+    journal: Journal,
+) -> Result<Journal, MiniCaldavError> {
+    save_event(client, credentials, journal.into_event())
+        .await
+        .map(Journal::from)
+}
+
+/// Delete the given journal entry.
+pub async fn remove_journal(
+    client: &Client,
+    credentials: &Credentials,
+    journal: Journal,
+) -> Result<(), MiniCaldavError> {
+    remove_event(client, credentials, journal.into_event()).await
+}
+
 /// Get all events in the given `Calendar`.
 /// This function returns a tuple of all events that could be parsed and all events that couldn't.
+/// Events the server reported with an error status (e.g. 404 or 403) are returned as `Resource` errors.
 /// If anything besides parsing the event data fails, an Err will be returned.
+/// Without time range and expansion, the cache of the client is used, see `ClientBuilder::cache`.
 pub async fn get_events(
     agent: &Client,
     credentials: &Credentials,
@@ -93,50 +184,630 @@ pub async fn get_events(
     end: Option<String>,
     expanded: bool,
 ) -> Result<(Vec<Event>, Vec<MiniCaldavError>), MiniCaldavError> {
+    get_events_with_progress(agent, credentials, calendar, start, end, expanded, |_| {}).await
+}
 
-
-    let event_refs = if calendar.is_subscription() {
+/// Like `get_events`, but calls `progress` while the events are downloaded, e.g. to show a progress bar.
+/// The total number of events is only known once the download is complete.
+/// Events from the cache, a subscription or expanded locally are reported once at the end.
+pub async fn get_events_with_progress<F: FnMut(Progress)>(
+    agent: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    start: Option<String>,
+    end: Option<String>,
+    expanded: bool,
+    mut progress: F,
+) -> Result<(Vec<Event>, Vec<MiniCaldavError>), MiniCaldavError> {
+    if let Some(cache) = agent.cache() {
+        if !calendar.is_subscription() && start.is_none() && end.is_none() && !expanded {
+            let (event_refs, resource_errors) = crate::cache::sync(
+                agent,
+                credentials,
+                cache,
+                &calendar.base_url,
+                calendar.url(),
+            )
+            .await?;
+            report_fetched(&mut progress, &event_refs, resource_errors.len());
+            return Ok(parse_event_refs(
+                agent,
+                event_refs,
+                resource_errors,
+                CouldNotParseEvent,
+            ));
+        }
+    }
+    let (event_refs, resource_errors) = if calendar.is_subscription() {
         let export_url = Url::parse(&format!("{}?export", calendar.url())).unwrap();
-        caldav::get_ical_events(
-            agent,
-            credentials,
-            export_url
-        )
-        .await?
+        let event_refs = caldav::get_ical_events(agent, credentials, export_url).await?;
+        report_fetched(&mut progress, &event_refs, 0);
+        (event_refs, Vec::new())
     } else {
-        caldav::get_events(
+        let (event_refs, resource_errors) = caldav::get_events_with_progress(
             agent,
             credentials,
             calendar.base_url.clone(),
             calendar.url().clone(),
-            start,
-            end,
+            start.clone(),
+            end.clone(),
             expanded,
+            &mut progress,
         )
-        .await?
+        .await?;
+        if expanded && !expanded_by_server(&event_refs) {
+            warn!(
+                "Server returned unexpanded data for {}, expanding locally",
+                calendar.url()
+            );
+            let (events, errors) =
+                get_events_expanded_locally(agent, credentials, calendar, start, end).await?;
+            progress(Progress {
+                fetched: events.len() + errors.len(),
+                total: Some(events.len() + errors.len()),
+                bytes: 0,
+            });
+            return Ok((events, errors));
+        }
+        (event_refs, resource_errors)
+    };
+    Ok(parse_event_refs(
+        agent,
+        event_refs,
+        resource_errors,
+        CouldNotParseEvent,
+    ))
+}
+
+/// Report all given objects as fetched.
+fn report_fetched<F: FnMut(Progress)>(
+    progress: &mut F,
+    event_refs: &[caldav::EventRef],
+    errors: usize,
+) {
+    let fetched = event_refs.len() + errors;
+    progress(Progress {
+        fetched,
+        total: Some(fetched),
+        bytes: event_refs.iter().map(|e| e.data.len() as u64).sum(),
+    });
+}
+
+/// Whether the server expanded all recurring objects, some servers ignore `expand` or return broken data.
+fn expanded_by_server(event_refs: &[caldav::EventRef]) -> bool {
+    event_refs.iter().all(|event_ref| {
+        ical::Ical::parse(&mut ical::LineIterator::new(&event_ref.data))
+            .map(|ical| {
+                ical.children.iter().all(|c| {
+                    c.get_first_property("RRULE").is_none()
+                        && c.get_first_property("RDATE").is_none()
+                })
+            })
+            .unwrap_or(false)
+    })
+}
+
+/// Get the events without server side expansion and expand them locally.
+/// Without the `tz` feature the events can not be expanded and are returned as they are.
+async fn get_events_expanded_locally(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    start: Option<String>,
+    end: Option<String>,
+) -> Result<(Vec<Event>, Vec<MiniCaldavError>), MiniCaldavError> {
+    let (event_refs, resource_errors) = caldav::get_events(
+        client,
+        credentials,
+        calendar.base_url.clone(),
+        calendar.url().clone(),
+        start.clone(),
+        end.clone(),
+        false,
+    )
+    .await?;
+    let (events, errors) =
+        parse_event_refs(client, event_refs, resource_errors, CouldNotParseEvent);
+    #[cfg(feature = "tz")]
+    {
+        use crate::recurrence::parse_naive;
+        use chrono::TimeZone;
+        let utc = |value: Option<String>, default: &str| {
+            parse_naive(value.as_deref().unwrap_or(default))
+                .map(|(naive, _)| chrono::Utc.from_utc_datetime(&naive))
+                .ok_or_else(|| RequestFailed(format!("Invalid time range {:?}", value)))
+        };
+        let start = utc(start, caldav::DEFAULT_RANGE_START)?;
+        let end = utc(end, caldav::DEFAULT_RANGE_END)?;
+        let events = events
+            .iter()
+            .filter_map(|event| event.expand(start, end, chrono_tz::UTC))
+            .collect();
+        Ok((events, errors))
+    }
+    #[cfg(not(feature = "tz"))]
+    {
+        let _ = (start, end);
+        warn!("Enable the tz feature to expand recurring events locally");
+        Ok((events, errors))
+    }
+}
+
+/// Get the events or todos with the given urls from the calendar, e.g. for an initial sync.
+/// The objects are fetched with calendar-multiget requests of 100 objects,
+/// running up to `parallelism` requests at a time.
+pub async fn get_events_by_url(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    urls: &[Url],
+    parallelism: usize,
+) -> Result<(Vec<Event>, Vec<MiniCaldavError>), MiniCaldavError> {
+    get_events_by_url_with_progress(client, credentials, calendar, urls, parallelism, |_| {}).await
+}
+
+/// Like `get_events_by_url`, but calls `progress` whenever a request is complete.
+pub async fn get_events_by_url_with_progress<F: FnMut(Progress)>(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    urls: &[Url],
+    parallelism: usize,
+    progress: F,
+) -> Result<(Vec<Event>, Vec<MiniCaldavError>), MiniCaldavError> {
+    let items = caldav::calendar_multiget_parallel_with_progress(
+        client,
+        credentials,
+        &calendar.base_url,
+        calendar.url(),
+        urls,
+        100,
+        parallelism,
+        progress,
+    )
+    .await?;
+    let mut event_refs = Vec::new();
+    let mut resource_errors = Vec::new();
+    for item in items {
+        match item {
+            caldav::MultigetItem::Found(event_ref) => event_refs.push(event_ref),
+            caldav::MultigetItem::Gone(error) | caldav::MultigetItem::Error(error) => {
+                resource_errors.push(error)
+            }
+        }
+    }
+    Ok(parse_event_refs(
+        client,
+        event_refs,
+        resource_errors,
+        CouldNotParseEvent,
+    ))
+}
+
+/// Get the events or todos of the given calendar matching the given query.
+/// The filtering is done by the server.
+pub async fn query_events(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    query: &caldav::CalendarQuery,
+) -> Result<(Vec<Event>, Vec<MiniCaldavError>), MiniCaldavError> {
+    let (event_refs, resource_errors) = caldav::query(
+        client,
+        credentials,
+        &calendar.base_url,
+        calendar.url(),
+        query,
+    )
+    .await?;
+    Ok(parse_event_refs(
+        client,
+        event_refs,
+        resource_errors,
+        CouldNotParseEvent,
+    ))
+}
+
+/// What `sync_changes` knows about a calendar after the last call.
+/// Start with `SyncState::default()` and store the returned state, e.g. serialized with `serde`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncState {
+    pub sync_token: Option<String>,
+    pub ctag: Option<String>,
+    /// The etags of all known objects of the calendar.
+    pub etags: HashMap<Url, String>,
+    /// Objects that could not be fetched or parsed, they are fetched again with the next call.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub failed: HashSet<Url>,
+}
+
+/// A change of a calendar found by `sync_changes`.
+#[derive(Debug)]
+pub enum Change {
+    /// The event was created or modified.
+    Updated(Event),
+    /// The object with the given url was deleted.
+    Deleted(Url),
+    /// The object could not be fetched or parsed. It is reported again with the next call.
+    Error(MiniCaldavError),
+}
+
+/// Get the changes of the given calendar since the call that returned `state`.
+/// Nothing is fetched if the `getctag` of the calendar did not change. Otherwise the changes are
+/// found with sync-collection (RFC 6578), or by comparing the etags of all objects on servers
+/// without it, and only the changed objects are downloaded.
+/// If a request fails, the error is returned and `state` should be passed to the next call again.
+pub async fn sync_changes(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    mut state: SyncState,
+) -> Result<(Vec<Change>, SyncState), MiniCaldavError> {
+    let ctag = caldav::get_ctag(client, credentials, calendar.url())
+        .await
+        .ok()
+        .filter(|ctag| !ctag.is_empty());
+    if ctag.is_some() && ctag == state.ctag && state.failed.is_empty() {
+        return Ok((Vec::new(), state));
+    }
+    let synced = caldav::sync_collection_all(
+        client,
+        credentials,
+        &calendar.base_url,
+        calendar.url(),
+        state.sync_token.clone(),
+    )
+    .await;
+    let (mut stale, deleted, sync_token) = match synced {
+        Ok(synced) if synced.listed_all => {
+            let (stale, deleted) = etag_changes(&state.etags, &synced.changed);
+            (stale, deleted, synced.sync_token)
+        }
+        Ok(synced) => {
+            let stale = synced
+                .changed
+                .iter()
+                .filter(|c| c.etag.is_none() || state.etags.get(&c.url) != c.etag.as_ref())
+                .map(|c| c.url.clone())
+                .collect();
+            (stale, synced.deleted, synced.sync_token)
+        }
+        Err(e) => {
+            debug!(
+                "sync-collection of {} failed, comparing etags: {:?}",
+                calendar.url(),
+                e
+            );
+            let listed =
+                caldav::get_etags(client, credentials, &calendar.base_url, calendar.url()).await?;
+            let (stale, deleted) = etag_changes(&state.etags, &listed);
+            (stale, deleted, None)
+        }
     };
+    for url in std::mem::take(&mut state.failed) {
+        if !stale.contains(&url) && !deleted.contains(&url) {
+            stale.push(url);
+        }
+    }
+
+    let mut changes = Vec::new();
+    for url in deleted {
+        if state.etags.remove(&url).is_some() {
+            changes.push(Change::Deleted(url));
+        }
+    }
+    if !stale.is_empty() {
+        let items = caldav::fetch_objects(
+            client,
+            credentials,
+            &calendar.base_url,
+            calendar.url(),
+            &stale,
+        )
+        .await?;
+        let mut event_refs = Vec::new();
+        let mut resource_errors = Vec::new();
+        for item in items {
+            match item {
+                caldav::MultigetItem::Found(event_ref) => event_refs.push(event_ref),
+                caldav::MultigetItem::Gone(error) => {
+                    if state.etags.remove(&error.url).is_some() {
+                        changes.push(Change::Deleted(error.url));
+                    }
+                }
+                caldav::MultigetItem::Error(error) => {
+                    state.failed.insert(error.url.clone());
+                    resource_errors.push(error);
+                }
+            }
+        }
+        let fetched = event_refs
+            .iter()
+            .map(|e| (e.url.clone(), e.etag.clone()))
+            .collect::<Vec<_>>();
+        let (events, errors) =
+            parse_event_refs(client, event_refs, resource_errors, CouldNotParseEvent);
+        // The etags of objects that could not be parsed are not stored, so they are not skipped
+        // as unchanged by the next call.
+        let parsed = events.iter().map(|e| &e.url).collect::<HashSet<_>>();
+        for (url, etag) in fetched {
+            match etag {
+                _ if !parsed.contains(&url) => {
+                    state.failed.insert(url);
+                }
+                Some(etag) => {
+                    state.etags.insert(url, etag);
+                }
+                None => {}
+            }
+        }
+        changes.extend(events.into_iter().map(Change::Updated));
+        changes.extend(errors.into_iter().map(Change::Error));
+    }
+    state.sync_token = sync_token;
+    state.ctag = ctag;
+    Ok((changes, state))
+}
+
+/// Compare the known etags with a listing of all objects,
+/// returning the urls of changed and of deleted objects.
+fn etag_changes(
+    known: &HashMap<Url, String>,
+    listed: &[caldav::ChangedResource],
+) -> (Vec<Url>, Vec<Url>) {
+    let stale = listed
+        .iter()
+        .filter(|c| c.etag.is_none() || known.get(&c.url) != c.etag.as_ref())
+        .map(|c| c.url.clone())
+        .collect();
+    let paths = listed
+        .iter()
+        .map(|c| c.url.path())
+        .collect::<std::collections::HashSet<_>>();
+    let deleted = known
+        .keys()
+        .filter(|url| !paths.contains(url.path()))
+        .cloned()
+        .collect();
+    (stale, deleted)
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(count = event_refs.len()))
+)]
+pub(crate) fn parse_event_refs(
+    client: &Client,
+    event_refs: Vec<caldav::EventRef>,
+    resource_errors: Vec<ResourceError>,
+    parse_error: fn(String, String) -> MiniCaldavError,
+) -> (Vec<Event>, Vec<MiniCaldavError>) {
     let mut events = Vec::new();
-    let mut errors = Vec::new();
+    let mut errors = resource_errors
+        .into_iter()
+        .map(Resource)
+        .collect::<Vec<MiniCaldavError>>();
     for event_ref in event_refs {
-        let lines = ical::LineIterator::new(&event_ref.data);
-        match ical::Ical::parse(&lines) {
-            Ok(ical) => events.push(Event {
-                url: event_ref.url.clone(),
-                etag: event_ref.etag.clone(),
-                ical,
-            }),
-            Err(e) => errors.push(CouldNotParseEvent(event_ref.data, format!("{:?}", e))),
+        let mut lines = ical::LineIterator::new(&event_ref.data);
+        match ical::Ical::parse(&mut lines) {
+            Ok(mut ical) => {
+                client.transform_fetched(&event_ref.url, &mut ical);
+                events.push(Event {
+                    url: event_ref.url.clone(),
+                    etag: event_ref.etag.clone(),
+                    schedule_tag: event_ref.schedule_tag.clone(),
+                    saved_hash: Some(content_hash(&event_ref.url, &ical)),
+                    ical,
+                })
+            }
+            Err(e) => errors.push(parse_error(event_ref.data, format!("{:?}", e))),
         }
     }
-    Ok((events, errors))
+    (events, errors)
 }
 
 /// Save the given event on the CalDAV server.
+/// With the `tz` feature, missing definitions of referenced time zones are added before upload.
+/// All METHOD properties (e.g. of an emailed invitation) are removed, see `MethodHandling::Strip`.
 pub async fn save_event(
+    client: &Client,
+    credentials: &Credentials,
+    event: Event,
+) -> Result<Event, MiniCaldavError> {
+    save_event_with_method_handling(client, credentials, event, MethodHandling::Strip).await
+}
+
+/// Merges the local and the server version of an event, see `ConflictStrategy::Merge`.
+pub type MergeFn = Box<dyn Fn(&Event, &Event) -> Event + Send + Sync>;
+
+/// How `save_event_resolving` handles changes of the event on the server.
+pub enum ConflictStrategy {
+    /// Overwrite the version on the server.
+    ForceOverwrite,
+    /// Drop the local changes and return the version on the server.
+    KeepServer,
+    /// Save the event returned by the function, called with the local and the server version.
+    Merge(MergeFn),
+}
+
+impl std::fmt::Debug for ConflictStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ForceOverwrite => write!(f, "ForceOverwrite"),
+            Self::KeepServer => write!(f, "KeepServer"),
+            Self::Merge(_) => write!(f, "Merge"),
+        }
+    }
+}
+
+/// Number of times `save_event_resolving` merges and retries before giving up.
+const MAX_CONFLICT_RETRIES: usize = 3;
+
+/// Save the given event only if it was not changed on the server since it was fetched.
+/// Otherwise the server version is fetched and the conflict is resolved with `strategy`.
+/// Returns `PreconditionFailed` if the event keeps changing while merging.
+/// If the event was deleted on the server, `KeepServer` returns the `Resource` error and the
+/// other strategies create it again.
+pub async fn save_event_resolving(
+    client: &Client,
+    credentials: &Credentials,
+    mut event: Event,
+    strategy: &ConflictStrategy,
+) -> Result<Event, MiniCaldavError> {
+    if !event.is_modified() {
+        debug!("Not saving unchanged event {}", event.url);
+        return Ok(event);
+    }
+    event.attach_missing_timezones();
+    for _ in 0..MAX_CONFLICT_RETRIES {
+        match put_event(
+            client,
+            credentials,
+            event.clone(),
+            MethodHandling::Strip,
+            true,
+        )
+        .await
+        {
+            Err(PreconditionFailed(_)) | Err(AlreadyExists(_)) => {}
+            result => return result,
+        }
+        debug!(
+            "Event {} changed on the server, resolving with {:?}",
+            event.url, strategy
+        );
+        let (event_refs, errors) =
+            caldav::get_objects(client, credentials, std::slice::from_ref(&event.url), 1).await?;
+        let server = match event_refs.into_iter().next() {
+            Some(event_ref) => {
+                let (mut events, mut errors) =
+                    parse_event_refs(client, vec![event_ref], vec![], CouldNotParseEvent);
+                if let Some(e) = errors.pop() {
+                    return Err(e);
+                }
+                events.pop()
+            }
+            None => match errors.into_iter().next() {
+                Some(e) if !e.is_gone() => return Err(Resource(e)),
+                Some(e) if matches!(strategy, ConflictStrategy::KeepServer) => {
+                    return Err(Resource(e))
+                }
+                _ => None,
+            },
+        };
+        let etag = server.as_ref().and_then(|s| s.etag.clone());
+        event = match (strategy, server) {
+            (ConflictStrategy::KeepServer, Some(server)) => return Ok(server),
+            (ConflictStrategy::Merge(merge), Some(server)) => merge(&event, &server),
+            _ => event,
+        };
+        event.etag = etag;
+    }
+    Err(PreconditionFailed(event.url))
+}
+
+/// Number of PUT requests running at the same time in `save_events`.
+const SAVE_PARALLELISM: usize = 8;
+
+/// Save the given events on the CalDAV server, running several requests at a time.
+/// Returns the result for each event in the order of the given events.
+pub async fn save_events(
+    client: &Client,
+    credentials: &Credentials,
+    events: Vec<Event>,
+) -> Vec<Result<Event, MiniCaldavError>> {
+    let requests = events
+        .into_iter()
+        .map(|event| save_event(client, credentials, event));
+    join_bounded(requests, SAVE_PARALLELISM).await
+}
+
+/// How to handle a METHOD property when saving an object.
+/// Calendar object resources must not contain METHOD (RFC 4791) and many servers reject them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MethodHandling {
+    /// Remove every METHOD property before upload, the removal is logged.
+    #[default]
+    Strip,
+    /// Do not upload and return `MiniCaldavError::ContainsMethod`.
+    Reject,
+    /// Upload the object as it is.
+    Keep,
+}
+
+/// Save the given event on the CalDAV server handling a METHOD property as configured.
+/// Nothing is sent if the event did not change since it was fetched or saved, see `Event::is_modified`.
+pub async fn save_event_with_method_handling(
+    client: &Client,
+    credentials: &Credentials,
+    mut event: Event,
+    method_handling: MethodHandling,
+) -> Result<Event, MiniCaldavError> {
+    if !event.is_modified() {
+        debug!("Not saving unchanged event {}", event.url);
+        return Ok(event);
+    }
+    event.attach_missing_timezones();
+    put_event(client, credentials, event, method_handling, false).await
+}
+
+/// The PRODID of objects created by minicaldav.
+pub const DEFAULT_PRODID: &str = "-//rust-minicaldav//EN";
+
+/// Add VERSION and PRODID to the VCALENDAR if they are missing, both are required by RFC 5545.
+/// Fails for other versions than 2.0.
+fn check_version_and_prodid(ical: &mut Ical) -> Result<(), MiniCaldavError> {
+    match ical.get_first_property("VERSION") {
+        Some(version) if version.value.trim() != "2.0" => {
+            return Err(InvalidObject(format!(
+                "unsupported iCalendar version {}",
+                version.value
+            )))
+        }
+        Some(_) => {}
+        None => ical
+            .properties
+            .insert(0, ical::Property::new("VERSION", "2.0")),
+    }
+    if ical.get_first_property("PRODID").is_none() {
+        ical.properties
+            .insert(0, ical::Property::new("PRODID", DEFAULT_PRODID));
+    }
+    Ok(())
+}
+
+/// Apply the `MethodHandling` to the METHOD properties of the VCALENDAR.
+fn handle_method(
+    url: &Url,
+    ical: &mut Ical,
+    method_handling: MethodHandling,
+) -> Result<(), MiniCaldavError> {
+    let Some(method) = ical.get_first_property("METHOD") else {
+        return Ok(());
+    };
+    match method_handling {
+        MethodHandling::Strip => {
+            debug!("Removing METHOD {} of {} before upload", method.value, url);
+            ical.properties.retain(|p| p.name != "METHOD");
+        }
+        MethodHandling::Reject => return Err(ContainsMethod(method.value.clone())),
+        MethodHandling::Keep => {}
+    }
+    Ok(())
+}
+
+/// Upload the event, with `conditional` only if it was not changed on the server, see `caldav::update_event`.
+pub(crate) async fn put_event(
     client: &Client,
     credentials: &Credentials,
     mut event: Event,
+    method_handling: MethodHandling,
+    conditional: bool,
 ) -> Result<Event, MiniCaldavError> {
+    check_version_and_prodid(&mut event.ical)?;
+    handle_method(&event.url, &mut event.ical, method_handling)?;
+
     for prop in &mut event.ical.properties {
         if prop.name == "SEQUENCE" {
             if let Ok(num) = prop.value.parse::<i64>() {
@@ -146,18 +817,148 @@ pub async fn save_event(
     }
 
     let event_ref = caldav::EventRef {
-        data: event.ical.serialize(),
+        data: client.ics_for_upload(&event.url, &event.ical),
+        etag: event.etag.clone(),
+        url: event.url,
+        schedule_tag: event.schedule_tag,
+    };
+    let event_ref = if conditional {
+        caldav::update_event(client, credentials, event_ref).await?
+    } else {
+        caldav::save_event(client, credentials, event_ref).await?
+    };
+    Ok(Event {
+        etag: event_ref.etag,
+        saved_hash: Some(content_hash(&event_ref.url, &event.ical)),
+        url: event_ref.url,
+        schedule_tag: event_ref.schedule_tag,
+        ..event
+    })
+}
+
+/// Create the given event in the calendar and return it with its etag.
+/// The url of the event is replaced by one derived from its UID (`<calendar>/<uid>.ics`).
+/// Returns `AlreadyExists` instead of overwriting an existing object with this url.
+pub async fn create_event(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    mut event: Event,
+) -> Result<Event, MiniCaldavError> {
+    // The UID of the main component, time zone definitions may come first.
+    let component = event.ical.children.iter().find(|c| c.name != "VTIMEZONE");
+    let uid = component
+        .and_then(|c| c.get_first_property("UID"))
+        .map(|p| p.value.clone())
+        .ok_or_else(|| {
+            let issue = match component {
+                Some(c) => ValidationIssue::MissingProperty {
+                    component: c.name.clone(),
+                    property: "UID".into(),
+                },
+                None => ValidationIssue::NoEvent,
+            };
+            InvalidObject(issue.to_string())
+        })?;
+    event.url = resource_url(calendar.url(), &uid)?;
+    event.attach_missing_timezones();
+    handle_method(&event.url, &mut event.ical, MethodHandling::Strip)?;
+    check_version_and_prodid(&mut event.ical)?;
+
+    let event_ref = caldav::EventRef {
+        data: client.ics_for_upload(&event.url, &event.ical),
         etag: None,
         url: event.url,
+        schedule_tag: None,
     };
-    let event_ref = caldav::save_event(client, credentials, event_ref).await?;
+    let event_ref = caldav::create_event(client, credentials, event_ref).await?;
     Ok(Event {
         etag: event_ref.etag,
+        saved_hash: Some(content_hash(&event_ref.url, &event.ical)),
         url: event_ref.url,
+        schedule_tag: event_ref.schedule_tag,
         ..event
     })
 }
 
+/// The url of the object with the given UID in the collection, `<collection>/<uid>.ics`.
+/// Characters of the UID that are not allowed in a path segment are percent-encoded.
+pub(crate) fn resource_url(collection_url: &Url, uid: &str) -> Result<Url, MiniCaldavError> {
+    let mut url = collection_url.clone();
+    url.path_segments_mut()
+        .map_err(|_| CouldNotJoinUrl(format!("{} can not have a path", collection_url)))?
+        .pop_if_empty()
+        .push(&format!("{}.ics", uid));
+    Ok(url)
+}
+
+/// Check whether the server stored the given event although saving it failed with an unknown outcome,
+/// e.g. a read timeout of the PUT, see `MiniCaldavError::is_unknown_outcome`.
+/// Pass the event as it was passed to `save_event`. Returns the stored event if the server has another
+/// version than `event` (any version for new events), or `None` if the event was not saved.
+pub async fn verify_saved_event(
+    client: &Client,
+    credentials: &Credentials,
+    event: &Event,
+) -> Result<Option<Event>, MiniCaldavError> {
+    let expected = caldav::ExpectedWrite::Put {
+        previous_etag: event.etag.clone(),
+    };
+    let caldav::WriteOutcome::Stored(event_ref) =
+        caldav::verify_write(client, credentials, &event.url, &expected).await?
+    else {
+        return Ok(None);
+    };
+    let (mut events, mut errors) =
+        parse_event_refs(client, vec![event_ref], vec![], CouldNotParseEvent);
+    match errors.pop() {
+        Some(e) => Err(e),
+        None => Ok(events.pop()),
+    }
+}
+
+/// Check whether the server removed the given event after `remove_event` failed with an unknown outcome,
+/// see `MiniCaldavError::is_unknown_outcome`.
+pub async fn verify_removed_event(
+    client: &Client,
+    credentials: &Credentials,
+    event: &Event,
+) -> Result<bool, MiniCaldavError> {
+    let outcome = caldav::verify_write(
+        client,
+        credentials,
+        &event.url,
+        &caldav::ExpectedWrite::Delete,
+    )
+    .await?;
+    Ok(matches!(outcome, caldav::WriteOutcome::Deleted))
+}
+
+/// Save the given event on the CalDAV server without its VTIMEZONE components.
+/// Only use this with servers supporting time zones by reference (RFC 7809),
+/// see `caldav::ServerCapabilities::calendar_no_timezone`.
+/// The returned event still contains the time zone definitions.
+pub async fn save_event_by_reference(
+    client: &Client,
+    credentials: &Credentials,
+    mut event: Event,
+) -> Result<Event, MiniCaldavError> {
+    if !event.is_modified() {
+        debug!("Not saving unchanged event {}", event.url);
+        return Ok(event);
+    }
+    let timezones = event.ical.strip_timezones();
+    let mut saved = put_event(client, credentials, event, MethodHandling::Strip, false).await?;
+    saved.ical.attach_timezones(|tzid| {
+        timezones
+            .iter()
+            .find(|tz| tz.defined_tzids().iter().any(|t| t == tzid))
+            .cloned()
+    });
+    saved.saved_hash = Some(content_hash(&saved.url, &saved.ical));
+    Ok(saved)
+}
+
 /// Remove the given event on the CalDAV server.
 pub async fn remove_event(
     client: &Client,
@@ -168,6 +969,7 @@ pub async fn remove_event(
         data: event.ical.serialize(),
         etag: event.etag,
         url: event.url,
+        schedule_tag: event.schedule_tag,
     };
     caldav::remove_event(client, credentials, event_ref).await?;
     Ok(())
@@ -185,6 +987,36 @@ pub async fn create_calendar(
     Ok(())
 }
 
+/// Create a calendar whose calendar time zone is the given IANA time zone (e.g. `Europe/Berlin`).
+#[cfg(feature = "tz")]
+pub async fn create_calendar_with_timezone(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calid: String,
+    name: String,
+    color: String,
+    tzid: &str,
+) -> Result<(), MiniCaldavError> {
+    let vtimezone =
+        crate::tz::vtimezone_for(tzid).ok_or_else(|| UnknownTimezone(tzid.to_string()))?;
+    let mut vcalendar = Ical::new("VCALENDAR".into());
+    vcalendar.add_property(ical::Property::new("PRODID", DEFAULT_PRODID));
+    vcalendar.add_property(ical::Property::new("VERSION", "2.0"));
+    vcalendar.add_component(vtimezone);
+    caldav::create_calendar_with_timezone(
+        client,
+        credentials,
+        base_url,
+        calid,
+        name,
+        color,
+        &vcalendar.serialize(),
+    )
+    .await?;
+    Ok(())
+}
+
 pub async fn remove_calendar(
     client: &Client,
     credentials: &Credentials,
@@ -195,459 +1027,5034 @@ pub async fn remove_calendar(
     Ok(())
 }
 
-/// A remote CalDAV calendar.
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone)]
-pub struct Calendar {
-    base_url: Url,
-    inner: caldav::CalendarRef,
+/// Share the given calendar with the user of the given email address.
+pub async fn share_calendar(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    email: &str,
+    read_write: bool,
+) -> Result<(), MiniCaldavError> {
+    caldav::share_calendar(
+        client,
+        credentials,
+        calendar.url(),
+        &mailto(email),
+        None,
+        read_write,
+    )
+    .await
 }
 
-impl Calendar {
-    pub fn url(&self) -> &Url {
-        &self.inner.url
-    }
-    pub fn name(&self) -> &String {
-        &self.inner.name
-    }
+/// Stop sharing the given calendar with the user of the given email address.
+pub async fn unshare_calendar(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    email: &str,
+) -> Result<(), MiniCaldavError> {
+    caldav::unshare_calendar(client, credentials, calendar.url(), &mailto(email)).await
+}
+
+/// Get all invitations to calendars other users shared with the current user.
+pub async fn list_share_invites(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: Url,
+) -> Result<Vec<caldav::ShareInvite>, MiniCaldavError> {
+    caldav::get_share_invites(client, credentials, base_url).await
+}
+
+/// Accept the given share invite. The shared calendar will show up in `get_calendars` afterwards.
+pub async fn accept_share_invite(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: Url,
+    invite: &caldav::ShareInvite,
+) -> Result<(), MiniCaldavError> {
+    caldav::reply_to_share_invite(client, credentials, base_url, invite, true).await
+}
+
+/// Decline the given share invite.
+pub async fn decline_share_invite(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: Url,
+    invite: &caldav::ShareInvite,
+) -> Result<(), MiniCaldavError> {
+    caldav::reply_to_share_invite(client, credentials, base_url, invite, false).await
+}
+
+fn mailto(email: &str) -> String {
+    if email.to_lowercase().starts_with("mailto:") {
+        email.to_string()
+    } else {
+        format!("mailto:{}", email)
+    }
+}
+
+/// Set custom DAV properties on the resource of the given event, e.g. app-specific sync metadata.
+/// Properties with value `None` are removed. Not all servers allow custom properties on events.
+pub async fn set_object_properties(
+    client: &Client,
+    credentials: &Credentials,
+    event: &Event,
+    properties: &[(caldav::DavPropertyName, Option<String>)],
+) -> Result<(), MiniCaldavError> {
+    caldav::set_object_properties(client, credentials, event.url(), properties).await
+}
+
+/// Get the given DAV properties of the resource of the given event.
+pub async fn get_object_properties(
+    client: &Client,
+    credentials: &Credentials,
+    event: &Event,
+    names: &[caldav::DavPropertyName],
+) -> Result<HashMap<caldav::DavPropertyName, String>, MiniCaldavError> {
+    caldav::get_object_properties(client, credentials, event.url(), names).await
+}
+
+/// Get the calendar-wide event settings of the given calendar, e.g. the default alarm.
+pub async fn get_calendar_defaults(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+) -> Result<caldav::CalendarDefaults, MiniCaldavError> {
+    caldav::get_calendar_defaults(client, credentials, calendar.url()).await
+}
+
+/// Store the given calendar-wide event settings, only `Some` values are changed.
+/// `duration` and `transparency` are not stored on the server.
+pub async fn set_calendar_defaults(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    defaults: &caldav::CalendarDefaults,
+) -> Result<(), MiniCaldavError> {
+    caldav::set_calendar_defaults(client, credentials, calendar.url(), defaults).await
+}
+
+/// Show or hide the given calendar in clients.
+pub async fn set_calendar_enabled(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &mut Calendar,
+    enabled: bool,
+) -> Result<(), MiniCaldavError> {
+    caldav::set_calendar_enabled(client, credentials, calendar.url(), enabled).await?;
+    calendar.inner.enabled = Some(enabled);
+    Ok(())
+}
+
+/// Change the color of the given calendar to the given RGB value.
+/// The color is written in the notation the server used before (e.g. `#RRGGBBAA` or a name),
+/// nothing is written if the color does not change.
+pub async fn set_calendar_color(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &mut Calendar,
+    rgb: (u8, u8, u8),
+) -> Result<(), MiniCaldavError> {
+    let current = calendar.calendar_color();
+    if current.as_ref().and_then(|c| c.rgb()) == Some(rgb) {
+        return Ok(());
+    }
+    let color = current
+        .unwrap_or_else(|| CalendarColor::parse("#000000"))
+        .with_rgb(rgb);
+    caldav::set_calendar_color(client, credentials, calendar.url(), color.as_str()).await?;
+    calendar.inner.color = Some(color.to_string());
+    Ok(())
+}
+
+/// Set the position of the given calendar in calendar lists.
+pub async fn set_calendar_order(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &mut Calendar,
+    order: i32,
+) -> Result<(), MiniCaldavError> {
+    caldav::set_calendar_order(client, credentials, calendar.url(), order).await?;
+    calendar.inner.order = Some(order);
+    Ok(())
+}
+
+/// Get the availability (e.g. working hours) the current user published on the server.
+/// Returns `None` if no availability is set.
+pub async fn get_availability(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: Url,
+) -> Result<Option<Availability>, MiniCaldavError> {
+    let principal_url = caldav::get_principal_url(client, credentials, base_url).await?;
+    let inbox_url = caldav::get_schedule_inbox_url(client, credentials, principal_url).await?;
+    caldav::get_calendar_availability(client, credentials, &inbox_url)
+        .await?
+        .map(|data| Availability::parse(&data))
+        .transpose()
+}
+
+/// Publish the given availability (e.g. working hours) of the current user on the server.
+pub async fn set_availability(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: Url,
+    availability: &Availability,
+) -> Result<(), MiniCaldavError> {
+    let principal_url = caldav::get_principal_url(client, credentials, base_url).await?;
+    let inbox_url = caldav::get_schedule_inbox_url(client, credentials, principal_url).await?;
+    caldav::set_calendar_availability(client, credentials, &inbox_url, &availability.serialize())
+        .await
+}
+
+/// Discover the WebDAV-Push support of the given calendar.
+pub async fn get_push_support(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+) -> Result<caldav::PushSupport, MiniCaldavError> {
+    caldav::get_push_support(client, credentials, calendar.url()).await
+}
+
+/// Register a Web Push subscription so the server notifies the client about changes of the given calendar.
+pub async fn register_push_subscription(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    subscription: &caldav::WebPushSubscription,
+    expires: Option<&str>,
+) -> Result<caldav::PushRegistration, MiniCaldavError> {
+    caldav::register_push_subscription(client, credentials, calendar.url(), subscription, expires)
+        .await
+}
+
+/// Remove a previously registered push subscription.
+pub async fn unregister_push_subscription(
+    client: &Client,
+    credentials: &Credentials,
+    registration: &caldav::PushRegistration,
+) -> Result<(), MiniCaldavError> {
+    caldav::unregister_push_subscription(client, credentials, registration).await
+}
+
+/// An event that was deleted and moved to the Nextcloud trashbin.
+#[cfg(feature = "nextcloud")]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct DeletedEvent {
+    pub event: Event,
+    /// The uri of the calendar the event was deleted from.
+    pub calendar_uri: Option<String>,
+    /// The time of deletion as sent by the server.
+    pub deleted_at: Option<String>,
+}
+
+/// Get all events in the Nextcloud trashbin.
+/// This function returns a tuple of all events that could be parsed and all events that couldn't.
+#[cfg(feature = "nextcloud")]
+pub async fn list_deleted_events(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+) -> Result<(Vec<DeletedEvent>, Vec<MiniCaldavError>), MiniCaldavError> {
+    let trashbin_url = crate::nextcloud::get_trashbin_url(client, credentials, base_url).await?;
+    let deleted = crate::nextcloud::get_deleted_objects(client, credentials, &trashbin_url).await?;
+    let mut events = Vec::new();
+    let mut errors = Vec::new();
+    for object in deleted {
+        let event_ref = object.event_ref;
+        let mut lines = ical::LineIterator::new(&event_ref.data);
+        match ical::Ical::parse(&mut lines) {
+            Ok(ical) => events.push(DeletedEvent {
+                event: Event::new(event_ref.etag.clone(), event_ref.url.clone(), ical),
+                calendar_uri: object.calendar_uri,
+                deleted_at: object.deleted_at,
+            }),
+            Err(e) => errors.push(CouldNotParseEvent(event_ref.data, format!("{:?}", e))),
+        }
+    }
+    Ok((events, errors))
+}
+
+/// Restore the given event from the Nextcloud trashbin into its original calendar.
+#[cfg(feature = "nextcloud")]
+pub async fn restore_event(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    event: &DeletedEvent,
+) -> Result<(), MiniCaldavError> {
+    let trashbin_url = crate::nextcloud::get_trashbin_url(client, credentials, base_url).await?;
+    crate::nextcloud::restore(client, credentials, &trashbin_url, event.event.url()).await
+}
+
+/// Get all calendars in the Nextcloud trashbin.
+#[cfg(feature = "nextcloud")]
+pub async fn list_deleted_calendars(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+) -> Result<Vec<crate::nextcloud::DeletedCalendarRef>, MiniCaldavError> {
+    let trashbin_url = crate::nextcloud::get_trashbin_url(client, credentials, base_url).await?;
+    crate::nextcloud::get_deleted_calendars(client, credentials, &trashbin_url).await
+}
+
+/// Restore the given calendar from the Nextcloud trashbin.
+#[cfg(feature = "nextcloud")]
+pub async fn restore_calendar(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calendar: &crate::nextcloud::DeletedCalendarRef,
+) -> Result<(), MiniCaldavError> {
+    let trashbin_url = crate::nextcloud::get_trashbin_url(client, credentials, base_url).await?;
+    crate::nextcloud::restore(client, credentials, &trashbin_url, &calendar.url).await
+}
+
+/// A remote CalDAV calendar.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Calendar {
+    base_url: Url,
+    inner: caldav::CalendarRef,
+}
+
+impl Calendar {
+    /// Reconstruct a calendar from a previously stored base url and reference, without discovery.
+    pub fn from_parts(base_url: Url, calendar_ref: caldav::CalendarRef) -> Self {
+        Self {
+            base_url,
+            inner: calendar_ref,
+        }
+    }
+    pub fn url(&self) -> &Url {
+        &self.inner.url
+    }
+    /// The url the calendar was discovered from.
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+    /// The discovered properties of the calendar.
+    pub fn calendar_ref(&self) -> &caldav::CalendarRef {
+        &self.inner
+    }
+    /// The base url and the discovered properties, e.g. to store them and use `from_parts` later.
+    pub fn into_ref(self) -> (Url, caldav::CalendarRef) {
+        (self.base_url, self.inner)
+    }
+    pub fn name(&self) -> &String {
+        &self.inner.name
+    }
     pub fn color(&self) -> Option<&String> {
         self.inner.color.as_ref()
     }
+    /// The parsed color, see `CalendarColor` to compare colors in different notations.
+    pub fn calendar_color(&self) -> Option<CalendarColor> {
+        self.inner.color.as_deref().map(CalendarColor::parse)
+    }
+    /// Whether the current user may modify the events of the calendar.
     pub fn writable(&self) -> bool {
-        println!("self.inner.privilege: {:?}", self.inner.privileges);
-        self.inner.privileges.iter().any(|p| p == "write")
+        self.privileges().can_write_content()
+    }
+    /// The normalized privileges of the current user.
+    pub fn privileges(&self) -> Privileges {
+        Privileges::from_names(&self.inner.privileges)
+    }
+    /// The supported component types, e.g. `VEVENT` and `VTODO`.
+    pub fn components(&self) -> &[String] {
+        &self.inner.components
+    }
+    pub fn is_subscription(&self) -> bool {
+        self.inner.is_subscription
+    }
+    /// The upstream ICS feed url of a subscribed (webcal) calendar, if the server provides it.
+    pub fn source_url(&self) -> Option<&Url> {
+        self.inner.source_url.as_ref()
+    }
+    /// Whether the calendar should be shown. Calendars hidden in other clients return `false`.
+    pub fn is_enabled(&self) -> bool {
+        self.inner.enabled.unwrap_or(true)
+    }
+    /// The position of the calendar in calendar lists, if set.
+    pub fn order(&self) -> Option<i32> {
+        self.inner.order
+    }
+}
+
+/// The expanded instances of the main component, the overridden instances and the moved instances.
+#[cfg(feature = "tz")]
+type InstanceStarts = (
+    crate::recurrence::Occurrences,
+    Vec<chrono::DateTime<chrono_tz::Tz>>,
+    Vec<chrono::DateTime<chrono_tz::Tz>>,
+);
+
+/// The reason an existing event does not take place.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cancellation {
+    /// The event is marked with `STATUS:CANCELLED`.
+    Status,
+    /// All instances of the recurring event are excluded with EXDATE or cancelled by overrides.
+    AllInstancesExcluded,
+}
+
+/// A problem of an event that servers are likely to reject, see `Event::validate`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// The calendar contains no VEVENT.
+    NoEvent,
+    /// A required property is missing in a component.
+    MissingProperty { component: String, property: String },
+    /// Properties that must not occur together, e.g. DTEND and DURATION.
+    ConflictingProperties {
+        component: String,
+        properties: (String, String),
+    },
+    /// A property value that is not valid for the property.
+    InvalidValue {
+        component: String,
+        property: String,
+        value: String,
+    },
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoEvent => write!(f, "no VEVENT component"),
+            Self::MissingProperty {
+                component,
+                property,
+            } => write!(f, "{} without {}", component, property),
+            Self::ConflictingProperties {
+                component,
+                properties: (a, b),
+            } => write!(f, "{} with both {} and {}", component, a, b),
+            Self::InvalidValue {
+                component,
+                property,
+                value,
+            } => write!(f, "invalid {} {:?} in {}", property, value, component),
+        }
+    }
+}
+
+/// A difference between two versions of an event, see `Event::diff`.
+/// `component` is the path of the component, e.g. `VCALENDAR/VEVENT/VALARM`, or
+/// `VCALENDAR/VEVENT[20240102T100000Z]` for the override of an instance.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyChange {
+    Added {
+        component: String,
+        property: Property,
+    },
+    Removed {
+        component: String,
+        property: Property,
+    },
+    /// The value or parameters of a property changed.
+    Changed {
+        component: String,
+        old: Property,
+        new: Property,
+    },
+}
+
+impl std::fmt::Display for PropertyChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Added {
+                component,
+                property,
+            } => write!(
+                f,
+                "{}: added {} {:?}",
+                component, property.name, property.value
+            ),
+            Self::Removed {
+                component,
+                property,
+            } => write!(
+                f,
+                "{}: removed {} {:?}",
+                component, property.name, property.value
+            ),
+            Self::Changed {
+                component,
+                old,
+                new,
+            } => write!(
+                f,
+                "{}: changed {} {:?} to {:?}",
+                component, old.name, old.value, new.value
+            ),
+        }
+    }
+}
+
+/// Properties that clients and servers update without changing the meaning of an event.
+const VOLATILE_PROPERTIES: &[&str] = &["DTSTAMP", "SEQUENCE", "LAST-MODIFIED"];
+
+/// The children of the component by their path, see `PropertyChange`.
+fn child_paths<'a>(path: &str, component: &'a Ical) -> Vec<(String, &'a Ical)> {
+    let key = |child: &Ical| {
+        child
+            .get_first_property("RECURRENCE-ID")
+            .or_else(|| child.get_first_property("TZID"))
+            .map(|p| format!("{}[{}]", child.name, p.value))
+            .unwrap_or_else(|| child.name.clone())
+    };
+    let keys = component.children.iter().map(key).collect::<Vec<_>>();
+    let mut paths = Vec::new();
+    for (i, (child, key)) in component.children.iter().zip(&keys).enumerate() {
+        let index = keys[..i].iter().filter(|k| *k == key).count();
+        let key = if index == 0 {
+            key.clone()
+        } else {
+            format!("{}#{}", key, index + 1)
+        };
+        paths.push((format!("{}/{}", path, key), child));
+    }
+    paths
+}
+
+fn diff_components(
+    path: &str,
+    old: Option<&Ical>,
+    new: Option<&Ical>,
+    changes: &mut Vec<PropertyChange>,
+) {
+    fn properties(component: Option<&Ical>) -> Vec<&ical::Property> {
+        component
+            .map(|c| {
+                c.properties
+                    .iter()
+                    .filter(|p| !VOLATILE_PROPERTIES.contains(&p.name.as_str()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    let mut removed = properties(old);
+    let mut added = properties(new);
+    // Unchanged properties, then properties with the same value and other parameters,
+    // then the remaining properties of the same name in order.
+    removed.retain(|p| match added.iter().position(|a| a == p) {
+        Some(i) => {
+            added.remove(i);
+            false
+        }
+        None => true,
+    });
+    for same_value in [true, false] {
+        removed.retain(|p| {
+            let pair = added
+                .iter()
+                .position(|a| a.name == p.name && (!same_value || a.value == p.value));
+            match pair {
+                Some(i) => {
+                    changes.push(PropertyChange::Changed {
+                        component: path.to_string(),
+                        old: (*p).clone().into(),
+                        new: added.remove(i).clone().into(),
+                    });
+                    false
+                }
+                None => true,
+            }
+        });
+    }
+    changes.extend(removed.into_iter().map(|p| PropertyChange::Removed {
+        component: path.to_string(),
+        property: p.clone().into(),
+    }));
+    changes.extend(added.into_iter().map(|p| PropertyChange::Added {
+        component: path.to_string(),
+        property: p.clone().into(),
+    }));
+
+    let old_children = old.map(|c| child_paths(path, c)).unwrap_or_default();
+    let new_children = new.map(|c| child_paths(path, c)).unwrap_or_default();
+    for (child_path, child) in &old_children {
+        let other = new_children
+            .iter()
+            .find(|(p, _)| p == child_path)
+            .map(|(_, c)| *c);
+        diff_components(child_path, Some(child), other, changes);
+    }
+    for (child_path, child) in &new_children {
+        if !old_children.iter().any(|(p, _)| p == child_path) {
+            diff_components(child_path, None, Some(child), changes);
+        }
+    }
+}
+
+/// When an alarm fires.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlarmTrigger {
+    /// A duration relative to the start (or end) of the event or todo, e.g. `-PT15M`.
+    Relative {
+        duration: String,
+        related_to_end: bool,
+    },
+    /// A UTC date-time, e.g. `20240101T090000Z`.
+    Absolute(String),
+}
+
+/// A VALARM of an event or todo.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alarm {
+    /// `DISPLAY`, `AUDIO` or `EMAIL`.
+    pub action: String,
+    pub trigger: AlarmTrigger,
+    pub description: Option<String>,
+    pub summary: Option<String>,
+    /// How often the alarm is repeated after it first fired, every `duration`.
+    pub repeat: Option<u32>,
+    pub duration: Option<String>,
+}
+
+impl Alarm {
+    /// Parse the given VALARM component, `None` if it has no ACTION or TRIGGER.
+    pub fn from_ical(ical: &Ical) -> Option<Self> {
+        let value = |name: &str| ical.get_first_property(name).map(|p| p.value.clone());
+        let trigger = ical.get_first_property("TRIGGER")?;
+        let trigger = if trigger.attributes.get("VALUE").map(|v| v.as_str()) == Some("DATE-TIME") {
+            AlarmTrigger::Absolute(trigger.value.clone())
+        } else {
+            AlarmTrigger::Relative {
+                duration: trigger.value.clone(),
+                related_to_end: trigger.attributes.get("RELATED").map(|v| v.as_str())
+                    == Some("END"),
+            }
+        };
+        Some(Self {
+            action: value("ACTION")?,
+            trigger,
+            description: value("DESCRIPTION"),
+            summary: value("SUMMARY"),
+            repeat: value("REPEAT").and_then(|r| r.trim().parse().ok()),
+            duration: value("DURATION"),
+        })
+    }
+
+    /// The VALARM component of this alarm.
+    /// Fails with `InvalidObject` if only one of `repeat` and `duration` is set, RFC 5545 requires both or neither.
+    pub fn to_ical(&self) -> Result<Ical, MiniCaldavError> {
+        let mut alarm = Ical::new("VALARM".into());
+        alarm.add_property(ical::Property::new("ACTION", &self.action));
+        alarm.add_property(match &self.trigger {
+            AlarmTrigger::Relative {
+                duration,
+                related_to_end: true,
+            } => ical::Property::new_with_attributes("TRIGGER", duration, vec![("RELATED", "END")]),
+            AlarmTrigger::Relative { duration, .. } => ical::Property::new("TRIGGER", duration),
+            AlarmTrigger::Absolute(date) => {
+                ical::Property::new_with_attributes("TRIGGER", date, vec![("VALUE", "DATE-TIME")])
+            }
+        });
+        // DISPLAY and EMAIL alarms require a DESCRIPTION (RFC 5545, section 3.6.6).
+        if let Some(description) = &self.description {
+            alarm.add_property(ical::Property::new("DESCRIPTION", description));
+        }
+        if let Some(summary) = &self.summary {
+            alarm.add_property(ical::Property::new("SUMMARY", summary));
+        }
+        match (self.repeat, &self.duration) {
+            (Some(repeat), Some(duration)) => {
+                alarm.add_property(ical::Property::new("REPEAT", &repeat.to_string()));
+                alarm.add_property(ical::Property::new("DURATION", duration));
+            }
+            (None, None) => {}
+            (Some(_), None) => {
+                return Err(InvalidObject(
+                    ValidationIssue::MissingProperty {
+                        component: "VALARM".into(),
+                        property: "DURATION".into(),
+                    }
+                    .to_string(),
+                ))
+            }
+            (None, Some(_)) => {
+                return Err(InvalidObject(
+                    ValidationIssue::MissingProperty {
+                        component: "VALARM".into(),
+                        property: "REPEAT".into(),
+                    }
+                    .to_string(),
+                ))
+            }
+        }
+        Ok(alarm)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// An event in a CalDAV calendar.
+/// Corresponds to exactly one `.ics` file
+#[derive(Debug, Clone)]
+pub struct Event {
+    etag: Option<String>,
+    url: Url,
+    ical: ical::Ical,
+    schedule_tag: Option<String>,
+    /// Hash of the content as it was fetched from or saved on the server.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    saved_hash: Option<u64>,
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        self.etag == other.etag
+            && self.url == other.url
+            && self.ical == other.ical
+            && self.schedule_tag == other.schedule_tag
+    }
+}
+
+impl Eq for Event {}
+
+/// Whether the value of the given property has a valid format, properties that are not checked are valid.
+fn valid_property_value(property: &ical::Property) -> bool {
+    match property.name.as_str() {
+        "DTSTART" | "DTEND" | "DUE" | "DTSTAMP" | "RECURRENCE-ID" | "CREATED" | "LAST-MODIFIED"
+        | "EXDATE" => property.value.split(',').all(valid_date_time),
+        "RDATE" => property
+            .value
+            .split(',')
+            .all(|v| valid_date_time(v.split('/').next().unwrap_or(v))),
+        #[cfg(feature = "tz")]
+        "RRULE" => crate::recurrence::RRule::parse(&property.value).is_ok(),
+        #[cfg(feature = "tz")]
+        "DURATION" => crate::recurrence::parse_duration(&property.value).is_some(),
+        _ => true,
+    }
+}
+
+/// Whether the given value is a DATE (`20240101`) or DATE-TIME (`20240101T100000` with optional `Z`).
+fn valid_date_time(value: &str) -> bool {
+    let value = value.trim();
+    let (date, time) = match value.split_once('T') {
+        Some((date, time)) => (date, Some(time.strip_suffix('Z').unwrap_or(time))),
+        None => (value, None),
+    };
+    let number = |s: &str, range: std::ops::RangeInclusive<u32>| {
+        s.bytes().all(|b| b.is_ascii_digit())
+            && s.parse::<u32>()
+                .map(|n| range.contains(&n))
+                .unwrap_or(false)
+    };
+    // Only ASCII input can be sliced at byte offsets.
+    if !value.is_ascii() {
+        return false;
+    }
+    let valid_date = date.len() == 8
+        && number(&date[0..4], 0..=9999)
+        && number(&date[4..6], 1..=12)
+        && number(&date[6..8], 1..=31);
+    let valid_time = time
+        .map(|t| {
+            t.len() == 6
+                && number(&t[0..2], 0..=23)
+                && number(&t[2..4], 0..=59)
+                && number(&t[4..6], 0..=60)
+        })
+        .unwrap_or(true);
+    valid_date && valid_time
+}
+
+/// Hash of the url and content of an object ignoring DTSTAMP, which does not change its meaning.
+/// Parameters are hashed in sorted order, so the hash does not depend on their order in the map.
+fn content_hash(url: &Url, ical: &Ical) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    fn hash_component(ical: &Ical, hasher: &mut impl Hasher) {
+        ical.name.hash(hasher);
+        let properties = ical.properties.iter().filter(|p| p.name != "DTSTAMP");
+        properties.clone().count().hash(hasher);
+        for p in properties {
+            p.name.hash(hasher);
+            p.value.hash(hasher);
+            let mut attributes = p.attributes.iter().collect::<Vec<_>>();
+            attributes.sort();
+            attributes.hash(hasher);
+        }
+        ical.children.len().hash(hasher);
+        for child in &ical.children {
+            hash_component(child, hasher);
+        }
+    }
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    url.hash(&mut hasher);
+    hash_component(ical, &mut hasher);
+    hasher.finish()
+}
+
+impl Event {
+    /// Construct a new event.
+    pub fn new(etag: Option<String>, url: Url, ical: ical::Ical) -> Self {
+        Self {
+            etag,
+            url,
+            ical,
+            schedule_tag: None,
+            saved_hash: None,
+        }
+    }
+
+    /// Reconstruct an event from its stored url, etag and iCalendar data, e.g. from a previous session.
+    /// The data is treated as the content stored on the server with the given etag.
+    pub fn from_raw(url: Url, etag: Option<String>, ics: &str) -> Result<Self, MiniCaldavError> {
+        let mut lines = ical::LineIterator::new(ics);
+        let ical = ical::Ical::parse(&mut lines)
+            .map_err(|e| CouldNotParseEvent(ics.to_string(), format!("{:?}", e)))?;
+        Ok(Self {
+            saved_hash: Some(content_hash(&url, &ical)),
+            etag,
+            url,
+            ical,
+            schedule_tag: None,
+        })
+    }
+
+    /// Whether the content or url changed since the event was fetched or last saved.
+    /// Always true for events that were not fetched or saved, e.g. created with `Event::new`.
+    /// DTSTAMP is ignored.
+    pub fn is_modified(&self) -> bool {
+        self.etag.is_none() || self.saved_hash != Some(content_hash(&self.url, &self.ical))
+    }
+
+    /// Check the event for problems servers reject uploads for (RFC 5545, section 3.6.1):
+    /// missing UID, DTSTAMP or DTSTART, both DTEND and DURATION, and malformed date values.
+    /// With the `tz` feature, RRULE and DURATION values are parsed as well.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        let events = self
+            .ical
+            .children
+            .iter()
+            .filter(|c| c.name == "VEVENT")
+            .collect::<Vec<_>>();
+        if events.is_empty() {
+            issues.push(ValidationIssue::NoEvent);
+        }
+        for event in events {
+            let component = event.name.clone();
+            let has = |name: &str| event.get_first_property(name).is_some();
+            for property in ["UID", "DTSTAMP", "DTSTART"] {
+                if !has(property) {
+                    issues.push(ValidationIssue::MissingProperty {
+                        component: component.clone(),
+                        property: property.to_string(),
+                    });
+                }
+            }
+            if has("DTEND") && has("DURATION") {
+                issues.push(ValidationIssue::ConflictingProperties {
+                    component: component.clone(),
+                    properties: ("DTEND".to_string(), "DURATION".to_string()),
+                });
+            }
+            for property in &event.properties {
+                if !valid_property_value(property) {
+                    issues.push(ValidationIssue::InvalidValue {
+                        component: component.clone(),
+                        property: property.name.clone(),
+                        value: property.value.clone(),
+                    });
+                }
+            }
+        }
+        issues
+    }
+
+    /// The properties that were added, removed or changed in `other` compared to this event,
+    /// e.g. to decide whether an upload is needed. DTSTAMP, SEQUENCE and LAST-MODIFIED are ignored.
+    pub fn diff(&self, other: &Event) -> Vec<PropertyChange> {
+        let mut changes = Vec::new();
+        diff_components(
+            &self.ical.name,
+            Some(&self.ical),
+            Some(&other.ical),
+            &mut changes,
+        );
+        changes
+    }
+
+    /// The full url of this event.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn update_url(&mut self, url: Url) {
+        self.url = url;
+    }
+
+    fn get_property(&self, name: &str, datatype: &str) -> Option<Property> {
+        self.ical.get(datatype).and_then(|ical| {
+            ical.properties.iter().find_map(|p| {
+                if p.name == name {
+                    Some(Property::from(p.clone()))
+                } else {
+                    None
+                }
+            })
+        })
+    }
+
+    /// Get the property of the given name or `None`.
+    pub fn pop_property(&mut self, name: &str) -> Option<Property> {
+        self.ical.get_mut("VEVENT").and_then(|ical| {
+            let index = ical.properties.iter().enumerate().find_map(|(i, p)| {
+                if p.name == name {
+                    Some(i)
+                } else {
+                    None
+                }
+            });
+
+            if let Some(index) = index {
+                Some(Property::from(ical.properties.remove(index)))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Remove all properties of the given name from all VEVENTs (including overridden instances)
+    /// and return them.
+    pub fn remove_property(&mut self, name: &str) -> Vec<Property> {
+        let mut removed = Vec::new();
+        for ical in self.ical.children.iter_mut().filter(|c| c.name == "VEVENT") {
+            let (matching, rest): (Vec<_>, _) = std::mem::take(&mut ical.properties)
+                .into_iter()
+                .partition(|p| p.name == name);
+            ical.properties = rest;
+            removed.extend(matching.into_iter().map(Property::from));
+        }
+        removed
+    }
+
+    /// Remove all properties of the given name from the first VEVENT only, which `set` and `add`
+    /// change.
+    fn remove_main_property(&mut self, name: &str) {
+        if let Some(ical) = self.ical.get_mut("VEVENT") {
+            ical.properties.retain(|p| p.name != name);
+        }
+    }
+
+    /// Keep only the properties of all VEVENTs (including overridden instances) for which
+    /// `predicate` returns `true`.
+    pub fn retain_properties<F>(&mut self, mut predicate: F)
+    where
+        F: FnMut(&ical::Property) -> bool,
+    {
+        for ical in self.ical.children.iter_mut().filter(|c| c.name == "VEVENT") {
+            ical.properties.retain(&mut predicate);
+        }
+    }
+
+    /// Rename all properties called `old` to `new` in all VEVENTs (including overridden
+    /// instances), keeping their values and attributes.
+    pub fn rename_property(&mut self, old: &str, new: &str) {
+        for ical in self.ical.children.iter_mut().filter(|c| c.name == "VEVENT") {
+            for p in ical.properties.iter_mut().filter(|p| p.name == old) {
+                p.name = new.into();
+            }
+        }
+    }
+
+    pub fn ical(&self) -> &Ical {
+        &self.ical
+    }
+
+    /// Serialize this event as `.ics` data.
+    /// With the `tz` feature, missing definitions of referenced time zones are added.
+    pub fn to_ics(&self) -> String {
+        #[cfg(feature = "tz")]
+        {
+            let mut ical = self.ical.clone();
+            ical.attach_timezones(crate::tz::vtimezone_for);
+            ical.serialize()
+        }
+        #[cfg(not(feature = "tz"))]
+        self.ical.serialize()
+    }
+
+    /// The main component (VEVENT or VTODO without RECURRENCE-ID) and the overrides of single instances.
+    #[cfg(feature = "tz")]
+    fn recurrence_set(&self) -> Option<(&Ical, Vec<&Ical>)> {
+        let components = self
+            .ical
+            .children
+            .iter()
+            .filter(|c| c.name == "VEVENT" || c.name == "VTODO");
+        let master = components
+            .clone()
+            .find(|c| c.get_first_property("RECURRENCE-ID").is_none())?;
+        let overrides = components
+            .filter(|c| c.name == master.name && c.get_first_property("RECURRENCE-ID").is_some())
+            .collect();
+        Some((master, overrides))
+    }
+
+    /// The start times of all instances, expanded locally, with overridden instances moved.
+    /// Cancelled instances are left out. Floating times and dates are interpreted in `tz`.
+    #[cfg(feature = "tz")]
+    fn instance_starts(&self, tz: chrono_tz::Tz) -> Option<InstanceStarts> {
+        use crate::recurrence::{occurrences, parse_date_time};
+        let (master, overrides) = self.recurrence_set()?;
+        let overridden = overrides
+            .iter()
+            .filter_map(|o| o.get_first_property("RECURRENCE-ID"))
+            .filter_map(|p| parse_date_time(p, tz))
+            .collect();
+        let moved = overrides
+            .iter()
+            .filter(|o| {
+                o.get_first_property("STATUS")
+                    .map(|s| s.value != "CANCELLED")
+                    .unwrap_or(true)
+            })
+            .filter_map(|o| o.get_first_property("DTSTART"))
+            .filter_map(|p| parse_date_time(p, tz))
+            .collect();
+        Some((occurrences(master, tz)?, overridden, moved))
+    }
+
+    /// Whether this event is cancelled although it still exists on the server, see `cancellation`.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation().is_some()
+    }
+
+    /// Why this event is cancelled, or `None` if it has instances that take place.
+    /// Detecting recurrences without any remaining instance needs the `tz` feature.
+    pub fn cancellation(&self) -> Option<Cancellation> {
+        let master = self
+            .ical
+            .children
+            .iter()
+            .filter(|c| c.name == "VEVENT" || c.name == "VTODO")
+            .find(|c| c.get_first_property("RECURRENCE-ID").is_none())?;
+        if master
+            .get_first_property("STATUS")
+            .map(|s| s.value.eq_ignore_ascii_case("CANCELLED"))
+            .unwrap_or(false)
+        {
+            return Some(Cancellation::Status);
+        }
+        #[cfg(feature = "tz")]
+        if self.all_instances_excluded() {
+            return Some(Cancellation::AllInstancesExcluded);
+        }
+        None
+    }
+
+    /// Whether every instance is excluded with EXDATE or cancelled by an override.
+    #[cfg(feature = "tz")]
+    fn all_instances_excluded(&self) -> bool {
+        // Give up on rules with more instances, they are hardly ever excluded completely.
+        const MAX_INSTANCES: usize = 10_000;
+        let Some((master, _)) = self.recurrence_set() else {
+            return false;
+        };
+        let unbounded = master
+            .get_first_property("RRULE")
+            .and_then(|p| crate::recurrence::RRule::parse(&p.value).ok())
+            .map(|rule| rule.count.is_none() && rule.until.is_none())
+            .unwrap_or(false);
+        if unbounded {
+            return false;
+        }
+        let Some((occurrences, overridden, moved)) = self.instance_starts(chrono_tz::UTC) else {
+            return false;
+        };
+        if !moved.is_empty() {
+            return false;
+        }
+        for (i, start) in occurrences.enumerate() {
+            if i >= MAX_INSTANCES || !overridden.contains(&start) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// The start of the first instance of this event that starts after `after`.
+    /// Recurrences (RRULE, RDATE, EXDATE and overridden instances) are expanded locally.
+    /// Floating times and all-day dates are interpreted in `tz`, the result is in `tz`.
+    #[cfg(feature = "tz")]
+    pub fn next_occurrence(
+        &self,
+        after: chrono::DateTime<chrono::Utc>,
+        tz: chrono_tz::Tz,
+    ) -> Option<chrono::DateTime<chrono_tz::Tz>> {
+        let (mut occurrences, overridden, moved) = self.instance_starts(tz)?;
+        let next = occurrences.find(|d| *d > after && !overridden.contains(d));
+        next.into_iter()
+            .chain(moved.into_iter().filter(|d| *d > after))
+            .min()
+            .map(|d| d.with_timezone(&tz))
+    }
+
+    /// The instances of this event overlapping `[start, end)` as separate components with RECURRENCE-ID,
+    /// like the events returned by `get_events` with `expanded`. Floating times and dates are interpreted in `tz`.
+    /// Returns `None` if no instance overlaps the range.
+    #[cfg(feature = "tz")]
+    pub fn expand(
+        &self,
+        start: chrono::DateTime<chrono::Utc>,
+        end: chrono::DateTime<chrono::Utc>,
+        tz: chrono_tz::Tz,
+    ) -> Option<Event> {
+        Some(Event {
+            ical: crate::recurrence::expand(&self.ical, start, end, tz)?,
+            ..self.clone()
+        })
+    }
+
+    /// The instances of this event overlapping `[start, end)`, sorted by start.
+    /// Recurrences are expanded locally, for servers that do not support the `expand` element of a calendar-query.
+    /// Floating times and dates are interpreted in `tz`.
+    #[cfg(feature = "tz")]
+    pub fn occurrences(
+        &self,
+        start: chrono::DateTime<chrono::Utc>,
+        end: chrono::DateTime<chrono::Utc>,
+        tz: chrono_tz::Tz,
+    ) -> Vec<crate::recurrence::Occurrence> {
+        crate::recurrence::occurrences_between(&self.ical, start, end, tz)
+    }
+
+    /// The number of instances of this event that start at or before `until`.
+    /// Floating times and all-day dates are interpreted in the time zone of `until`.
+    #[cfg(feature = "tz")]
+    pub fn occurrence_count_until(&self, until: chrono::DateTime<chrono_tz::Tz>) -> usize {
+        match self.instance_starts(until.timezone()) {
+            Some((occurrences, overridden, moved)) => {
+                occurrences
+                    .take_while(|d| *d <= until)
+                    .filter(|d| !overridden.contains(d))
+                    .count()
+                    + moved.iter().filter(|d| **d <= until).count()
+            }
+            None => 0,
+        }
+    }
+
+    /// The parsed DTSTART of the main component (VEVENT or VTODO without RECURRENCE-ID).
+    #[cfg(feature = "tz")]
+    pub fn dtstart(&self) -> Option<crate::recurrence::DateTimeValue> {
+        self.date_time_property("DTSTART")
+    }
+
+    /// The parsed DTEND of the main component. Events with a DURATION instead have no DTEND.
+    #[cfg(feature = "tz")]
+    pub fn dtend(&self) -> Option<crate::recurrence::DateTimeValue> {
+        self.date_time_property("DTEND")
+    }
+
+    /// The parsed DUE of the main component of a todo.
+    #[cfg(feature = "tz")]
+    pub fn due(&self) -> Option<crate::recurrence::DateTimeValue> {
+        self.date_time_property("DUE")
+    }
+
+    #[cfg(feature = "tz")]
+    fn date_time_property(&self, name: &str) -> Option<crate::recurrence::DateTimeValue> {
+        let (master, _) = self.recurrence_set()?;
+        crate::recurrence::DateTimeValue::parse(master.get_first_property(name)?)
+    }
+
+    /// All values of the EXDATE properties of the main component.
+    #[cfg(feature = "tz")]
+    pub fn exdates(&self) -> Vec<crate::recurrence::DateTimeValue> {
+        self.date_time_values("EXDATE")
+    }
+
+    /// All values of the RDATE properties of the main component, periods as their start.
+    #[cfg(feature = "tz")]
+    pub fn rdates(&self) -> Vec<crate::recurrence::DateTimeValue> {
+        self.date_time_values("RDATE")
+    }
+
+    #[cfg(feature = "tz")]
+    fn date_time_values(&self, name: &str) -> Vec<crate::recurrence::DateTimeValue> {
+        let Some((master, _)) = self.recurrence_set() else {
+            return Vec::new();
+        };
+        master
+            .properties
+            .iter()
+            .filter(|p| p.name == name)
+            .flat_map(crate::recurrence::DateTimeValue::parse_all)
+            .collect()
+    }
+
+    /// Exclude the instance starting at `date`, e.g. to delete a single instance of a recurring event.
+    /// The value is added to an EXDATE with the same TZID or VALUE=DATE, or as a new EXDATE.
+    /// Pass the date in the time zone of DTSTART, some servers only match EXDATEs in that zone.
+    #[cfg(feature = "tz")]
+    pub fn add_exdate(&mut self, date: crate::recurrence::DateTimeValue) {
+        if self.exdates().iter().any(|d| d.same_as(&date)) {
+            return;
+        }
+        let Some(master) = self.main_component_mut() else {
+            return;
+        };
+        let same_type = |p: &&mut ical::Property| {
+            p.name == "EXDATE"
+                && (p.attributes.get("VALUE").map(|v| v.as_str()) == Some("DATE")) == date.is_date()
+                && p.attributes.get("TZID").map(|t| t.as_str()) == date.tzid()
+        };
+        match master.properties.iter_mut().find(same_type) {
+            Some(exdate) => {
+                exdate.value.push(',');
+                exdate.value.push_str(&date.value());
+            }
+            None => master.add_property(date.to_property("EXDATE")),
+        }
+    }
+
+    /// Remove `date` from the EXDATEs, so the instance is part of the event again.
+    /// EXDATEs without values left are removed. Returns whether the date was excluded.
+    #[cfg(feature = "tz")]
+    pub fn remove_exdate(&mut self, date: &crate::recurrence::DateTimeValue) -> bool {
+        let Some(master) = self.main_component_mut() else {
+            return false;
+        };
+        let mut removed = false;
+        for exdate in master.properties.iter_mut().filter(|p| p.name == "EXDATE") {
+            let values = crate::recurrence::DateTimeValue::parse_all(exdate);
+            let kept = values
+                .iter()
+                .filter(|v| !v.same_as(date))
+                .map(|v| v.value())
+                .collect::<Vec<_>>();
+            if kept.len() != values.len() {
+                removed = true;
+                exdate.value = kept.join(",");
+            }
+        }
+        master
+            .properties
+            .retain(|p| p.name != "EXDATE" || !p.value.is_empty());
+        removed
+    }
+
+    /// The main component (VEVENT or VTODO without RECURRENCE-ID).
+    #[cfg(feature = "tz")]
+    fn main_component_mut(&mut self) -> Option<&mut Ical> {
+        self.ical
+            .children
+            .iter_mut()
+            .filter(|c| c.name == "VEVENT" || c.name == "VTODO")
+            .find(|c| c.get_first_property("RECURRENCE-ID").is_none())
+    }
+
+    /// Add VTIMEZONE components for all referenced time zones that are not defined in this event.
+    #[cfg(feature = "tz")]
+    pub fn add_missing_timezones(&mut self) {
+        self.ical.attach_timezones(crate::tz::vtimezone_for);
+    }
+
+    /// `add_missing_timezones` with the `tz` feature, nothing without it.
+    fn attach_missing_timezones(&mut self) {
+        #[cfg(feature = "tz")]
+        self.add_missing_timezones();
+    }
+
+    /// Rewrite Windows time zone names (as written by Outlook) used as TZID to IANA TZIDs.
+    /// Call this before `save_event` to store the event with standard TZIDs.
+    pub fn normalize_timezones(&mut self) {
+        self.ical.normalize_tzids();
+    }
+
+    pub fn ical_mut(&mut self) -> &mut Ical {
+        &mut self.ical
+    }
+
+    pub fn add(&mut self, property: Property) {
+        if let Some(ical) = self.ical.get_mut("VEVENT") {
+            ical.properties.push(property.into());
+        }
+    }
+
+    pub fn property(&self, name: &str) -> Option<Property> {
+        self.get_property(name, "VEVENT")
+    }
+
+    /// Get the property of the given name or `None`.
+    pub fn property_todo(&self, name: &str) -> Option<Property> {
+        self.get_property(name, "VTODO")
+    }
+
+    /// Get the value of the given property name or `None`.
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.ical.get("VEVENT").and_then(|ical| {
+            ical.properties
+                .iter()
+                .find_map(|p| if p.name == name { Some(&p.value) } else { None })
+        })
+    }
+
+    /// Get the value of the given TEXT property (e.g. DESCRIPTION or LOCATION) as plain text,
+    /// with escaped line breaks, commas, semicolons and backslashes converted.
+    pub fn get_unescaped(&self, name: &str) -> Option<String> {
+        self.get(name).map(|value| ical::unescape_text(value))
+    }
+
+    /// Set the given TEXT property to the given plain text, escaping it as needed, see `get_unescaped`.
+    pub fn set_escaped(&mut self, name: &str, text: &str) {
+        self.set(name, &ical::escape_text(text));
+    }
+
+    /// The CATEGORIES of this event, from all CATEGORIES properties.
+    pub fn categories(&self) -> Vec<String> {
+        self.text_list("CATEGORIES")
+    }
+
+    /// Replace the CATEGORIES of this event. An empty list removes them.
+    pub fn set_categories(&mut self, categories: Vec<String>) {
+        self.set_text_list("CATEGORIES", &categories);
+    }
+
+    /// The RESOURCES of this event, e.g. rooms or equipment.
+    pub fn resources(&self) -> Vec<String> {
+        self.text_list("RESOURCES")
+    }
+
+    /// Replace the RESOURCES of this event. An empty list removes them.
+    pub fn set_resources(&mut self, resources: Vec<String>) {
+        self.set_text_list("RESOURCES", &resources);
+    }
+
+    fn text_list(&self, name: &str) -> Vec<String> {
+        self.get_all(name)
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|value| ical::split_text_list(value))
+            .collect()
+    }
+
+    fn set_text_list(&mut self, name: &str, values: &[String]) {
+        self.remove_main_property(name);
+        if !values.is_empty() {
+            self.add(ical::Property::new(name, &ical::join_text_list(values)).into());
+        }
+    }
+
+    /// Get the value of the given property name or `None`.
+    pub fn get_all(&self, name: &str) -> Option<Vec<&String>> {
+        self.ical.get("VEVENT").map(|ical| {
+            ical.properties
+                .iter()
+                .filter_map(|p| if p.name == name { Some(&p.value) } else { None })
+                .collect::<Vec<&String>>()
+        })
+    }
+
+    /// Set the value of the given property name or create a new property.
+    pub fn set(&mut self, name: &str, value: &str) {
+        match self
+            .ical
+            .get_mut("VEVENT")
+            .and_then(|e| e.properties.iter_mut().find(|p| p.name == name))
+        {
+            Some(p) => {
+                p.value = value.into();
+            }
+            None => self.add(Property::new(name, value)),
+        }
+    }
+
+    pub fn set_property_attribute(&mut self, name: &str, attr_name: &str, attr_value: &str) {
+        if let Some(p) = self
+            .ical
+            .get_mut("VEVENT")
+            .and_then(|e| e.properties.iter_mut().find(|p| p.name == name))
+        {
+            p.attributes.insert(attr_name.into(), attr_value.into());
+        }
+    }
+
+    pub fn remove_property_attribute(&mut self, name: &str, attr_name: &str) {
+        if let Some(p) = self
+            .ical
+            .get_mut("VEVENT")
+            .and_then(|e| e.properties.iter_mut().find(|p| p.name == name))
+        {
+            p.attributes.remove(attr_name);
+        }
+    }
+
+    /// Set LOCATION, GEO and X-APPLE-STRUCTURED-LOCATION of this event together,
+    /// so clients that support it (e.g. Apple Calendar) show the location on a map.
+    /// `text` is the plain (unescaped) location text.
+    pub fn set_location_with_geo(&mut self, text: &str, lat: f64, lon: f64) {
+        self.set("LOCATION", &ical::escape_text(text));
+        self.set("GEO", &format!("{};{}", lat, lon));
+        self.remove_main_property("X-APPLE-STRUCTURED-LOCATION");
+        let title = ical::quote_param_value(text);
+        self.add(Property::new_with_attributes(
+            "X-APPLE-STRUCTURED-LOCATION",
+            &format!("geo:{},{}", lat, lon),
+            vec![
+                ("VALUE", "URI"),
+                ("X-ADDRESS", &title),
+                ("X-APPLE-RADIUS", "70"),
+                ("X-TITLE", &title),
+            ],
+        ));
+    }
+
+    /// Get the ATTENDEE with the given calendar address (with or without `mailto:`) or `None`.
+    pub fn attendee(&self, address: &str) -> Option<Attendee> {
+        self.ical.get("VEVENT").and_then(|ical| {
+            ical.properties
+                .iter()
+                .find(|p| p.name == "ATTENDEE" && same_address(&p.value, address))
+                .map(Attendee::from)
+        })
+    }
+
+    /// All ATTENDEEs of this event.
+    pub fn attendees(&self) -> Vec<Attendee> {
+        self.ical
+            .get("VEVENT")
+            .map(|ical| {
+                ical.properties
+                    .iter()
+                    .filter(|p| p.name == "ATTENDEE")
+                    .map(Attendee::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Add the given attendee to all VEVENTs (including overridden instances),
+    /// or update its parameters where it already exists.
+    pub fn add_attendee(&mut self, attendee: &Attendee) {
+        for ical in self.ical.children.iter_mut().filter(|c| c.name == "VEVENT") {
+            match ical
+                .properties
+                .iter_mut()
+                .find(|p| p.name == "ATTENDEE" && same_address(&p.value, &attendee.address))
+            {
+                Some(p) => attendee.apply_to(p),
+                None => {
+                    let mut p = ical::Property::new("ATTENDEE", &attendee.address);
+                    attendee.apply_to(&mut p);
+                    ical.properties.push(p);
+                }
+            }
+        }
+    }
+
+    /// Remove the attendee with the given address (with or without `mailto:`) from all VEVENTs.
+    /// Returns whether the attendee was found.
+    pub fn remove_attendee(&mut self, address: &str) -> bool {
+        let mut removed = false;
+        for ical in self.ical.children.iter_mut().filter(|c| c.name == "VEVENT") {
+            let count = ical.properties.len();
+            ical.properties
+                .retain(|p| !(p.name == "ATTENDEE" && same_address(&p.value, address)));
+            removed |= ical.properties.len() != count;
+        }
+        removed
+    }
+
+    /// Set the PARTSTAT of the attendee with the given address in all VEVENTs, e.g. to `ACCEPTED`.
+    /// Returns whether the attendee was found.
+    pub fn set_partstat(&mut self, address: &str, partstat: &str) -> bool {
+        let mut found = false;
+        for ical in self.ical.children.iter_mut().filter(|c| c.name == "VEVENT") {
+            for p in ical
+                .properties
+                .iter_mut()
+                .filter(|p| p.name == "ATTENDEE" && same_address(&p.value, address))
+            {
+                p.attributes.insert("PARTSTAT".into(), partstat.to_string());
+                p.attributes.remove("RSVP");
+                found = true;
+            }
+        }
+        found
+    }
+
+    /// Get the SCHEDULE-STATUS of all ATTENDEEs and the ORGANIZER of this event, keyed by calendar address.
+    /// Properties without SCHEDULE-STATUS are omitted.
+    pub fn schedule_statuses(&self) -> Vec<(String, Vec<ScheduleStatus>)> {
+        self.ical
+            .get("VEVENT")
+            .map(|ical| {
+                ical.properties
+                    .iter()
+                    .filter(|p| p.name == "ATTENDEE" || p.name == "ORGANIZER")
+                    .map(|p| (p.value.clone(), ScheduleStatus::parse_all(p)))
+                    .filter(|(_, status)| !status.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Follow the DELEGATED-TO parameters starting at the attendee with the given address.
+    /// The returned list starts with the given attendee and ends with the last delegate.
+    /// If an attendee delegated to multiple attendees, only the first one is followed.
+    pub fn delegation_chain(&self, address: &str) -> Vec<Attendee> {
+        let mut chain: Vec<Attendee> = Vec::new();
+        let mut next = self.attendee(address);
+        while let Some(attendee) = next.take() {
+            if chain
+                .iter()
+                .any(|a| same_address(&a.address, &attendee.address))
+            {
+                break;
+            }
+            next = attendee
+                .delegated_to
+                .first()
+                .and_then(|to| self.attendee(to));
+            chain.push(attendee);
+        }
+        chain
+    }
+
+    /// Delegate the participation of attendee `from` to the calendar address `to` in all VEVENTs.
+    /// The delegator gets PARTSTAT=DELEGATED and DELEGATED-TO, the delegate DELEGATED-FROM.
+    /// The delegate is added as ATTENDEE if it does not exist yet.
+    /// Returns whether `from` was found, the event is left unchanged otherwise.
+    pub fn delegate(&mut self, from: &str, to: &str) -> bool {
+        let is_attendee = |p: &ical::Property, address: &str| {
+            p.name == "ATTENDEE" && same_address(&p.value, address)
+        };
+        let add_param_value = |p: &mut ical::Property, name: &str, value: &str| {
+            let mut values = p
+                .attributes
+                .get(name)
+                .map(|v| ical::split_param_values(v))
+                .unwrap_or_default();
+            if !values.iter().any(|v| same_address(v, value)) {
+                values.push(value.to_string());
+            }
+            p.attributes
+                .insert(name.into(), ical::join_param_values(&values));
+        };
+
+        let mut found = false;
+        for ical in self.ical.children.iter_mut().filter(|c| c.name == "VEVENT") {
+            let Some(delegator) = ical.properties.iter_mut().find(|p| is_attendee(p, from)) else {
+                continue;
+            };
+            add_param_value(delegator, "DELEGATED-TO", to);
+            delegator
+                .attributes
+                .insert("PARTSTAT".into(), "DELEGATED".into());
+            let from = delegator.value.clone();
+            found = true;
+            match ical.properties.iter_mut().find(|p| is_attendee(p, to)) {
+                Some(delegate) => add_param_value(delegate, "DELEGATED-FROM", &from),
+                None => {
+                    let mut delegate = ical::Property::new_with_attributes(
+                        "ATTENDEE",
+                        to,
+                        vec![("PARTSTAT", "NEEDS-ACTION"), ("RSVP", "TRUE")],
+                    );
+                    add_param_value(&mut delegate, "DELEGATED-FROM", &from);
+                    ical.properties.push(delegate);
+                }
+            }
+        }
+        found
+    }
+
+    /// Get all properties of this event.
+    fn get_properties(&self, datatype: &str) -> Vec<(&String, &String)> {
+        self.ical
+            .get(datatype)
+            .map(|ical| {
+                ical.properties
+                    .iter()
+                    .map(|p| (&p.name, &p.value))
+                    .collect::<Vec<(&String, &String)>>()
+            })
+            .unwrap_or_else(|| {
+                error!("Could not get properties: No VEVENT section.");
+                Vec::new()
+            })
+    }
+
+    pub fn into_properties(self) -> Vec<Property> {
+        for ical in self.ical.children {
+            if ical.name == "VEVENT" {
+                return ical.properties.into_iter().map(Property::from).collect();
+            }
+        }
+        Vec::new()
+    }
+
+    /// Get all properties of this event.
+    pub fn properties(&self) -> Vec<(&String, &String)> {
+        self.get_properties("VEVENT")
+    }
+
+    /// Get all properties of this todo.
+    pub fn properties_todo(&self) -> Vec<(&String, &String)> {
+        self.get_properties("VTODO")
+    }
+
+    /// Iterate over all properties of this event in the order they appear in the source.
+    pub fn properties_iter(&self) -> impl Iterator<Item = &ical::Property> {
+        self.ical
+            .get("VEVENT")
+            .into_iter()
+            .flat_map(|ical| ical.properties.iter())
+    }
+
+    /// The number of properties of this event.
+    pub fn property_count(&self) -> usize {
+        self.ical
+            .get("VEVENT")
+            .map(|ical| ical.properties.len())
+            .unwrap_or(0)
+    }
+
+    /// Get the property at the given index or `None`.
+    pub fn property_at(&self, index: usize) -> Option<Property> {
+        self.ical
+            .get("VEVENT")
+            .and_then(|ical| ical.properties.get(index))
+            .map(|p| Property::from(p.clone()))
+    }
+
+    /// Get the index of the first property of the given name or `None`.
+    pub fn property_index(&self, name: &str) -> Option<usize> {
+        self.ical
+            .get("VEVENT")
+            .and_then(|ical| ical.properties.iter().position(|p| p.name == name))
+    }
+
+    /// Replace the property at the given index. Returns the old property or `None` if the index is out of bounds.
+    pub fn set_property_at(&mut self, index: usize, property: Property) -> Option<Property> {
+        self.ical
+            .get_mut("VEVENT")
+            .and_then(|ical| ical.properties.get_mut(index))
+            .map(|p| Property::from(std::mem::replace(p, property.into())))
+    }
+
+    /// Insert a property at the given index. If the index is out of bounds the property is appended.
+    pub fn insert_property_at(&mut self, index: usize, property: Property) {
+        if let Some(ical) = self.ical.get_mut("VEVENT") {
+            let index = index.min(ical.properties.len());
+            ical.properties.insert(index, property.into());
+        }
+    }
+
+    /// Remove the property at the given index. Returns `None` if the index is out of bounds.
+    pub fn remove_property_at(&mut self, index: usize) -> Option<Property> {
+        self.ical.get_mut("VEVENT").and_then(|ical| {
+            if index < ical.properties.len() {
+                Some(Property::from(ical.properties.remove(index)))
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn etag(&self) -> Option<&String> {
+        self.etag.as_ref()
+    }
+
+    pub fn builder(url: Url) -> EventBuilder {
+        EventBuilder {
+            url,
+            etag: None,
+            properties: vec![],
+            defaults: None,
+            prodid: None,
+            timezones_by_reference: false,
+            alarms: vec![],
+        }
+    }
+
+    pub fn set_etag(&mut self, etag: Option<String>) {
+        self.etag = etag
+    }
+
+    /// The `Schedule-Tag` of this event on scheduling enabled servers (RFC 6638).
+    /// If set, saving the event only succeeds if the schedule tag on the server still matches.
+    pub fn schedule_tag(&self) -> Option<&String> {
+        self.schedule_tag.as_ref()
+    }
+
+    pub fn set_schedule_tag(&mut self, schedule_tag: Option<String>) {
+        self.schedule_tag = schedule_tag
+    }
+
+    pub fn add_component(&mut self, ical: Ical) {
+        self.ical_mut().add_component(ical);
+    }
+
+    pub fn get_first_component_mut(&mut self) -> Option<&mut Ical> {
+        self.ical.children.first_mut()
+    }
+
+    /// Get the override of the instance with the given RECURRENCE-ID, creating it if it does not exist yet.
+    /// A new override is a copy of the main component (same UID and SEQUENCE) without RRULE, RDATE and EXDATE,
+    /// moved to the start of the instance. The RECURRENCE-ID is written like DTSTART.
+    /// Returns `None` if the event has no main component with a valid DTSTART.
+    #[cfg(feature = "tz")]
+    pub fn create_override(
+        &mut self,
+        recurrence_id: crate::recurrence::DateTimeValue,
+    ) -> Option<&mut Ical> {
+        use crate::recurrence::DateTimeValue;
+
+        let (master, overrides) = self.recurrence_set()?;
+        let dtstart = DateTimeValue::parse(master.get_first_property("DTSTART")?)?;
+        let start = recurrence_id.like(&dtstart);
+        let existing = overrides.iter().position(|o| {
+            o.get_first_property("RECURRENCE-ID")
+                .and_then(DateTimeValue::parse)
+                .map(|r| r.same_as(&start))
+                .unwrap_or(false)
+        });
+        if let Some(index) = existing {
+            let recurid = overrides[index]
+                .get_first_property("RECURRENCE-ID")?
+                .value
+                .clone();
+            return self.get_component_by_recurid(&recurid);
+        }
+
+        let offset = start.to_utc(chrono_tz::UTC) - dtstart.to_utc(chrono_tz::UTC);
+        let mut instance = master.clone();
+        for prop in &mut instance.properties {
+            if prop.name == "DTEND" || prop.name == "DUE" {
+                if let Some(end) = DateTimeValue::parse(prop) {
+                    *prop = end.shifted(offset).to_property(&prop.name);
+                }
+            }
+        }
+        instance.properties.retain(|p| {
+            !matches!(
+                p.name.as_str(),
+                "DTSTART" | "RRULE" | "RDATE" | "EXDATE" | "RECURRENCE-ID"
+            )
+        });
+        instance.properties.insert(0, start.to_property("DTSTART"));
+        instance.add_property(start.to_property("RECURRENCE-ID"));
+        self.ical.add_component(instance);
+        self.ical.children.last_mut()
+    }
+
+    /// Complete the todo at `completed`.
+    /// A recurring todo is advanced to its next instance instead (RFC 5545, section 3.8.5.3):
+    /// DTSTART and DUE move to the next instance, a COUNT in the RRULE is reduced and the status is reset.
+    /// The todo is completed once no instance is left. Floating times and dates are interpreted in `floating`.
+    /// Returns the new DTSTART (or DUE, for todos without DTSTART) of an advanced todo.
+    #[cfg(feature = "tz")]
+    pub fn complete_todo(
+        &mut self,
+        completed: chrono::DateTime<chrono::Utc>,
+        floating: chrono_tz::Tz,
+    ) -> Option<crate::recurrence::DateTimeValue> {
+        use crate::recurrence::{anchor_property, occurrences, parse_date_time, DateTimeValue};
+
+        let todo = self
+            .ical
+            .children
+            .iter_mut()
+            .filter(|c| c.name == "VTODO")
+            .find(|c| c.get_first_property("RECURRENCE-ID").is_none())?;
+        let next = anchor_property(todo).and_then(|anchor| {
+            let name = anchor.name.clone();
+            let current = DateTimeValue::parse(anchor)?;
+            let start = parse_date_time(anchor, floating)?;
+            let next = occurrences(todo, floating)?.find(|s| *s > start)?;
+            let next = match current {
+                DateTimeValue::Date(_) => DateTimeValue::Date(next.date_naive()),
+                DateTimeValue::Floating(_) => DateTimeValue::Floating(next.naive_local()),
+                DateTimeValue::Utc(_) => DateTimeValue::Utc(next.with_timezone(&chrono::Utc)),
+                DateTimeValue::Zoned(_) => DateTimeValue::Zoned(next),
+            };
+            Some((name, current, next))
+        });
+        let Some((anchor, current, next)) = next else {
+            mark_completed(todo, completed.timestamp());
+            return None;
+        };
+
+        let offset = next.to_utc(floating) - current.to_utc(floating);
+        for prop in &mut todo.properties {
+            if prop.name == anchor {
+                *prop = next.to_property(&anchor);
+            } else if prop.name == "DUE" {
+                if let Some(due) = DateTimeValue::parse(prop) {
+                    *prop = due.shifted(offset).to_property("DUE");
+                }
+            } else if prop.name == "RRULE" {
+                prop.value = prop
+                    .value
+                    .split(';')
+                    .map(|part| match part.split_once('=') {
+                        Some((key, count)) if key.eq_ignore_ascii_case("COUNT") => count
+                            .parse::<u32>()
+                            .map(|c| format!("COUNT={}", c.saturating_sub(1).max(1)))
+                            .unwrap_or_else(|_| part.to_string()),
+                        _ => part.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(";");
+            }
+        }
+        todo.properties
+            .retain(|p| !matches!(p.name.as_str(), "COMPLETED" | "PERCENT-COMPLETE"));
+        if todo.get_first_property("STATUS").is_some() {
+            todo.replace_first_property("STATUS", "NEEDS-ACTION", vec![]);
+        }
+        Some(next)
+    }
+
+    /// The alarms of the main component (VEVENT or VTODO without RECURRENCE-ID).
+    pub fn alarms(&self) -> Vec<Alarm> {
+        self.ical
+            .children
+            .iter()
+            .filter(|c| c.name == "VEVENT" || c.name == "VTODO")
+            .find(|c| c.get_first_property("RECURRENCE-ID").is_none())
+            .map(|c| {
+                c.children
+                    .iter()
+                    .filter(|a| a.name == "VALARM")
+                    .filter_map(Alarm::from_ical)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn get_component_by_recurid(&mut self, recurid: &str) -> Option<&mut Ical> {
+        // search for the first component with name RECURRENCE-ID
+        // where the value matches `recurid`
+        // In other words, return the component with matching RECURRENCE-ID
+        // if it exists
+        self.ical.children.iter_mut().find(|comp| {
+            comp.get_first_property("RECURRENCE-ID")
+                .map(|p| p.value == recurid)
+                .unwrap_or(false)
+        })
+    }
+}
+
+/// Formats the ICAL data of the event, e.g. to write it to an `.ics` file.
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.ical.fmt(f)
+    }
+}
+
+/// The url of events parsed with `FromStr`, which do not know where they are stored.
+const UNSAVED_EVENT_URL: &str = "urn:minicaldav:unsaved";
+
+/// Parses the ICAL data of a new event, e.g. read from an `.ics` file.
+/// The event has no url on a server yet: set one with `Event::update_url` before saving it.
+impl std::str::FromStr for Event {
+    type Err = MiniCaldavError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ical = ical::Ical::parse(&mut ical::LineIterator::new(s))
+            .map_err(|e| CouldNotParseEvent(s.to_string(), format!("{:?}", e)))?;
+        Ok(Self::new(
+            None,
+            Url::parse(UNSAVED_EVENT_URL).unwrap(),
+            ical,
+        ))
+    }
+}
+
+/// The STATUS of a todo (RFC 5545, section 3.8.1.11).
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TodoStatus {
+    NeedsAction,
+    InProcess,
+    Completed,
+    Cancelled,
+    /// A non-standard status, with its value.
+    Other(String),
+}
+
+impl TodoStatus {
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "NEEDS-ACTION" => Self::NeedsAction,
+            "IN-PROCESS" => Self::InProcess,
+            "COMPLETED" => Self::Completed,
+            "CANCELLED" => Self::Cancelled,
+            _ => Self::Other(value.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::NeedsAction => "NEEDS-ACTION",
+            Self::InProcess => "IN-PROCESS",
+            Self::Completed => "COMPLETED",
+            Self::Cancelled => "CANCELLED",
+            Self::Other(value) => value,
+        }
+    }
+}
+
+/// Which todos `get_todos` returns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TodoFilter {
+    #[default]
+    All,
+    /// Todos that are neither completed nor cancelled.
+    Open,
+    Completed,
+}
+
+impl TodoFilter {
+    /// The query letting the server filter the todos, `None` for all todos.
+    /// Servers can only filter by single properties, the result is checked with `matches` as well.
+    /// A todo is completed by STATUS, COMPLETED or PERCENT-COMPLETE, so completed todos are
+    /// fetched unfiltered: a filter on any one of them would miss todos with only the others.
+    fn query(&self) -> Option<caldav::CalendarQuery> {
+        match self {
+            Self::All | Self::Completed => None,
+            Self::Open => Some(
+                caldav::CalendarQuery::todos()
+                    .prop_filter(caldav::PropFilter::is_not_defined("COMPLETED")),
+            ),
+        }
+    }
+
+    pub fn matches(&self, todo: &Todo) -> bool {
+        match self {
+            Self::All => true,
+            Self::Open => !todo.is_completed() && todo.status() != Some(TodoStatus::Cancelled),
+            Self::Completed => todo.is_completed(),
+        }
+    }
+}
+
+/// The RELTYPE of a RELATED-TO property (RFC 5545, section 3.2.15).
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelationType {
+    /// The related component is the parent, the default without RELTYPE.
+    Parent,
+    Child,
+    Sibling,
+    /// A non-standard type, with its value.
+    Other(String),
+}
+
+impl RelationType {
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "PARENT" => Self::Parent,
+            "CHILD" => Self::Child,
+            "SIBLING" => Self::Sibling,
+            _ => Self::Other(value.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Parent => "PARENT",
+            Self::Child => "CHILD",
+            Self::Sibling => "SIBLING",
+            Self::Other(value) => value,
+        }
+    }
+}
+
+/// A RELATED-TO property: the UID of the related component and how it is related.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relation {
+    pub uid: String,
+    pub rel_type: RelationType,
+}
+
+/// A todo with its subtasks, see `todo_tree`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TodoNode {
+    pub todo: Todo,
+    pub children: Vec<TodoNode>,
+}
+
+/// Arrange the given todos in trees of subtasks by their RELATED-TO properties.
+/// A todo is a subtask if it names the parent with RELTYPE=PARENT or the parent names it with
+/// RELTYPE=CHILD. Todos whose parent is not in the list are roots, cycles of parents are broken by
+/// making their first todo in the given order a root. Todos that only lead into a cycle keep their
+/// parent. The order of the given todos is kept among siblings.
+pub fn todo_tree(todos: Vec<Todo>) -> Vec<TodoNode> {
+    let uids = todos
+        .iter()
+        .map(|todo| todo.uid().cloned())
+        .collect::<Vec<_>>();
+    let index_of = |uid: &str| uids.iter().position(|u| u.as_deref() == Some(uid));
+    let mut parents = vec![None; todos.len()];
+    for (index, todo) in todos.iter().enumerate() {
+        for relation in todo.relations() {
+            let Some(other) = index_of(&relation.uid).filter(|&other| other != index) else {
+                continue;
+            };
+            match relation.rel_type {
+                RelationType::Parent => parents[index] = parents[index].or(Some(other)),
+                RelationType::Child => parents[other] = parents[other].or(Some(index)),
+                _ => {}
+            }
+        }
+    }
+    // Follow the parents of each todo not seen yet. A walk that comes back to a todo of the same
+    // walk has found a cycle, which is broken once at its first todo.
+    let mut visited = vec![false; todos.len()];
+    for start in 0..todos.len() {
+        let mut path = Vec::new();
+        let mut current = Some(start);
+        while let Some(index) = current.filter(|&index| !visited[index]) {
+            visited[index] = true;
+            path.push(index);
+            current = parents[index];
+        }
+        let Some(position) = current.and_then(|stop| path.iter().position(|&i| i == stop)) else {
+            continue;
+        };
+        let first = *path[position..].iter().min().expect("a cycle has todos");
+        parents[first] = None;
+    }
+
+    fn build(index: usize, todos: &mut [Option<Todo>], parents: &[Option<usize>]) -> TodoNode {
+        let children = (0..parents.len())
+            .filter(|&child| parents[child] == Some(index))
+            .map(|child| build(child, todos, parents))
+            .collect();
+        TodoNode {
+            todo: todos[index].take().expect("each todo is in the tree once"),
+            children,
+        }
+    }
+    let mut todos = todos.into_iter().map(Some).collect::<Vec<_>>();
+    (0..parents.len())
+        .filter(|&index| parents[index].is_none())
+        .map(|index| build(index, &mut todos, &parents))
+        .collect()
+}
+
+/// Set STATUS:COMPLETED, PERCENT-COMPLETE:100 and COMPLETED (given in seconds since the epoch)
+/// of the given VTODO, see `Todo::complete` and `Event::complete_todo`.
+fn mark_completed(todo: &mut Ical, completed: i64) {
+    let completed = caldav::format_utc_seconds(completed);
+    for (name, value) in [
+        ("STATUS", TodoStatus::Completed.as_str()),
+        ("PERCENT-COMPLETE", "100"),
+        ("COMPLETED", &completed),
+    ] {
+        match todo.properties.iter_mut().find(|p| p.name == name) {
+            Some(p) => p.value = value.into(),
+            None => todo.properties.push(ical::Property::new(name, value)),
+        }
+    }
+}
+
+/// A todo in a CalDAV calendar, corresponding to one `.ics` file with a VTODO.
+/// Save and remove todos with the functions for events, see `into_event`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Todo {
+    event: Event,
+}
+
+impl From<Event> for Todo {
+    fn from(event: Event) -> Self {
+        Self { event }
+    }
+}
+
+impl From<Todo> for Event {
+    fn from(todo: Todo) -> Self {
+        todo.event
+    }
+}
+
+impl Todo {
+    pub fn event(&self) -> &Event {
+        &self.event
+    }
+
+    pub fn into_event(self) -> Event {
+        self.event
+    }
+
+    pub fn url(&self) -> &Url {
+        self.event.url()
+    }
+
+    pub fn etag(&self) -> Option<&String> {
+        self.event.etag()
+    }
+
+    /// The value of the given property of the VTODO.
+    fn get(&self, name: &str) -> Option<&String> {
+        self.event
+            .ical
+            .get("VTODO")?
+            .get_first_property(name)
+            .map(|p| &p.value)
+    }
+
+    fn set(&mut self, name: &str, value: &str) {
+        if let Some(todo) = self.event.ical.get_mut("VTODO") {
+            match todo.properties.iter_mut().find(|p| p.name == name) {
+                Some(p) => p.value = value.into(),
+                None => todo.properties.push(ical::Property::new(name, value)),
+            }
+        }
+    }
+
+    pub fn uid(&self) -> Option<&String> {
+        self.get("UID")
+    }
+
+    /// The RELATED-TO properties of the todo.
+    pub fn relations(&self) -> Vec<Relation> {
+        let Some(todo) = self.event.ical.get("VTODO") else {
+            return Vec::new();
+        };
+        todo.properties
+            .iter()
+            .filter(|p| p.name == "RELATED-TO")
+            .map(|p| Relation {
+                uid: p.value.clone(),
+                rel_type: p
+                    .attributes
+                    .get("RELTYPE")
+                    .map(|t| RelationType::parse(t))
+                    .unwrap_or(RelationType::Parent),
+            })
+            .collect()
+    }
+
+    /// The UID of the parent todo named with RELTYPE=PARENT.
+    pub fn parent_uid(&self) -> Option<String> {
+        self.relations()
+            .into_iter()
+            .find(|r| r.rel_type == RelationType::Parent)
+            .map(|r| r.uid)
+    }
+
+    /// Replace the parent relation, `None` makes the todo a top-level todo.
+    pub fn set_parent_uid(&mut self, parent_uid: Option<&str>) {
+        if let Some(todo) = self.event.ical.get_mut("VTODO") {
+            todo.properties.retain(|p| {
+                p.name != "RELATED-TO"
+                    || p.attributes
+                        .get("RELTYPE")
+                        .is_some_and(|t| RelationType::parse(t) != RelationType::Parent)
+            });
+        }
+        if let Some(uid) = parent_uid {
+            self.add_relation(Relation {
+                uid: uid.to_string(),
+                rel_type: RelationType::Parent,
+            });
+        }
+    }
+
+    /// Add a RELATED-TO property.
+    pub fn add_relation(&mut self, relation: Relation) {
+        if let Some(todo) = self.event.ical.get_mut("VTODO") {
+            todo.properties.push(ical::Property::new_with_attributes(
+                "RELATED-TO",
+                &relation.uid,
+                vec![("RELTYPE", relation.rel_type.as_str())],
+            ));
+        }
+    }
+
+    /// The SUMMARY as plain text.
+    pub fn summary(&self) -> Option<String> {
+        self.get("SUMMARY").map(|value| ical::unescape_text(value))
+    }
+
+    pub fn status(&self) -> Option<TodoStatus> {
+        self.get("STATUS").map(|value| TodoStatus::parse(value))
+    }
+
+    /// Set the STATUS. COMPLETED and PERCENT-COMPLETE are not changed.
+    pub fn set_status(&mut self, status: TodoStatus) {
+        self.set("STATUS", status.as_str());
+    }
+
+    /// The PERCENT-COMPLETE, between 0 and 100.
+    pub fn percent_complete(&self) -> Option<u8> {
+        let percent = self.get("PERCENT-COMPLETE")?.trim().parse::<u64>().ok()?;
+        Some(percent.min(100) as u8)
+    }
+
+    pub fn set_percent_complete(&mut self, percent: u8) {
+        self.set("PERCENT-COMPLETE", &percent.min(100).to_string());
+    }
+
+    /// The PRIORITY from 1 (highest) to 9 (lowest), `None` if it is not set or 0 (undefined).
+    pub fn priority(&self) -> Option<u8> {
+        let priority = self.get("PRIORITY")?.trim().parse::<u8>().ok()?;
+        Some(priority).filter(|p| (1..=9).contains(p))
+    }
+
+    /// The parsed DUE.
+    #[cfg(feature = "tz")]
+    pub fn due(&self) -> Option<crate::recurrence::DateTimeValue> {
+        self.event.due()
+    }
+
+    /// Mark the todo as done: STATUS:COMPLETED, COMPLETED set to now, PERCENT-COMPLETE:100 and
+    /// an incremented SEQUENCE. Save it with `save_event`, or use `complete_todo`.
+    /// With the `tz` feature, a recurring todo is advanced to its next instance instead,
+    /// see `Event::complete_todo`; floating times are interpreted as UTC.
+    pub fn complete(&mut self) {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        self.complete_at(now.as_secs() as i64);
+    }
+
+    fn complete_at(&mut self, seconds: i64) {
+        #[cfg(feature = "tz")]
+        {
+            let completed = chrono::DateTime::from_timestamp(seconds, 0).unwrap_or_default();
+            if self
+                .event
+                .complete_todo(completed, chrono_tz::UTC)
+                .is_some()
+            {
+                self.increment_sequence();
+                return;
+            }
+        }
+        if let Some(todo) = self.event.ical.get_mut("VTODO") {
+            mark_completed(todo, seconds);
+        }
+        self.increment_sequence();
+    }
+
+    /// Mark the todo as not done: STATUS:NEEDS-ACTION, no COMPLETED, PERCENT-COMPLETE:0 and
+    /// an incremented SEQUENCE. Save it with `save_event`, or use `reopen_todo`.
+    pub fn reopen(&mut self) {
+        self.set_status(TodoStatus::NeedsAction);
+        if let Some(todo) = self.event.ical.get_mut("VTODO") {
+            todo.properties.retain(|p| p.name != "COMPLETED");
+        }
+        self.set_percent_complete(0);
+        self.increment_sequence();
+    }
+
+    fn increment_sequence(&mut self) {
+        let sequence = self
+            .get("SEQUENCE")
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        self.set("SEQUENCE", &(sequence + 1).to_string());
+    }
+
+    /// Whether the todo has STATUS:COMPLETED, a COMPLETED date or PERCENT-COMPLETE:100.
+    pub fn is_completed(&self) -> bool {
+        self.status() == Some(TodoStatus::Completed)
+            || self.get("COMPLETED").is_some()
+            || self.percent_complete() == Some(100)
+    }
+}
+
+/// A journal entry in a CalDAV calendar, e.g. a diary entry or note,
+/// corresponding to one `.ics` file with a VJOURNAL.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Journal {
+    event: Event,
+}
+
+impl From<Event> for Journal {
+    fn from(event: Event) -> Self {
+        Self { event }
+    }
+}
+
+impl From<Journal> for Event {
+    fn from(journal: Journal) -> Self {
+        journal.event
+    }
+}
+
+impl Journal {
+    /// Start building a new journal entry stored at the given url.
+    pub fn builder(url: Url) -> JournalBuilder {
+        JournalBuilder {
+            url,
+            properties: Vec::new(),
+        }
+    }
+
+    pub fn event(&self) -> &Event {
+        &self.event
+    }
+
+    pub fn into_event(self) -> Event {
+        self.event
+    }
+
+    pub fn url(&self) -> &Url {
+        self.event.url()
+    }
+
+    pub fn etag(&self) -> Option<&String> {
+        self.event.etag()
+    }
+
+    fn component(&self) -> Option<&Ical> {
+        self.event.ical.get("VJOURNAL")
+    }
+
+    fn get(&self, name: &str) -> Option<&String> {
+        self.component()?.get_first_property(name).map(|p| &p.value)
+    }
+
+    fn set(&mut self, name: &str, value: &str) {
+        if let Some(journal) = self.event.ical.get_mut("VJOURNAL") {
+            match journal.properties.iter_mut().find(|p| p.name == name) {
+                Some(p) => p.value = value.into(),
+                None => journal.properties.push(ical::Property::new(name, value)),
+            }
+        }
+    }
+
+    pub fn uid(&self) -> Option<&String> {
+        self.get("UID")
+    }
+
+    /// The SUMMARY as plain text.
+    pub fn summary(&self) -> Option<String> {
+        self.get("SUMMARY").map(|value| ical::unescape_text(value))
+    }
+
+    pub fn set_summary(&mut self, text: &str) {
+        self.set("SUMMARY", &ical::escape_text(text));
+    }
+
+    /// The DESCRIPTION, i.e. the text of the entry, as plain text.
+    pub fn description(&self) -> Option<String> {
+        self.get("DESCRIPTION")
+            .map(|value| ical::unescape_text(value))
+    }
+
+    pub fn set_description(&mut self, text: &str) {
+        self.set("DESCRIPTION", &ical::escape_text(text));
+    }
+
+    /// The value of DTSTART, e.g. `20240101` for the day of a diary entry.
+    pub fn start(&self) -> Option<&String> {
+        self.get("DTSTART")
+    }
+
+    /// The CATEGORIES, from all CATEGORIES properties.
+    pub fn categories(&self) -> Vec<String> {
+        let Some(journal) = self.component() else {
+            return Vec::new();
+        };
+        journal
+            .properties
+            .iter()
+            .filter(|p| p.name == "CATEGORIES")
+            .flat_map(|p| ical::split_text_list(&p.value))
+            .collect()
+    }
+
+    /// Replace the CATEGORIES. An empty list removes them.
+    pub fn set_categories(&mut self, categories: Vec<String>) {
+        if let Some(journal) = self.event.ical.get_mut("VJOURNAL") {
+            journal.properties.retain(|p| p.name != "CATEGORIES");
+        }
+        if !categories.is_empty() {
+            self.set("CATEGORIES", &ical::join_text_list(&categories));
+        }
+    }
+}
+
+/// Builder for a new `Journal`, see `Journal::builder`.
+pub struct JournalBuilder {
+    url: Url,
+    properties: Vec<ical::Property>,
+}
+
+impl JournalBuilder {
+    pub fn build(self) -> Journal {
+        let mut journal = Ical::new("VJOURNAL".into());
+        journal.properties = self.properties;
+        let mut ical = Ical::new("VCALENDAR".into());
+        ical.add_property(ical::Property::new("VERSION", "2.0"));
+        ical.add_property(ical::Property::new("PRODID", DEFAULT_PRODID));
+        ical.add_component(journal);
+        Journal {
+            event: Event::new(None, self.url, ical),
+        }
+    }
+
+    pub fn uid(mut self, value: String) -> Self {
+        self.properties.push(ical::Property::new("UID", &value));
+        self
+    }
+
+    pub fn timestamp(mut self, value: String) -> Self {
+        self.properties.push(ical::Property::new("DTSTAMP", &value));
+        self
+    }
+
+    /// The title of the entry as plain text.
+    pub fn summary(mut self, text: &str) -> Self {
+        self.properties
+            .push(ical::Property::new("SUMMARY", &ical::escape_text(text)));
+        self
+    }
+
+    /// The text of the entry as plain text.
+    pub fn description(mut self, text: &str) -> Self {
+        self.properties
+            .push(ical::Property::new("DESCRIPTION", &ical::escape_text(text)));
+        self
+    }
+
+    /// The date or time the entry belongs to, e.g. `20240101` with `VALUE=DATE` for a diary entry.
+    pub fn start(mut self, value: String, attributes: Vec<(&str, &str)>) -> Self {
+        self.properties.push(ical::Property::new_with_attributes(
+            "DTSTART", &value, attributes,
+        ));
+        self
+    }
+
+    pub fn categories(mut self, categories: Vec<String>) -> Self {
+        if !categories.is_empty() {
+            self.properties.push(ical::Property::new(
+                "CATEGORIES",
+                &ical::join_text_list(&categories),
+            ));
+        }
+        self
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Property {
+    name: String,
+    value: String,
+    attributes: HashMap<String, String>,
+}
+
+impl Property {
+    pub fn new(name: &str, value: &str) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            attributes: Default::default(),
+        }
+    }
+
+    pub fn new_with_attributes(name: &str, value: &str, attribs: Vec<(&str, &str)>) -> Self {
+        let mut attributes = HashMap::new();
+        for (k, v) in attribs {
+            attributes.insert(k.into(), v.into());
+        }
+        Self {
+            name: name.into(),
+            value: value.into(),
+            attributes,
+        }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn value(&self) -> &String {
+        &self.value
+    }
+
+    pub fn into_value(self) -> String {
+        self.value
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&String> {
+        self.attributes.get(name)
+    }
+}
+
+impl From<ical::Property> for Property {
+    fn from(p: ical::Property) -> Self {
+        Self {
+            name: p.name,
+            value: p.value,
+            attributes: p.attributes,
+        }
+    }
+}
+
+impl From<Property> for ical::Property {
+    fn from(p: Property) -> Self {
+        Self {
+            name: p.name,
+            value: p.value,
+            attributes: p.attributes,
+        }
+    }
+}
+
+/// An ATTENDEE of an event.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attendee {
+    /// The calendar address, usually `mailto:...`.
+    pub address: String,
+    /// The CN parameter, the display name of the attendee.
+    pub cn: Option<String>,
+    /// The PARTSTAT parameter, e.g. `ACCEPTED` or `DELEGATED`.
+    pub partstat: Option<String>,
+    /// The ROLE parameter, e.g. `REQ-PARTICIPANT` or `CHAIR`.
+    pub role: Option<String>,
+    /// The RSVP parameter, whether a reply is expected.
+    pub rsvp: Option<bool>,
+    /// The calendar addresses this attendee delegated to.
+    pub delegated_to: Vec<String>,
+    /// The calendar addresses this attendee was delegated from.
+    pub delegated_from: Vec<String>,
+    /// The SCHEDULE-STATUS parameter set by the server after scheduling operations.
+    pub schedule_status: Vec<ScheduleStatus>,
+}
+
+impl From<&ical::Property> for Attendee {
+    fn from(p: &ical::Property) -> Self {
+        let values = |name: &str| {
+            p.attributes
+                .get(name)
+                .map(|v| ical::split_param_values(v))
+                .unwrap_or_default()
+        };
+        Self {
+            address: p.value.clone(),
+            cn: p.attributes.get("CN").map(|v| ical::unquote_param_value(v)),
+            partstat: p.attributes.get("PARTSTAT").cloned(),
+            role: p.attributes.get("ROLE").cloned(),
+            rsvp: p
+                .attributes
+                .get("RSVP")
+                .map(|v| v.eq_ignore_ascii_case("TRUE")),
+            delegated_to: values("DELEGATED-TO"),
+            delegated_from: values("DELEGATED-FROM"),
+            schedule_status: ScheduleStatus::parse_all(p),
+        }
+    }
+}
+
+impl Attendee {
+    /// A new attendee with the given calendar address, e.g. `mailto:alice@example.com`,
+    /// with `PARTSTAT=NEEDS-ACTION` and `RSVP=TRUE`.
+    pub fn new(address: &str) -> Self {
+        Self {
+            address: address.to_string(),
+            cn: None,
+            partstat: Some("NEEDS-ACTION".into()),
+            role: None,
+            rsvp: Some(true),
+            delegated_to: Vec::new(),
+            delegated_from: Vec::new(),
+            schedule_status: Vec::new(),
+        }
+    }
+
+    /// The email address of the attendee, the address without `mailto:`.
+    pub fn email(&self) -> &str {
+        strip_mailto(&self.address)
+    }
+
+    /// Write the parameters of this attendee to the given ATTENDEE property.
+    /// Other parameters (e.g. SCHEDULE-STATUS or CUTYPE) are kept.
+    fn apply_to(&self, p: &mut ical::Property) {
+        p.value = self.address.clone();
+        let mut set = |name: &str, value: Option<String>| match value {
+            Some(value) => {
+                p.attributes.insert(name.into(), value);
+            }
+            None => {
+                p.attributes.remove(name);
+            }
+        };
+        set("CN", self.cn.as_deref().map(ical::quote_param_value));
+        set("PARTSTAT", self.partstat.clone());
+        set("ROLE", self.role.clone());
+        set(
+            "RSVP",
+            self.rsvp
+                .map(|rsvp| if rsvp { "TRUE" } else { "FALSE" }.to_string()),
+        );
+        let list = |values: &[String]| {
+            Some(ical::join_param_values(values)).filter(|_| !values.is_empty())
+        };
+        set("DELEGATED-TO", list(&self.delegated_to));
+        set("DELEGATED-FROM", list(&self.delegated_from));
+    }
+}
+
+fn strip_mailto(address: &str) -> &str {
+    match address.get(..7) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("mailto:") => &address[7..],
+        _ => address,
+    }
+}
+
+/// Whether both calendar addresses are the same, ignoring case and a `mailto:` prefix.
+fn same_address(a: &str, b: &str) -> bool {
+    strip_mailto(a).eq_ignore_ascii_case(strip_mailto(b))
+}
+
+/// The delivery status of a scheduling message as given in the SCHEDULE-STATUS parameter (RFC 6638, 3.2.9).
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleStatus {
+    /// 1.0: The scheduling message is pending.
+    Pending,
+    /// 1.1: The scheduling message has been sent.
+    Sent,
+    /// 1.2: The scheduling message has been delivered.
+    Delivered,
+    /// 3.7: The calendar user address is unknown to the server.
+    InvalidCalendarUser,
+    /// 3.8: The organizer is not allowed to schedule the calendar user.
+    NoAuthorization,
+    /// 5.x: The scheduling message could not be delivered.
+    DeliveryFailed(String),
+    /// Any other status code.
+    Other(String),
+}
+
+impl ScheduleStatus {
+    /// Parse a single status code like `1.2`.
+    pub fn parse(code: &str) -> Self {
+        match code.trim() {
+            "1.0" => Self::Pending,
+            "1.1" => Self::Sent,
+            "1.2" => Self::Delivered,
+            "3.7" => Self::InvalidCalendarUser,
+            "3.8" => Self::NoAuthorization,
+            code if code.starts_with("5.") => Self::DeliveryFailed(code.to_string()),
+            code => Self::Other(code.to_string()),
+        }
+    }
+
+    /// Parse all status codes of the SCHEDULE-STATUS parameter of the given property.
+    fn parse_all(p: &ical::Property) -> Vec<Self> {
+        p.attributes
+            .get("SCHEDULE-STATUS")
+            .map(|v| {
+                ical::split_param_values(v)
+                    .iter()
+                    .map(|c| Self::parse(c))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Whether the scheduling message did not reach the calendar user.
+    pub fn is_failure(&self) -> bool {
+        match self {
+            Self::InvalidCalendarUser | Self::NoAuthorization | Self::DeliveryFailed(_) => true,
+            Self::Other(code) => code.starts_with("3.") || code.starts_with("5."),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct EventBuilder {
+    url: Url,
+    etag: Option<String>,
+    properties: Vec<ical::Property>,
+    defaults: Option<caldav::CalendarDefaults>,
+    prodid: Option<String>,
+    timezones_by_reference: bool,
+    alarms: Vec<Alarm>,
+}
+
+impl EventBuilder {
+    fn build_event(mut self, name: String) -> Event {
+        // The alarms of the builder have no REPEAT.
+        let mut children = self
+            .alarms
+            .iter()
+            .filter_map(|alarm| alarm.to_ical().ok())
+            .collect::<Vec<_>>();
+        // The defaults are defined for events only.
+        let defaults = self.defaults.take().filter(|_| name == "VEVENT");
+        if let Some(defaults) = defaults {
+            let has = |name: &str| self.properties.iter().any(|p| p.name == name);
+            // All-day events last whole days, a default duration of hours does not fit.
+            let timed = self.properties.iter().any(|p| {
+                p.name == "DTSTART"
+                    && !p
+                        .attributes
+                        .get("VALUE")
+                        .map(|v| v.eq_ignore_ascii_case("DATE"))
+                        .unwrap_or(false)
+            });
+            let duration = defaults
+                .duration
+                .filter(|_| timed && !has("DTEND") && !has("DURATION"));
+            let transparency = defaults.transparency.filter(|_| !has("TRANSP"));
+            if let Some(duration) = duration {
+                self.properties
+                    .push(ical::Property::new("DURATION", &duration));
+            }
+            if let Some(transparency) = transparency {
+                self.properties
+                    .push(ical::Property::new("TRANSP", &transparency));
+            }
+            if let Some(trigger) = defaults.alarm_offset {
+                let mut alarm = Ical::new("VALARM".into());
+                alarm.add_property(ical::Property::new("ACTION", "DISPLAY"));
+                alarm.add_property(ical::Property::new("TRIGGER", &trigger));
+                alarm.add_property(ical::Property::new("DESCRIPTION", "Reminder"));
+                children.push(alarm);
+            }
+        }
+        let mut event = Event {
+            etag: self.etag,
+            url: self.url,
+            schedule_tag: None,
+            saved_hash: None,
+            ical: ical::Ical {
+                name: "VCALENDAR".into(),
+                properties: vec![
+                    ical::Property::new("VERSION", "2.0"),
+                    ical::Property::new("PRODID", self.prodid.as_deref().unwrap_or(DEFAULT_PRODID)),
+                ],
+                children: vec![ical::Ical {
+                    name,
+                    properties: self.properties,
+                    children,
+                }],
+            },
+        };
+        if !self.timezones_by_reference {
+            event.attach_missing_timezones();
+        }
+        event
+    }
+
+    pub fn build(self) -> Event {
+        self.build_event("VEVENT".into())
+    }
+
+    pub fn build_todo(self) -> Event {
+        self.build_event("VTODO".into())
+    }
+
+    pub fn etag(mut self, etag: Option<String>) -> Self {
+        self.etag = etag;
+        self
+    }
+
+    /// The PRODID of the VCALENDAR, e.g. `-//Example Corp.//My App 1.0//EN`. [`DEFAULT_PRODID`] by default.
+    pub fn prodid(mut self, prodid: &str) -> Self {
+        self.prodid = Some(prodid.to_string());
+        self
+    }
+
+    /// Do not attach VTIMEZONE components for the referenced TZIDs on build.
+    /// Only for servers supporting time zones by reference (RFC 7809), see `save_event_by_reference`.
+    /// Without the `tz` feature no VTIMEZONE components are attached in any case.
+    pub fn timezones_by_reference(mut self, by_reference: bool) -> Self {
+        self.timezones_by_reference = by_reference;
+        self
+    }
+
+    /// Add an alarm, e.g. `AlarmTrigger::Relative { duration: "-PT15M".into(), related_to_end: false }`
+    /// with action `DISPLAY` and the text to show as description.
+    pub fn alarm(mut self, trigger: AlarmTrigger, action: &str, description: &str) -> Self {
+        self.alarms.push(Alarm {
+            action: action.to_string(),
+            trigger,
+            description: Some(description.to_string()),
+            summary: None,
+            repeat: None,
+            duration: None,
+        });
+        self
+    }
+
+    /// Apply the given calendar defaults to events on build: the default alarm, and the default
+    /// duration and TRANSP unless DTEND or DURATION, or TRANSP are set.
+    pub fn defaults(mut self, defaults: &caldav::CalendarDefaults) -> Self {
+        self.defaults = Some(defaults.clone());
+        self
+    }
+
+    pub fn uid(mut self, value: String) -> Self {
+        self.properties.push(ical::Property {
+            name: "UID".to_string(),
+            value,
+            attributes: HashMap::new(),
+        });
+        self
+    }
+
+    pub fn timestamp(mut self, value: String) -> Self {
+        self.properties.push(ical::Property {
+            name: "DTSTAMP".to_string(),
+            value,
+            attributes: HashMap::new(),
+        });
+        self
+    }
+
+    pub fn summary(mut self, value: String) -> Self {
+        self.properties.push(ical::Property {
+            name: "SUMMARY".to_string(),
+            value,
+            attributes: HashMap::new(),
+        });
+        self
+    }
+
+    pub fn priority(mut self, value: String) -> Self {
+        self.properties.push(ical::Property {
+            name: "PRIORITY".to_string(),
+            value,
+            attributes: HashMap::new(),
+        });
+        self
+    }
+
+    pub fn duedate(mut self, value: String) -> Self {
+        self.properties.push(ical::Property {
+            name: "DUE".to_string(),
+            value,
+            attributes: HashMap::new(),
+        });
+        self
+    }
+
+    pub fn status(mut self, value: String) -> Self {
+        self.properties.push(ical::Property {
+            name: "STATUS".to_string(),
+            value,
+            attributes: HashMap::new(),
+        });
+        self
+    }
+
+    pub fn generic(mut self, name: String, value: String) -> Self {
+        self.properties.push(ical::Property {
+            name,
+            value,
+            attributes: HashMap::new(),
+        });
+        self
+    }
+
+    pub fn location(mut self, value: Option<String>) -> Self {
+        if let Some(value) = value {
+            self.properties.push(ical::Property {
+                name: "LOCATION".to_string(),
+                value,
+                attributes: HashMap::new(),
+            });
+        }
+        self
+    }
+
+    pub fn start(mut self, value: String, attributes: Vec<(&str, &str)>) -> Self {
+        let mut attribs = HashMap::new();
+        for (k, v) in attributes {
+            attribs.insert(k.into(), v.into());
+        }
+        self.properties.push(ical::Property {
+            name: "DTSTART".to_string(),
+            value,
+            attributes: attribs,
+        });
+        self
+    }
+
+    pub fn end(mut self, value: String, attributes: Vec<(&str, &str)>) -> Self {
+        let mut attribs = HashMap::new();
+        for (k, v) in attributes {
+            attribs.insert(k.into(), v.into());
+        }
+        self.properties.push(ical::Property {
+            name: "DTEND".to_string(),
+            value,
+            attributes: attribs,
+        });
+        self
+    }
+
+    pub fn description(mut self, value: Option<String>) -> Self {
+        if let Some(value) = value {
+            self.properties.push(ical::Property {
+                name: "DESCRIPTION".to_string(),
+                value,
+                attributes: HashMap::new(),
+            });
+        }
+        self
+    }
+
+    pub fn rrule(mut self, value: Option<String>) -> Self {
+        if let Some(value) = value {
+            self.properties.push(ical::Property {
+                name: "RRULE".to_string(),
+                value,
+                attributes: HashMap::new(),
+            });
+        }
+        self
+    }
+}
+
+/// Availability of a calendar user (RFC 7953), e.g. working hours.
+/// Corresponds to a VCALENDAR containing VAVAILABILITY components.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Availability {
+    ical: ical::Ical,
+}
+
+impl Availability {
+    pub fn builder() -> AvailabilityBuilder {
+        AvailabilityBuilder {
+            properties: vec![],
+            slots: vec![],
+        }
+    }
+
+    /// Parse the given VCALENDAR data.
+    pub fn parse(data: &str) -> Result<Self, MiniCaldavError> {
+        let mut lines = ical::LineIterator::new(data);
+        let ical = ical::Ical::parse(&mut lines)
+            .map_err(|e| CouldNotParseAvailability(data.to_string(), format!("{:?}", e)))?;
+        if ical.get("VAVAILABILITY").is_none() {
+            return Err(CouldNotParseAvailability(
+                data.to_string(),
+                "No VAVAILABILITY component".to_string(),
+            ));
+        }
+        Ok(Self { ical })
+    }
+
+    pub fn ical(&self) -> &Ical {
+        &self.ical
+    }
+
+    pub fn ical_mut(&mut self) -> &mut Ical {
+        &mut self.ical
+    }
+
+    /// All VAVAILABILITY components.
+    pub fn components(&self) -> impl Iterator<Item = &Ical> {
+        self.ical
+            .children
+            .iter()
+            .filter(|c| c.name == "VAVAILABILITY")
+    }
+
+    /// The BUSYTYPE of the first VAVAILABILITY component.
+    /// Time outside of the available slots has this busy type, `BUSY-UNAVAILABLE` if not set.
+    pub fn busy_type(&self) -> Option<&String> {
+        self.components()
+            .next()
+            .and_then(|c| c.get_first_property("BUSYTYPE"))
+            .map(|p| &p.value)
+    }
+
+    /// The AVAILABLE slots of all VAVAILABILITY components.
+    pub fn available_slots(&self) -> Vec<AvailableSlot> {
+        self.components()
+            .flat_map(|c| c.children.iter())
+            .filter(|c| c.name == "AVAILABLE")
+            .filter_map(AvailableSlot::from_ical)
+            .collect()
+    }
+
+    pub fn serialize(&self) -> String {
+        self.ical.serialize()
+    }
+}
+
+impl std::fmt::Display for Availability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.ical.fmt(f)
+    }
+}
+
+impl std::str::FromStr for Availability {
+    type Err = MiniCaldavError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// An AVAILABLE subcomponent of a VAVAILABILITY, i.e. a (recurring) time slot the user is available.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvailableSlot {
+    pub uid: String,
+    pub start: Property,
+    pub end: Option<Property>,
+    pub rrule: Option<String>,
+    pub summary: Option<String>,
+}
+
+impl AvailableSlot {
+    fn from_ical(ical: &Ical) -> Option<Self> {
+        let value = |name: &str| ical.get_first_property(name).map(|p| p.value.clone());
+        Some(Self {
+            uid: value("UID").unwrap_or_default(),
+            start: Property::from(ical.get_first_property("DTSTART")?.clone()),
+            end: ical
+                .get_first_property("DTEND")
+                .map(|p| Property::from(p.clone())),
+            rrule: value("RRULE"),
+            summary: value("SUMMARY"),
+        })
+    }
+
+    /// The slot as AVAILABLE component. A slot without UID gets one derived from its content.
+    fn into_ical(self, dtstamp: Option<&ical::Property>) -> Ical {
+        let mut ical = Ical::new("AVAILABLE".into());
+        let uid = if self.uid.is_empty() {
+            let content = format!(
+                "{} {:?} {:?} {:?} {:?}",
+                self.start.value(),
+                self.start.attribute("TZID"),
+                self.end.as_ref().map(|p| p.value()),
+                self.rrule,
+                self.summary
+            );
+            format!("{}@minicaldav", crate::util::stable_hash(&content))
+        } else {
+            self.uid
+        };
+        ical.add_property(ical::Property::new("UID", &uid));
+        if let Some(dtstamp) = dtstamp {
+            ical.add_property(dtstamp.clone());
+        }
+        ical.add_property(self.start.into());
+        if let Some(end) = self.end {
+            ical.add_property(end.into());
+        }
+        if let Some(rrule) = self.rrule {
+            ical.add_property(ical::Property::new("RRULE", &rrule));
+        }
+        if let Some(summary) = self.summary {
+            ical.add_property(ical::Property::new("SUMMARY", &summary));
+        }
+        ical
+    }
+}
+
+#[derive(Debug)]
+pub struct AvailabilityBuilder {
+    properties: Vec<ical::Property>,
+    slots: Vec<AvailableSlot>,
+}
+
+impl AvailabilityBuilder {
+    pub fn build(self) -> Availability {
+        let dtstamp = self
+            .properties
+            .iter()
+            .find(|p| p.name == "DTSTAMP")
+            .cloned();
+        let mut vavailability = Ical::new("VAVAILABILITY".into());
+        vavailability.properties = self.properties;
+        for slot in self.slots {
+            vavailability.add_component(slot.into_ical(dtstamp.as_ref()));
+        }
+        let mut ical = Ical::new("VCALENDAR".into());
+        ical.add_property(ical::Property::new("VERSION", "2.0"));
+        ical.add_property(ical::Property::new("PRODID", DEFAULT_PRODID));
+        ical.add_component(vavailability);
+        Availability { ical }
+    }
+
+    pub fn uid(mut self, value: String) -> Self {
+        self.properties.push(ical::Property::new("UID", &value));
+        self
+    }
+
+    pub fn timestamp(mut self, value: String) -> Self {
+        self.properties.push(ical::Property::new("DTSTAMP", &value));
+        self
+    }
+
+    /// One of `BUSY`, `BUSY-UNAVAILABLE` or `BUSY-TENTATIVE`.
+    pub fn busy_type(mut self, value: String) -> Self {
+        self.properties
+            .push(ical::Property::new("BUSYTYPE", &value));
+        self
+    }
+
+    pub fn summary(mut self, value: String) -> Self {
+        self.properties.push(ical::Property::new("SUMMARY", &value));
+        self
+    }
+
+    /// Start of the period in which the availability applies.
+    pub fn start(mut self, value: String, attributes: Vec<(&str, &str)>) -> Self {
+        self.properties.push(ical::Property::new_with_attributes(
+            "DTSTART", &value, attributes,
+        ));
+        self
+    }
+
+    /// End of the period in which the availability applies.
+    pub fn end(mut self, value: String, attributes: Vec<(&str, &str)>) -> Self {
+        self.properties.push(ical::Property::new_with_attributes(
+            "DTEND", &value, attributes,
+        ));
+        self
     }
-    pub fn is_subscription(&self) -> bool {
-        self.inner.is_subscription
+
+    pub fn available(mut self, slot: AvailableSlot) -> Self {
+        self.slots.push(slot);
+        self
     }
 }
 
+/// Get the free-busy time of the calendar between `start` and `end`, UTC date-times like `20240101T000000Z`.
+pub async fn get_free_busy(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    start: &str,
+    end: &str,
+) -> Result<FreeBusy, MiniCaldavError> {
+    let data = caldav::free_busy_query(client, credentials, calendar.url(), start, end).await?;
+    FreeBusy::parse(&data)
+}
+
+/// The answer of one attendee to a free-busy request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreeBusyResponse {
+    pub recipient: String,
+    /// The `request-status`, e.g. `2.0;Success`.
+    pub request_status: String,
+    /// The free-busy time of the recipient, if the request succeeded.
+    pub free_busy: Option<FreeBusy>,
+}
+
+/// Ask the server for the free-busy time of the attendees of the given request by sending it
+/// to the scheduling outbox of the organizer, see `caldav::get_schedule_outbox_url`.
+/// The request must have the METHOD `REQUEST`, an ORGANIZER and at least one ATTENDEE.
+pub async fn query_free_busy(
+    client: &Client,
+    credentials: &Credentials,
+    outbox_url: &Url,
+    request: &FreeBusy,
+) -> Result<Vec<FreeBusyResponse>, MiniCaldavError> {
+    let responses =
+        caldav::post_to_outbox(client, credentials, outbox_url, request.serialize()).await?;
+    Ok(responses
+        .into_iter()
+        .map(|response| FreeBusyResponse {
+            free_busy: response
+                .calendar_data
+                .as_deref()
+                .and_then(|data| FreeBusy::parse(data).ok()),
+            recipient: response.recipient,
+            request_status: response.request_status,
+        })
+        .collect())
+}
+
+/// Free and busy time of a calendar user (VFREEBUSY), e.g. the result of a free-busy-query REPORT
+/// or the body of a free-busy request sent to the scheduling outbox.
+/// Corresponds to a VCALENDAR containing a VFREEBUSY component.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-/// An event in a CalDAV calendar.
-/// Corresponds to exactly one `.ics` file
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub struct Event {
-    etag: Option<String>,
-    url: Url,
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreeBusy {
     ical: ical::Ical,
 }
 
-impl Event {
-    /// Construct a new event.
-    pub fn new(etag: Option<String>, url: Url, ical: ical::Ical) -> Self {
-        Self { etag, url, ical }
+impl FreeBusy {
+    pub fn builder() -> FreeBusyBuilder {
+        FreeBusyBuilder {
+            method: None,
+            properties: vec![],
+            periods: vec![],
+        }
     }
 
-    /// The full url of this event.
-    pub fn url(&self) -> &Url {
-        &self.url
+    /// Parse the given VCALENDAR data.
+    pub fn parse(data: &str) -> Result<Self, MiniCaldavError> {
+        let mut lines = ical::LineIterator::new(data);
+        let ical = ical::Ical::parse(&mut lines)
+            .map_err(|e| CouldNotParseFreeBusy(data.to_string(), format!("{:?}", e)))?;
+        if ical.get("VFREEBUSY").is_none() {
+            return Err(CouldNotParseFreeBusy(
+                data.to_string(),
+                "No VFREEBUSY component".to_string(),
+            ));
+        }
+        Ok(Self { ical })
     }
 
-    pub fn update_url(&mut self, url: Url) {
-        self.url = url;
+    pub fn ical(&self) -> &Ical {
+        &self.ical
     }
 
-    fn get_property(&self, name: &str, datatype: &str) -> Option<Property> {
-        self.ical.get(datatype).and_then(|ical| {
-            ical.properties.iter().find_map(|p| {
-                if p.name == name {
-                    Some(Property::from(p.clone()))
-                } else {
-                    None
-                }
+    pub fn ical_mut(&mut self) -> &mut Ical {
+        &mut self.ical
+    }
+
+    fn component(&self) -> Option<&Ical> {
+        self.ical.get("VFREEBUSY")
+    }
+
+    /// The METHOD of the VCALENDAR, e.g. `REQUEST` or `REPLY` for scheduling messages.
+    pub fn method(&self) -> Option<&String> {
+        self.ical.get_first_property("METHOD").map(|p| &p.value)
+    }
+
+    /// Start of the queried time range.
+    pub fn start(&self) -> Option<Property> {
+        self.component()
+            .and_then(|c| c.get_first_property("DTSTART"))
+            .map(|p| Property::from(p.clone()))
+    }
+
+    /// End of the queried time range.
+    pub fn end(&self) -> Option<Property> {
+        self.component()
+            .and_then(|c| c.get_first_property("DTEND"))
+            .map(|p| Property::from(p.clone()))
+    }
+
+    pub fn organizer(&self) -> Option<&String> {
+        self.component()
+            .and_then(|c| c.get_first_property("ORGANIZER"))
+            .map(|p| &p.value)
+    }
+
+    pub fn attendees(&self) -> Vec<&String> {
+        self.component()
+            .map(|c| {
+                c.properties
+                    .iter()
+                    .filter(|p| p.name == "ATTENDEE")
+                    .map(|p| &p.value)
+                    .collect()
             })
-        })
+            .unwrap_or_default()
     }
 
-    /// Get the property of the given name or `None`.
-    pub fn pop_property(&mut self, name: &str) -> Option<Property> {
-        self.ical.get_mut("VEVENT").and_then(|ical| {
-            let index = ical.properties.iter().enumerate().find_map(|(i, p)| {
-                if p.name == name {
-                    Some(i)
-                } else {
-                    None
-                }
-            });
+    /// The periods of all FREEBUSY properties.
+    pub fn periods(&self) -> Vec<FreeBusyPeriod> {
+        self.component()
+            .map(|c| {
+                c.properties
+                    .iter()
+                    .filter(|p| p.name == "FREEBUSY")
+                    .flat_map(FreeBusyPeriod::from_property)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
 
-            if let Some(index) = index {
-                Some(Property::from(ical.properties.remove(index)))
-            } else {
-                None
-            }
-        })
+    /// The periods in which the user is not free, i.e. with any FBTYPE but `FREE`.
+    pub fn busy_periods(&self) -> Vec<FreeBusyPeriod> {
+        self.periods()
+            .into_iter()
+            .filter(|p| p.fb_type != "FREE")
+            .collect()
     }
 
-    pub fn ical(&self) -> &Ical {
-        &self.ical
+    pub fn serialize(&self) -> String {
+        self.ical.serialize()
     }
+}
 
-    pub fn ical_mut(&mut self) -> &mut Ical {
-        &mut self.ical
+impl std::fmt::Display for FreeBusy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.ical.fmt(f)
     }
+}
 
-    pub fn add(&mut self, property: Property) {
-        if let Some(ical) = self.ical.get_mut("VEVENT") {
-            ical.properties.push(property.into());
+impl std::str::FromStr for FreeBusy {
+    type Err = MiniCaldavError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// One period of a FREEBUSY property.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreeBusyPeriod {
+    /// One of `FREE`, `BUSY`, `BUSY-UNAVAILABLE` or `BUSY-TENTATIVE`.
+    pub fb_type: String,
+    /// UTC date-time, e.g. `20240101T100000Z`.
+    pub start: String,
+    /// UTC date-time or a duration, e.g. `PT1H`.
+    pub end: String,
+}
+
+impl FreeBusyPeriod {
+    pub fn busy(start: &str, end: &str) -> Self {
+        Self {
+            fb_type: "BUSY".into(),
+            start: start.into(),
+            end: end.into(),
         }
     }
 
-    pub fn property(&self, name: &str) -> Option<Property> {
-        self.get_property(name, "VEVENT")
+    fn from_property(property: &ical::Property) -> Vec<Self> {
+        let fb_type = property
+            .attributes
+            .get("FBTYPE")
+            .cloned()
+            .unwrap_or_else(|| "BUSY".into());
+        property
+            .value
+            .split(',')
+            .filter_map(|period| period.trim().split_once('/'))
+            .map(|(start, end)| Self {
+                fb_type: fb_type.clone(),
+                start: start.into(),
+                end: end.into(),
+            })
+            .collect()
     }
 
-    /// Get the property of the given name or `None`.
-    pub fn property_todo(&self, name: &str) -> Option<Property> {
-        self.get_property(name, "VTODO")
+    fn into_property(self) -> ical::Property {
+        ical::Property::new_with_attributes(
+            "FREEBUSY",
+            &format!("{}/{}", self.start, self.end),
+            vec![("FBTYPE", &self.fb_type)],
+        )
     }
+}
 
-    /// Get the value of the given property name or `None`.
-    pub fn get(&self, name: &str) -> Option<&String> {
-        self.ical.get("VEVENT").and_then(|ical| {
-            ical.properties
-                .iter()
-                .find_map(|p| if p.name == name { Some(&p.value) } else { None })
-        })
+#[derive(Debug)]
+pub struct FreeBusyBuilder {
+    method: Option<String>,
+    properties: Vec<ical::Property>,
+    periods: Vec<FreeBusyPeriod>,
+}
+
+impl FreeBusyBuilder {
+    pub fn build(self) -> FreeBusy {
+        let mut vfreebusy = Ical::new("VFREEBUSY".into());
+        vfreebusy.properties = self.properties;
+        for period in self.periods {
+            vfreebusy.add_property(period.into_property());
+        }
+        let mut ical = Ical::new("VCALENDAR".into());
+        ical.add_property(ical::Property::new("VERSION", "2.0"));
+        ical.add_property(ical::Property::new("PRODID", DEFAULT_PRODID));
+        if let Some(method) = self.method {
+            ical.add_property(ical::Property::new("METHOD", &method));
+        }
+        ical.add_component(vfreebusy);
+        FreeBusy { ical }
     }
 
-    /// Get the value of the given property name or `None`.
-    pub fn get_all(&self, name: &str) -> Option<Vec<&String>> {
-        self.ical.get("VEVENT").map(|ical| {
+    /// The METHOD of the scheduling message, `REQUEST` to query the outbox or `REPLY`.
+    pub fn method(mut self, value: String) -> Self {
+        self.method = Some(value);
+        self
+    }
+
+    pub fn uid(mut self, value: String) -> Self {
+        self.properties.push(ical::Property::new("UID", &value));
+        self
+    }
+
+    pub fn timestamp(mut self, value: String) -> Self {
+        self.properties.push(ical::Property::new("DTSTAMP", &value));
+        self
+    }
+
+    /// Start of the queried time range as UTC date-time.
+    pub fn start(mut self, value: String) -> Self {
+        self.properties.push(ical::Property::new("DTSTART", &value));
+        self
+    }
+
+    /// End of the queried time range as UTC date-time.
+    pub fn end(mut self, value: String) -> Self {
+        self.properties.push(ical::Property::new("DTEND", &value));
+        self
+    }
+
+    /// The calendar user address of the organizer, e.g. `mailto:alice@example.com`.
+    pub fn organizer(mut self, value: String) -> Self {
+        self.properties
+            .push(ical::Property::new("ORGANIZER", &value));
+        self
+    }
+
+    /// The calendar user address of an attendee whose free-busy time is queried or reported.
+    pub fn attendee(mut self, value: String) -> Self {
+        self.properties
+            .push(ical::Property::new("ATTENDEE", &value));
+        self
+    }
+
+    pub fn period(mut self, period: FreeBusyPeriod) -> Self {
+        self.periods.push(period);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_url() {
+        let calendar = Url::parse("https://example.com/cal/personal/").unwrap();
+        assert_eq!(
+            resource_url(&calendar, "abc-123@example.com")
+                .unwrap()
+                .as_str(),
+            "https://example.com/cal/personal/abc-123@example.com.ics"
+        );
+        assert_eq!(
+            resource_url(&calendar, "a/b c").unwrap().as_str(),
+            "https://example.com/cal/personal/a%2Fb%20c.ics"
+        );
+        let calendar = Url::parse("https://example.com/cal/personal").unwrap();
+        assert_eq!(
+            resource_url(&calendar, "x").unwrap().as_str(),
+            "https://example.com/cal/personal/x.ics"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_calendar_from_parts() {
+        let base_url = Url::parse("https://example.com/dav/").unwrap();
+        // Written before components, source_url, enabled and order were added.
+        let old = r##"{"url":"https://example.com/dav/work/","name":"Work","color":"#ff0000","privileges":["read"],"is_subscription":false}"##;
+        let calendar_ref: caldav::CalendarRef = serde_json::from_str(old).unwrap();
+        let calendar = Calendar::from_parts(base_url.clone(), calendar_ref);
+        assert_eq!(calendar.url().as_str(), "https://example.com/dav/work/");
+        assert_eq!(calendar.name(), "Work");
+        assert!(calendar.components().is_empty());
+        assert_eq!(calendar.source_url(), None);
+
+        let json = serde_json::to_string(&calendar.inner).unwrap();
+        let calendar_ref: caldav::CalendarRef = serde_json::from_str(&json).unwrap();
+        let restored = Calendar::from_parts(base_url, calendar_ref);
+        assert_eq!(
+            format!("{:?}", restored.inner),
+            format!("{:?}", calendar.inner)
+        );
+        assert_eq!(restored.base_url(), calendar.base_url());
+    }
+
+    #[tokio::test]
+    async fn test_create_event_without_uid() {
+        use crate::test_server::{Response, TestServer};
+
+        let server = TestServer::start(vec![Response::new(201).header("ETag", "\"1\"")]);
+        let client = Client::new();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let calendar = server.calendar("/cal/");
+        let event = Event::builder(server.join("/cal/a.ics")).build();
+        let error = create_event(&client, &credentials, &calendar, event)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, InvalidObject(reason) if reason == "VEVENT without UID"),
+            "{:?}",
+            error
+        );
+
+        let object = |components: &str| {
+            let ics = format!("BEGIN:VCALENDAR\n{}END:VCALENDAR\n", components);
+            Event::from_raw(server.join("/cal/a.ics"), None, &ics).unwrap()
+        };
+        let todo = object("BEGIN:VTODO\nSUMMARY:A\nEND:VTODO\n");
+        let error = create_event(&client, &credentials, &calendar, todo)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, InvalidObject(reason) if reason == "VTODO without UID"),
+            "{:?}",
+            error
+        );
+
+        // The UID is read from the event, not from the time zone before it.
+        let event = object(
+            "BEGIN:VTIMEZONE\nTZID:Europe/Berlin\nEND:VTIMEZONE\nBEGIN:VEVENT\nUID:b\nEND:VEVENT\n",
+        );
+        let created = create_event(&client, &credentials, &calendar, event)
+            .await
+            .unwrap();
+        assert_eq!(created.url(), &server.join("/cal/b.ics"));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].url, "/cal/b.ics");
+    }
+
+    #[test]
+    fn test_handle_method() {
+        let url = Url::parse("https://example.com/cal/a.ics").unwrap();
+        let ical = || {
+            Event::from_raw(
+                url.clone(),
+                None,
+                "BEGIN:VCALENDAR\nMETHOD:REQUEST\nMETHOD:REQUEST\nBEGIN:VEVENT\nUID:a\nEND:VEVENT\nEND:VCALENDAR\n",
+            )
+            .unwrap()
+            .ical
+        };
+        let methods = |ical: &Ical| {
             ical.properties
                 .iter()
-                .filter_map(|p| if p.name == name { Some(&p.value) } else { None })
-                .collect::<Vec<&String>>()
-        })
+                .filter(|p| p.name == "METHOD")
+                .count()
+        };
+
+        let mut stripped = ical();
+        handle_method(&url, &mut stripped, MethodHandling::Strip).unwrap();
+        assert_eq!(methods(&stripped), 0);
+        let mut kept = ical();
+        handle_method(&url, &mut kept, MethodHandling::Keep).unwrap();
+        assert_eq!(methods(&kept), 2);
+        assert!(matches!(
+            handle_method(&url, &mut ical(), MethodHandling::Reject),
+            Err(ContainsMethod(method)) if method == "REQUEST"
+        ));
     }
 
-    /// Set the value of the given property name or create a new property.
-    pub fn set(&mut self, name: &str, value: &str) {
-        match self
-            .ical
-            .get_mut("VEVENT")
-            .and_then(|e| e.properties.iter_mut().find(|p| p.name == name))
+    #[test]
+    fn test_cancellation() {
+        let event = |body: &str| {
+            Event::from_raw(
+                Url::parse("https://example.com/cal/a.ics").unwrap(),
+                None,
+                &format!("BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nDTSTART:20240101T100000Z\n{}END:VCALENDAR\n", body),
+            )
+            .unwrap()
+        };
+        assert_eq!(event("END:VEVENT\n").cancellation(), None);
+        assert_eq!(
+            event("STATUS:CANCELLED\nEND:VEVENT\n").cancellation(),
+            Some(Cancellation::Status)
+        );
+        assert!(event("STATUS:CANCELLED\nEND:VEVENT\n").is_cancelled());
+
+        #[cfg(feature = "tz")]
         {
-            Some(p) => {
-                p.value = value.into();
-            }
-            None => self.add(Property::new(name, value)),
+            let excluded =
+                "RRULE:FREQ=DAILY;COUNT=3\nEXDATE:20240101T100000Z,20240102T100000Z\nEND:VEVENT\n";
+            let last = |status: &str| {
+                format!(
+                    "BEGIN:VEVENT\nUID:a\nRECURRENCE-ID:20240103T100000Z\nDTSTART:20240103T100000Z\n{}END:VEVENT\n",
+                    status
+                )
+            };
+            assert_eq!(
+                event(&format!("{}{}", excluded, last("STATUS:CANCELLED\n"))).cancellation(),
+                Some(Cancellation::AllInstancesExcluded)
+            );
+            // The last instance still takes place.
+            assert_eq!(event(excluded).cancellation(), None);
+            assert_eq!(
+                event(&format!("{}{}", excluded, last(""))).cancellation(),
+                None
+            );
+            assert_eq!(
+                event("RRULE:FREQ=DAILY\nEXDATE:20240101T100000Z\nEND:VEVENT\n").cancellation(),
+                None
+            );
         }
     }
 
-    pub fn set_property_attribute(&mut self, name: &str, attr_name: &str, attr_value: &str) {
-        if let Some(p) = self
-            .ical
-            .get_mut("VEVENT")
-            .and_then(|e| e.properties.iter_mut().find(|p| p.name == name))
-        {
-            p.attributes.insert(attr_name.into(), attr_value.into());
-        }
+    #[test]
+    fn test_event_from_str() {
+        let text =
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nSUMMARY:Test\nEND:VEVENT\nEND:VCALENDAR\n";
+        let mut event: Event = text.parse().unwrap();
+        assert_eq!(event.to_string(), text);
+        assert_eq!(event.url().as_str(), UNSAVED_EVENT_URL);
+        assert!(event.is_modified());
+        let url = Url::parse("https://example.com/cal/a.ics").unwrap();
+        event.update_url(url.clone());
+        assert_eq!(event.url(), &url);
+        assert!(matches!(
+            "BEGIN:VEVENT\nUID:a\n".parse::<Event>(),
+            Err(CouldNotParseEvent(..))
+        ));
     }
 
-    pub fn remove_property_attribute(&mut self, name: &str, attr_name: &str) {
-        if let Some(p) = self
-            .ical
-            .get_mut("VEVENT")
-            .and_then(|e| e.properties.iter_mut().find(|p| p.name == name))
+    #[test]
+    fn test_property_removal() {
+        let mut event = Event::from_raw(
+            Url::parse("https://example.com/cal/a.ics").unwrap(),
+            None,
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nX-FOO:1\nSUMMARY:Test\nX-FOO;X-P=1:2\nX-BAR:3\nX-OLD:4\nEND:VEVENT\nEND:VCALENDAR\n",
+        )
+        .unwrap();
+        let names = |event: &Event| {
+            event
+                .properties_iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        let removed = event.remove_property("X-FOO");
+        assert_eq!(
+            removed
+                .iter()
+                .map(|p| p.value().as_str())
+                .collect::<Vec<_>>(),
+            ["1", "2"]
+        );
+        assert_eq!(removed[1].attribute("X-P").map(|v| v.as_str()), Some("1"));
+        assert!(event.remove_property("X-FOO").is_empty());
+        assert_eq!(names(&event), "UID,SUMMARY,X-BAR,X-OLD");
+
+        event.rename_property("X-OLD", "X-NEW");
+        assert_eq!(event.get("X-NEW").map(|v| v.as_str()), Some("4"));
+        assert!(event.get("X-OLD").is_none());
+
+        event.retain_properties(|p| !p.name.starts_with("X-"));
+        assert_eq!(names(&event), "UID,SUMMARY");
+        assert!(event.is_modified());
+
+        // Overridden instances are changed as well.
+        let mut event = Event::from_raw(
+            Url::parse("https://example.com/cal/b.ics").unwrap(),
+            None,
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:b\nX-FOO:1\nX-OLD:2\nX-BAR:3\nEND:VEVENT\nBEGIN:VEVENT\nUID:b\nRECURRENCE-ID:20240102T100000Z\nX-FOO:4\nX-OLD:5\nX-BAR:6\nEND:VEVENT\nEND:VCALENDAR\n",
+        )
+        .unwrap();
+        assert_eq!(event.remove_property("X-FOO").len(), 2);
+        event.rename_property("X-OLD", "X-NEW");
+        event.retain_properties(|p| p.name != "X-BAR");
+        let names = event
+            .ical()
+            .children
+            .iter()
+            .map(|ical| {
+                let names = ical.properties.iter().map(|p| p.name.as_str());
+                names.collect::<Vec<_>>().join(",")
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["UID,X-NEW", "UID,RECURRENCE-ID,X-NEW"]);
+    }
+
+    #[test]
+    fn test_indexed_properties() {
+        let mut event = Event::from_raw(
+            Url::parse("https://example.com/cal/a.ics").unwrap(),
+            None,
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nSUMMARY:Test\nDTSTART:20240101T100000Z\nEND:VEVENT\nEND:VCALENDAR\n",
+        )
+        .unwrap();
+        let names = |event: &Event| {
+            event
+                .properties_iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        // The order of the source is kept.
+        assert_eq!(names(&event), "UID,SUMMARY,DTSTART");
+        assert_eq!(event.property_count(), 3);
+        assert_eq!(event.property_index("DTSTART"), Some(2));
+        assert_eq!(event.property_index("LOCATION"), None);
+        assert_eq!(event.property_at(1).unwrap().value(), "Test");
+        assert!(event.property_at(3).is_none());
+
+        let old = event.set_property_at(1, Property::new("SUMMARY", "Renamed"));
+        assert_eq!(old.unwrap().value(), "Test");
+        assert!(event
+            .set_property_at(3, Property::new("LOCATION", "Berlin"))
+            .is_none());
+        event.insert_property_at(1, Property::new("LOCATION", "Berlin"));
+        event.insert_property_at(10, Property::new("X-LAST", "1"));
+        assert_eq!(names(&event), "UID,LOCATION,SUMMARY,DTSTART,X-LAST");
+        assert_eq!(event.get("SUMMARY").map(|v| v.as_str()), Some("Renamed"));
+
+        assert_eq!(event.remove_property_at(0).unwrap().name(), "UID");
+        assert!(event.remove_property_at(4).is_none());
+        assert_eq!(names(&event), "LOCATION,SUMMARY,DTSTART,X-LAST");
+    }
+
+    #[test]
+    fn test_set_location_with_geo() {
+        let mut event = Event::from_raw(
+            Url::parse("https://example.com/cal/a.ics").unwrap(),
+            None,
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nLOCATION:Old\nEND:VEVENT\nEND:VCALENDAR\n",
+        )
+        .unwrap();
+        event.set_location_with_geo("Old", 0.0, 0.0);
+        event.set_location_with_geo("Cafe \"Mitte\", Berlin", 52.52, 13.405);
+
+        assert_eq!(
+            event.get("LOCATION").map(|v| v.as_str()),
+            Some("Cafe \"Mitte\"\\, Berlin")
+        );
+        assert_eq!(event.get("GEO").map(|v| v.as_str()), Some("52.52;13.405"));
+        // Setting the location again replaces the structured location.
+        let structured = event
+            .properties_iter()
+            .filter(|p| p.name == "X-APPLE-STRUCTURED-LOCATION")
+            .collect::<Vec<_>>();
+        assert_eq!(structured.len(), 1);
+        let structured = structured[0];
+        assert_eq!(structured.value, "geo:52.52,13.405");
+        let attr = |name: &str| structured.attributes.get(name).map(|v| v.as_str());
+        assert_eq!(attr("VALUE"), Some("URI"));
+        assert_eq!(attr("X-APPLE-RADIUS"), Some("70"));
+        assert_eq!(attr("X-TITLE"), Some("\"Cafe ^'Mitte^', Berlin\""));
+        assert_eq!(attr("X-ADDRESS"), attr("X-TITLE"));
+    }
+
+    #[tokio::test]
+    async fn test_set_calendar_enabled_and_order() {
+        use crate::test_server::{Response, TestServer};
+
+        let server = TestServer::start(vec![
+            Response::new(200),
+            Response::new(200),
+            Response::new(403),
+        ]);
+        let mut calendar = server.calendar("/cal/");
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        assert!(calendar.is_enabled());
+        assert_eq!(calendar.order(), None);
+
+        set_calendar_enabled(&client, &credentials, &mut calendar, false)
+            .await
+            .unwrap();
+        assert!(!calendar.is_enabled());
+        set_calendar_order(&client, &credentials, &mut calendar, 3)
+            .await
+            .unwrap();
+        assert_eq!(calendar.order(), Some(3));
+        // A failed request leaves the calendar unchanged.
+        assert!(set_calendar_order(&client, &credentials, &mut calendar, 5)
+            .await
+            .is_err());
+        assert_eq!(calendar.order(), Some(3));
+
+        let requests = server.requests();
+        assert!(requests
+            .iter()
+            .all(|r| r.method == "PROPPATCH" && r.url == "/cal/"));
+        assert!(requests[0]
+            .body
+            .contains("<O:calendar-enabled>0</O:calendar-enabled>"));
+        assert!(requests[1]
+            .body
+            .contains("<A:calendar-order>3</A:calendar-order>"));
+    }
+
+    #[test]
+    fn test_version_and_prodid() {
+        let url = Url::parse("https://example.com/cal/a.ics").unwrap();
+        let event = Event::builder(url.clone())
+            .uid("a".into())
+            .prodid("-//Example//App//EN")
+            .build();
+        assert_eq!(
+            event.ical().get_first_property("VERSION").unwrap().value,
+            "2.0"
+        );
+        assert_eq!(
+            event.ical().get_first_property("PRODID").unwrap().value,
+            "-//Example//App//EN"
+        );
+
+        #[cfg(feature = "tz")]
         {
-            p.attributes.remove(attr_name);
+            let builder = || {
+                Event::builder(url.clone())
+                    .uid("a".into())
+                    .start("20240101T100000".into(), vec![("TZID", "Europe/Paris")])
+            };
+            assert_eq!(
+                builder().build().ical().get("VTIMEZONE").map(|tz| tz
+                    .get_first_property("TZID")
+                    .unwrap()
+                    .value
+                    .as_str()),
+                Some("Europe/Paris")
+            );
+            let event = builder().timezones_by_reference(true).build();
+            assert!(event.ical().get("VTIMEZONE").is_none());
         }
+
+        let mut ical = Ical::new("VCALENDAR".into());
+        check_version_and_prodid(&mut ical).unwrap();
+        assert_eq!(ical.properties[0].value, DEFAULT_PRODID);
+        assert_eq!(ical.properties[1].value, "2.0");
+        ical.properties[1].value = "1.0".into();
+        assert!(matches!(
+            check_version_and_prodid(&mut ical),
+            Err(InvalidObject(reason)) if reason == "unsupported iCalendar version 1.0"
+        ));
     }
 
-    /// Get all properties of this event.
-    fn get_properties(&self, datatype: &str) -> Vec<(&String, &String)> {
-        self.ical
-            .get(datatype)
-            .map(|ical| {
-                ical.properties
-                    .iter()
-                    .map(|p| (&p.name, &p.value))
-                    .collect::<Vec<(&String, &String)>>()
-            })
-            .unwrap_or_else(|| {
-                error!("Could not get properties: No VEVENT section.");
-                Vec::new()
-            })
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_exdates() {
+        use crate::recurrence::DateTimeValue;
+
+        let mut event = Event::new(
+            None,
+            Url::parse("https://example.com/cal/a.ics").unwrap(),
+            Ical::parse(&mut ical::LineIterator::new(
+                "BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:a
+DTSTART;TZID=Europe/Berlin:20240101T100000
+RRULE:FREQ=DAILY
+EXDATE;TZID=Europe/Berlin:20240102T100000,20240103T100000
+RDATE;VALUE=DATE:20240201,20240301
+END:VEVENT
+END:VCALENDAR",
+            ))
+            .unwrap(),
+        );
+        assert_eq!(event.rdates().len(), 2);
+        let exdates = event.exdates();
+        assert_eq!(exdates.len(), 2);
+
+        let Some(DateTimeValue::Zoned(start)) = event.dtstart() else {
+            panic!("DTSTART is not zoned");
+        };
+        event.add_exdate(DateTimeValue::Zoned(start + chrono::Duration::days(4)));
+        event.add_exdate(DateTimeValue::Utc(
+            (start + chrono::Duration::days(4)).with_timezone(&chrono::Utc),
+        ));
+        event.add_exdate(DateTimeValue::Date(start.date_naive()));
+        let value = |event: &Event| {
+            event
+                .property("EXDATE")
+                .map(|p| p.value.clone())
+                .unwrap_or_default()
+        };
+        assert_eq!(
+            value(&event),
+            "20240102T100000,20240103T100000,20240105T100000"
+        );
+        assert_eq!(event.exdates().len(), 4);
+
+        assert!(event.remove_exdate(&exdates[0]));
+        assert!(!event.remove_exdate(&exdates[0]));
+        assert_eq!(value(&event), "20240103T100000,20240105T100000");
+    }
+
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_create_override() {
+        use crate::recurrence::DateTimeValue;
+
+        let mut event = Event::new(
+            None,
+            Url::parse("https://example.com/cal/a.ics").unwrap(),
+            Ical::parse(&mut ical::LineIterator::new(
+                "BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:a
+SEQUENCE:2
+DTSTART;TZID=Europe/Berlin:20240101T100000
+DTEND;TZID=Europe/Berlin:20240101T110000
+RRULE:FREQ=DAILY
+EXDATE;TZID=Europe/Berlin:20240102T100000
+SUMMARY:Daily
+END:VEVENT
+END:VCALENDAR",
+            ))
+            .unwrap(),
+        );
+        let utc = |s: &str| {
+            DateTimeValue::Utc(
+                chrono::NaiveDateTime::parse_from_str(s, "%Y%m%dT%H%M%SZ")
+                    .unwrap()
+                    .and_utc(),
+            )
+        };
+        let instance = event.create_override(utc("20240105T090000Z")).unwrap();
+        let value = |c: &Ical, name: &str| c.get_first_property(name).map(|p| p.value.clone());
+        assert_eq!(
+            value(instance, "DTSTART").as_deref(),
+            Some("20240105T100000")
+        );
+        assert_eq!(value(instance, "DTEND").as_deref(), Some("20240105T110000"));
+        assert_eq!(
+            value(instance, "RECURRENCE-ID").as_deref(),
+            Some("20240105T100000")
+        );
+        assert_eq!(value(instance, "SEQUENCE").as_deref(), Some("2"));
+        assert_eq!(value(instance, "RRULE"), None);
+        assert_eq!(value(instance, "EXDATE"), None);
+        instance
+            .properties
+            .push(ical::Property::new("SUMMARY", "Moved"));
+
+        event.create_override(utc("20240105T090000Z")).unwrap();
+        assert_eq!(event.ical().children.len(), 2);
+        assert_eq!(
+            event
+                .occurrences(
+                    utc("20240105T000000Z").to_utc(chrono_tz::UTC),
+                    utc("20240106T000000Z").to_utc(chrono_tz::UTC),
+                    chrono_tz::UTC,
+                )
+                .len(),
+            1
+        );
     }
 
-    pub fn into_properties(self) -> Vec<Property> {
-        for ical in self.ical.children {
-            if ical.name == "VEVENT" {
-                return ical.properties.into_iter().map(Property::from).collect();
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_occurrences() {
+        let event = Event::from_raw(
+            Url::parse("https://example.com/cal/a.ics").unwrap(),
+            None,
+            "BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:a
+SUMMARY:Daily
+DTSTART:20240101T100000
+DURATION:PT2H
+RRULE:FREQ=DAILY;COUNT=5
+EXDATE:20240103T100000
+RDATE:20240110T100000
+END:VEVENT
+BEGIN:VEVENT
+UID:a
+RECURRENCE-ID:20240104T100000
+SUMMARY:Moved
+DTSTART:20240104T180000
+DURATION:PT1H
+END:VEVENT
+END:VCALENDAR
+",
+        )
+        .unwrap();
+        let utc = |s: &str| {
+            chrono::NaiveDateTime::parse_from_str(s, "%Y%m%dT%H%M%SZ")
+                .unwrap()
+                .and_utc()
+        };
+        // Floating times are in Europe/Berlin, the instance of January 2nd ends within the range.
+        let occurrences = event.occurrences(
+            utc("20240102T100000Z"),
+            utc("20240111T000000Z"),
+            chrono_tz::Europe::Berlin,
+        );
+        let summary = |o: &crate::recurrence::Occurrence| {
+            o.component
+                .get_first_property("SUMMARY")
+                .map(|p| p.value.clone())
+                .unwrap()
+        };
+        assert_eq!(
+            occurrences
+                .iter()
+                .map(|o| (o.start, o.end, o.recurrence_id, summary(o)))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    utc("20240102T090000Z"),
+                    utc("20240102T110000Z"),
+                    Some(utc("20240102T090000Z")),
+                    "Daily".to_string()
+                ),
+                (
+                    utc("20240104T170000Z"),
+                    utc("20240104T180000Z"),
+                    Some(utc("20240104T090000Z")),
+                    "Moved".to_string()
+                ),
+                (
+                    utc("20240105T090000Z"),
+                    utc("20240105T110000Z"),
+                    Some(utc("20240105T090000Z")),
+                    "Daily".to_string()
+                ),
+                (
+                    utc("20240110T090000Z"),
+                    utc("20240110T110000Z"),
+                    Some(utc("20240110T090000Z")),
+                    "Daily".to_string()
+                ),
+            ]
+        );
+        assert!(event
+            .occurrences(
+                utc("20240201T000000Z"),
+                utc("20240301T000000Z"),
+                chrono_tz::Europe::Berlin
+            )
+            .is_empty());
+    }
+
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_complete_recurring_todo() {
+        use crate::recurrence::DateTimeValue;
+
+        let url = Url::parse("https://example.com/cal/t.ics").unwrap();
+        let mut todo = Event::from_raw(
+            url.clone(),
+            None,
+            "BEGIN:VCALENDAR
+BEGIN:VTODO
+UID:t
+DTSTART;TZID=Europe/Berlin:20240101T090000
+DUE;TZID=Europe/Berlin:20240101T120000
+RRULE:FREQ=WEEKLY;COUNT=2
+STATUS:IN-PROCESS
+PERCENT-COMPLETE:50
+END:VTODO
+END:VCALENDAR",
+        )
+        .unwrap();
+        let completed = chrono::DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let next = todo.complete_todo(completed, chrono_tz::UTC).unwrap();
+        assert_eq!(next.value(), "20240108T090000");
+        let value = |e: &Event, name: &str| e.property_todo(name).map(|p| p.value);
+        assert_eq!(value(&todo, "DUE").as_deref(), Some("20240108T120000"));
+        assert_eq!(
+            value(&todo, "RRULE").as_deref(),
+            Some("FREQ=WEEKLY;COUNT=1")
+        );
+        assert_eq!(value(&todo, "STATUS").as_deref(), Some("NEEDS-ACTION"));
+        assert_eq!(value(&todo, "PERCENT-COMPLETE"), None);
+
+        assert_eq!(todo.complete_todo(completed, chrono_tz::UTC), None);
+        assert_eq!(value(&todo, "STATUS").as_deref(), Some("COMPLETED"));
+        assert_eq!(
+            value(&todo, "COMPLETED").as_deref(),
+            Some("20240101T100000Z")
+        );
+
+        // Todos without DTSTART recur from their DUE.
+        let mut todo = Event::from_raw(
+            url,
+            None,
+            "BEGIN:VCALENDAR
+BEGIN:VTODO
+UID:d
+DUE;VALUE=DATE:20240131
+RRULE:FREQ=MONTHLY;BYMONTHDAY=-1
+END:VTODO
+END:VCALENDAR",
+        )
+        .unwrap();
+        let expanded = crate::recurrence::occurrences_between(
+            todo.ical(),
+            completed,
+            completed + chrono::Duration::days(70),
+            chrono_tz::UTC,
+        );
+        assert_eq!(
+            expanded
+                .iter()
+                .filter_map(|o| o.component.get_first_property("DUE"))
+                .map(|p| p.value.as_str())
+                .collect::<Vec<_>>(),
+            ["20240131", "20240229"]
+        );
+        assert_eq!(
+            todo.complete_todo(completed, chrono_tz::UTC),
+            Some(DateTimeValue::Date(
+                chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_alarms() {
+        let event = Event::builder(Url::parse("https://example.com/cal/a.ics").unwrap())
+            .uid("a".into())
+            .alarm(
+                AlarmTrigger::Relative {
+                    duration: "-PT15M".into(),
+                    related_to_end: false,
+                },
+                "DISPLAY",
+                "Soon",
+            )
+            .alarm(
+                AlarmTrigger::Absolute("20240101T090000Z".into()),
+                "AUDIO",
+                "Now",
+            )
+            .build();
+        let alarms = event.alarms();
+        assert_eq!(alarms.len(), 2);
+        assert_eq!(alarms[0].description.as_deref(), Some("Soon"));
+        assert_eq!(
+            alarms[1].trigger,
+            AlarmTrigger::Absolute("20240101T090000Z".into())
+        );
+
+        let valarm = Ical::parse(&mut ical::LineIterator::new(
+            "BEGIN:VALARM\nACTION:DISPLAY\nTRIGGER;RELATED=END:PT0S\nREPEAT:2\nDURATION:PT5M\nDESCRIPTION:Done\nEND:VALARM",
+        ))
+        .unwrap();
+        let alarm = Alarm::from_ical(&valarm).unwrap();
+        assert_eq!(alarm.repeat, Some(2));
+        assert_eq!(
+            alarm.trigger,
+            AlarmTrigger::Relative {
+                duration: "PT0S".into(),
+                related_to_end: true
+            }
+        );
+        assert_eq!(
+            Alarm::from_ical(&alarm.to_ical().unwrap()),
+            Some(alarm.clone())
+        );
+        let without_duration = Alarm {
+            duration: None,
+            ..alarm
+        };
+        assert!(matches!(
+            without_duration.to_ical(),
+            Err(InvalidObject(reason)) if reason == "VALARM without DURATION"
+        ));
+
+        let defaults = caldav::CalendarDefaults {
+            alarm_offset: Some("-PT10M".into()),
+            schedule_transparency: Some("TRANSPARENT".into()),
+            duration: Some("PT1H".into()),
+            transparency: Some("TRANSPARENT".into()),
+        };
+        let builder = || {
+            Event::builder(Url::parse("https://example.com/cal/b.ics").unwrap())
+                .uid("b".into())
+                .defaults(&defaults)
+        };
+        let event = builder().start("20240101T100000Z".into(), vec![]).build();
+        assert_eq!(
+            event.alarms()[0].trigger,
+            AlarmTrigger::Relative {
+                duration: "-PT10M".into(),
+                related_to_end: false
             }
-        }
-        Vec::new()
+        );
+        assert_eq!(event.get("DURATION").map(|d| d.as_str()), Some("PT1H"));
+        assert_eq!(event.get("TRANSP").map(|t| t.as_str()), Some("TRANSPARENT"));
+        // Explicitly set properties take precedence.
+        let event = builder()
+            .start("20240101T100000Z".into(), vec![])
+            .end("20240101T103000Z".into(), vec![])
+            .generic("TRANSP".into(), "OPAQUE".into())
+            .build();
+        assert!(event.get("DURATION").is_none());
+        assert_eq!(event.get("TRANSP").map(|t| t.as_str()), Some("OPAQUE"));
+        // All-day events and events without start get no default duration.
+        let event = builder()
+            .start("20240101".into(), vec![("VALUE", "DATE")])
+            .build();
+        assert!(event.get("DURATION").is_none());
+        assert!(builder().build().get("DURATION").is_none());
+        let todo = builder().build_todo();
+        assert!(todo.alarms().is_empty());
+        assert!(todo.get("TRANSP").is_none());
     }
 
-    /// Get all properties of this event.
-    pub fn properties(&self) -> Vec<(&String, &String)> {
-        self.get_properties("VEVENT")
-    }
+    #[test]
+    fn test_schedule_status() {
+        assert_eq!(ScheduleStatus::parse("1.0"), ScheduleStatus::Pending);
+        assert_eq!(ScheduleStatus::parse("1.1"), ScheduleStatus::Sent);
+        assert_eq!(ScheduleStatus::parse(" 1.2 "), ScheduleStatus::Delivered);
+        assert_eq!(
+            ScheduleStatus::parse("3.7"),
+            ScheduleStatus::InvalidCalendarUser
+        );
+        assert_eq!(
+            ScheduleStatus::parse("3.8"),
+            ScheduleStatus::NoAuthorization
+        );
+        assert_eq!(
+            ScheduleStatus::parse("5.3"),
+            ScheduleStatus::DeliveryFailed("5.3".into())
+        );
+        assert_eq!(
+            ScheduleStatus::parse("2.0"),
+            ScheduleStatus::Other("2.0".into())
+        );
 
-    /// Get all properties of this todo.
-    pub fn properties_todo(&self) -> Vec<(&String, &String)> {
-        self.get_properties("VTODO")
+        assert!(!ScheduleStatus::Delivered.is_failure());
+        assert!(ScheduleStatus::NoAuthorization.is_failure());
+        assert!(ScheduleStatus::DeliveryFailed("5.1".into()).is_failure());
+        assert!(ScheduleStatus::Other("3.1".into()).is_failure());
+        assert!(!ScheduleStatus::Other("2.0".into()).is_failure());
+
+        let statuses =
+            |line: &str| ScheduleStatus::parse_all(&ical::Property::parse(line).unwrap());
+        assert_eq!(
+            statuses("ATTENDEE;SCHEDULE-STATUS=2.0,5.1:mailto:a@example.com"),
+            [
+                ScheduleStatus::Other("2.0".into()),
+                ScheduleStatus::DeliveryFailed("5.1".into())
+            ]
+        );
+        assert_eq!(
+            statuses("ATTENDEE;SCHEDULE-STATUS=\"3.7\":mailto:a@example.com"),
+            [ScheduleStatus::InvalidCalendarUser]
+        );
+        assert!(statuses("ATTENDEE;PARTSTAT=ACCEPTED:mailto:a@example.com").is_empty());
     }
 
-    pub fn etag(&self) -> Option<&String> {
-        self.etag.as_ref()
+    #[test]
+    fn test_attendees() {
+        let mut event = Event::builder(Url::parse("https://example.com/cal/a.ics").unwrap())
+            .uid("a".into())
+            .build();
+        let mut alice = Attendee::new("mailto:alice@example.com");
+        alice.cn = Some("Doe, Alice: \"A\"".into());
+        alice.role = Some("CHAIR".into());
+        event.add_attendee(&alice);
+        event.add_attendee(&Attendee::new("mailto:bob@example.com"));
+
+        let serialized = event.to_string();
+        assert!(serialized.contains("CN=\"Doe, Alice: ^'A^'\""));
+        let mut event = Event::new(None, event.url().clone(), serialized.parse().unwrap());
+        let attendees = event.attendees();
+        assert_eq!(attendees.len(), 2);
+        assert_eq!(attendees[0].email(), "alice@example.com");
+        assert_eq!(attendees[0].cn.as_deref(), Some("Doe, Alice: \"A\""));
+        assert_eq!(attendees[0].role.as_deref(), Some("CHAIR"));
+        assert_eq!(attendees[1].rsvp, Some(true));
+
+        assert!(event.set_partstat("BOB@example.com", "ACCEPTED"));
+        let bob = event.attendee("mailto:bob@example.com").unwrap();
+        assert_eq!(bob.partstat.as_deref(), Some("ACCEPTED"));
+        assert_eq!(bob.rsvp, None);
+        assert_eq!(event.attendee("Bob@example.com"), Some(bob));
+        assert_eq!(event.attendee("carol@example.com"), None);
+
+        assert!(event.remove_attendee("alice@example.com"));
+        assert!(!event.remove_attendee("alice@example.com"));
+        assert_eq!(event.attendees().len(), 1);
     }
 
-    pub fn builder(url: Url) -> EventBuilder {
-        EventBuilder {
-            url,
-            etag: None,
-            properties: vec![],
+    #[test]
+    fn test_delegate() {
+        let url = Url::parse("https://example.com/cal/a.ics").unwrap();
+        let data = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nDTSTART:20240101T100000Z\nRRULE:FREQ=DAILY\nATTENDEE:MAILTO:alice@example.com\nEND:VEVENT\nBEGIN:VEVENT\nUID:a\nRECURRENCE-ID:20240102T100000Z\nDTSTART:20240102T120000Z\nATTENDEE:mailto:alice@example.com\nEND:VEVENT\nEND:VCALENDAR\n";
+        let mut event = Event::from_raw(url, None, data).unwrap();
+
+        let unchanged = event.to_string();
+        assert!(!event.delegate("carol@example.com", "mailto:bob@example.com"));
+        assert_eq!(event.to_string(), unchanged);
+
+        assert!(event.delegate("alice@example.com", "mailto:bob@example.com"));
+        for (vevent, alice) in event
+            .ical()
+            .children
+            .iter()
+            .filter(|c| c.name == "VEVENT")
+            .zip(["MAILTO:alice@example.com", "mailto:alice@example.com"])
+        {
+            let attendees = vevent
+                .properties
+                .iter()
+                .filter(|p| p.name == "ATTENDEE")
+                .map(Attendee::from)
+                .collect::<Vec<_>>();
+            assert_eq!(attendees.len(), 2);
+            assert_eq!(attendees[0].partstat.as_deref(), Some("DELEGATED"));
+            assert_eq!(attendees[0].delegated_to, ["mailto:bob@example.com"]);
+            assert_eq!(attendees[1].email(), "bob@example.com");
+            assert_eq!(attendees[1].delegated_from, [alice]);
         }
-    }
 
-    pub fn set_etag(&mut self, etag: Option<String>) {
-        self.etag = etag
-    }
+        let chain = event.delegation_chain("alice@example.com");
+        assert_eq!(
+            chain.iter().map(|a| a.email()).collect::<Vec<_>>(),
+            ["alice@example.com", "bob@example.com"]
+        );
 
-    pub fn add_component(&mut self, ical: Ical) {
-        self.ical_mut().add_component(ical);
+        // Delegating again does not add the delegate twice.
+        assert!(event.delegate("mailto:ALICE@example.com", "bob@example.com"));
+        assert_eq!(event.attendees().len(), 2);
+        assert_eq!(
+            event.attendees()[0].delegated_to,
+            ["mailto:bob@example.com"]
+        );
     }
 
-    pub fn get_first_component_mut(&mut self) -> Option<&mut Ical> {
-        self.ical.children.first_mut()
+    #[test]
+    fn test_is_modified() {
+        let url = Url::parse("https://example.com/cal/a.ics").unwrap();
+        let data = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nDTSTAMP:20240101T000000Z\nSUMMARY:A\nEND:VEVENT\nEND:VCALENDAR\n";
+        let (mut events, _) = parse_event_refs(
+            &Client::new(),
+            vec![caldav::EventRef {
+                etag: Some("\"1\"".into()),
+                url: url.clone(),
+                data: data.into(),
+                schedule_tag: None,
+            }],
+            vec![],
+            CouldNotParseEvent,
+        );
+        let mut event = events.remove(0);
+        assert!(!event.is_modified());
+        event.ical_mut().children[0].properties[1].value = "20240202T000000Z".into();
+        assert!(!event.is_modified());
+        event.ical_mut().children[0].properties[2].value = "B".into();
+        assert!(event.is_modified());
+        assert!(Event::new(None, url.clone(), Ical::default()).is_modified());
+
+        let restored = Event::from_raw(url.clone(), Some("\"1\"".into()), data).unwrap();
+        assert!(!restored.is_modified());
+
+        // Only DTSTAMP itself is ignored, and the order of parameters does not matter.
+        let data = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nDTSTAMPED:1\nATTENDEE;CN=A;ROLE=CHAIR;RSVP=TRUE:mailto:a@example.com\nEND:VEVENT\nEND:VCALENDAR\n";
+        let mut event = Event::from_raw(url.clone(), Some("\"1\"".into()), data).unwrap();
+        let attendee = &mut event.ical_mut().children[0].properties[2];
+        let mut attributes = attendee.attributes.drain().collect::<Vec<_>>();
+        attributes.reverse();
+        attendee.attributes = attributes.into_iter().collect::<HashMap<_, _>>();
+        assert!(!event.is_modified());
+        event.ical_mut().children[0].properties[1].value = "2".into();
+        assert!(event.is_modified());
+        assert!(Event::from_raw(url, None, "BEGIN:VCALENDAR\n").is_err());
     }
 
-    pub fn get_component_by_recurid(&mut self, recurid: &str) -> Option<&mut Ical> {
-        // search for the first component with name RECURRENCE-ID
-        // where the value matches `recurid`
-        // In other words, return the component with matching RECURRENCE-ID
-        // if it exists
-        self.ical.children.iter_mut().find(|comp| {
-            comp.get_first_property("RECURRENCE-ID")
-                .map(|p| p.value == recurid)
-                .unwrap_or(false)
-        })
+    #[test]
+    fn test_categories() {
+        let url = Url::parse("https://example.com/cal/a.ics").unwrap();
+        let mut event = Event::from_raw(
+            url,
+            None,
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nCATEGORIES:Work,Project\\, Phase 2\nCATEGORIES:Travel\nEND:VEVENT\nEND:VCALENDAR\n",
+        )
+        .unwrap();
+        assert_eq!(event.categories(), ["Work", "Project, Phase 2", "Travel"]);
+        assert!(event.resources().is_empty());
+
+        event.set_categories(vec!["Home".into(), "a;b".into()]);
+        assert_eq!(event.get_all("CATEGORIES").unwrap(), ["Home,a\\;b"]);
+        event.set_resources(vec!["Room 1".into()]);
+        assert_eq!(event.resources(), ["Room 1"]);
+        event.set_categories(vec![]);
+        assert!(event.get("CATEGORIES").is_none());
     }
-}
 
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Property {
-    name: String,
-    value: String,
-    attributes: HashMap<String, String>,
-}
+    #[test]
+    fn test_validate() {
+        let url = Url::parse("https://example.com/cal/a.ics").unwrap();
+        let valid = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nDTSTAMP:20240101T000000Z\nDTSTART;VALUE=DATE:20240101\nEXDATE:20240102,20240103\nEND:VEVENT\nEND:VCALENDAR\n";
+        assert_eq!(
+            Event::from_raw(url.clone(), None, valid)
+                .unwrap()
+                .validate(),
+            []
+        );
 
-impl Property {
-    pub fn new(name: &str, value: &str) -> Self {
-        Self {
-            name: name.into(),
-            value: value.into(),
-            attributes: Default::default(),
-        }
+        let invalid = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nDTSTART:2024-01-01T10:00\nDTEND:20240101T110000\nDURATION:PT1H\nEND:VEVENT\nEND:VCALENDAR\n";
+        let issues = Event::from_raw(url.clone(), None, invalid)
+            .unwrap()
+            .validate();
+        assert_eq!(
+            issues,
+            [
+                ValidationIssue::MissingProperty {
+                    component: "VEVENT".into(),
+                    property: "DTSTAMP".into()
+                },
+                ValidationIssue::ConflictingProperties {
+                    component: "VEVENT".into(),
+                    properties: ("DTEND".into(), "DURATION".into())
+                },
+                ValidationIssue::InvalidValue {
+                    component: "VEVENT".into(),
+                    property: "DTSTART".into(),
+                    value: "2024-01-01T10:00".into()
+                },
+            ]
+        );
+        assert_eq!(issues[0].to_string(), "VEVENT without DTSTAMP");
+
+        let todo = "BEGIN:VCALENDAR\nBEGIN:VTODO\nUID:a\nEND:VTODO\nEND:VCALENDAR\n";
+        assert_eq!(
+            Event::from_raw(url, None, todo).unwrap().validate(),
+            [ValidationIssue::NoEvent]
+        );
+        assert!(valid_date_time("20240229T235960Z"));
+        assert!(!valid_date_time("20241301"));
+        assert!(!valid_date_time("202€0101"));
+        assert!(!valid_date_time("20240101T12€000"));
     }
 
-    pub fn new_with_attributes(name: &str, value: &str, attribs: Vec<(&str, &str)>) -> Self {
-        let mut attributes = HashMap::new();
-        for (k, v) in attribs {
-            attributes.insert(k.into(), v.into());
-        }
-        Self {
-            name: name.into(),
-            value: value.into(),
-            attributes,
+    #[tokio::test]
+    async fn test_save_events() {
+        use crate::test_server::{Response, TestServer};
+
+        // The requests run concurrently, so each server answers all its requests the same way.
+        let stored = TestServer::start(vec![
+            Response::new(201).header("ETag", "\"1\""),
+            Response::new(201).header("ETag", "\"1\""),
+        ]);
+        let failing = TestServer::start(vec![Response::new(500)]);
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let event = |url: Url| {
+            let uid = url.path().to_string();
+            Event::builder(url).uid(uid).build()
+        };
+        let urls = [
+            stored.join("/cal/a.ics"),
+            failing.join("/cal/b.ics"),
+            stored.join("/cal/c.ics"),
+            Url::parse("http://127.0.0.1:1/cal/d.ics").unwrap(),
+        ];
+        let results = save_events(
+            &client,
+            &credentials,
+            urls.iter().cloned().map(event).collect(),
+        )
+        .await;
+
+        assert_eq!(results.len(), 4);
+        for i in [0, 2] {
+            let saved = results[i].as_ref().unwrap();
+            assert_eq!(saved.url(), &urls[i]);
+            assert_eq!(saved.etag(), Some(&"\"1\"".to_string()));
+            assert!(!saved.is_modified());
         }
+        assert!(
+            matches!(&results[1], Err(Http(e)) if e.status == 500 && e.url == urls[1]),
+            "{:?}",
+            results[1]
+        );
+        assert!(
+            matches!(&results[3], Err(ConnectFailed(_))),
+            "{:?}",
+            results[3]
+        );
+        let mut paths = stored
+            .requests()
+            .into_iter()
+            .map(|r| r.url)
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(paths, ["/cal/a.ics", "/cal/c.ics"]);
+        assert_eq!(failing.requests().len(), 1);
     }
 
-    pub fn name(&self) -> &String {
-        &self.name
-    }
+    #[tokio::test]
+    async fn test_save_event_resolving() {
+        use crate::test_server::{Response, TestServer};
 
-    pub fn value(&self) -> &String {
-        &self.value
-    }
+        // A server answering the PUT with 412, the GET with the server version and the second PUT with 201.
+        let server_ics = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:server\r\nBEGIN:VEVENT\r\nUID:a\r\nSUMMARY:Server\r\nLOCATION:Room\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let server = TestServer::start(vec![
+            Response::new(412),
+            Response::new(200).header("ETag", "\"2\"").body(server_ics),
+            Response::new(201).header("ETag", "\"3\""),
+        ]);
+        let url = server.join("/cal/a.ics");
 
-    pub fn into_value(self) -> String {
-        self.value
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let mut event = Event::from_raw(
+            url,
+            Some("\"1\"".into()),
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nSUMMARY:Local\nEND:VEVENT\nEND:VCALENDAR\n",
+        )
+        .unwrap();
+        event.set("SUMMARY", "Local change");
+        let strategy = ConflictStrategy::Merge(Box::new(|local, server| {
+            let mut merged = server.clone();
+            merged.set("SUMMARY", local.get("SUMMARY").unwrap());
+            merged
+        }));
+        let saved = save_event_resolving(&client, &credentials, event, &strategy)
+            .await
+            .unwrap();
+        assert_eq!(saved.etag().map(|e| e.as_str()), Some("\"3\""));
+        assert_eq!(saved.get("SUMMARY").unwrap(), "Local change");
+        assert_eq!(saved.get("LOCATION").unwrap(), "Room");
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].header("If-Match"), Some("\"1\""));
+        assert_eq!(requests[1].method, "GET");
+        assert_eq!(requests[2].method, "PUT");
+        assert_eq!(requests[2].header("If-Match"), Some("\"2\""));
     }
 
-    pub fn attribute(&self, name: &str) -> Option<&String> {
-        self.attributes.get(name)
+    #[tokio::test]
+    async fn test_sync_changes_retries_failed() {
+        use crate::test_server::{Response, TestServer};
+
+        let ctag = Response::multistatus(
+            r#"<d:multistatus xmlns:d="DAV:" xmlns:cs="http://calendarserver.org/ns/">
+                <d:response><d:href>/cal/</d:href><d:propstat>
+                    <d:prop><cs:getctag>1</cs:getctag></d:prop>
+                    <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat></d:response>
+            </d:multistatus>"#,
+        );
+        let object = |name: &str, data: &str| {
+            format!(
+                r#"<d:response><d:href>/cal/{}</d:href><d:propstat>
+                    <d:prop><d:getetag>"1"</d:getetag><c:calendar-data>{}</c:calendar-data></d:prop>
+                    <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat></d:response>"#,
+                name, data
+            )
+        };
+        let multiget = |objects: &[String]| {
+            Response::multistatus(&format!(
+                r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">{}</d:multistatus>"#,
+                objects.concat()
+            ))
+        };
+        let ics = |uid: &str| {
+            format!(
+                "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:{}\nEND:VEVENT\nEND:VCALENDAR\n",
+                uid
+            )
+        };
+        let server = TestServer::start(vec![
+            ctag.clone(),
+            Response::multistatus(
+                r#"<d:multistatus xmlns:d="DAV:">
+                    <d:response><d:href>/cal/a.ics</d:href><d:propstat>
+                        <d:prop><d:getetag>"1"</d:getetag></d:prop><d:status>HTTP/1.1 200 OK</d:status>
+                    </d:propstat></d:response>
+                    <d:response><d:href>/cal/b.ics</d:href><d:propstat>
+                        <d:prop><d:getetag>"1"</d:getetag></d:prop><d:status>HTTP/1.1 200 OK</d:status>
+                    </d:propstat></d:response>
+                    <d:sync-token>token-1</d:sync-token>
+                </d:multistatus>"#,
+            ),
+            multiget(&[
+                object("a.ics", &ics("a")),
+                object("b.ics", "BEGIN:VCALENDAR"),
+            ]),
+            // The ctag did not change, but b.ics could not be parsed.
+            ctag,
+            Response::multistatus(
+                r#"<d:multistatus xmlns:d="DAV:"><d:sync-token>token-2</d:sync-token></d:multistatus>"#,
+            ),
+            multiget(&[object("b.ics", &ics("b"))]),
+        ]);
+        let calendar = server.calendar("/cal/");
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+
+        let (changes, state) = sync_changes(&client, &credentials, &calendar, SyncState::default())
+            .await
+            .unwrap();
+        assert!(
+            matches!(&changes[..], [Change::Updated(a), Change::Error(CouldNotParseEvent(..))] if a.url().path() == "/cal/a.ics")
+        );
+        assert_eq!(state.sync_token.as_deref(), Some("token-1"));
+        assert_eq!(
+            state.failed,
+            HashSet::from([calendar.url().join("b.ics").unwrap()])
+        );
+        assert_eq!(state.etags.len(), 1);
+
+        let (changes, state) = sync_changes(&client, &credentials, &calendar, state)
+            .await
+            .unwrap();
+        assert!(matches!(&changes[..], [Change::Updated(b)] if b.url().path() == "/cal/b.ics"));
+        assert_eq!(state.sync_token.as_deref(), Some("token-2"));
+        assert!(state.failed.is_empty());
+        assert_eq!(state.etags.len(), 2);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 6);
+        assert!(requests[4]
+            .body
+            .contains("<d:sync-token>token-1</d:sync-token>"));
+        assert!(requests[5].body.contains("/cal/b.ics") && !requests[5].body.contains("a.ics"));
     }
-}
 
-impl From<ical::Property> for Property {
-    fn from(p: ical::Property) -> Self {
-        Self {
-            name: p.name,
-            value: p.value,
-            attributes: p.attributes,
-        }
+    #[test]
+    fn test_etag_changes() {
+        let url = |name: &str| {
+            Url::parse("https://example.com/cal/work/")
+                .unwrap()
+                .join(name)
+                .unwrap()
+        };
+        let resource = |name: &str, etag: Option<&str>| caldav::ChangedResource {
+            url: url(name),
+            etag: etag.map(|e| e.to_string()),
+        };
+        let known = HashMap::from([
+            (url("same.ics"), "\"1\"".to_string()),
+            (url("changed.ics"), "\"1\"".to_string()),
+            (url("deleted.ics"), "\"1\"".to_string()),
+        ]);
+        let listed = [
+            resource("same.ics", Some("\"1\"")),
+            resource("changed.ics", Some("\"2\"")),
+            resource("new.ics", Some("\"1\"")),
+            resource("no-etag.ics", None),
+        ];
+        let (mut stale, deleted) = etag_changes(&known, &listed);
+        stale.sort();
+        assert_eq!(
+            stale,
+            [url("changed.ics"), url("new.ics"), url("no-etag.ics")]
+        );
+        assert_eq!(deleted, [url("deleted.ics")]);
     }
-}
 
-impl From<Property> for ical::Property {
-    fn from(p: Property) -> Self {
-        Self {
-            name: p.name,
-            value: p.value,
-            attributes: p.attributes,
+    #[test]
+    fn test_todo() {
+        let url = Url::parse("https://example.com/cal/work/a.ics").unwrap();
+        let ics = "BEGIN:VCALENDAR\nBEGIN:VTODO\nUID:a\nSUMMARY:Write report\\, draft\nSTATUS:IN-PROCESS\nPERCENT-COMPLETE:40\nPRIORITY:0\nEND:VTODO\nEND:VCALENDAR\n";
+        let mut todo = Todo::from(Event::from_raw(url, None, ics).unwrap());
+        assert_eq!(todo.summary().as_deref(), Some("Write report, draft"));
+        assert_eq!(todo.status(), Some(TodoStatus::InProcess));
+        assert_eq!(todo.percent_complete(), Some(40));
+        assert_eq!(todo.priority(), None);
+        assert!(TodoFilter::Open.matches(&todo));
+        assert!(!TodoFilter::Completed.matches(&todo));
+
+        todo.set_percent_complete(100);
+        assert!(todo.is_completed());
+        assert!(TodoFilter::Completed.matches(&todo));
+
+        todo.set_percent_complete(0);
+        todo.set_status(TodoStatus::Cancelled);
+        assert!(!todo.is_completed());
+        assert!(!TodoFilter::Open.matches(&todo));
+        assert!(todo.into_event().to_ics().contains("STATUS:CANCELLED"));
+
+        // Existing properties keep their position and parameters.
+        let mut todo = Todo::from(
+            Event::from_raw(
+                Url::parse("https://example.com/cal/work/d.ics").unwrap(),
+                None,
+                "BEGIN:VCALENDAR\nBEGIN:VTODO\nUID:d\nSTATUS;X-SOURCE=app:NEEDS-ACTION\nDUE;TZID=Europe/Berlin:20240101T100000\nEND:VTODO\nEND:VCALENDAR\n",
+            )
+            .unwrap(),
+        );
+        todo.set_status(TodoStatus::InProcess);
+        let vtodo = todo.event().ical().get("VTODO").unwrap();
+        assert_eq!(vtodo.properties[1].name, "STATUS");
+        assert_eq!(vtodo.properties[1].value, "IN-PROCESS");
+        assert_eq!(vtodo.properties[1].attributes["X-SOURCE"], "app");
+        assert_eq!(vtodo.properties[2].attributes["TZID"], "Europe/Berlin");
+
+        let mut todo = Todo::from(
+            Event::from_raw(
+                Url::parse("https://example.com/cal/work/b.ics").unwrap(),
+                None,
+                "BEGIN:VCALENDAR\nBEGIN:VTODO\nUID:b\nSEQUENCE:3\nSTATUS:NEEDS-ACTION\nEND:VTODO\nEND:VCALENDAR\n",
+            )
+            .unwrap(),
+        );
+        todo.complete_at(1704103200);
+        assert_eq!(todo.status(), Some(TodoStatus::Completed));
+        assert_eq!(
+            todo.get("COMPLETED").map(|s| s.as_str()),
+            Some("20240101T100000Z")
+        );
+        assert_eq!(todo.percent_complete(), Some(100));
+        assert_eq!(todo.get("SEQUENCE").map(|s| s.as_str()), Some("4"));
+        todo.reopen();
+        assert!(!todo.is_completed());
+        assert_eq!(todo.status(), Some(TodoStatus::NeedsAction));
+        assert_eq!(todo.get("SEQUENCE").map(|s| s.as_str()), Some("5"));
+
+        let open = TodoFilter::Open.query().unwrap().to_xml();
+        assert!(open
+            .contains(r#"<c:prop-filter name="COMPLETED"><c:is-not-defined /></c:prop-filter>"#));
+        assert!(TodoFilter::All.query().is_none());
+        assert!(TodoFilter::Completed.query().is_none());
+
+        // Completed by only one of the properties: in exactly one of Open and Completed.
+        for property in ["COMPLETED:20240101T100000Z", "PERCENT-COMPLETE:100"] {
+            let ics = format!(
+                "BEGIN:VCALENDAR\nBEGIN:VTODO\nUID:c\n{}\nEND:VTODO\nEND:VCALENDAR\n",
+                property
+            );
+            let url = Url::parse("https://example.com/cal/work/c.ics").unwrap();
+            let todo = Todo::from(Event::from_raw(url, None, &ics).unwrap());
+            assert!(TodoFilter::Completed.matches(&todo), "{}", property);
+            assert!(!TodoFilter::Open.matches(&todo), "{}", property);
         }
     }
-}
 
-#[derive(Debug)]
-pub struct EventBuilder {
-    url: Url,
-    etag: Option<String>,
-    properties: Vec<ical::Property>,
-}
+    #[tokio::test]
+    async fn test_complete_and_reopen_todo() {
+        use crate::test_server::{Response, TestServer};
 
-impl EventBuilder {
-    fn build_event(self, name: String) -> Event {
-        Event {
-            etag: self.etag,
-            url: self.url,
-            ical: ical::Ical {
-                name: "VCALENDAR".into(),
-                properties: vec![],
-                children: vec![ical::Ical {
-                    name,
-                    properties: self.properties,
-                    children: vec![],
-                }],
-            },
+        let server = TestServer::start(vec![
+            Response::new(204).header("ETag", "\"2\""),
+            Response::new(204).header("ETag", "\"3\""),
+        ]);
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let todo = Todo::from(
+            Event::from_raw(
+                server.join("/cal/t.ics"),
+                Some("\"1\"".into()),
+                "BEGIN:VCALENDAR\nBEGIN:VTODO\nUID:t\nSTATUS:NEEDS-ACTION\nEND:VTODO\nEND:VCALENDAR\n",
+            )
+            .unwrap(),
+        );
+
+        let todo = complete_todo(&client, &credentials, todo).await.unwrap();
+        assert!(todo.is_completed());
+        assert_eq!(todo.etag().map(|e| e.as_str()), Some("\"2\""));
+        let todo = reopen_todo(&client, &credentials, todo).await.unwrap();
+        assert!(!todo.is_completed());
+        assert_eq!(todo.etag().map(|e| e.as_str()), Some("\"3\""));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests
+            .iter()
+            .all(|r| r.method == "PUT" && r.url == "/cal/t.ics"));
+        for line in [
+            "STATUS:COMPLETED",
+            "COMPLETED:",
+            "PERCENT-COMPLETE:100",
+            "SEQUENCE:1",
+        ] {
+            assert!(requests[0].body.contains(line), "{}", line);
         }
+        for line in ["STATUS:NEEDS-ACTION", "PERCENT-COMPLETE:0", "SEQUENCE:2"] {
+            assert!(requests[1].body.contains(line), "{}", line);
+        }
+        assert!(!requests[1].body.contains("\nCOMPLETED:"));
     }
 
-    pub fn build(self) -> Event {
-        self.build_event("VEVENT".into())
-    }
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_todo_complete_recurring() {
+        let url = Url::parse("https://example.com/cal/work/r.ics").unwrap();
+        let ics = "BEGIN:VCALENDAR\nBEGIN:VTODO\nUID:r\nSEQUENCE:1\nDUE:20240101T120000Z\nRRULE:FREQ=DAILY;COUNT=2\nSTATUS:NEEDS-ACTION\nEND:VTODO\nEND:VCALENDAR\n";
+        let mut todo = Todo::from(Event::from_raw(url, None, ics).unwrap());
+        todo.complete_at(1704103200);
+        assert!(!todo.is_completed());
+        assert_eq!(todo.status(), Some(TodoStatus::NeedsAction));
+        assert_eq!(
+            todo.get("DUE").map(|s| s.as_str()),
+            Some("20240102T120000Z")
+        );
+        assert_eq!(todo.get("SEQUENCE").map(|s| s.as_str()), Some("2"));
 
-    pub fn build_todo(self) -> Event {
-        self.build_event("VTODO".into())
+        // The last instance completes the todo.
+        todo.complete_at(1704189600);
+        assert!(todo.is_completed());
+        assert_eq!(
+            todo.get("COMPLETED").map(|s| s.as_str()),
+            Some("20240102T100000Z")
+        );
+        assert_eq!(todo.get("SEQUENCE").map(|s| s.as_str()), Some("3"));
     }
 
-    pub fn etag(mut self, etag: Option<String>) -> Self {
-        self.etag = etag;
-        self
-    }
+    #[test]
+    fn test_todo_tree() {
+        let todo = |uid: &str, related: &str| {
+            let ics = format!(
+                "BEGIN:VCALENDAR\nBEGIN:VTODO\nUID:{}\n{}END:VTODO\nEND:VCALENDAR\n",
+                uid, related
+            );
+            let url = Url::parse(&format!("https://example.com/cal/{}.ics", uid)).unwrap();
+            Todo::from(Event::from_raw(url, None, &ics).unwrap())
+        };
+        let todos = vec![
+            todo("child", "RELATED-TO:root\n"),
+            todo("root", "RELATED-TO;RELTYPE=CHILD:other-child\n"),
+            todo("other-child", ""),
+            todo("grandchild", "RELATED-TO;RELTYPE=PARENT:child\n"),
+            todo("orphan", "RELATED-TO:missing\n"),
+            todo("cycle-a", "RELATED-TO:cycle-b\n"),
+            todo("cycle-b", "RELATED-TO:cycle-a\n"),
+        ];
+        assert_eq!(todos[0].parent_uid().as_deref(), Some("root"));
+        assert_eq!(todos[1].relations()[0].rel_type, RelationType::Child);
 
-    pub fn uid(mut self, value: String) -> Self {
-        self.properties.push(ical::Property {
-            name: "UID".to_string(),
-            value,
-            attributes: HashMap::new(),
-        });
-        self
-    }
+        fn uids(nodes: &[TodoNode]) -> Vec<String> {
+            nodes
+                .iter()
+                .map(|n| {
+                    let uid = n.todo.uid().unwrap().clone();
+                    match uids(&n.children) {
+                        children if children.is_empty() => uid,
+                        children => format!("{}({})", uid, children.join(",")),
+                    }
+                })
+                .collect()
+        }
+        assert_eq!(
+            uids(&todo_tree(todos)),
+            [
+                "root(child(grandchild),other-child)",
+                "orphan",
+                "cycle-a(cycle-b)"
+            ]
+        );
 
-    pub fn timestamp(mut self, value: String) -> Self {
-        self.properties.push(ical::Property {
-            name: "DTSTAMP".to_string(),
-            value,
-            attributes: HashMap::new(),
-        });
-        self
-    }
+        // A todo whose parent is on a cycle is not part of the cycle and stays a subtask.
+        let todos = vec![
+            todo("tail", "RELATED-TO:loop-b\n"),
+            todo("loop-c", "RELATED-TO:loop-b\n"),
+            todo("loop-b", "RELATED-TO:loop-c\n"),
+        ];
+        assert_eq!(uids(&todo_tree(todos)), ["loop-c(loop-b(tail))"]);
+        let todos = vec![
+            todo("loop-b", "RELATED-TO:loop-c\n"),
+            todo("loop-c", "RELATED-TO:loop-b\n"),
+            todo("tail", "RELATED-TO:loop-b\n"),
+        ];
+        assert_eq!(uids(&todo_tree(todos)), ["loop-b(loop-c,tail)"]);
 
-    pub fn summary(mut self, value: String) -> Self {
-        self.properties.push(ical::Property {
-            name: "SUMMARY".to_string(),
-            value,
-            attributes: HashMap::new(),
-        });
-        self
+        let mut moved = todo(
+            "grandchild",
+            "RELATED-TO;RELTYPE=PARENT:child\nRELATED-TO;RELTYPE=SIBLING:x\n",
+        );
+        moved.set_parent_uid(Some("root"));
+        assert_eq!(moved.parent_uid().as_deref(), Some("root"));
+        assert_eq!(moved.relations().len(), 2);
+        moved.set_parent_uid(None);
+        assert_eq!(moved.parent_uid(), None);
     }
 
-    pub fn priority(mut self, value: String) -> Self {
-        self.properties.push(ical::Property {
-            name: "PRIORITY".to_string(),
-            value,
-            attributes: HashMap::new(),
-        });
-        self
-    }
+    #[test]
+    fn test_journal() {
+        let url = Url::parse("https://example.com/cal/diary/entry.ics").unwrap();
+        let mut journal = Journal::builder(url.clone())
+            .uid("entry".into())
+            .timestamp("20240101T120000Z".into())
+            .summary("Holidays; day 1")
+            .description("Arrived, rained.\nWent to bed early.")
+            .start("20240101".into(), vec![("VALUE", "DATE")])
+            .categories(vec!["travel".into(), "family".into()])
+            .build();
+        let ics = journal.event().to_ics();
+        assert!(ics.contains("BEGIN:VJOURNAL"));
+        assert!(ics.contains("SUMMARY:Holidays\\; day 1"));
 
-    pub fn duedate(mut self, value: String) -> Self {
-        self.properties.push(ical::Property {
-            name: "DUE".to_string(),
-            value,
-            attributes: HashMap::new(),
-        });
-        self
-    }
+        let parsed = Journal::from(Event::from_raw(url.clone(), None, &ics).unwrap());
+        assert_eq!(parsed.uid().map(|s| s.as_str()), Some("entry"));
+        assert_eq!(parsed.summary().as_deref(), Some("Holidays; day 1"));
+        assert_eq!(
+            parsed.description().as_deref(),
+            Some("Arrived, rained.\nWent to bed early.")
+        );
+        assert_eq!(parsed.start().map(|s| s.as_str()), Some("20240101"));
+        assert_eq!(parsed.categories(), ["travel", "family"]);
 
-    pub fn status(mut self, value: String) -> Self {
-        self.properties.push(ical::Property {
-            name: "STATUS".to_string(),
-            value,
-            attributes: HashMap::new(),
-        });
-        self
-    }
+        journal.set_categories(Vec::new());
+        journal.set_summary("Holidays");
+        assert!(journal.categories().is_empty());
+        assert_eq!(journal.summary().as_deref(), Some("Holidays"));
 
-    pub fn generic(mut self, name: String, value: String) -> Self {
-        self.properties.push(ical::Property {
-            name,
-            value,
-            attributes: HashMap::new(),
-        });
-        self
+        let mut journal = Journal::from(
+            Event::from_raw(
+                url,
+                None,
+                "BEGIN:VCALENDAR\nBEGIN:VJOURNAL\nUID:entry\nSUMMARY;LANGUAGE=de:Urlaub\nDTSTART;VALUE=DATE:20240101\nEND:VJOURNAL\nEND:VCALENDAR\n",
+            )
+            .unwrap(),
+        );
+        journal.set_summary("Ferien");
+        let vjournal = journal.event().ical().get("VJOURNAL").unwrap();
+        assert_eq!(vjournal.properties[1].name, "SUMMARY");
+        assert_eq!(vjournal.properties[1].value, "Ferien");
+        assert_eq!(vjournal.properties[1].attributes["LANGUAGE"], "de");
     }
 
-    pub fn location(mut self, value: Option<String>) -> Self {
-        if let Some(value) = value {
-            self.properties.push(ical::Property {
-                name: "LOCATION".to_string(),
-                value,
-                attributes: HashMap::new(),
-            });
-        }
-        self
+    #[test]
+    fn test_diff() {
+        let url = Url::parse("https://example.com/cal/a.ics").unwrap();
+        let old = Event::from_raw(
+            url.clone(),
+            None,
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nDTSTAMP:20240101T000000Z\nSEQUENCE:1\nSUMMARY:Old\nATTENDEE;PARTSTAT=NEEDS-ACTION:mailto:a@example.com\nATTENDEE:mailto:b@example.com\nLOCATION:Room\nEND:VEVENT\nEND:VCALENDAR\n",
+        )
+        .unwrap();
+        let new = Event::from_raw(
+            url,
+            None,
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nDTSTAMP:20240202T000000Z\nSEQUENCE:2\nSUMMARY:New\nATTENDEE:mailto:b@example.com\nATTENDEE;PARTSTAT=ACCEPTED:mailto:a@example.com\nBEGIN:VALARM\nTRIGGER:-PT5M\nEND:VALARM\nEND:VEVENT\nBEGIN:VEVENT\nUID:a\nRECURRENCE-ID:20240102T100000Z\nSUMMARY:Moved\nEND:VEVENT\nEND:VCALENDAR\n",
+        )
+        .unwrap();
+        assert!(old.diff(&old).is_empty());
+        assert_eq!(
+            old.diff(&new)
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>(),
+            [
+                "VCALENDAR/VEVENT: changed ATTENDEE \"mailto:a@example.com\" to \"mailto:a@example.com\"",
+                "VCALENDAR/VEVENT: changed SUMMARY \"Old\" to \"New\"",
+                "VCALENDAR/VEVENT: removed LOCATION \"Room\"",
+                "VCALENDAR/VEVENT/VALARM: added TRIGGER \"-PT5M\"",
+                "VCALENDAR/VEVENT[20240102T100000Z]: added UID \"a\"",
+                "VCALENDAR/VEVENT[20240102T100000Z]: added RECURRENCE-ID \"20240102T100000Z\"",
+                "VCALENDAR/VEVENT[20240102T100000Z]: added SUMMARY \"Moved\"",
+            ]
+        );
+        assert!(matches!(
+            &new.diff(&old)[0],
+            PropertyChange::Changed { old, new, .. }
+                if old.attribute("PARTSTAT").map(|p| p.as_str()) == Some("ACCEPTED")
+                    && new.attribute("PARTSTAT").map(|p| p.as_str()) == Some("NEEDS-ACTION")
+        ));
     }
 
-    pub fn start(mut self, value: String, attributes: Vec<(&str, &str)>) -> Self {
-        let mut attribs = HashMap::new();
-        for (k, v) in attributes {
-            attribs.insert(k.into(), v.into());
-        }
-        self.properties.push(ical::Property {
-            name: "DTSTART".to_string(),
-            value,
-            attributes: attribs,
-        });
-        self
-    }
+    #[test]
+    fn test_availability() {
+        let data = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//Example//EN\r\nBEGIN:VAVAILABILITY\r\nUID:work\r\nDTSTAMP:20240101T000000Z\r\nBUSYTYPE:BUSY-TENTATIVE\r\nBEGIN:AVAILABLE\r\nUID:weekdays\r\nDTSTAMP:20240101T000000Z\r\nDTSTART;TZID=Europe/Berlin:20240101T090000\r\nDTEND;TZID=Europe/Berlin:20240101T170000\r\nRRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR\r\nSUMMARY:Office\r\nEND:AVAILABLE\r\nEND:VAVAILABILITY\r\nEND:VCALENDAR\r\n";
+        let availability: Availability = data.parse().unwrap();
+        assert_eq!(
+            availability.busy_type().map(|b| b.as_str()),
+            Some("BUSY-TENTATIVE")
+        );
+        let slots = availability.available_slots();
+        assert_eq!(slots.len(), 1);
+        assert_eq!(slots[0].uid, "weekdays");
+        assert_eq!(slots[0].start.value(), "20240101T090000");
+        assert_eq!(
+            slots[0].start.attribute("TZID").map(|t| t.as_str()),
+            Some("Europe/Berlin")
+        );
+        assert_eq!(slots[0].end.as_ref().unwrap().value(), "20240101T170000");
+        assert_eq!(slots[0].summary.as_deref(), Some("Office"));
+        assert!(Availability::parse("BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n").is_err());
 
-    pub fn end(mut self, value: String, attributes: Vec<(&str, &str)>) -> Self {
-        let mut attribs = HashMap::new();
-        for (k, v) in attributes {
-            attribs.insert(k.into(), v.into());
-        }
-        self.properties.push(ical::Property {
-            name: "DTEND".to_string(),
-            value,
-            attributes: attribs,
-        });
-        self
+        let slot = |uid: &str| AvailableSlot {
+            uid: uid.into(),
+            start: Property::new("DTSTART", "20240101T090000Z"),
+            end: Some(Property::new("DTEND", "20240101T120000Z")),
+            rrule: Some("FREQ=DAILY".into()),
+            summary: None,
+        };
+        let built = Availability::builder()
+            .uid("work".into())
+            .timestamp("20240101T000000Z".into())
+            .busy_type("BUSY".into())
+            .available(slot("morning"))
+            .available(slot(""))
+            .build();
+        let parsed = Availability::parse(&built.serialize()).unwrap();
+        assert_eq!(parsed, built);
+        let slots = parsed.available_slots();
+        assert_eq!(slots[0], slot("morning"));
+        // A slot without UID gets a stable one.
+        assert!(slots[1].uid.ends_with("@minicaldav"));
+        let again = Availability::builder().available(slot("")).build();
+        assert_eq!(again.available_slots()[0].uid, slots[1].uid);
+        assert!(!built.serialize().contains("UID:\r\n"));
     }
 
-    pub fn description(mut self, value: Option<String>) -> Self {
-        if let Some(value) = value {
-            self.properties.push(ical::Property {
-                name: "DESCRIPTION".to_string(),
-                value,
-                attributes: HashMap::new(),
-            });
-        }
-        self
+    #[test]
+    fn test_free_busy() {
+        let data = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//Example//EN\r\nMETHOD:REPLY\r\nBEGIN:VFREEBUSY\r\nUID:fb-1\r\nDTSTAMP:20240101T000000Z\r\nDTSTART:20240101T000000Z\r\nDTEND:20240102T000000Z\r\nORGANIZER:mailto:alice@example.com\r\nATTENDEE:mailto:bob@example.com\r\nFREEBUSY;FBTYPE=BUSY-TENTATIVE:20240101T090000Z/PT1H,20240101T140000Z/20240101T150000Z\r\nFREEBUSY:20240101T120000Z/20240101T130000Z\r\nFREEBUSY;FBTYPE=FREE:20240101T160000Z/PT2H\r\nEND:VFREEBUSY\r\nEND:VCALENDAR\r\n";
+        let free_busy: FreeBusy = data.parse().unwrap();
+        assert_eq!(free_busy.method().map(|m| m.as_str()), Some("REPLY"));
+        assert_eq!(
+            free_busy.organizer().map(|o| o.as_str()),
+            Some("mailto:alice@example.com")
+        );
+        assert_eq!(free_busy.attendees(), ["mailto:bob@example.com"]);
+        assert_eq!(
+            free_busy.periods(),
+            [
+                FreeBusyPeriod {
+                    fb_type: "BUSY-TENTATIVE".into(),
+                    start: "20240101T090000Z".into(),
+                    end: "PT1H".into(),
+                },
+                FreeBusyPeriod {
+                    fb_type: "BUSY-TENTATIVE".into(),
+                    start: "20240101T140000Z".into(),
+                    end: "20240101T150000Z".into(),
+                },
+                FreeBusyPeriod::busy("20240101T120000Z", "20240101T130000Z"),
+                FreeBusyPeriod {
+                    fb_type: "FREE".into(),
+                    start: "20240101T160000Z".into(),
+                    end: "PT2H".into(),
+                },
+            ]
+        );
+        assert_eq!(free_busy.busy_periods().len(), 3);
+        assert!(FreeBusy::parse("BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n").is_err());
     }
 
-    pub fn rrule(mut self, value: Option<String>) -> Self {
-        if let Some(value) = value {
-            self.properties.push(ical::Property {
-                name: "RRULE".to_string(),
-                value,
-                attributes: HashMap::new(),
-            });
-        }
-        self
+    #[test]
+    fn test_free_busy_builder() {
+        let free_busy = FreeBusy::builder()
+            .method("REQUEST".into())
+            .uid("fb-2".into())
+            .timestamp("20240101T000000Z".into())
+            .start("20240101T000000Z".into())
+            .end("20240102T000000Z".into())
+            .organizer("mailto:alice@example.com".into())
+            .attendee("mailto:bob@example.com".into())
+            .period(FreeBusyPeriod::busy("20240101T120000Z", "PT30M"))
+            .build();
+        let parsed = FreeBusy::parse(&free_busy.serialize()).unwrap();
+        assert_eq!(parsed, free_busy);
+        assert_eq!(parsed.method().map(|m| m.as_str()), Some("REQUEST"));
+        assert_eq!(
+            parsed.start().map(|p| p.value().clone()),
+            Some("20240101T000000Z".to_string())
+        );
+        assert_eq!(
+            parsed.periods(),
+            [FreeBusyPeriod::busy("20240101T120000Z", "PT30M")]
+        );
     }
 }
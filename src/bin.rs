@@ -16,20 +16,6 @@
 
 //! Simple CLI tool mostly for testing purposes currently.
 
-#[cfg(feature = "cli")]
-#[macro_use]
-extern crate log;
-
-#[cfg(feature = "cli")]
-mod api;
-#[cfg(feature = "cli")]
-mod caldav;
-#[cfg(feature = "cli")]
-mod ical;
-
-#[cfg(feature = "cli")]
-mod credentials;
-
 #[cfg(not(feature = "cli"))]
 pub fn main() {}
 
@@ -37,13 +23,12 @@ pub fn main() {}
 pub fn main() {
     env_logger::init();
 
-    use api::Credentials;
-    use ureq::Agent;
+    use minicaldav::{blocking, Client, Credentials};
     use url::Url;
 
     #[rustfmt::skip]
     const FUNCTIONS: [(&str, &str); 2] = [
-        ("get_calendars                        ", "Get a list of calendars without events",),
+        ("get_calendars [--json]               ", "Get a list of calendars without events",),
         ("get_events    <Name of the calendar> ", "Get a list of all events in the given calendar."),
     ];
 
@@ -55,7 +40,7 @@ pub fn main() {
         println!("Use either one of:\n{}", functions);
     }
 
-    fn login() -> (String, String, String) {
+    fn login() -> (Url, Credentials) {
         let url = if let Ok(url) = std::env::var("URL") {
             url
         } else {
@@ -66,18 +51,23 @@ pub fn main() {
         } else {
             read("Enter email")
         };
-        println!("Enter password or token (start with 'Bearer')");
+        eprintln!("Enter password or token (start with 'Bearer')");
         let password = rpassword::read_password().unwrap();
-        println!("loading...");
-        (url, email, password)
+        eprintln!("loading...");
+        let credentials = if password.starts_with("Bearer") {
+            Credentials::Bearer(password.replace("Bearer", "").trim().to_string())
+        } else {
+            Credentials::Basic(email, password)
+        };
+        (Url::parse(url.trim()).unwrap(), credentials)
     }
 
     fn read(message: &str) -> String {
-        println!("{}", message);
+        eprintln!("{}", message);
         let mut buffer = String::new();
         let stdin = std::io::stdin();
         stdin.read_line(&mut buffer).unwrap();
-        buffer
+        buffer.trim().to_string()
     }
 
     let args: Vec<String> = std::env::args().collect();
@@ -87,45 +77,35 @@ pub fn main() {
     }
 
     let fun = args.get(1).unwrap();
-    let agent = Agent::new();
+    let client = Client::new();
     match fun.as_str() {
         "get_calendars" => {
-            let (url, user, pswd) = login();
-
-            let credentials = if pswd.starts_with("Bearer") {
-                Credentials::Bearer(pswd.replace("Bearer", ""))
+            let (url, credentials) = login();
+            let calendars = blocking::get_calendars(&client, &credentials, url).unwrap();
+            let infos = calendars.iter().map(CalendarInfo::new).collect::<Vec<_>>();
+            if args.iter().any(|a| a == "--json") {
+                println!("{}", serde_json::to_string_pretty(&infos).unwrap());
             } else {
-                Credentials::Basic(user, pswd)
-            };
-            let calendars =
-                api::get_calendars(agent, &credentials, &Url::parse(&url).unwrap()).unwrap();
-            for calendar in calendars {
-                println!("{} {}", calendar.name(), calendar.url().as_str());
+                print!("{}", format_table(&infos));
             }
         }
         "get_events" => {
-            let (url, user, pswd) = login();
-
-            let credentials = if pswd.starts_with("Bearer") {
-                Credentials::Bearer(pswd.replace("Bearer", "").trim().to_string())
-            } else {
-                Credentials::Basic(user, pswd)
-            };
+            let (url, credentials) = login();
 
             let name = if args.len() >= 3 {
                 args.get(2).unwrap().clone()
             } else {
                 read("Calendar name:")
             };
-            let calendars =
-                api::get_calendars(agent.clone(), &credentials, &Url::parse(&url).unwrap())
-                    .unwrap();
+            let calendars = blocking::get_calendars(&client, &credentials, url).unwrap();
 
-            println!("search for calendar '{:?}'", name);
+            eprintln!("search for calendar '{:?}'", name);
 
             for calendar in calendars {
                 if calendar.name() == &name {
-                    let events = api::get_events(agent.clone(), &credentials, &calendar).unwrap();
+                    let events =
+                        blocking::get_events(&client, &credentials, &calendar, None, None, false)
+                            .unwrap();
                     for event in events.0 {
                         for (k, v) in event.properties() {
                             println!("{}: {}", k, v);
@@ -138,3 +118,169 @@ pub fn main() {
         _ => help(),
     }
 }
+
+#[cfg(feature = "cli")]
+#[derive(serde::Serialize)]
+struct CalendarInfo<'a> {
+    name: &'a str,
+    url: &'a str,
+    color: Option<&'a str>,
+    components: &'a [String],
+    /// The privileges of the current user as reported by the server.
+    privileges: &'a [String],
+    writable: bool,
+    subscription: bool,
+}
+
+#[cfg(feature = "cli")]
+impl<'a> CalendarInfo<'a> {
+    fn new(calendar: &'a minicaldav::Calendar) -> Self {
+        Self {
+            name: calendar.name(),
+            url: calendar.url().as_str(),
+            color: calendar.color().map(|c| c.as_str()),
+            components: calendar.components(),
+            privileges: &calendar.calendar_ref().privileges,
+            writable: calendar.writable(),
+            subscription: calendar.is_subscription(),
+        }
+    }
+
+    fn columns(&self) -> [String; 7] {
+        let yes_no = |b: bool| if b { "yes" } else { "no" }.to_string();
+        let list = |values: &[String]| {
+            if values.is_empty() {
+                "-".to_string()
+            } else {
+                values.join(",")
+            }
+        };
+        [
+            self.name.to_string(),
+            self.color.unwrap_or("-").to_string(),
+            self.components.join(","),
+            list(self.privileges),
+            yes_no(self.writable),
+            yes_no(self.subscription),
+            self.url.to_string(),
+        ]
+    }
+}
+
+#[cfg(feature = "cli")]
+fn format_table(calendars: &[CalendarInfo]) -> String {
+    let header = [
+        "NAME",
+        "COLOR",
+        "COMPONENTS",
+        "PRIVILEGES",
+        "WRITABLE",
+        "SUBSCRIPTION",
+        "URL",
+    ]
+    .map(String::from);
+    let rows = std::iter::once(header)
+        .chain(calendars.iter().map(CalendarInfo::columns))
+        .collect::<Vec<_>>();
+    let mut widths = [0; 7];
+    for row in &rows {
+        for (width, column) in widths.iter_mut().zip(row) {
+            *width = (*width).max(column.chars().count());
+        }
+    }
+    let mut table = String::new();
+    for row in rows {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(column, width)| format!("{:width$}", column, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use super::*;
+    use minicaldav::caldav::CalendarRef;
+    use minicaldav::Calendar;
+    use url::Url;
+
+    fn calendar(privileges: &[&str]) -> Calendar {
+        Calendar::from_parts(
+            Url::parse("https://example.com/").unwrap(),
+            CalendarRef {
+                url: Url::parse("https://example.com/cal/work/").unwrap(),
+                name: "Work".into(),
+                color: Some("#ff0000".into()),
+                privileges: privileges.iter().map(|p| p.to_string()).collect(),
+                components: vec!["VEVENT".into(), "VTODO".into()],
+                is_subscription: false,
+                source_url: None,
+                enabled: None,
+                order: None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_format_table() {
+        let writable = calendar(&["read", "all"]);
+        let read_only = calendar(&[]);
+        let table = format_table(&[CalendarInfo::new(&writable), CalendarInfo::new(&read_only)]);
+        let rows = table
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            [
+                vec![
+                    "NAME",
+                    "COLOR",
+                    "COMPONENTS",
+                    "PRIVILEGES",
+                    "WRITABLE",
+                    "SUBSCRIPTION",
+                    "URL"
+                ],
+                vec![
+                    "Work",
+                    "#ff0000",
+                    "VEVENT,VTODO",
+                    "read,all",
+                    "yes",
+                    "no",
+                    "https://example.com/cal/work/"
+                ],
+                vec![
+                    "Work",
+                    "#ff0000",
+                    "VEVENT,VTODO",
+                    "-",
+                    "no",
+                    "no",
+                    "https://example.com/cal/work/"
+                ],
+            ]
+        );
+        // The columns are aligned.
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0].find("PRIVILEGES"), lines[1].find("read,all"));
+    }
+
+    #[test]
+    fn test_json() {
+        let calendar = calendar(&["read", "write-content"]);
+        let json = serde_json::to_value([CalendarInfo::new(&calendar)]).unwrap();
+        assert_eq!(
+            json[0]["privileges"],
+            serde_json::json!(["read", "write-content"])
+        );
+        assert_eq!(json[0]["writable"], true);
+        assert_eq!(json[0]["url"], "https://example.com/cal/work/");
+    }
+}
@@ -0,0 +1,369 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Synchronous versions of the main API functions for CLI tools and simple scripts.
+//!
+//! The requests are run on an internal runtime. Do not call these functions from within an async runtime,
+//! they panic there. Call them in `tokio::task::spawn_blocking` instead.
+//!
+//! ```rust,no_run
+//! let client = minicaldav::Client::new();
+//! let url = url::Url::parse("http://mycaldav.com/").unwrap();
+//! let credentials = minicaldav::Credentials::Basic("foo".into(), "s3cret!".into());
+//! let calendars = minicaldav::blocking::get_calendars(&client, &credentials, url).unwrap();
+//! for calendar in calendars {
+//!     let (events, errors) =
+//!         minicaldav::blocking::get_events(&client, &credentials, &calendar, None, None, false)
+//!             .unwrap();
+//!     println!("{} events, {} errors", events.len(), errors.len());
+//! }
+//! ```
+
+use std::future::Future;
+use std::sync::OnceLock;
+
+use crate::client::Client;
+use url::Url;
+
+use crate::caldav::CalendarQuery;
+use crate::errors::MiniCaldavError;
+use crate::{
+    Calendar, Change, ConflictStrategy, Credentials, Event, Journal, Progress, SyncState, Todo,
+    TodoFilter,
+};
+
+/// Run the given future to completion on the shared runtime.
+/// All requests use the same runtime so connections of a `Client` stay usable between calls.
+fn block_on<F: Future>(future: F) -> F::Output {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    RUNTIME
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("Could not create runtime for blocking requests")
+        })
+        .block_on(future)
+}
+
+/// Simple connection check to the DAV server
+pub fn check_connection(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+) -> Result<Url, MiniCaldavError> {
+    block_on(crate::check_connection(client, credentials, base_url))
+}
+
+/// Get all calendars from the given CalDAV endpoint.
+pub fn get_calendars(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: Url,
+) -> Result<Vec<Calendar>, MiniCaldavError> {
+    block_on(crate::get_calendars(client, credentials, base_url))
+}
+
+/// Get the todos in the given `Calendar` matching `filter`, see `minicaldav::get_todos`.
+pub fn get_todos(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    filter: TodoFilter,
+) -> Result<(Vec<Todo>, Vec<MiniCaldavError>), MiniCaldavError> {
+    block_on(crate::get_todos(client, credentials, calendar, filter))
+}
+
+/// Mark the todo as done and save it, see `minicaldav::complete_todo`.
+pub fn complete_todo(
+    client: &Client,
+    credentials: &Credentials,
+    todo: Todo,
+) -> Result<Todo, MiniCaldavError> {
+    block_on(crate::complete_todo(client, credentials, todo))
+}
+
+/// Mark the todo as not done and save it, see `minicaldav::reopen_todo`.
+pub fn reopen_todo(
+    client: &Client,
+    credentials: &Credentials,
+    todo: Todo,
+) -> Result<Todo, MiniCaldavError> {
+    block_on(crate::reopen_todo(client, credentials, todo))
+}
+
+/// Get all journal entries in the given `Calendar`, see `minicaldav::get_journals`.
+pub fn get_journals(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+) -> Result<(Vec<Journal>, Vec<MiniCaldavError>), MiniCaldavError> {
+    block_on(crate::get_journals(client, credentials, calendar))
+}
+
+/// Save the given journal entry, see `minicaldav::save_journal`.
+pub fn save_journal(
+    client: &Client,
+    credentials: &Credentials,
+    journal: Journal,
+) -> Result<Journal, MiniCaldavError> {
+    block_on(crate::save_journal(client, credentials, journal))
+}
+
+/// Delete the given journal entry, see `minicaldav::remove_journal`.
+pub fn remove_journal(
+    client: &Client,
+    credentials: &Credentials,
+    journal: Journal,
+) -> Result<(), MiniCaldavError> {
+    block_on(crate::remove_journal(client, credentials, journal))
+}
+
+/// Get all events in the given `Calendar`, see `minicaldav::get_events`.
+pub fn get_events(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    start: Option<String>,
+    end: Option<String>,
+    expanded: bool,
+) -> Result<(Vec<Event>, Vec<MiniCaldavError>), MiniCaldavError> {
+    block_on(crate::get_events(
+        client,
+        credentials,
+        calendar,
+        start,
+        end,
+        expanded,
+    ))
+}
+
+/// Get all events in the given `Calendar` and report the download progress,
+/// see `minicaldav::get_events_with_progress`.
+pub fn get_events_with_progress<F: FnMut(Progress)>(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    start: Option<String>,
+    end: Option<String>,
+    expanded: bool,
+    progress: F,
+) -> Result<(Vec<Event>, Vec<MiniCaldavError>), MiniCaldavError> {
+    block_on(crate::get_events_with_progress(
+        client,
+        credentials,
+        calendar,
+        start,
+        end,
+        expanded,
+        progress,
+    ))
+}
+
+/// Get the events or todos of the given calendar matching the given query.
+pub fn query_events(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    query: &CalendarQuery,
+) -> Result<(Vec<Event>, Vec<MiniCaldavError>), MiniCaldavError> {
+    block_on(crate::query_events(client, credentials, calendar, query))
+}
+
+/// Save the given event on the CalDAV server, see `minicaldav::save_event`.
+pub fn save_event(
+    client: &Client,
+    credentials: &Credentials,
+    event: Event,
+) -> Result<Event, MiniCaldavError> {
+    block_on(crate::save_event(client, credentials, event))
+}
+
+/// Check whether the server stored the given event, see `minicaldav::verify_saved_event`.
+pub fn verify_saved_event(
+    client: &Client,
+    credentials: &Credentials,
+    event: &Event,
+) -> Result<Option<Event>, MiniCaldavError> {
+    block_on(crate::verify_saved_event(client, credentials, event))
+}
+
+/// Check whether the server removed the given event, see `minicaldav::verify_removed_event`.
+pub fn verify_removed_event(
+    client: &Client,
+    credentials: &Credentials,
+    event: &Event,
+) -> Result<bool, MiniCaldavError> {
+    block_on(crate::verify_removed_event(client, credentials, event))
+}
+
+/// Save the given event resolving conflicts with `strategy`, see `minicaldav::save_event_resolving`.
+pub fn save_event_resolving(
+    client: &Client,
+    credentials: &Credentials,
+    event: Event,
+    strategy: &ConflictStrategy,
+) -> Result<Event, MiniCaldavError> {
+    block_on(crate::save_event_resolving(
+        client,
+        credentials,
+        event,
+        strategy,
+    ))
+}
+
+/// Save the given events on the CalDAV server, see `minicaldav::save_events`.
+pub fn save_events(
+    client: &Client,
+    credentials: &Credentials,
+    events: Vec<Event>,
+) -> Vec<Result<Event, MiniCaldavError>> {
+    block_on(crate::save_events(client, credentials, events))
+}
+
+/// Create the given event in the calendar at a url derived from its UID, see `minicaldav::create_event`.
+pub fn create_event(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    event: Event,
+) -> Result<Event, MiniCaldavError> {
+    block_on(crate::create_event(client, credentials, calendar, event))
+}
+
+/// Remove the given event on the CalDAV server.
+pub fn remove_event(
+    client: &Client,
+    credentials: &Credentials,
+    event: Event,
+) -> Result<(), MiniCaldavError> {
+    block_on(crate::remove_event(client, credentials, event))
+}
+
+pub fn create_calendar(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calid: String,
+    name: String,
+    color: String,
+) -> Result<(), MiniCaldavError> {
+    block_on(crate::create_calendar(
+        client,
+        credentials,
+        base_url,
+        calid,
+        name,
+        color,
+    ))
+}
+
+pub fn remove_calendar(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calid: String,
+) -> Result<(), MiniCaldavError> {
+    block_on(crate::remove_calendar(client, credentials, base_url, calid))
+}
+
+/// Get the changes of the given calendar since the last call, see `minicaldav::sync_changes`.
+pub fn sync_changes(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    state: SyncState,
+) -> Result<(Vec<Change>, SyncState), MiniCaldavError> {
+    block_on(crate::sync_changes(client, credentials, calendar, state))
+}
+
+/// Send the queued offline changes to the server, see `minicaldav::offline::OfflineQueue::replay`.
+pub fn replay_offline_queue(
+    queue: &crate::offline::OfflineQueue,
+    client: &Client,
+    credentials: &Credentials,
+) -> crate::offline::ReplayReport {
+    block_on(queue.replay(client, credentials))
+}
+
+/// Copy the events of `source` into `target`, see `minicaldav::mirror::mirror`.
+pub fn mirror(
+    client: &Client,
+    credentials: &Credentials,
+    source: &Calendar,
+    target: &Calendar,
+    options: &crate::mirror::MirrorOptions,
+) -> Result<crate::mirror::MirrorReport, MiniCaldavError> {
+    block_on(crate::mirror::mirror(
+        client,
+        credentials,
+        source,
+        target,
+        options,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Response, TestServer};
+
+    fn client() -> Client {
+        Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_shared_runtime() {
+        let server = TestServer::start(vec![Response::new(200), Response::new(200)]);
+        let client = client();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        for _ in 0..2 {
+            assert_eq!(
+                check_connection(&client, &credentials, server.url()).unwrap(),
+                *server.url()
+            );
+        }
+        assert_eq!(
+            block_on(async { tokio::runtime::Handle::current().runtime_flavor() }),
+            tokio::runtime::RuntimeFlavor::CurrentThread
+        );
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_from_blocking_task() {
+        let server = TestServer::start(vec![Response::new(200)]);
+        let url = server.url().clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let credentials = Credentials::Basic("foo".into(), "bar".into());
+            check_connection(&client(), &credentials, &url)
+        })
+        .await
+        .unwrap();
+        assert_eq!(result.unwrap(), *server.url());
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "Cannot start a runtime from within a runtime")]
+    async fn test_inside_runtime() {
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let url = Url::parse("http://127.0.0.1:1/").unwrap();
+        let _ = check_connection(&client(), &credentials, &url);
+    }
+}
@@ -0,0 +1,796 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Local cache of calendar objects, so repeated syncs only download the objects that changed.
+//!
+//! A client built with `ClientBuilder::cache` uses the cache in `get_events` when all events of a
+//! calendar are requested, i.e. without time range and expansion. Other objects of the calendar,
+//! e.g. todos, are cached as well but not returned. The changes are found with
+//! sync-collection (RFC 6578), or with `getctag` on servers without it.
+//!
+//! ```rust,no_run
+//! # async fn run(calendar: minicaldav::Calendar) {
+//! let client = minicaldav::Client::builder()
+//!     .cache(minicaldav::cache::FileCache::new("/tmp/minicaldav").unwrap())
+//!     .build()
+//!     .unwrap();
+//! let credentials = minicaldav::Credentials::Basic("foo".into(), "s3cret!".into());
+//! // Only the first call downloads all events.
+//! let (events, _) = minicaldav::get_events(&client, &credentials, &calendar, None, None, false)
+//!     .await
+//!     .unwrap();
+//! # }
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use url::Url;
+
+use crate::caldav::{self, collection_url, EventRef, MultigetItem};
+use crate::client::Client;
+use crate::credentials::Credentials;
+use crate::errors::MiniCaldavError;
+use crate::errors::ResourceError;
+
+/// An object as stored on the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedObject {
+    pub etag: Option<String>,
+    pub schedule_tag: Option<String>,
+    pub data: String,
+}
+
+/// What the cache knows about the state of a collection on the server.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CollectionState {
+    pub sync_token: Option<String>,
+    pub ctag: Option<String>,
+}
+
+/// Storage of cached objects and collection states, see the module documentation.
+/// Collections are identified by their url with trailing slash.
+pub trait CacheStore: Send + Sync {
+    fn get_event(&self, url: &Url) -> Option<CachedObject>;
+    fn put_event(&self, url: &Url, object: CachedObject);
+    fn remove_event(&self, url: &Url);
+    /// The urls of all cached objects of the collection.
+    fn event_urls(&self, collection_url: &Url) -> Vec<Url>;
+    fn collection_state(&self, collection_url: &Url) -> Option<CollectionState>;
+    fn set_collection_state(&self, collection_url: &Url, state: CollectionState);
+}
+
+impl std::fmt::Debug for dyn CacheStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CacheStore")
+    }
+}
+
+/// A cache that is lost when the program exits.
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    events: Mutex<HashMap<Url, CachedObject>>,
+    collections: Mutex<HashMap<Url, CollectionState>>,
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CacheStore for MemoryCache {
+    fn get_event(&self, url: &Url) -> Option<CachedObject> {
+        self.events.lock().unwrap().get(url).cloned()
+    }
+
+    fn put_event(&self, url: &Url, object: CachedObject) {
+        self.events.lock().unwrap().insert(url.clone(), object);
+    }
+
+    fn remove_event(&self, url: &Url) {
+        self.events.lock().unwrap().remove(url);
+    }
+
+    fn event_urls(&self, collection_url: &Url) -> Vec<Url> {
+        self.events
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|url| url.as_str().starts_with(collection_url.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    fn collection_state(&self, collection_url: &Url) -> Option<CollectionState> {
+        self.collections
+            .lock()
+            .unwrap()
+            .get(collection_url)
+            .cloned()
+    }
+
+    fn set_collection_state(&self, collection_url: &Url, state: CollectionState) {
+        self.collections
+            .lock()
+            .unwrap()
+            .insert(collection_url.clone(), state);
+    }
+}
+
+/// A cache storing each object and collection state in a file of the given directory.
+/// Errors reading or writing the files are logged, the objects are downloaded again then.
+#[derive(Debug, Clone)]
+pub struct FileCache {
+    dir: PathBuf,
+}
+
+impl FileCache {
+    /// Use the given directory, creating it if it does not exist.
+    pub fn new(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, url: &Url, extension: &str) -> PathBuf {
+        self.dir.join(format!(
+            "{}.{}",
+            crate::util::stable_hash(url.as_str()),
+            extension
+        ))
+    }
+
+    /// Read the lines of the given file: the url, the given number of header lines and the rest.
+    fn read(&self, path: PathBuf, headers: usize) -> Option<Vec<String>> {
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!("Could not read cache file {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        let parts = content
+            .splitn(headers + 2, '\n')
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        if parts.len() == headers + 2 {
+            Some(parts)
+        } else {
+            warn!("Invalid cache file {}", path.display());
+            None
+        }
+    }
+
+    /// Replace the file atomically, so readers never see a partly written file.
+    fn write(&self, path: PathBuf, content: &str) {
+        let tmp = path.with_extension("tmp");
+        if let Err(e) = std::fs::write(&tmp, content).and_then(|_| std::fs::rename(&tmp, &path)) {
+            warn!("Could not write cache file {}: {}", path.display(), e);
+        }
+    }
+
+    fn remove(&self, path: PathBuf) {
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                warn!("Could not remove cache file {}: {}", path.display(), e)
+            }
+            _ => {}
+        }
+    }
+}
+
+impl CacheStore for FileCache {
+    fn get_event(&self, url: &Url) -> Option<CachedObject> {
+        let mut parts = self.read(self.path(url, "ics"), 2)?;
+        if parts[0] != url.as_str() {
+            return None;
+        }
+        let data = parts.pop()?;
+        let schedule_tag = Some(parts.pop()?).filter(|tag| !tag.is_empty());
+        let etag = Some(parts.pop()?).filter(|etag| !etag.is_empty());
+        Some(CachedObject {
+            etag,
+            schedule_tag,
+            data,
+        })
+    }
+
+    fn put_event(&self, url: &Url, object: CachedObject) {
+        let content = format!(
+            "{}\n{}\n{}\n{}",
+            url,
+            object.etag.unwrap_or_default(),
+            object.schedule_tag.unwrap_or_default(),
+            object.data
+        );
+        self.write(self.path(url, "ics"), &content);
+    }
+
+    fn remove_event(&self, url: &Url) {
+        self.remove(self.path(url, "ics"));
+    }
+
+    fn event_urls(&self, collection_url: &Url) -> Vec<Url> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Could not read cache {}: {}", self.dir.display(), e);
+                return Vec::new();
+            }
+        };
+        let mut urls = Vec::new();
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("ics") {
+                continue;
+            }
+            let mut line = String::new();
+            let read = std::fs::File::open(&path)
+                .and_then(|file| BufReader::new(file).read_line(&mut line));
+            if read.is_err() {
+                continue;
+            }
+            match Url::parse(line.trim_end()) {
+                Ok(url) if url.as_str().starts_with(collection_url.as_str()) => urls.push(url),
+                _ => {}
+            }
+        }
+        urls
+    }
+
+    fn collection_state(&self, collection_url: &Url) -> Option<CollectionState> {
+        let parts = self.read(self.path(collection_url, "state"), 2)?;
+        if parts[0] != collection_url.as_str() {
+            return None;
+        }
+        let value = |s: &String| Some(s.trim_end().to_string()).filter(|s| !s.is_empty());
+        Some(CollectionState {
+            sync_token: value(&parts[1]),
+            ctag: value(&parts[2]),
+        })
+    }
+
+    fn set_collection_state(&self, collection_url: &Url, state: CollectionState) {
+        let content = format!(
+            "{}\n{}\n{}\n",
+            collection_url,
+            state.sync_token.unwrap_or_default(),
+            state.ctag.unwrap_or_default()
+        );
+        self.write(self.path(collection_url, "state"), &content);
+    }
+}
+
+/// Update the cached objects of the given calendar from the server and return the cached events.
+/// Objects the server returned an error for are returned as `ResourceError`.
+pub async fn sync(
+    client: &Client,
+    credentials: &Credentials,
+    store: &dyn CacheStore,
+    base_url: &Url,
+    calendar_url: &Url,
+) -> Result<(Vec<EventRef>, Vec<ResourceError>), MiniCaldavError> {
+    let calendar_url = collection_url(calendar_url.clone());
+    let mut state = store.collection_state(&calendar_url).unwrap_or_default();
+    let mut errors = Vec::new();
+    let synced = sync_changes(
+        client,
+        credentials,
+        store,
+        (base_url, &calendar_url),
+        &mut state,
+        &mut errors,
+    )
+    .await;
+    match synced {
+        Err(e) if sync_unsupported(&e) => {
+            debug!(
+                "sync-collection of {} failed, using getctag: {:?}",
+                calendar_url, e
+            );
+            state.sync_token = None;
+            sync_with_ctag(
+                client,
+                credentials,
+                store,
+                (base_url, &calendar_url),
+                &mut state,
+                &mut errors,
+            )
+            .await?;
+        }
+        result => result?,
+    }
+    store.set_collection_state(&calendar_url, state);
+
+    let event_refs = store
+        .event_urls(&calendar_url)
+        .into_iter()
+        .filter_map(|url| {
+            let object = store.get_event(&url)?;
+            // sync-collection reports all objects, e.g. todos as well.
+            if !has_component(&object.data, "VEVENT") {
+                return None;
+            }
+            Some(EventRef {
+                etag: object.etag,
+                url,
+                data: object.data,
+                schedule_tag: object.schedule_tag,
+            })
+        })
+        .collect();
+    Ok((event_refs, errors))
+}
+
+/// Whether sync-collection failed because the server does not support it or does not accept the
+/// sync token, so getctag is used instead. Other errors, e.g. timeouts, are returned.
+fn sync_unsupported(e: &MiniCaldavError) -> bool {
+    match e {
+        MiniCaldavError::InvalidSyncToken(_) => true,
+        e => e.http_error().is_some_and(|e| {
+            matches!(e.status, 400 | 405 | 501)
+                || e.precondition().as_deref() == Some("supported-report")
+        }),
+    }
+}
+
+/// Whether the iCalendar data contains a component with the given name.
+fn has_component(data: &str, name: &str) -> bool {
+    data.lines().any(|line| {
+        line.trim_end()
+            .split_once(':')
+            .map(|(begin, value)| {
+                begin.eq_ignore_ascii_case("BEGIN") && value.eq_ignore_ascii_case(name)
+            })
+            .unwrap_or(false)
+    })
+}
+
+async fn sync_changes(
+    client: &Client,
+    credentials: &Credentials,
+    store: &dyn CacheStore,
+    (base_url, calendar_url): (&Url, &Url),
+    state: &mut CollectionState,
+    errors: &mut Vec<ResourceError>,
+) -> Result<(), MiniCaldavError> {
+    let changes = caldav::sync_collection_all(
+        client,
+        credentials,
+        base_url,
+        calendar_url,
+        state.sync_token.clone(),
+    )
+    .await?;
+    if changes.listed_all {
+        let listed = changes
+            .changed
+            .iter()
+            .map(|c| c.url.path())
+            .collect::<HashSet<_>>();
+        for url in store.event_urls(calendar_url) {
+            if !listed.contains(url.path()) {
+                store.remove_event(&url);
+            }
+        }
+    }
+    for url in &changes.deleted {
+        store.remove_event(url);
+    }
+    let stale = changes
+        .changed
+        .iter()
+        .filter(|c| {
+            c.etag.is_none()
+                || store.get_event(&c.url).and_then(|o| o.etag).as_ref() != c.etag.as_ref()
+        })
+        .map(|c| c.url.clone())
+        .collect::<Vec<_>>();
+    let known_errors = errors.len();
+    fetch(
+        client,
+        credentials,
+        store,
+        (base_url, calendar_url),
+        &stale,
+        errors,
+    )
+    .await?;
+    // After errors the old token is kept, so the next sync reports the failed objects again.
+    // Objects cached in the meantime are not fetched again, their etags did not change.
+    if errors.len() == known_errors {
+        state.sync_token = changes.sync_token;
+    }
+    Ok(())
+}
+
+async fn sync_with_ctag(
+    client: &Client,
+    credentials: &Credentials,
+    store: &dyn CacheStore,
+    (base_url, calendar_url): (&Url, &Url),
+    state: &mut CollectionState,
+    errors: &mut Vec<ResourceError>,
+) -> Result<(), MiniCaldavError> {
+    let ctag = caldav::get_ctag(client, credentials, calendar_url)
+        .await
+        .ok()
+        .filter(|ctag| !ctag.is_empty());
+    if ctag.is_some() && ctag == state.ctag {
+        return Ok(());
+    }
+    let (event_refs, resource_errors) = caldav::get_events(
+        client,
+        credentials,
+        base_url.clone(),
+        calendar_url.clone(),
+        None,
+        None,
+        false,
+    )
+    .await?;
+    // Only events are listed, other objects cached by sync-collection are kept.
+    let listed = event_refs
+        .iter()
+        .map(|e| e.url.path())
+        .collect::<HashSet<_>>();
+    for url in store.event_urls(calendar_url) {
+        let is_event = store
+            .get_event(&url)
+            .is_some_and(|object| has_component(&object.data, "VEVENT"));
+        if is_event && !listed.contains(url.path()) {
+            store.remove_event(&url);
+        }
+    }
+    for event_ref in event_refs {
+        store.put_event(
+            &event_ref.url,
+            CachedObject {
+                etag: event_ref.etag,
+                schedule_tag: event_ref.schedule_tag,
+                data: event_ref.data,
+            },
+        );
+    }
+    errors.extend(resource_errors);
+    state.ctag = ctag;
+    Ok(())
+}
+
+async fn fetch(
+    client: &Client,
+    credentials: &Credentials,
+    store: &dyn CacheStore,
+    (base_url, calendar_url): (&Url, &Url),
+    urls: &[Url],
+    errors: &mut Vec<ResourceError>,
+) -> Result<(), MiniCaldavError> {
+    let items = caldav::fetch_objects(client, credentials, base_url, calendar_url, urls).await?;
+    for item in items {
+        match item {
+            MultigetItem::Found(event_ref) => store.put_event(
+                &event_ref.url,
+                CachedObject {
+                    etag: event_ref.etag,
+                    schedule_tag: event_ref.schedule_tag,
+                    data: event_ref.data,
+                },
+            ),
+            MultigetItem::Gone(e) => store.remove_event(&e.url),
+            MultigetItem::Error(error) => errors.push(error),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Response, TestServer};
+
+    fn check_store(store: &dyn CacheStore) {
+        let calendar = Url::parse("https://example.com/cal/work/").unwrap();
+        let a = calendar.join("a.ics").unwrap();
+        let b = calendar.join("b.ics").unwrap();
+        let other = Url::parse("https://example.com/cal/home/c.ics").unwrap();
+        let object = |etag: Option<&str>, data: &str| CachedObject {
+            etag: etag.map(|e| e.to_string()),
+            schedule_tag: etag.map(|e| format!("schedule-{}", e)),
+            data: data.to_string(),
+        };
+
+        assert_eq!(store.get_event(&a), None);
+        store.put_event(
+            &a,
+            object(Some("\"1\""), "BEGIN:VCALENDAR\nEND:VCALENDAR\n"),
+        );
+        store.put_event(&b, object(None, ""));
+        store.put_event(&other, object(Some("\"1\""), "x"));
+        assert_eq!(
+            store.get_event(&a),
+            Some(object(Some("\"1\""), "BEGIN:VCALENDAR\nEND:VCALENDAR\n"))
+        );
+        assert_eq!(store.get_event(&b), Some(object(None, "")));
+        let mut urls = store.event_urls(&calendar);
+        urls.sort();
+        assert_eq!(urls, [a.clone(), b.clone()]);
+
+        store.remove_event(&a);
+        assert_eq!(store.event_urls(&calendar), [b]);
+
+        assert_eq!(store.collection_state(&calendar), None);
+        let state = CollectionState {
+            sync_token: Some("http://example.com/sync/1".into()),
+            ctag: None,
+        };
+        store.set_collection_state(&calendar, state.clone());
+        assert_eq!(store.collection_state(&calendar), Some(state));
+    }
+
+    fn object_response(name: &str, etag: &str, schedule_tag: Option<&str>, data: &str) -> String {
+        format!(
+            r#"<d:response><d:href>/cal/{}</d:href><d:propstat><d:prop>
+                <d:getetag>{}</d:getetag>{}<c:calendar-data>{}</c:calendar-data>
+            </d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>"#,
+            name,
+            etag,
+            schedule_tag
+                .map(|tag| format!("<c:schedule-tag>{}</c:schedule-tag>", tag))
+                .unwrap_or_default(),
+            data
+        )
+    }
+
+    fn multistatus(responses: &[String]) -> Response {
+        Response::multistatus(&format!(
+            r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav" xmlns:cs="http://calendarserver.org/ns/">{}</d:multistatus>"#,
+            responses.concat()
+        ))
+    }
+
+    fn ics(component: &str, uid: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\nBEGIN:{0}\nUID:{1}\nEND:{0}\nEND:VCALENDAR\n",
+            component, uid
+        )
+    }
+
+    fn client() -> (Client, Credentials) {
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        (client, Credentials::Basic("foo".into(), "bar".into()))
+    }
+
+    fn paths(event_refs: &[EventRef]) -> Vec<&str> {
+        let mut paths = event_refs.iter().map(|e| e.url.path()).collect::<Vec<_>>();
+        paths.sort();
+        paths
+    }
+
+    #[tokio::test]
+    async fn test_sync_changes() {
+        let sync_response = |changed: &[(&str, &str)], deleted: &[&str], token: &str| {
+            let mut responses = changed
+                .iter()
+                .map(|(name, etag)| {
+                    format!(
+                        r#"<d:response><d:href>/cal/{}</d:href><d:propstat>
+                            <d:prop><d:getetag>{}</d:getetag></d:prop><d:status>HTTP/1.1 200 OK</d:status>
+                        </d:propstat></d:response>"#,
+                        name, etag
+                    )
+                })
+                .collect::<Vec<_>>();
+            responses.extend(deleted.iter().map(|name| {
+                format!(
+                    r#"<d:response><d:href>/cal/{}</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>"#,
+                    name
+                )
+            }));
+            responses.push(format!("<d:sync-token>{}</d:sync-token>", token));
+            multistatus(&responses)
+        };
+        let server = TestServer::start(vec![
+            sync_response(
+                &[("a.ics", "1"), ("b.ics", "2"), ("t.ics", "1")],
+                &[],
+                "token-1",
+            ),
+            multistatus(&[
+                object_response("b.ics", "2", Some("s2"), &ics("VEVENT", "b")),
+                object_response("t.ics", "1", None, &ics("VTODO", "t")),
+            ]),
+            sync_response(&[], &["a.ics"], "token-2"),
+        ]);
+        let calendar = server.join("/cal/");
+        let store = MemoryCache::new();
+        let cached = |etag: &str, data: String| CachedObject {
+            etag: Some(etag.into()),
+            schedule_tag: None,
+            data,
+        };
+        store.put_event(
+            &calendar.join("a.ics").unwrap(),
+            cached("1", ics("VEVENT", "a")),
+        );
+        store.put_event(
+            &calendar.join("old.ics").unwrap(),
+            cached("1", ics("VEVENT", "old")),
+        );
+        let (client, credentials) = client();
+
+        // The full listing removes old.ics, only the changed b.ics and t.ics are fetched.
+        let (event_refs, errors) = sync(&client, &credentials, &store, server.url(), &calendar)
+            .await
+            .unwrap();
+        assert!(errors.is_empty());
+        assert_eq!(paths(&event_refs), ["/cal/a.ics", "/cal/b.ics"]);
+        let b = event_refs
+            .iter()
+            .find(|e| e.url.path() == "/cal/b.ics")
+            .unwrap();
+        assert_eq!(b.schedule_tag.as_deref(), Some("s2"));
+        assert_eq!(store.event_urls(&calendar).len(), 3);
+        assert_eq!(
+            store
+                .collection_state(&calendar)
+                .unwrap()
+                .sync_token
+                .as_deref(),
+            Some("token-1")
+        );
+
+        let (event_refs, _) = sync(&client, &credentials, &store, server.url(), &calendar)
+            .await
+            .unwrap();
+        assert_eq!(paths(&event_refs), ["/cal/b.ics"]);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[1].body.contains("/cal/b.ics") && !requests[1].body.contains("a.ics"));
+        assert!(requests[2]
+            .body
+            .contains("<d:sync-token>token-1</d:sync-token>"));
+    }
+
+    #[tokio::test]
+    async fn test_sync_with_ctag() {
+        let ctag = || {
+            multistatus(&[r#"<d:response><d:href>/cal/</d:href><d:propstat>
+                <d:prop><cs:getctag>1</cs:getctag></d:prop><d:status>HTTP/1.1 200 OK</d:status>
+            </d:propstat></d:response>"#
+                .to_string()])
+        };
+        // A server without sync-collection.
+        let unsupported = || {
+            Response::new(403).body(r#"<d:error xmlns:d="DAV:"><d:supported-report/></d:error>"#)
+        };
+        let server = TestServer::start(vec![
+            unsupported(),
+            ctag(),
+            multistatus(&[object_response("a.ics", "1", None, &ics("VEVENT", "a"))]),
+            Response::new(501),
+            ctag(),
+        ]);
+        let calendar = server.join("/cal/");
+        let store = MemoryCache::new();
+        let cached = |data: String| CachedObject {
+            etag: None,
+            schedule_tag: None,
+            data,
+        };
+        store.put_event(
+            &calendar.join("old.ics").unwrap(),
+            cached(ics("VEVENT", "old")),
+        );
+        store.put_event(&calendar.join("t.ics").unwrap(), cached(ics("VTODO", "t")));
+        let (client, credentials) = client();
+
+        // Only events are listed, the cached todo is kept.
+        let (event_refs, _) = sync(&client, &credentials, &store, server.url(), &calendar)
+            .await
+            .unwrap();
+        assert_eq!(paths(&event_refs), ["/cal/a.ics"]);
+        assert!(store.get_event(&calendar.join("t.ics").unwrap()).is_some());
+        assert!(store
+            .get_event(&calendar.join("old.ics").unwrap())
+            .is_none());
+        assert_eq!(
+            store.collection_state(&calendar),
+            Some(CollectionState {
+                sync_token: None,
+                ctag: Some("1".into())
+            })
+        );
+
+        // The ctag did not change, nothing is fetched.
+        let (event_refs, _) = sync(&client, &credentials, &store, server.url(), &calendar)
+            .await
+            .unwrap();
+        assert_eq!(paths(&event_refs), ["/cal/a.ics"]);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 5);
+        assert_eq!(requests[2].method, "REPORT");
+        assert_eq!(requests[4].method, "PROPFIND");
+    }
+
+    #[tokio::test]
+    async fn test_sync_errors_are_returned() {
+        // Server errors and timeouts are no reason to download the calendar again.
+        let server = TestServer::start(vec![Response::new(503)]);
+        let calendar = server.join("/cal/");
+        let store = MemoryCache::new();
+        let state = CollectionState {
+            sync_token: Some("token-1".into()),
+            ctag: Some("1".into()),
+        };
+        store.set_collection_state(&calendar, state.clone());
+        let (client, credentials) = client();
+
+        let error = sync(&client, &credentials, &store, server.url(), &calendar)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, MiniCaldavError::Http(e) if e.status == 503),
+            "{:?}",
+            error
+        );
+        assert_eq!(store.collection_state(&calendar), Some(state));
+        assert_eq!(server.requests().len(), 1);
+
+        let server = TestServer::unresponsive();
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .timeout(std::time::Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let error = sync(
+            &client,
+            &credentials,
+            &store,
+            server.url(),
+            &server.join("/cal/"),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, MiniCaldavError::Timeout(_)), "{:?}", error);
+    }
+
+    #[test]
+    fn test_has_component() {
+        assert!(has_component(&ics("VEVENT", "a"), "VEVENT"));
+        assert!(has_component("begin:vevent\r\n", "VEVENT"));
+        assert!(!has_component(&ics("VTODO", "a"), "VEVENT"));
+    }
+
+    #[test]
+    fn test_memory_cache() {
+        check_store(&MemoryCache::new());
+    }
+
+    #[test]
+    fn test_file_cache() {
+        let dir = std::env::temp_dir().join(format!("minicaldav-cache-{}", std::process::id()));
+        check_store(&FileCache::new(&dir).unwrap());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
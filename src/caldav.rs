@@ -16,20 +16,31 @@
 
 //! CalDAV client implementation using ureq.
 
+use std::cell::RefCell;
+use std::collections::HashMap;
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+use crate::client::{join_bounded, read_text, read_text_limited, Client};
 use crate::credentials::Credentials;
 
 use reqwest::{
-    header::{ACCEPT, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, USER_AGENT},
-    Client, Method,
+    header::{ACCEPT, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE},
+    Method,
 };
 
-use crate::xml_templates::build_create_calendar_xml;
+use crate::xml_templates::{
+    build_calendar_availability_xml, build_calendar_color_xml, build_calendar_defaults_xml,
+    build_calendar_enabled_xml, build_calendar_order_xml, build_create_calendar_xml,
+    build_invite_reply_xml, build_object_properties_request_xml, build_object_properties_xml,
+    build_share_xml, build_unshare_xml, escape_xml, DEFAULT_CALENDAR_TIMEZONE,
+};
 
 use crate::errors::MiniCaldavError::{self, *};
+use crate::errors::{CheckStatus, HttpError, ResourceError, TimeoutKind};
+use crate::privileges::Privileges;
 
 /// Send a PROPFIND to the given url using the given HTTP Basic authorization and search the result XML for a value.
 /// # Arguments
@@ -48,19 +59,18 @@ pub async fn propfind_get(
     prop_path: &[&str],
     depth: &str,
 ) -> Result<(String, xmltree::Element), MiniCaldavError> {
-    let auth = get_auth_header(credentials);
-
     let propfind = Method::from_bytes(b"PROPFIND").unwrap();
 
     let content = client
         .request(propfind, url.as_str())
-        .header(USER_AGENT, "rust-minicaldav")
         .header(CONTENT_TYPE, "application/xml; charset=utf-8")
         .header(ACCEPT, "text/xml, text/calendar")
-        .header(AUTHORIZATION, auth)
+        .authorization(credentials)
         .header("Depth", depth)
         .body(body)
         .send()
+        .await?
+        .check_status()
         .await?;
 
     trace!("CalDAV propfind response: {:?}", content);
@@ -94,23 +104,76 @@ pub async fn propfind_get(
     }
 }
 
+/// Send a PROPFIND with the given body to the given url and return the parsed response XML.
+/// Use this for server specific properties that are not covered by this crate.
+pub async fn raw_propfind(
+    client: &Client,
+    credentials: &Credentials,
+    url: &Url,
+    body: &str,
+    depth: &str,
+) -> Result<xmltree::Element, MiniCaldavError> {
+    let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+    raw_xml_request(client, credentials, propfind, url, body, depth).await
+}
+
+/// Send a REPORT with the given body to the given url and return the parsed response XML.
+/// Use this for server specific queries that are not covered by this crate.
+pub async fn raw_report(
+    client: &Client,
+    credentials: &Credentials,
+    url: &Url,
+    body: &str,
+    depth: &str,
+) -> Result<xmltree::Element, MiniCaldavError> {
+    let report = Method::from_bytes(b"REPORT").unwrap();
+    raw_xml_request(client, credentials, report, url, body, depth).await
+}
+
+pub(crate) async fn raw_xml_request(
+    client: &Client,
+    credentials: &Credentials,
+    method: Method,
+    url: &Url,
+    body: &str,
+    depth: &str,
+) -> Result<xmltree::Element, MiniCaldavError> {
+    let content = client
+        .request(method, url.as_str())
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .header(ACCEPT, "text/xml, text/calendar")
+        .authorization(credentials)
+        .header("Depth", depth)
+        .body(body.to_string())
+        .send()
+        .await?
+        .check_status()
+        .await?
+        .text()
+        .await?;
+
+    trace!("CalDAV raw response: {:?}", content);
+    Ok(xmltree::Element::parse(content.as_bytes())?)
+}
+
 /// Discover the content url of the DAV server
 pub async fn discover_url(
     client: &Client,
     credentials: &Credentials,
     base_url: Url,
 ) -> Result<Url, MiniCaldavError> {
-    let auth = get_auth_header(credentials);
-
     let base_url = base_url.join("/.well-known/caldav")?;
-    println!("base_url: {:?}", base_url);
+    debug!("Discovering the DAV url with {}", base_url);
 
     let response = client
-        .get(base_url)
-        .header(AUTHORIZATION, &auth)
+        .get(base_url.clone())
+        .authorization(credentials)
         .send()
         .await?;
 
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(WellKnownNotFound(base_url));
+    }
     Ok(response.url().clone())
 }
 
@@ -122,18 +185,14 @@ pub async fn check_connetion(
     credentials: &Credentials,
     url: &Url,
 ) -> Result<Url, MiniCaldavError> {
-    let auth = get_auth_header(credentials);
-
     let response = client
         .get(url.as_str())
-        .header(USER_AGENT, "rust-minicaldav")
-        .header(AUTHORIZATION, auth.clone())
+        .authorization(credentials)
         .send()
         .await?;
 
-
-    println!("{:#?}", response);
-    let response_url = response.error_for_status()?.url().clone();
+    trace!("Connection check response: {:?}", response);
+    let response_url = response.check_status().await?.url().clone();
 
     Ok(response_url)
 }
@@ -166,9 +225,13 @@ pub async fn get_principal_url(
         ],
         "0",
     )
-    .await?
+    .await
+    .map_err(|e| match e {
+        PathNotExists(_) => PrincipalNotFound(url.clone()),
+        e => e,
+    })?
     .0;
-    Ok(url.join(&principal_url)?)
+    client.resolve_href(&url, &principal_url)
 }
 
 pub static HOMESET_REQUEST: &str = r#"
@@ -194,10 +257,84 @@ pub async fn get_home_set_url(
         &["response", "propstat", "prop", "calendar-home-set", "href"],
         "0",
     )
-    .await?
+    .await
+    .map_err(|e| match e {
+        PathNotExists(_) => HomeSetNotFound(url.clone()),
+        e => e,
+    })?
     .0;
 
-    Ok(url.join(&homeset_url)?)
+    Ok(collection_url(client.resolve_href(&url, &homeset_url)?))
+}
+
+pub static CTAG_REQUEST: &str = r#"
+    <d:propfind xmlns:d="DAV:" xmlns:cs="http://calendarserver.org/ns/">
+      <d:prop>
+        <cs:getctag />
+      </d:prop>
+    </d:propfind>
+"#;
+
+/// Get the `getctag` of the given collection, which changes whenever one of its objects changes.
+/// Not standardized, but supported by most servers without sync-collection.
+pub async fn get_ctag(
+    client: &Client,
+    credentials: &Credentials,
+    calendar_url: &Url,
+) -> Result<String, MiniCaldavError> {
+    Ok(propfind_get(
+        client,
+        credentials,
+        calendar_url,
+        CTAG_REQUEST.to_string(),
+        &["response", "propstat", "prop", "getctag"],
+        "0",
+    )
+    .await?
+    .0
+    .trim()
+    .to_string())
+}
+
+/// Discover the calendar home set from the principal of the user at `base_url`.
+/// Falls back to the principal url and `base_url` if the server does not tell them,
+/// other errors, e.g. of authentication or the connection, are returned.
+pub(crate) async fn discover_home_set_url(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+) -> Result<Url, MiniCaldavError> {
+    let principal_url = discover_principal_url(client, credentials, base_url).await?;
+    match get_home_set_url(client, credentials, principal_url).await {
+        Err(e) if is_not_provided(&e) => Ok(collection_url(base_url.clone())),
+        result => result,
+    }
+}
+
+/// Get the principal of the user at `base_url`, `base_url` if the server does not tell it.
+pub(crate) async fn discover_principal_url(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+) -> Result<Url, MiniCaldavError> {
+    match get_principal_url(client, credentials, base_url.clone()).await {
+        Err(e) if is_not_provided(&e) => Ok(base_url.clone()),
+        result => result,
+    }
+}
+
+/// Whether the error tells that the server does not provide the url asked for during discovery.
+pub(crate) fn is_not_provided(e: &MiniCaldavError) -> bool {
+    matches!(e, PrincipalNotFound(_) | HomeSetNotFound(_) | NotFound(_))
+}
+
+/// Add the trailing slash to the url of a collection. Several servers answer requests to
+/// collections without it with 301 or 404, and resources can only be joined to it with it.
+pub(crate) fn collection_url(mut url: Url) -> Url {
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    url
 }
 
 pub static CALENDARS_REQUEST: &str = r#"
@@ -207,7 +344,10 @@ pub static CALENDARS_REQUEST: &str = r#"
         <d:resourcetype />
         <d:current-user-privilege-set/>
         <calendar-color xmlns="http://apple.com/ns/ical/" />
+        <calendar-order xmlns="http://apple.com/ns/ical/" />
+        <calendar-enabled xmlns="http://owncloud.org/ns" />
         <c:supported-calendar-component-set />
+        <source xmlns="http://calendarserver.org/ns/" />
     </d:prop>
 </d:propfind>
 "#;
@@ -219,8 +359,11 @@ pub static CALENDARS_QUERY: &str = r#"
         <d:displayname />
         <d:current-user-privilege-set/>
         <calendar-color xmlns="http://apple.com/ns/ical/" />
+        <calendar-order xmlns="http://apple.com/ns/ical/" />
+        <calendar-enabled xmlns="http://owncloud.org/ns" />
         <d:resourcetype />
         <c:supported-calendar-component-set />
+        <source xmlns="http://calendarserver.org/ns/" />
     </d:prop>
     <c:filter>
         <c:comp-filter name="VCALENDAR" />
@@ -228,36 +371,61 @@ pub static CALENDARS_QUERY: &str = r#"
 </c:calendar-query>
 "#;
 
+/// How `get_calendars` lists the calendars of the user.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CalendarDiscovery {
+    /// PROPFIND with Depth 1 on the calendar home set.
+    Propfind,
+    /// `CALENDARS_QUERY` on the base url, for servers and proxies that forbid PROPFIND on the home set.
+    Query,
+    /// `Propfind`, falling back to `Query` if it fails.
+    #[default]
+    Both,
+    /// PROPFIND with Depth infinity on the principal, for servers that only list calendars this way.
+    /// At most `max_responses` resources of the response are examined. Repeated hrefs and collections
+    /// more than `MAX_DEEP_DISCOVERY_LEVELS` below the principal are skipped to survive binding loops.
+    /// Never tried by `Both`, as most servers refuse or answer slowly.
+    Deep { max_responses: usize },
+}
+
+impl CalendarDiscovery {
+    /// The strategies to try in this order.
+    fn attempts(self) -> Vec<CalendarDiscovery> {
+        match self {
+            Self::Both => vec![Self::Propfind, Self::Query],
+            attempt => vec![attempt],
+        }
+    }
+}
+
+/// How many levels below the principal `CalendarDiscovery::Deep` looks for calendars.
+pub const MAX_DEEP_DISCOVERY_LEVELS: usize = 8;
+
 /// Get calendars for the given credentials.
 pub async fn get_calendars(
     client: &Client,
     credentials: &Credentials,
     base_url: Url,
 ) -> Result<Vec<CalendarRef>, MiniCaldavError> {
-    let mut calendars = Vec::new();
-
-    let principal_url = get_principal_url(client, credentials, base_url.clone())
-        .await
-        .unwrap_or_else(|_| base_url.clone());
-
-    let homeset_url = get_home_set_url(client, credentials, principal_url)
+    get_calendars_with_discovery(client, credentials, base_url, CalendarDiscovery::Both)
         .await
-        .unwrap_or_else(|_| base_url.clone());
-
-    let prop = propfind_get(
-        client,
-        credentials,
-        &homeset_url,
-        CALENDARS_REQUEST.to_string(),
-        &[],
-        "1",
-    )
-    .await;
+        .map(|(calendars, _)| calendars)
+}
 
-    let root = match prop {
-        Ok(p) => p.1,
-        Err(_) => {
-            propfind_get(
+/// Get calendars for the given credentials with the given strategy.
+/// Returns the strategy that succeeded, `Propfind` or `Query`, with the calendars.
+/// If all strategies fail, the error of the last one is returned.
+pub async fn get_calendars_with_discovery(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: Url,
+    discovery: CalendarDiscovery,
+) -> Result<(Vec<CalendarRef>, CalendarDiscovery), MiniCaldavError> {
+    let mut error = None;
+    for attempt in discovery.attempts() {
+        let result = match attempt {
+            CalendarDiscovery::Query => propfind_get(
                 client,
                 credentials,
                 &base_url,
@@ -265,11 +433,114 @@ pub async fn get_calendars(
                 &[],
                 "1",
             )
-            .await?
-            .1
+            .await
+            .map(|(_, root)| root),
+            CalendarDiscovery::Deep { max_responses } => {
+                match discover_principal_url(client, credentials, &base_url).await {
+                    Ok(principal_url) => {
+                        propfind_deep(client, credentials, &principal_url, max_responses).await
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            _ => match discover_home_set_url(client, credentials, &base_url).await {
+                Ok(homeset_url) => propfind_get(
+                    client,
+                    credentials,
+                    &homeset_url,
+                    CALENDARS_REQUEST.to_string(),
+                    &[],
+                    "1",
+                )
+                .await
+                .map(|(_, root)| root),
+                Err(e) => Err(e),
+            },
+        };
+        match result {
+            Ok(root) => return Ok((parse_calendars(client, &base_url, &root), attempt)),
+            Err(e) => {
+                debug!("Calendar discovery with {:?} failed: {:?}", attempt, e);
+                error = Some(e);
+            }
         }
+    }
+    Err(error.unwrap_or_else(|| RequestFailed("No calendar discovery strategy".into())))
+}
+
+/// Send `CALENDARS_REQUEST` with Depth infinity and keep the responses `CalendarDiscovery::Deep` examines.
+async fn propfind_deep(
+    client: &Client,
+    credentials: &Credentials,
+    url: &Url,
+    max_responses: usize,
+) -> Result<xmltree::Element, MiniCaldavError> {
+    let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+    let response = client
+        .request(propfind, url.as_str())
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .header(ACCEPT, "text/xml")
+        .authorization(credentials)
+        .header("Depth", "infinity")
+        .body(CALENDARS_REQUEST.to_string())
+        .send()
+        .await?
+        .check_status()
+        .await?;
+    let text = match client.max_report_size() {
+        Some(max) => read_text_limited(response, max).await?.ok_or_else(|| {
+            RequestFailed(format!("Depth infinity PROPFIND of {} is too large", url))
+        })?,
+        None => read_text(response, |_| {}).await?,
     };
+    let mut root = xmltree::Element::parse(text.as_bytes())?;
+    limit_deep_responses(client, url, &mut root, max_responses);
+    Ok(root)
+}
+
+/// Drop repeated hrefs, hrefs too far below `url` and all responses after the first `max_responses`.
+fn limit_deep_responses(
+    client: &Client,
+    url: &Url,
+    root: &mut xmltree::Element,
+    max_responses: usize,
+) {
+    let levels = |url: &Url| url.path().trim_end_matches('/').split('/').count();
+    let mut seen = std::collections::HashSet::new();
+    let mut examined = 0;
+    root.children.retain(|child| {
+        let Some(response) = child.as_element() else {
+            return false;
+        };
+        examined += 1;
+        if examined > max_responses {
+            return false;
+        }
+        let Some(href) = response
+            .get_child("href")
+            .and_then(|e| e.get_text())
+            .and_then(|href| client.resolve_href(url, &href).ok())
+            .map(collection_url)
+        else {
+            return false;
+        };
+        if levels(&href).saturating_sub(levels(url)) > MAX_DEEP_DISCOVERY_LEVELS {
+            debug!("Skipping {} of deep discovery, it is too deep", href);
+            return false;
+        }
+        seen.insert(href)
+    });
+    if examined > max_responses {
+        warn!(
+            "Deep discovery of {} stopped after {} of {} resources",
+            url, max_responses, examined
+        );
+    }
+}
 
+/// Parse the calendars of a PROPFIND or calendar-query response.
+fn parse_calendars(client: &Client, base_url: &Url, root: &xmltree::Element) -> Vec<CalendarRef> {
+    let mut calendars = Vec::new();
     for response in &root.children {
         if let Some(response) = response.as_element() {
             let name = response
@@ -282,27 +553,29 @@ pub async fn get_calendars(
                 .and_then(|e| e.get_child("prop"))
                 .and_then(|e| e.get_child("calendar-color"))
                 .and_then(|e| e.get_text());
+            let enabled = response
+                .get_child("propstat")
+                .and_then(|e| e.get_child("prop"))
+                .and_then(|e| e.get_child("calendar-enabled"))
+                .and_then(|e| e.get_text())
+                .and_then(|text| match text.trim() {
+                    "1" | "true" => Some(true),
+                    "0" | "false" => Some(false),
+                    _ => None,
+                });
+            let order = response
+                .get_child("propstat")
+                .and_then(|e| e.get_child("prop"))
+                .and_then(|e| e.get_child("calendar-order"))
+                .and_then(|e| e.get_text())
+                .and_then(|text| text.trim().parse().ok());
             let privileges: Vec<String> = response
                 .get_child("propstat")
                 .and_then(|e| e.get_child("prop"))
                 .and_then(|e| e.get_child("current-user-privilege-set"))
-                .map(|e| {
-                    let mut list = Vec::new();
-                    for privs in &e.children {
-                        if let Some(p) = privs.as_element() {
-                            for c in &p.children {
-                                if let Some(c) = c.as_element() {
-                                    list.push(c.name.clone());
-                                }
-                            }
-                        }
-                    }
-                    list
-                })
+                .map(Privileges::names_from_xml)
                 .unwrap_or_else(Vec::new);
 
-            println!("{:#?}", privileges);
-
             let is_calendar = response
                 .get_child("propstat")
                 .and_then(|e| e.get_child("prop"))
@@ -316,38 +589,44 @@ pub async fn get_calendars(
                 .and_then(|e| e.get_child("resourcetype"))
                 .map(|e| e.get_child("subscribed").is_some())
                 .unwrap_or(false);
-            let supports_vevents = response
+            let source_url = response
+                .get_child("propstat")
+                .and_then(|e| e.get_child("prop"))
+                .and_then(|e| e.get_child("source"))
+                .and_then(|e| e.get_child("href"))
+                .and_then(|e| e.get_text())
+                .and_then(|href| client.resolve_href(base_url, &href).ok());
+            let components: Vec<String> = response
                 .get_child("propstat")
                 .and_then(|e| e.get_child("prop"))
                 .and_then(|e| e.get_child("supported-calendar-component-set"))
                 .map(|e| {
-                    for c in &e.children {
-                        if let Some(child) = c.as_element() {
-                            if child.name == "comp" {
-                                if let Some(name) = child.attributes.get("name") {
-                                    if (name == "VEVENT") || (name == "VTODO") {
-                                        return true;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    false
+                    e.children
+                        .iter()
+                        .filter_map(|c| c.as_element())
+                        .filter(|c| c.name == "comp")
+                        .filter_map(|c| c.attributes.get("name").cloned())
+                        .collect()
                 })
-                .unwrap_or(false);
+                .unwrap_or_default();
+            let supports_vevents = components.iter().any(|c| c == "VEVENT" || c == "VTODO");
             let href = response.get_child("href").and_then(|e| e.get_text());
 
             if !(is_calendar || is_subscription) || !supports_vevents {
                 continue;
             }
             if let Some((href, name)) = href.and_then(|href| name.map(|name| (href, name))) {
-                if let Ok(url) = base_url.join(&href) {
+                if let Ok(url) = client.resolve_href(base_url, &href) {
                     calendars.push(CalendarRef {
-                        url,
+                        url: collection_url(url),
                         name: name.to_string(),
                         color: color.map(|c| c.into()),
                         is_subscription,
+                        source_url,
                         privileges,
+                        components,
+                        enabled,
+                        order,
                     })
                 } else {
                     error!("Could not parse url: {}/{}", base_url, href);
@@ -357,7 +636,7 @@ pub async fn get_calendars(
             }
         }
     }
-    Ok(calendars)
+    calendars
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -367,7 +646,20 @@ pub struct CalendarRef {
     pub name: String,
     pub color: Option<String>,
     pub privileges: Vec<String>,
+    /// The supported component types, e.g. `VEVENT` and `VTODO`.
+    /// Empty if unknown, e.g. for calendars saved by older versions.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub components: Vec<String>,
     pub is_subscription: bool,
+    /// The upstream ICS feed of a subscribed calendar (`cs:source`).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub source_url: Option<Url>,
+    /// Whether the calendar is shown in clients (`oc:calendar-enabled`), `None` if the server does not report it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub enabled: Option<bool>,
+    /// The position of the calendar in calendar lists (`calendar-order` of the Apple namespace).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub order: Option<i32>,
 }
 
 impl std::fmt::Debug for CalendarRef {
@@ -375,6 +667,13 @@ impl std::fmt::Debug for CalendarRef {
         f.debug_struct("CalendarRef")
             .field("url", &self.url.to_string())
             .field("name", &self.name)
+            .field("components", &self.components)
+            .field(
+                "source_url",
+                &self.source_url.as_ref().map(|u| u.to_string()),
+            )
+            .field("enabled", &self.enabled)
+            .field("order", &self.order)
             .finish()
     }
 }
@@ -384,6 +683,8 @@ pub struct EventRef {
     pub etag: Option<String>,
     pub url: Url,
     pub data: String,
+    /// The `Schedule-Tag` of the resource on servers supporting CalDAV scheduling (RFC 6638).
+    pub schedule_tag: Option<String>,
 }
 
 impl std::fmt::Debug for EventRef {
@@ -392,6 +693,7 @@ impl std::fmt::Debug for EventRef {
             .field("etag", &self.etag)
             .field("url", &self.url.to_string())
             .field("data", &self.data)
+            .field("schedule_tag", &self.schedule_tag)
             .finish()
     }
 }
@@ -400,6 +702,7 @@ pub static CALENDAR_EVENTS_REQUEST: &str = r#"
     <c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
         <d:prop>
             <d:getetag />
+            <c:schedule-tag />
             <c:calendar-data />
         </d:prop>
         <c:filter>
@@ -416,13 +719,17 @@ pub static CALENDAR_EVENTS_REQUEST: &str = r#"
 //    <c:time-range start="20250103T000000Z" end="20260105T000000Z"/>
 //</c:comp-filter>
 
+/// The time range of calendar queries without start or end.
+pub(crate) const DEFAULT_RANGE_START: &str = "20000103T000000Z";
+pub(crate) const DEFAULT_RANGE_END: &str = "21000105T000000Z";
+
 fn build_calendar_request_string(
     start: Option<String>,
     end: Option<String>,
     expanded: bool,
 ) -> String {
-    let start = start.as_deref().unwrap_or("20000103T000000Z");
-    let end = end.as_deref().unwrap_or("21000105T000000Z");
+    let start = start.as_deref().unwrap_or(DEFAULT_RANGE_START);
+    let end = end.as_deref().unwrap_or(DEFAULT_RANGE_END);
 
     let req = if expanded {
         format!(
@@ -432,12 +739,7 @@ fn build_calendar_request_string(
             start, end
         )
     } else {
-        format!(
-            r#"<c:calendar-data>
-          <c:limit-recurrance-set start="{}" end="{}"/>
-       </c:calendar-data>"#,
-            start, end
-        )
+        "<c:calendar-data />".to_string()
     };
 
     let range = format!(
@@ -452,6 +754,7 @@ fn build_calendar_request_string(
     <c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
         <d:prop>
             <d:getetag />
+            <c:schedule-tag />
             {}
         </d:prop>
         <c:filter>
@@ -469,6 +772,7 @@ pub static CALENDAR_TODOS_REQUEST: &str = r#"
     <c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
         <d:prop>
             <d:getetag />
+            <c:schedule-tag />
             <c:calendar-data />
         </d:prop>
         <c:filter>
@@ -488,9 +792,250 @@ pub async fn get_events(
     start: Option<String>,
     end: Option<String>,
     expanded: bool,
-) -> Result<Vec<EventRef>, MiniCaldavError> {
-    let auth = get_auth_header(credentials);
+) -> Result<(Vec<EventRef>, Vec<ResourceError>), MiniCaldavError> {
+    get_events_with_progress(
+        client,
+        credentials,
+        base_url,
+        calendar_url,
+        start,
+        end,
+        expanded,
+        |_| {},
+    )
+    .await
+}
+
+/// Like `get_events`, but calls `progress` while the response is downloaded and once it is parsed.
+/// The total number of events is only known at the end.
+/// Expanded queries split into time windows (see `ClientBuilder::max_report_size`) report only the end.
+#[allow(clippy::too_many_arguments)]
+pub async fn get_events_with_progress<F: FnMut(Progress)>(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: Url,
+    calendar_url: Url,
+    start: Option<String>,
+    end: Option<String>,
+    expanded: bool,
+    mut progress: F,
+) -> Result<(Vec<EventRef>, Vec<ResourceError>), MiniCaldavError> {
+    // Without expansion the query has no time range that could be split.
+    if let Some(max) = client.max_report_size().filter(|_| expanded) {
+        let (event_refs, errors) = get_events_in_windows(
+            client,
+            credentials,
+            &base_url,
+            calendar_url,
+            (start, end),
+            max,
+        )
+        .await?;
+        let fetched = event_refs.len() + errors.len();
+        progress(Progress {
+            fetched,
+            total: Some(fetched),
+            bytes: event_refs.iter().map(|e| e.data.len() as u64).sum(),
+        });
+        return Ok((event_refs, errors));
+    }
+    let response =
+        send_events_report(client, credentials, calendar_url, start, end, expanded).await?;
+    let mut bytes = 0;
+    let content = read_text(response, |len| {
+        bytes += len as u64;
+        progress(Progress {
+            fetched: 0,
+            total: None,
+            bytes,
+        });
+    })
+    .await?;
+    let (event_refs, errors) = parse_events_report(client, &base_url, &content)?;
+    let fetched = event_refs.len() + errors.len();
+    progress(Progress {
+        fetched,
+        total: Some(fetched),
+        bytes,
+    });
+    Ok((event_refs, errors))
+}
+
+/// The smallest time range in seconds a calendar query is split into.
+const MIN_REPORT_WINDOW: i64 = 24 * 60 * 60;
+
+/// Query the expanded events of the given time range, splitting it in halves while the response is
+/// larger than `max` bytes or the server times out, see `ClientBuilder::max_report_size`.
+async fn get_events_in_windows(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calendar_url: Url,
+    (start, end): (Option<String>, Option<String>),
+    max: usize,
+) -> Result<(Vec<EventRef>, Vec<ResourceError>), MiniCaldavError> {
+    let start = start.as_deref().unwrap_or(DEFAULT_RANGE_START);
+    let end = end.as_deref().unwrap_or(DEFAULT_RANGE_END);
+    let (Some(start), Some(end)) = (parse_utc_seconds(start), parse_utc_seconds(end)) else {
+        return Err(RequestFailed(format!(
+            "Invalid time range {} - {}",
+            start, end
+        )));
+    };
+    let calendar_url = collection_url(calendar_url);
+    let mut windows = vec![(start, end)];
+    let mut event_refs = Vec::new();
+    let mut errors: Vec<ResourceError> = Vec::new();
+    while let Some((window_start, window_end)) = windows.pop() {
+        let splittable = window_end - window_start > MIN_REPORT_WINDOW;
+        let response = client
+            .request(Method::from_bytes(b"REPORT").unwrap(), calendar_url.clone())
+            .authorization(credentials)
+            .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+            .header(ACCEPT, "text/xml, text/calendar")
+            .header("Depth", "1")
+            .body(build_calendar_request_string(
+                Some(format_utc_seconds(window_start)),
+                Some(format_utc_seconds(window_end)),
+                true,
+            ))
+            .send()
+            .await;
+        let content = match response {
+            Ok(response) if splittable && matches!(response.status().as_u16(), 504 | 507) => None,
+            Ok(response) if splittable => {
+                match read_text_limited(response.check_status().await?, max).await {
+                    Err(e) if e.is_timeout() => None,
+                    content => content?,
+                }
+            }
+            Ok(response) => Some(read_text(response.check_status().await?, |_| {}).await?),
+            Err(Timeout(TimeoutKind::Read | TimeoutKind::Deadline)) if splittable => None,
+            Err(e) => return Err(e),
+        };
+        match content {
+            Some(content) => {
+                let (refs, resource_errors) = parse_events_report(client, base_url, &content)?;
+                merge_event_refs(&mut event_refs, refs);
+                for error in resource_errors {
+                    if !errors.iter().any(|e| e.url == error.url) {
+                        errors.push(error);
+                    }
+                }
+            }
+            None => {
+                let middle = window_start + (window_end - window_start) / 2;
+                debug!(
+                    "Splitting calendar query {} - {} of {}",
+                    format_utc_seconds(window_start),
+                    format_utc_seconds(window_end),
+                    calendar_url
+                );
+                // The earlier half is queried first, so events are returned in order of the windows.
+                windows.push((middle, window_end));
+                windows.push((window_start, middle));
+            }
+        }
+    }
+    Ok((event_refs, errors))
+}
+
+/// Add the expanded events of a time window to the events of the previous windows.
+/// Recurring events are returned for each window they have instances in, the instances of the
+/// same event are merged into one calendar. Objects that can not be parsed are kept as returned,
+/// so they are reported when the events are parsed.
+fn merge_event_refs(event_refs: &mut Vec<EventRef>, new: Vec<EventRef>) {
+    for event_ref in new {
+        let Some(existing) = event_refs.iter_mut().find(|e| e.url == event_ref.url) else {
+            event_refs.push(event_ref);
+            continue;
+        };
+        let parse =
+            |data: &str| crate::ical::Ical::parse(&mut crate::ical::LineIterator::new(data));
+        let (Ok(mut merged), Ok(instances)) = (parse(&existing.data), parse(&event_ref.data))
+        else {
+            event_refs.push(event_ref);
+            continue;
+        };
+        let recurrence_id = |c: &crate::ical::Ical| {
+            c.get_first_property("RECURRENCE-ID")
+                .map(|p| p.value.clone())
+        };
+        for instance in instances.children {
+            let known = merged
+                .children
+                .iter()
+                .any(|c| c.name == instance.name && recurrence_id(c) == recurrence_id(&instance));
+            if !known {
+                merged.add_component(instance);
+            }
+        }
+        existing.data = merged.serialize();
+    }
+}
+
+/// Parse a UTC DATE-TIME like `20240101T000000Z` into seconds since 1970.
+fn parse_utc_seconds(value: &str) -> Option<i64> {
+    let value = value.strip_suffix('Z')?;
+    if value.len() != 15 || value.as_bytes()[8] != b'T' {
+        return None;
+    }
+    let number = |range: std::ops::Range<usize>| value.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (number(0..4)?, number(4..6)?, number(6..8)?);
+    let (hour, minute, second) = (number(9..11)?, number(11..13)?, number(13..15)?);
+    // Days since 1970 of the proleptic Gregorian calendar, see http://howardhinnant.github.io/date_algorithms.html
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Format seconds since 1970 as UTC DATE-TIME, the inverse of `parse_utc_seconds`.
+pub(crate) fn format_utc_seconds(seconds: i64) -> String {
+    let days = seconds.div_euclid(86400) + 719468;
+    let time = seconds.rem_euclid(86400);
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+fn parse_events_report(
+    client: &Client,
+    base_url: &Url,
+    content: &str,
+) -> Result<(Vec<EventRef>, Vec<ResourceError>), MiniCaldavError> {
+    trace!("Read CalDAV events: {:?}", content);
+    let root = xmltree::Element::parse(content.as_bytes())?;
+    Ok(parse_calendar_data_response(client, base_url, &root))
+}
 
+/// Send the calendar-query REPORT for the events of the given calendar.
+pub(crate) async fn send_events_report(
+    client: &Client,
+    credentials: &Credentials,
+    calendar_url: Url,
+    start: Option<String>,
+    end: Option<String>,
+    expanded: bool,
+) -> Result<reqwest::Response, MiniCaldavError> {
     let xml = if expanded {
         &build_calendar_request_string(start, end, expanded)
     } else {
@@ -498,277 +1043,3692 @@ pub async fn get_events(
     };
 
     let request = client
-        .request(Method::from_bytes(b"REPORT").unwrap(), calendar_url)
-        .header(USER_AGENT, "rust-minicaldav")
-        .header(AUTHORIZATION, auth)
+        .request(
+            Method::from_bytes(b"REPORT").unwrap(),
+            collection_url(calendar_url),
+        )
+        .authorization(credentials)
         .header(CONTENT_TYPE, "application/xml; charset=utf-8")
         .header(ACCEPT, "text/xml, text/calendar")
         .header("Depth", "1")
         .body(xml.to_string());
 
-    let content = request.send().await?.text().await?;
+    request.send().await?.check_status().await
+}
 
-    trace!("Read CalDAV events: {:?}", content);
-    // println!("content: {}", content);
-    let reader = content.as_bytes();
+/// Parse the responses of a calendar-query or calendar-multiget REPORT.
+/// Responses without calendar data are returned as `ResourceError` with their status code.
+fn parse_calendar_data_response(
+    client: &Client,
+    base_url: &Url,
+    root: &xmltree::Element,
+) -> (Vec<EventRef>, Vec<ResourceError>) {
+    parse_object_data_response(client, base_url, root, "calendar-data")
+}
 
-    let root = xmltree::Element::parse(reader)?;
+/// Parse the responses of a REPORT returning the data of objects in the property `data_name`,
+/// e.g. `calendar-data` or the `address-data` of CardDAV.
+pub(crate) fn parse_object_data_response(
+    client: &Client,
+    base_url: &Url,
+    root: &xmltree::Element,
+    data_name: &str,
+) -> (Vec<EventRef>, Vec<ResourceError>) {
     let mut events = Vec::new();
-    for c in &root.children {
-        if let Some(child) = c.as_element() {
-            let href = child.get_child("href").and_then(|e| e.get_text());
-            let etag = child
-                .get_child("propstat")
-                .and_then(|e| e.get_child("prop"))
-                .and_then(|e| e.get_child("getetag"))
-                .and_then(|e| e.get_text())
-                .map(|e| e.to_string());
-            let data = child
-                .get_child("propstat")
-                .and_then(|e| e.get_child("prop"))
-                .and_then(|e| e.get_child("calendar-data"))
-                .and_then(|e| e.get_text());
-            if href.is_none() || etag.is_none() || data.is_none() {
+    let mut errors = Vec::new();
+    for response in root
+        .children
+        .iter()
+        .filter_map(|c| c.as_element())
+        .filter(|e| e.name == "response")
+    {
+        let href = match response.get_child("href").and_then(|e| e.get_text()) {
+            Some(href) => href,
+            None => continue,
+        };
+        let url = match client.resolve_href(base_url, &href) {
+            Ok(url) => url,
+            Err(_) => {
+                error!("Could not parse url {}/{}", base_url, href);
                 continue;
             }
+        };
+        let description = response
+            .get_child("responsedescription")
+            .and_then(|e| e.get_text())
+            .map(|e| e.trim().to_string());
 
-            if let Some((href, data)) = href.and_then(|href| data.map(|data| (href, data))) {
-                if let Ok(url) = base_url.join(&href) {
-                    events.push(EventRef {
-                        url,
-                        data: data.to_string(),
-                        etag,
-                    })
-                } else {
-                    error!("Could not parse url {}/{}", base_url, href)
-                }
+        // A status directly in the response applies to the whole resource, e.g. a 404 for an
+        // unknown href in a calendar-multiget.
+        if let Some(status) = response.get_child("status").and_then(|e| e.get_text()) {
+            let status = parse_status_code(&status);
+            if !status.map(|s| (200..300).contains(&s)).unwrap_or(false) {
+                errors.push(ResourceError {
+                    url,
+                    status,
+                    description,
+                });
+                continue;
             }
         }
-    }
 
-    Ok(events)
-}
+        let propstats = response
+            .children
+            .iter()
+            .filter_map(|c| c.as_element())
+            .filter(|e| e.name == "propstat")
+            .collect::<Vec<_>>();
+        let prop = |name: &str| {
+            propstats
+                .iter()
+                .filter(|p| {
+                    p.get_child("status")
+                        .and_then(|s| s.get_text())
+                        .and_then(|s| parse_status_code(&s))
+                        .map(|s| (200..300).contains(&s))
+                        .unwrap_or(true)
+                })
+                .filter_map(|p| p.get_child("prop"))
+                .filter_map(|p| p.get_child(name))
+                .find_map(|e| e.get_text())
+                .map(|e| e.to_string())
+        };
+        let etag = prop("getetag");
+        let schedule_tag = prop("schedule-tag");
+        let data = prop(data_name);
 
-pub async fn get_ical_events(
-    client: &Client,
-    credentials: &Credentials,
-    calendar_url: Url,
-) -> Result<Vec<EventRef>, MiniCaldavError> {
-    let auth = get_auth_header(credentials);
+        match (etag, data) {
+            (Some(etag), Some(data)) => events.push(EventRef {
+                url,
+                data,
+                etag: Some(etag),
+                schedule_tag,
+            }),
+            _ => {
+                let status = propstats
+                    .iter()
+                    .filter_map(|p| p.get_child("status").and_then(|s| s.get_text()))
+                    .filter_map(|s| parse_status_code(&s))
+                    .find(|s| !(200..300).contains(s));
+                errors.push(ResourceError {
+                    url,
+                    status,
+                    description,
+                })
+            }
+        }
+    }
+    (events, errors)
+}
 
-    let response = client
-        .get(calendar_url.clone())
-        .header(USER_AGENT, "rust-minicaldav")
-        .header(AUTHORIZATION, auth)
-        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
-        .header(ACCEPT, "text/xml, text/calendar")
-        .header("Depth", "1")
-        .send().await?.text().await?;
+/// A parsed multistatus response (RFC 4918).
+#[derive(Debug, Clone)]
+pub struct Multistatus {
+    pub responses: Vec<MultistatusResponse>,
+    /// The `sync-token` of a sync-collection response.
+    pub sync_token: Option<String>,
+}
 
-    // println!("response: {:?}", response);
-    let events = vec![EventRef {
-        url: calendar_url,
-        data: response.to_string(),
-        etag: None,
-    }];
-    Ok(events)
+/// One `response` element of a multistatus response.
+#[derive(Debug, Clone)]
+pub struct MultistatusResponse {
+    pub url: Url,
+    /// The status of the whole resource, if the server reported one instead of propstats.
+    pub status: Option<u16>,
+    pub propstats: Vec<Propstat>,
+    pub description: Option<String>,
 }
-fn get_auth_header(credentials: &Credentials) -> String {
-    match credentials {
-        Credentials::Basic(username, password) => {
-            format!(
-                "Basic {}",
-                base64::encode(format!("{}:{}", username, password)) // username, password
-            )
-        }
-        Credentials::Bearer(token) => format!("Bearer {}", token),
-    }
+
+/// A group of properties that share the same status.
+#[derive(Debug, Clone)]
+pub struct Propstat {
+    pub status: Option<u16>,
+    pub props: Vec<xmltree::Element>,
 }
 
-/// Get ICAL formatted todos from the CalDAV server.
-pub async fn get_todos(
+impl Multistatus {
+    /// Parse a multistatus element. Relative hrefs are resolved against `base_url`.
+    pub fn from_xml(client: &Client, base_url: &Url, root: &xmltree::Element) -> Self {
+        let responses = root
+            .children
+            .iter()
+            .filter_map(|c| c.as_element())
+            .filter(|e| e.name == "response")
+            .filter_map(|response| {
+                let href = response.get_child("href").and_then(|e| e.get_text())?;
+                let url = match client.resolve_href(base_url, &href) {
+                    Ok(url) => url,
+                    Err(_) => {
+                        error!("Could not parse url {}/{}", base_url, href);
+                        return None;
+                    }
+                };
+                let propstats = response
+                    .children
+                    .iter()
+                    .filter_map(|c| c.as_element())
+                    .filter(|e| e.name == "propstat")
+                    .map(|propstat| Propstat {
+                        status: propstat
+                            .get_child("status")
+                            .and_then(|s| s.get_text())
+                            .and_then(|s| parse_status_code(&s)),
+                        props: propstat
+                            .get_child("prop")
+                            .map(|p| {
+                                p.children
+                                    .iter()
+                                    .filter_map(|c| c.as_element())
+                                    .cloned()
+                                    .collect()
+                            })
+                            .unwrap_or_default(),
+                    })
+                    .collect();
+                Some(MultistatusResponse {
+                    url,
+                    status: response
+                        .get_child("status")
+                        .and_then(|s| s.get_text())
+                        .and_then(|s| parse_status_code(&s)),
+                    propstats,
+                    description: response
+                        .get_child("responsedescription")
+                        .and_then(|e| e.get_text())
+                        .map(|e| e.trim().to_string()),
+                })
+            })
+            .collect();
+        Self {
+            responses,
+            sync_token: root
+                .get_child("sync-token")
+                .and_then(|e| e.get_text())
+                .map(|t| t.trim().to_string()),
+        }
+    }
+}
+
+impl MultistatusResponse {
+    /// The property with the given (local) name from a successful propstat.
+    pub fn prop(&self, name: &str) -> Option<&xmltree::Element> {
+        self.propstats
+            .iter()
+            .filter(|p| p.status.map(|s| (200..300).contains(&s)).unwrap_or(true))
+            .flat_map(|p| p.props.iter())
+            .find(|e| e.name == name)
+    }
+
+    /// The text of the property with the given (local) name from a successful propstat.
+    pub fn prop_text(&self, name: &str) -> Option<String> {
+        self.prop(name)
+            .and_then(|e| e.get_text())
+            .map(|t| t.to_string())
+    }
+}
+
+/// Parse the status code of a status line like `HTTP/1.1 404 Not Found`.
+pub(crate) fn parse_status_code(status: &str) -> Option<u16> {
+    status
+        .split_whitespace()
+        .nth(1)
+        .and_then(|c| c.parse().ok())
+}
+
+/// Collation used to compare text in a `text-match` filter (RFC 4790).
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Collation {
+    /// Case insensitive comparison of ASCII characters. The default of CalDAV servers.
+    #[default]
+    AsciiCasemap,
+    /// Exact, case sensitive comparison.
+    Octet,
+    /// Case insensitive comparison of unicode characters. Not supported by all servers.
+    UnicodeCasemap,
+}
+
+impl Collation {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::AsciiCasemap => "i;ascii-casemap",
+            Self::Octet => "i;octet",
+            Self::UnicodeCasemap => "i;unicode-casemap",
+        }
+    }
+}
+
+/// A `prop-filter` of a calendar-query, matching components by one of their properties.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropFilter {
+    name: String,
+    is_not_defined: bool,
+    text: Option<String>,
+    collation: Collation,
+    negate: bool,
+}
+
+impl PropFilter {
+    /// Match components that have the property of the given name.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_uppercase(),
+            is_not_defined: false,
+            text: None,
+            collation: Collation::default(),
+            negate: false,
+        }
+    }
+
+    /// Match components that do not have the property of the given name.
+    pub fn is_not_defined(name: &str) -> Self {
+        Self {
+            is_not_defined: true,
+            ..Self::new(name)
+        }
+    }
+
+    /// Match components whose property value contains the given text.
+    pub fn contains(mut self, text: &str) -> Self {
+        self.text = Some(text.to_string());
+        self
+    }
+
+    pub fn collation(mut self, collation: Collation) -> Self {
+        self.collation = collation;
+        self
+    }
+
+    /// Match components whose property value does NOT contain the text.
+    pub fn negate(mut self) -> Self {
+        self.negate = true;
+        self
+    }
+
+    fn to_xml(&self) -> String {
+        let name = escape_xml(&self.name);
+        if self.is_not_defined {
+            return format!(
+                r#"<c:prop-filter name="{}"><c:is-not-defined /></c:prop-filter>"#,
+                name
+            );
+        }
+        match &self.text {
+            Some(text) => format!(
+                r#"<c:prop-filter name="{}"><c:text-match collation="{}" negate-condition="{}">{}</c:text-match></c:prop-filter>"#,
+                name,
+                self.collation.as_str(),
+                if self.negate { "yes" } else { "no" },
+                escape_xml(text)
+            ),
+            None => format!(r#"<c:prop-filter name="{}" />"#, name),
+        }
+    }
+}
+
+/// Builder for a calendar-query REPORT (RFC 4791) to let the server filter components.
+///
+/// ```rust
+/// use minicaldav::caldav::{CalendarQuery, PropFilter};
+/// let query = CalendarQuery::events()
+///     .prop_filter(PropFilter::new("CATEGORIES").contains("work"))
+///     .prop_filter(PropFilter::new("SUMMARY").contains("standup"));
+/// ```
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarQuery {
+    component: String,
+    start: Option<String>,
+    end: Option<String>,
+    prop_filters: Vec<PropFilter>,
+}
+
+impl CalendarQuery {
+    /// Query components of the given name, e.g. `VEVENT`.
+    pub fn new(component: &str) -> Self {
+        Self {
+            component: component.to_uppercase(),
+            start: None,
+            end: None,
+            prop_filters: Vec::new(),
+        }
+    }
+
+    /// Query VEVENT components.
+    pub fn events() -> Self {
+        Self::new("VEVENT")
+    }
+
+    /// Query VTODO components.
+    pub fn todos() -> Self {
+        Self::new("VTODO")
+    }
+
+    /// Only match components overlapping the given UTC time range, e.g. `20240101T000000Z`.
+    /// An open end is expressed with `None`.
+    pub fn time_range(mut self, start: Option<String>, end: Option<String>) -> Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+
+    /// Add a property filter. Components have to match all filters.
+    pub fn prop_filter(mut self, filter: PropFilter) -> Self {
+        self.prop_filters.push(filter);
+        self
+    }
+
+    /// The calendar-query REPORT body.
+    pub fn to_xml(&self) -> String {
+        let time_range = if self.start.is_some() || self.end.is_some() {
+            let start = self
+                .start
+                .as_ref()
+                .map(|s| format!(r#" start="{}""#, escape_xml(s)))
+                .unwrap_or_default();
+            let end = self
+                .end
+                .as_ref()
+                .map(|e| format!(r#" end="{}""#, escape_xml(e)))
+                .unwrap_or_default();
+            format!("<c:time-range{}{} />", start, end)
+        } else {
+            String::new()
+        };
+        let prop_filters = self
+            .prop_filters
+            .iter()
+            .map(|f| f.to_xml())
+            .collect::<Vec<String>>()
+            .join("");
+        format!(
+            r#"<?xml version="1.0" encoding="utf-8" ?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+    <d:prop>
+        <d:getetag />
+        <c:schedule-tag />
+        <c:calendar-data />
+    </d:prop>
+    <c:filter>
+        <c:comp-filter name="VCALENDAR">
+            <c:comp-filter name="{}">{}{}</c:comp-filter>
+        </c:comp-filter>
+    </c:filter>
+</c:calendar-query>
+"#,
+            escape_xml(&self.component),
+            time_range,
+            prop_filters
+        )
+    }
+}
+
+/// Send the given calendar-query to the given calendar and return the matching objects.
+pub async fn query(
     client: &Client,
     credentials: &Credentials,
     base_url: &Url,
-    calendar_ref: &CalendarRef,
-) -> Result<Vec<EventRef>, MiniCaldavError> {
-    let auth = get_auth_header(credentials);
+    calendar_url: &Url,
+    query: &CalendarQuery,
+) -> Result<(Vec<EventRef>, Vec<ResourceError>), MiniCaldavError> {
+    let calendar_url = &collection_url(calendar_url.clone());
+    let content = client
+        .request(
+            Method::from_bytes(b"REPORT").unwrap(),
+            calendar_url.as_str(),
+        )
+        .authorization(credentials)
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .header(ACCEPT, "text/xml, text/calendar")
+        .header("Depth", "1")
+        .body(query.to_xml())
+        .send()
+        .await?
+        .check_status()
+        .await?
+        .text()
+        .await?;
 
-    let report = Method::from_bytes(b"REPORT").unwrap();
+    trace!("Read CalDAV query result: {:?}", content);
+    let root = xmltree::Element::parse(content.as_bytes())?;
+    Ok(parse_calendar_data_response(client, base_url, &root))
+}
+
+/// The progress of a bulk download, e.g. to show a progress bar during an initial sync.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// The number of objects fetched so far.
+    pub fetched: usize,
+    /// The number of objects to fetch, if already known.
+    pub total: Option<usize>,
+    /// The number of response bytes downloaded so far.
+    pub bytes: u64,
+}
+
+/// The result for one object of a calendar-multiget.
+#[derive(Debug, Clone)]
+pub enum MultigetItem {
+    Found(EventRef),
+    /// The object does not exist anymore (404 or 410), e.g. it was deleted since it was listed.
+    Gone(ResourceError),
+    /// The server returned another error for the object.
+    Error(ResourceError),
+}
+
+/// Fetch the given objects of a calendar with a calendar-multiget REPORT.
+pub async fn calendar_multiget(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calendar_url: &Url,
+    urls: &[Url],
+) -> Result<Vec<MultigetItem>, MiniCaldavError> {
+    Ok(multiget(client, credentials, base_url, calendar_url, urls)
+        .await?
+        .0)
+}
 
+/// Send a calendar-multiget REPORT and return its items and the size of the response in bytes.
+async fn multiget(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calendar_url: &Url,
+    urls: &[Url],
+) -> Result<(Vec<MultigetItem>, u64), MiniCaldavError> {
+    let calendar_url = &collection_url(calendar_url.clone());
+    if urls.is_empty() {
+        return Ok((Vec::new(), 0));
+    }
     let content = client
-        .request(report, calendar_ref.url.as_str())
-        .header(USER_AGENT, "rust-minicaldav")
-        .header(AUTHORIZATION, &auth)
+        .request(
+            Method::from_bytes(b"REPORT").unwrap(),
+            calendar_url.as_str(),
+        )
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .header(ACCEPT, "text/xml, text/calendar")
+        .authorization(credentials)
         .header("Depth", "1")
-        .header(CONTENT_TYPE, "application/xml; chatset=utf-8")
-        .body(CALENDAR_TODOS_REQUEST.as_bytes())
+        .body(build_multiget_xml(urls))
         .send()
         .await?
+        .check_status()
+        .await?
         .text()
         .await?;
+    trace!("Read CalDAV multiget: {:?}", content);
+    let root = xmltree::Element::parse(content.as_bytes())?;
+    Ok((
+        parse_multiget_response(client, base_url, &root),
+        content.len() as u64,
+    ))
+}
+
+/// Fetch the given objects with calendar-multiget REPORTs of at most `chunk_size` objects,
+/// running up to `parallelism` requests at a time.
+pub async fn calendar_multiget_parallel(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calendar_url: &Url,
+    urls: &[Url],
+    chunk_size: usize,
+    parallelism: usize,
+) -> Result<Vec<MultigetItem>, MiniCaldavError> {
+    calendar_multiget_parallel_with_progress(
+        client,
+        credentials,
+        base_url,
+        calendar_url,
+        urls,
+        chunk_size,
+        parallelism,
+        |_| {},
+    )
+    .await
+}
+
+/// Like `calendar_multiget_parallel`, but calls `progress` whenever a request is complete.
+#[allow(clippy::too_many_arguments)]
+pub async fn calendar_multiget_parallel_with_progress<F: FnMut(Progress)>(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calendar_url: &Url,
+    urls: &[Url],
+    chunk_size: usize,
+    parallelism: usize,
+    progress: F,
+) -> Result<Vec<MultigetItem>, MiniCaldavError> {
+    // The requests run concurrently on the same task, so they can share the state.
+    let state = RefCell::new((
+        Progress {
+            fetched: 0,
+            total: Some(urls.len()),
+            bytes: 0,
+        },
+        progress,
+    ));
+    let requests = urls.chunks(chunk_size.max(1)).map(|chunk| {
+        let state = &state;
+        async move {
+            let (items, bytes) =
+                multiget(client, credentials, base_url, calendar_url, chunk).await?;
+            let (current, progress) = &mut *state.borrow_mut();
+            current.fetched += chunk.len();
+            current.bytes += bytes;
+            progress(*current);
+            Ok::<_, MiniCaldavError>(items)
+        }
+    });
+    let mut items = Vec::new();
+    for result in join_bounded(requests, parallelism).await {
+        items.extend(result?);
+    }
+    Ok(items)
+}
+
+/// Fetch the given objects with one GET each, running up to `parallelism` requests at a time.
+/// Use this for servers without calendar-multiget support.
+pub async fn get_objects(
+    client: &Client,
+    credentials: &Credentials,
+    urls: &[Url],
+    parallelism: usize,
+) -> Result<(Vec<EventRef>, Vec<ResourceError>), MiniCaldavError> {
+    let requests = urls.iter().map(|url| get_object(client, credentials, url));
+    let mut event_refs = Vec::new();
+    let mut errors = Vec::new();
+    for result in join_bounded(requests, parallelism).await {
+        match result? {
+            Ok(event_ref) => event_refs.push(event_ref),
+            Err(error) => errors.push(error),
+        }
+    }
+    Ok((event_refs, errors))
+}
+
+async fn get_object(
+    client: &Client,
+    credentials: &Credentials,
+    url: &Url,
+) -> Result<Result<EventRef, ResourceError>, MiniCaldavError> {
+    let response = client
+        .get(url.as_str())
+        .authorization(credentials)
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Ok(Err(ResourceError {
+            url: url.clone(),
+            status: Some(status.as_u16()),
+            description: status.canonical_reason().map(|r| r.to_string()),
+        }));
+    }
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    let etag = header("ETag");
+    let schedule_tag = header("Schedule-Tag");
+    Ok(Ok(EventRef {
+        etag,
+        url: url.clone(),
+        data: response.text().await?,
+        schedule_tag,
+    }))
+}
+
+/// The beginning of an object, fetched with `get_object_head`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectHead {
+    pub url: Url,
+    pub etag: Option<String>,
+    /// The first bytes of the object, without a line that was cut off.
+    pub data: String,
+    /// Whether `data` is the whole object.
+    pub complete: bool,
+    /// The size of the whole object in bytes, if the server reported it.
+    pub size: Option<u64>,
+}
+
+impl ObjectHead {
+    /// The first property with the given name in the fetched data, e.g. `SUMMARY` or `DTSTART`.
+    /// Properties after the fetched part are not found.
+    pub fn property(&self, name: &str) -> Option<crate::ical::Property> {
+        let mut lines = Vec::<String>::new();
+        for line in self.data.lines() {
+            match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+                (Some(continuation), Some(last)) => last.push_str(continuation),
+                _ => lines.push(line.to_string()),
+            }
+        }
+        lines
+            .iter()
+            .filter(|line| {
+                line.split([':', ';'])
+                    .next()
+                    .map(|n| n.eq_ignore_ascii_case(name))
+                    .unwrap_or(false)
+            })
+            .find_map(|line| crate::ical::Property::parse(line).ok())
+    }
+}
+
+/// Fetch only the first `max_bytes` of the given object with a ranged GET, e.g. to list objects with
+/// huge inline attachments. If the server ignores the `Range` header, the download is stopped after `max_bytes`.
+pub async fn get_object_head(
+    client: &Client,
+    credentials: &Credentials,
+    url: &Url,
+    max_bytes: usize,
+) -> Result<ObjectHead, MiniCaldavError> {
+    let response = client
+        .get(url.as_str())
+        .header(RANGE, format!("bytes=0-{}", max_bytes.max(1) - 1))
+        .authorization(credentials)
+        .send()
+        .await?;
+    if response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        // Only an empty object has no first byte.
+        return Ok(ObjectHead {
+            url: url.clone(),
+            etag: None,
+            data: String::new(),
+            complete: true,
+            size: Some(0),
+        });
+    }
+    let mut response = response.check_status().await?;
+    let partial = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    let header = |name: reqwest::header::HeaderName| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    let etag = header(reqwest::header::ETAG);
+    let size: Option<u64> = if partial {
+        header(CONTENT_RANGE).and_then(|range| {
+            range
+                .rsplit_once('/')
+                .and_then(|(_, size)| size.parse().ok())
+        })
+    } else {
+        header(CONTENT_LENGTH).and_then(|length| length.parse().ok())
+    };
+
+    // Servers ignoring the range send the whole object, stop reading after `max_bytes`.
+    let mut body = Vec::new();
+    let mut ended = false;
+    while body.len() < max_bytes {
+        match response.chunk().await? {
+            Some(chunk) => body.extend_from_slice(&chunk),
+            None => {
+                ended = true;
+                break;
+            }
+        }
+    }
+    let complete = match size {
+        Some(size) => size <= body.len().min(max_bytes) as u64,
+        None => ended && (body.len() < max_bytes || !partial),
+    };
+    body.truncate(max_bytes);
+    let mut data = String::from_utf8_lossy(&body).into_owned();
+    if !complete {
+        // The last line is cut off, possibly in the middle of a character.
+        let end = data.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        data.truncate(end);
+    }
+    Ok(ObjectHead {
+        url: url.clone(),
+        etag,
+        data,
+        complete,
+        size,
+    })
+}
+
+fn parse_multiget_response(
+    client: &Client,
+    base_url: &Url,
+    root: &xmltree::Element,
+) -> Vec<MultigetItem> {
+    let (event_refs, errors) = parse_calendar_data_response(client, base_url, root);
+    event_refs
+        .into_iter()
+        .map(MultigetItem::Found)
+        .chain(errors.into_iter().map(|e| {
+            if e.is_gone() {
+                MultigetItem::Gone(e)
+            } else {
+                MultigetItem::Error(e)
+            }
+        }))
+        .collect()
+}
+
+fn build_multiget_xml(urls: &[Url]) -> String {
+    let hrefs = urls
+        .iter()
+        .map(|u| format!("<d:href>{}</d:href>", escape_xml(u.path())))
+        .collect::<Vec<String>>()
+        .join("\n        ");
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<c:calendar-multiget xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+    <d:prop>
+        <d:getetag />
+        <c:schedule-tag />
+        <c:calendar-data />
+    </d:prop>
+    {}
+</c:calendar-multiget>
+"#,
+        hrefs
+    )
+}
+
+/// A resource reported as changed by a sync-collection REPORT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedResource {
+    pub url: Url,
+    pub etag: Option<String>,
+}
+
+/// The result of a sync-collection REPORT (RFC 6578).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncCollectionResult {
+    /// The token to pass to the next sync-collection request.
+    pub sync_token: Option<String>,
+    /// Resources created or modified since the given token.
+    pub changed: Vec<ChangedResource>,
+    /// Resources deleted since the given token.
+    pub deleted: Vec<Url>,
+    /// The server did not report all changes. Request again with the new token to get the rest.
+    pub truncated: bool,
+}
+
+impl SyncCollectionResult {
+    /// Parse the given sync-collection response.
+    pub fn from_xml(
+        client: &Client,
+        base_url: &Url,
+        collection_url: &Url,
+        root: &xmltree::Element,
+    ) -> Self {
+        let mut result = Self {
+            sync_token: root
+                .get_child("sync-token")
+                .and_then(|e| e.get_text())
+                .map(|t| t.trim().to_string()),
+            ..Default::default()
+        };
+        for response in root
+            .children
+            .iter()
+            .filter_map(|c| c.as_element())
+            .filter(|e| e.name == "response")
+        {
+            let url = match response
+                .get_child("href")
+                .and_then(|e| e.get_text())
+                .and_then(|href| client.resolve_href(base_url, &href).ok())
+            {
+                Some(url) => url,
+                None => continue,
+            };
+            let status = response
+                .get_child("status")
+                .and_then(|e| e.get_text())
+                .and_then(|s| parse_status_code(&s));
+            if url.path() == collection_url.path() {
+                result.truncated |= status == Some(507);
+                continue;
+            }
+            if status == Some(404) {
+                result.deleted.push(url);
+                continue;
+            }
+            let etag = response
+                .children
+                .iter()
+                .filter_map(|c| c.as_element())
+                .filter(|e| e.name == "propstat")
+                .filter_map(|p| p.get_child("prop"))
+                .filter_map(|p| p.get_child("getetag"))
+                .find_map(|e| e.get_text())
+                .map(|e| e.to_string());
+            result.changed.push(ChangedResource { url, etag });
+        }
+        result
+    }
+}
+
+pub static ETAGS_REQUEST: &str = r#"
+    <d:propfind xmlns:d="DAV:">
+      <d:prop>
+        <d:getetag />
+        <d:resourcetype />
+      </d:prop>
+    </d:propfind>
+"#;
+
+/// List the objects of the given calendar with their etags, e.g. to find changes on servers
+/// without sync-collection.
+pub async fn get_etags(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calendar_url: &Url,
+) -> Result<Vec<ChangedResource>, MiniCaldavError> {
+    let calendar_url = collection_url(calendar_url.clone());
+    let root = propfind_get(
+        client,
+        credentials,
+        &calendar_url,
+        ETAGS_REQUEST.to_string(),
+        &[],
+        "1",
+    )
+    .await?
+    .1;
+    Ok(parse_etags(client, base_url, &calendar_url, &root))
+}
+
+fn parse_etags(
+    client: &Client,
+    base_url: &Url,
+    calendar_url: &Url,
+    root: &xmltree::Element,
+) -> Vec<ChangedResource> {
+    let mut resources = Vec::new();
+    for response in root.children.iter().filter_map(|c| c.as_element()) {
+        let Some(url) = response
+            .get_child("href")
+            .and_then(|e| e.get_text())
+            .and_then(|href| client.resolve_href(base_url, &href).ok())
+        else {
+            continue;
+        };
+        let prop = response
+            .get_child("propstat")
+            .and_then(|e| e.get_child("prop"));
+        let is_collection = prop
+            .and_then(|e| e.get_child("resourcetype"))
+            .map(|e| e.get_child("collection").is_some())
+            .unwrap_or(false);
+        if is_collection || url.path() == calendar_url.path() {
+            continue;
+        }
+        let etag = prop
+            .and_then(|e| e.get_child("getetag"))
+            .and_then(|e| e.get_text())
+            .map(|e| e.to_string());
+        resources.push(ChangedResource { url, etag });
+    }
+    resources
+}
+
+/// Get the changes of the given calendar since `sync_token` with a sync-collection REPORT (RFC 6578).
+/// Without a token, all resources of the calendar are reported as changed.
+/// Returns `MiniCaldavError::InvalidSyncToken` if the server does not accept the token anymore,
+/// a full sync without token is needed then.
+pub async fn sync_collection(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calendar_url: &Url,
+    sync_token: Option<&str>,
+) -> Result<SyncCollectionResult, MiniCaldavError> {
+    let calendar_url = &collection_url(calendar_url.clone());
+    let response = client
+        .request(
+            Method::from_bytes(b"REPORT").unwrap(),
+            calendar_url.as_str(),
+        )
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .header(ACCEPT, "text/xml")
+        .authorization(credentials)
+        .body(build_sync_collection_xml(sync_token.unwrap_or_default()))
+        .send()
+        .await?;
+
+    let status = response.status();
+    let content = response.text().await?;
+    trace!("CalDAV sync-collection response: {:?}", content);
+    if (status.as_u16() == 403 || status.as_u16() == 409) && content.contains("valid-sync-token") {
+        return Err(InvalidSyncToken(sync_token.unwrap_or_default().to_string()));
+    }
+    if !status.is_success() {
+        return Err(HttpError::new(calendar_url.clone(), status.as_u16(), &content).into());
+    }
+
+    let root = xmltree::Element::parse(content.as_bytes())?;
+    Ok(SyncCollectionResult::from_xml(
+        client,
+        base_url,
+        calendar_url,
+        &root,
+    ))
+}
+
+/// Number of sync-collection requests of one `sync_collection_all` call or watcher poll,
+/// for servers reporting the changes in parts.
+pub(crate) const MAX_SYNC_ROUNDS: usize = 16;
+
+/// Number of objects fetched with one calendar-multiget by `fetch_objects`.
+const MULTIGET_CHUNK_SIZE: usize = 100;
+
+/// Number of calendar-multiget requests of `fetch_objects` running at the same time.
+const MULTIGET_PARALLELISM: usize = 4;
+
+/// The changes reported in all parts of a sync-collection, see `sync_collection_all`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct CollectionChanges {
+    pub sync_token: Option<String>,
+    pub changed: Vec<ChangedResource>,
+    pub deleted: Vec<Url>,
+    /// `changed` lists all objects of the calendar, objects missing in it were deleted.
+    pub listed_all: bool,
+}
+
+/// Request sync-collection until the server reported all changes since `sync_token`.
+/// Starts over without token if the server does not accept the token anymore.
+pub(crate) async fn sync_collection_all(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calendar_url: &Url,
+    sync_token: Option<String>,
+) -> Result<CollectionChanges, MiniCaldavError> {
+    let mut changes = CollectionChanges {
+        sync_token,
+        ..Default::default()
+    };
+    for _ in 0..MAX_SYNC_ROUNDS {
+        let token = changes.sync_token.take();
+        let result = match sync_collection(
+            client,
+            credentials,
+            base_url,
+            calendar_url,
+            token.as_deref(),
+        )
+        .await
+        {
+            Err(InvalidSyncToken(_)) if token.is_some() => {
+                changes = CollectionChanges::default();
+                continue;
+            }
+            result => result?,
+        };
+        // Without token all objects are listed. Objects listed in a later part are reported again.
+        if token.is_none() && !result.truncated {
+            changes.listed_all = true;
+        }
+        changes.changed.extend(result.changed);
+        changes.deleted.extend(result.deleted);
+        changes.sync_token = result.sync_token;
+        if !result.truncated {
+            break;
+        }
+    }
+    Ok(changes)
+}
+
+/// Fetch the given objects of a calendar with parallel calendar-multiget requests.
+pub(crate) async fn fetch_objects(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calendar_url: &Url,
+    urls: &[Url],
+) -> Result<Vec<MultigetItem>, MiniCaldavError> {
+    calendar_multiget_parallel(
+        client,
+        credentials,
+        base_url,
+        calendar_url,
+        urls,
+        MULTIGET_CHUNK_SIZE,
+        MULTIGET_PARALLELISM,
+    )
+    .await
+}
+
+fn build_sync_collection_xml(sync_token: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:sync-collection xmlns:d="DAV:">
+    <d:sync-token>{}</d:sync-token>
+    <d:sync-level>1</d:sync-level>
+    <d:prop>
+        <d:getetag />
+    </d:prop>
+</d:sync-collection>
+"#,
+        escape_xml(sync_token)
+    )
+}
+
+pub async fn get_ical_events(
+    client: &Client,
+    credentials: &Credentials,
+    calendar_url: Url,
+) -> Result<Vec<EventRef>, MiniCaldavError> {
+    let response = client
+        .get(calendar_url.clone())
+        .authorization(credentials)
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .header(ACCEPT, "text/xml, text/calendar")
+        .header("Depth", "1")
+        .send()
+        .await?
+        .check_status()
+        .await?
+        .text()
+        .await?;
+
+    // println!("response: {:?}", response);
+    let events = vec![EventRef {
+        url: calendar_url,
+        data: response.to_string(),
+        etag: None,
+        schedule_tag: None,
+    }];
+    Ok(events)
+}
+pub(crate) fn get_auth_header(credentials: &Credentials) -> Option<String> {
+    match credentials {
+        Credentials::Basic(username, password) => Some(format!(
+            "Basic {}",
+            base64::encode(format!("{}:{}", username, password)) // username, password
+        )),
+        Credentials::Bearer(token) => Some(format!("Bearer {}", token)),
+        Credentials::None => None,
+    }
+}
+
+/// Get ICAL formatted todos from the CalDAV server.
+pub async fn get_todos(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calendar_ref: &CalendarRef,
+) -> Result<(Vec<EventRef>, Vec<ResourceError>), MiniCaldavError> {
+    let report = Method::from_bytes(b"REPORT").unwrap();
+
+    let content = client
+        .request(report, collection_url(calendar_ref.url.clone()))
+        .authorization(credentials)
+        .header("Depth", "1")
+        .header(CONTENT_TYPE, "application/xml; chatset=utf-8")
+        .body(CALENDAR_TODOS_REQUEST.as_bytes())
+        .send()
+        .await?
+        .check_status()
+        .await?
+        .text()
+        .await?;
+
+    trace!("Read CalDAV events: {:?}", content);
+    let reader = content.as_bytes();
+
+    let root = xmltree::Element::parse(reader)?;
+    Ok(parse_calendar_data_response(client, base_url, &root))
+}
+
+/// Save the given event on the CalDAV server.
+/// If no event for the events url exist it will create a new event.
+/// Otherwise this is an update operation.
+/// With a schedule tag, returns `PreconditionFailed` if the tag on the server does not match anymore.
+pub async fn save_event(
+    client: &Client,
+    credentials: &Credentials,
+    event_ref: EventRef,
+) -> Result<EventRef, MiniCaldavError> {
+    let EventRef {
+        data,
+        url,
+        schedule_tag,
+        ..
+    } = event_ref.clone();
+
+    let content_length = data.len();
+
+    let mut request = client
+        .put(url)
+        .header(CONTENT_TYPE, "text/calendar")
+        .header(CONTENT_LENGTH, content_length.to_string())
+        .authorization(credentials);
+    if let Some(schedule_tag) = schedule_tag {
+        request = request.header("If-Schedule-Tag-Match", schedule_tag);
+    }
+
+    let response = request.body(data).send().await?;
+    if response.status() == reqwest::StatusCode::PRECONDITION_FAILED {
+        return Err(PreconditionFailed(event_ref.url));
+    }
+    let response = response.check_status().await?;
+
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    Ok(EventRef {
+        etag: header("ETag"),
+        schedule_tag: header("Schedule-Tag"),
+        ..event_ref
+    })
+}
+
+/// Save the given event on the CalDAV server with `If-Match` set to its etag.
+/// Returns `PreconditionFailed` instead of overwriting changes made since the event was fetched.
+/// Events without etag are created with `create_event`.
+pub async fn update_event(
+    client: &Client,
+    credentials: &Credentials,
+    event_ref: EventRef,
+) -> Result<EventRef, MiniCaldavError> {
+    let Some(etag) = event_ref.etag.clone() else {
+        return create_event(client, credentials, event_ref).await;
+    };
+    let mut request = client
+        .put(event_ref.url.clone())
+        .header(CONTENT_TYPE, "text/calendar")
+        .header(CONTENT_LENGTH, event_ref.data.len().to_string())
+        .header("If-Match", etag)
+        .authorization(credentials);
+    if let Some(schedule_tag) = &event_ref.schedule_tag {
+        request = request.header("If-Schedule-Tag-Match", schedule_tag.as_str());
+    }
+    let response = request.body(event_ref.data.clone()).send().await?;
+    if response.status() == reqwest::StatusCode::PRECONDITION_FAILED {
+        return Err(PreconditionFailed(event_ref.url));
+    }
+    let response = response.check_status().await?;
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    Ok(EventRef {
+        etag: header("ETag"),
+        schedule_tag: header("Schedule-Tag"),
+        ..event_ref
+    })
+}
+
+/// Create the given event on the CalDAV server with `If-None-Match: *`.
+/// Returns `AlreadyExists` instead of overwriting an existing object with the same url.
+pub async fn create_event(
+    client: &Client,
+    credentials: &Credentials,
+    event_ref: EventRef,
+) -> Result<EventRef, MiniCaldavError> {
+    let response = client
+        .put(event_ref.url.clone())
+        .header(CONTENT_TYPE, "text/calendar")
+        .header(CONTENT_LENGTH, event_ref.data.len().to_string())
+        .header("If-None-Match", "*")
+        .authorization(credentials)
+        .body(event_ref.data.clone())
+        .send()
+        .await?;
+    if response.status() == reqwest::StatusCode::PRECONDITION_FAILED {
+        return Err(AlreadyExists(event_ref.url));
+    }
+    let response = response.check_status().await?;
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    Ok(EventRef {
+        etag: header("ETag"),
+        schedule_tag: header("Schedule-Tag"),
+        ..event_ref
+    })
+}
+
+/// Delete the given event from the CalDAV server.
+pub async fn remove_event(
+    client: &Client,
+    credentials: &Credentials,
+    event_ref: EventRef,
+) -> Result<(), MiniCaldavError> {
+    let response = client
+        .delete(event_ref.url.as_str())
+        .authorization(credentials)
+        .send()
+        .await?;
+
+    response.check_status().await?;
+
+    Ok(())
+}
+
+/// Delete the given event with `If-Match` set to its etag.
+/// Returns `PreconditionFailed` instead of deleting changes made since the event was fetched.
+/// Events without etag are deleted unconditionally, objects that do not exist anymore count as deleted.
+pub async fn remove_event_if_match(
+    client: &Client,
+    credentials: &Credentials,
+    event_ref: EventRef,
+) -> Result<(), MiniCaldavError> {
+    let mut request = client
+        .delete(event_ref.url.as_str())
+        .authorization(credentials);
+    if let Some(etag) = &event_ref.etag {
+        request = request.header("If-Match", etag.as_str());
+    }
+    let response = request.send().await?;
+    match response.status() {
+        reqwest::StatusCode::PRECONDITION_FAILED => Err(PreconditionFailed(event_ref.url)),
+        reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE => Ok(()),
+        _ => {
+            response.check_status().await?;
+            Ok(())
+        }
+    }
+}
+
+/// The write whose outcome `verify_write` checks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpectedWrite {
+    /// A PUT of the object, with the ETag the object had before (`None` if it was created).
+    Put { previous_etag: Option<String> },
+    /// A DELETE of the object.
+    Delete,
+}
+
+/// Whether a write took effect, see `verify_write`.
+#[derive(Debug, Clone)]
+pub enum WriteOutcome {
+    /// The PUT took effect, with the object as stored by the server.
+    Stored(EventRef),
+    /// The DELETE took effect.
+    Deleted,
+    /// The write did not take effect, it can be retried.
+    NotApplied,
+}
+
+/// Check whether a PUT or DELETE of the object at `url` took effect on the server,
+/// e.g. after it failed with `MiniCaldavError::is_unknown_outcome`.
+/// A PUT counts as stored if the object has another ETag than before, which may also be the
+/// change of another client. Use a unique UID or compare the data of the returned object to tell them apart.
+pub async fn verify_write(
+    client: &Client,
+    credentials: &Credentials,
+    url: &Url,
+    expected: &ExpectedWrite,
+) -> Result<WriteOutcome, MiniCaldavError> {
+    let object = get_object(client, credentials, url).await?;
+    write_outcome(expected, object)
+}
+
+fn write_outcome(
+    expected: &ExpectedWrite,
+    object: Result<EventRef, ResourceError>,
+) -> Result<WriteOutcome, MiniCaldavError> {
+    match (expected, object) {
+        (ExpectedWrite::Put { previous_etag }, Ok(event_ref)) => {
+            if previous_etag.is_some() && event_ref.etag == *previous_etag {
+                Ok(WriteOutcome::NotApplied)
+            } else {
+                Ok(WriteOutcome::Stored(event_ref))
+            }
+        }
+        (ExpectedWrite::Put { .. }, Err(e)) if e.is_gone() => Ok(WriteOutcome::NotApplied),
+        (ExpectedWrite::Delete, Ok(_)) => Ok(WriteOutcome::NotApplied),
+        (ExpectedWrite::Delete, Err(e)) if e.is_gone() => Ok(WriteOutcome::Deleted),
+        (_, Err(e)) => Err(Resource(e)),
+    }
+}
+
+/// Send a MKCOL request to create a new calendar collection
+pub async fn create_calendar(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calid: String,
+    name: String,
+    color: String,
+) -> Result<(), MiniCaldavError> {
+    create_calendar_with_timezone(
+        client,
+        credentials,
+        base_url,
+        calid,
+        name,
+        color,
+        DEFAULT_CALENDAR_TIMEZONE,
+    )
+    .await
+}
+
+/// Send a MKCOL request to create a new calendar collection with the given calendar time zone.
+/// `timezone` is a VCALENDAR object containing exactly one VTIMEZONE component.
+pub async fn create_calendar_with_timezone(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calid: String,
+    name: String,
+    color: String,
+    timezone: &str,
+) -> Result<(), MiniCaldavError> {
+    let homeset_url = discover_home_set_url(client, credentials, base_url).await?;
+
+    let new_cal_url = homeset_url.join(&calid)?;
+
+    let mkcol = Method::from_bytes(b"MKCOL").unwrap();
+
+    let body = build_create_calendar_xml(name, color, timezone);
+
+    let response = client
+        .request(mkcol, new_cal_url)
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .header(ACCEPT, "text/xml, text/calendar")
+        .authorization(credentials)
+        .body(body)
+        .send()
+        .await?;
+
+    response.check_status().await?;
+
+    Ok(())
+}
+
+pub async fn remove_calendar(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calid: String,
+) -> Result<(), MiniCaldavError> {
+    let homeset_url = discover_home_set_url(client, credentials, base_url).await?;
+
+    let cal_url = homeset_url.join(&calid)?;
+
+    let response = client
+        .delete(cal_url)
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .authorization(credentials)
+        .send()
+        .await?;
+
+    response.check_status().await?;
+
+    Ok(())
+}
+
+/// Share the calendar at the given url with another user (calendarserver-sharing).
+/// `sharee` is the calendar address of the user, e.g. `mailto:bob@example.com`.
+pub async fn share_calendar(
+    client: &Client,
+    credentials: &Credentials,
+    calendar_url: &Url,
+    sharee: &str,
+    common_name: Option<&str>,
+    read_write: bool,
+) -> Result<(), MiniCaldavError> {
+    post_xml(
+        client,
+        credentials,
+        calendar_url,
+        build_share_xml(sharee, common_name, None, read_write),
+    )
+    .await
+}
+
+/// Stop sharing the calendar at the given url with the given user.
+pub async fn unshare_calendar(
+    client: &Client,
+    credentials: &Credentials,
+    calendar_url: &Url,
+    sharee: &str,
+) -> Result<(), MiniCaldavError> {
+    post_xml(client, credentials, calendar_url, build_unshare_xml(sharee)).await
+}
+
+async fn post_xml(
+    client: &Client,
+    credentials: &Credentials,
+    url: &Url,
+    body: String,
+) -> Result<(), MiniCaldavError> {
+    client
+        .post(url.as_str())
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .authorization(credentials)
+        .body(body)
+        .send()
+        .await?
+        .check_status()
+        .await?;
+
+    Ok(())
+}
+
+pub static NOTIFICATION_URL_REQUEST: &str = r#"
+    <d:propfind xmlns:d="DAV:" xmlns:cs="http://calendarserver.org/ns/">
+       <d:prop>
+           <cs:notification-URL />
+       </d:prop>
+    </d:propfind>
+"#;
+
+/// Get the url of the notification collection of the given principal.
+pub async fn get_notification_url(
+    client: &Client,
+    credentials: &Credentials,
+    principal_url: Url,
+) -> Result<Url, MiniCaldavError> {
+    let notification_url = propfind_get(
+        client,
+        credentials,
+        &principal_url,
+        NOTIFICATION_URL_REQUEST.to_string(),
+        &["response", "propstat", "prop", "notification-URL", "href"],
+        "0",
+    )
+    .await?
+    .0;
+    client.resolve_href(&principal_url, &notification_url)
+}
+
+pub static SCHEDULE_INBOX_URL_REQUEST: &str = r#"
+    <d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+       <d:prop>
+           <c:schedule-inbox-URL />
+       </d:prop>
+    </d:propfind>
+"#;
+
+/// Get the url of the scheduling inbox of the given principal.
+pub async fn get_schedule_inbox_url(
+    client: &Client,
+    credentials: &Credentials,
+    principal_url: Url,
+) -> Result<Url, MiniCaldavError> {
+    let inbox_url = propfind_get(
+        client,
+        credentials,
+        &principal_url,
+        SCHEDULE_INBOX_URL_REQUEST.to_string(),
+        &["response", "propstat", "prop", "schedule-inbox-URL", "href"],
+        "0",
+    )
+    .await?
+    .0;
+    client.resolve_href(&principal_url, &inbox_url)
+}
+
+pub static SCHEDULE_OUTBOX_URL_REQUEST: &str = r#"
+    <d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+       <d:prop>
+           <c:schedule-outbox-URL />
+       </d:prop>
+    </d:propfind>
+"#;
+
+/// Get the url of the scheduling outbox of the given principal.
+pub async fn get_schedule_outbox_url(
+    client: &Client,
+    credentials: &Credentials,
+    principal_url: Url,
+) -> Result<Url, MiniCaldavError> {
+    let outbox_url = propfind_get(
+        client,
+        credentials,
+        &principal_url,
+        SCHEDULE_OUTBOX_URL_REQUEST.to_string(),
+        &[
+            "response",
+            "propstat",
+            "prop",
+            "schedule-outbox-URL",
+            "href",
+        ],
+        "0",
+    )
+    .await?
+    .0;
+    client.resolve_href(&principal_url, &outbox_url)
+}
+
+/// Get the free-busy time of the given calendar between `start` and `end` (UTC date-times,
+/// e.g. `20240101T000000Z`) with a free-busy-query REPORT. Returns the VCALENDAR data.
+pub async fn free_busy_query(
+    client: &Client,
+    credentials: &Credentials,
+    calendar_url: &Url,
+    start: &str,
+    end: &str,
+) -> Result<String, MiniCaldavError> {
+    let calendar_url = &collection_url(calendar_url.clone());
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<c:free-busy-query xmlns:c="urn:ietf:params:xml:ns:caldav">
+    <c:time-range start="{}" end="{}"/>
+</c:free-busy-query>
+"#,
+        escape_xml(start),
+        escape_xml(end)
+    );
+    let content = client
+        .request(
+            Method::from_bytes(b"REPORT").unwrap(),
+            calendar_url.as_str(),
+        )
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .header(ACCEPT, "text/calendar")
+        .authorization(credentials)
+        .header("Depth", "1")
+        .body(body)
+        .send()
+        .await?
+        .check_status()
+        .await?
+        .text()
+        .await?;
+    trace!("CalDAV free-busy response: {:?}", content);
+    Ok(content)
+}
+
+/// The answer for one recipient of a scheduling request (RFC 6638).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleResponse {
+    /// The calendar user address of the recipient, e.g. `mailto:bob@example.com`.
+    pub recipient: String,
+    /// The `request-status`, e.g. `2.0;Success`.
+    pub request_status: String,
+    pub calendar_data: Option<String>,
+}
+
+impl ScheduleResponse {
+    pub fn is_success(&self) -> bool {
+        self.request_status.starts_with('2')
+    }
+
+    fn from_xml(root: &xmltree::Element) -> Vec<Self> {
+        root.children
+            .iter()
+            .filter_map(|c| c.as_element())
+            .filter(|e| e.name == "response")
+            .map(|response| {
+                let text = |element: Option<&xmltree::Element>| {
+                    element
+                        .and_then(|e| e.get_text())
+                        .map(|t| t.trim().to_string())
+                };
+                Self {
+                    recipient: text(
+                        response
+                            .get_child("recipient")
+                            .and_then(|r| r.get_child("href").or(Some(r))),
+                    )
+                    .unwrap_or_default(),
+                    request_status: text(response.get_child("request-status")).unwrap_or_default(),
+                    calendar_data: text(response.get_child("calendar-data")),
+                }
+            })
+            .collect()
+    }
+}
+
+/// POST the given scheduling message (a VCALENDAR with METHOD, e.g. a VFREEBUSY request)
+/// to the scheduling outbox and return the answers of the recipients.
+pub async fn post_to_outbox(
+    client: &Client,
+    credentials: &Credentials,
+    outbox_url: &Url,
+    data: String,
+) -> Result<Vec<ScheduleResponse>, MiniCaldavError> {
+    let content = client
+        .post(outbox_url.as_str())
+        .header(CONTENT_TYPE, "text/calendar; charset=utf-8")
+        .header(ACCEPT, "text/xml")
+        .authorization(credentials)
+        .body(data)
+        .send()
+        .await?
+        .check_status()
+        .await?
+        .text()
+        .await?;
+    trace!("CalDAV schedule response: {:?}", content);
+    let root = xmltree::Element::parse(content.as_bytes())?;
+    Ok(ScheduleResponse::from_xml(&root))
+}
+
+pub static CALENDAR_AVAILABILITY_REQUEST: &str = r#"
+    <d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+       <d:prop>
+           <c:calendar-availability />
+       </d:prop>
+    </d:propfind>
+"#;
+
+/// Get the `calendar-availability` property (RFC 7953) of the given scheduling inbox.
+/// Returns the VCALENDAR data or `None` if the property is not set.
+pub async fn get_calendar_availability(
+    client: &Client,
+    credentials: &Credentials,
+    inbox_url: &Url,
+) -> Result<Option<String>, MiniCaldavError> {
+    let availability = propfind_get(
+        client,
+        credentials,
+        inbox_url,
+        CALENDAR_AVAILABILITY_REQUEST.to_string(),
+        &["response", "propstat", "prop", "calendar-availability"],
+        "0",
+    )
+    .await?
+    .0;
+    if availability.trim().is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(availability))
+    }
+}
+
+/// Set the `calendar-availability` property (RFC 7953) of the given scheduling inbox
+/// to the given VCALENDAR data.
+pub async fn set_calendar_availability(
+    client: &Client,
+    credentials: &Credentials,
+    inbox_url: &Url,
+    data: &str,
+) -> Result<(), MiniCaldavError> {
+    proppatch(
+        client,
+        credentials,
+        inbox_url,
+        build_calendar_availability_xml(data),
+    )
+    .await
+}
+
+pub static CALENDAR_DEFAULTS_REQUEST: &str = r#"
+    <d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+       <d:prop>
+           <c:default-alarm-vevent-datetime />
+           <c:schedule-calendar-transp />
+       </d:prop>
+    </d:propfind>
+"#;
+
+/// Calendar-wide settings for events, applied to new events by `EventBuilder::defaults`.
+/// The settings with a standard CalDAV property are stored on the server, the others have to be
+/// stored by the app and are `None` when read from the server.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CalendarDefaults {
+    /// Trigger of the default alarm of timed events relative to the start as ical duration,
+    /// e.g. `-PT15M` (`default-alarm-vevent-datetime`, RFC 9074).
+    pub alarm_offset: Option<String>,
+    /// Whether the events of the calendar count as busy time in free-busy lookups, `OPAQUE` or
+    /// `TRANSPARENT` (`schedule-calendar-transp`, RFC 6638).
+    pub schedule_transparency: Option<String>,
+    /// Default duration of new timed events as ical duration, e.g. `PT1H`. Not stored on the server.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub duration: Option<String>,
+    /// Default TRANSP of new events, `OPAQUE` or `TRANSPARENT`. Not stored on the server.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub transparency: Option<String>,
+}
+
+impl CalendarDefaults {
+    /// Parse the properties of the given PROPFIND response.
+    pub fn from_xml(root: &xmltree::Element) -> Self {
+        let mut defaults = Self::default();
+        let props = root
+            .get_child("response")
+            .into_iter()
+            .flat_map(|r| r.children.iter())
+            .filter_map(|c| c.as_element())
+            .filter(|c| c.name == "propstat")
+            .filter_map(|p| p.get_child("prop"))
+            .flat_map(|p| p.children.iter())
+            .filter_map(|c| c.as_element());
+        for prop in props {
+            match prop.name.as_str() {
+                "default-alarm-vevent-datetime" => {
+                    defaults.alarm_offset = prop.get_text().and_then(|t| {
+                        t.lines()
+                            .filter_map(|l| crate::ical::Property::parse(l.trim()).ok())
+                            .find(|p| p.name == "TRIGGER")
+                            .map(|p| p.value)
+                    })
+                }
+                "schedule-calendar-transp" => {
+                    defaults.schedule_transparency = prop
+                        .children
+                        .iter()
+                        .filter_map(|c| c.as_element())
+                        .next()
+                        .map(|c| c.name.to_uppercase())
+                }
+                _ => {}
+            }
+        }
+        defaults
+    }
+}
+
+/// Get the calendar-wide event settings of the given calendar.
+/// Properties the server does not support or that are not set are `None`.
+pub async fn get_calendar_defaults(
+    client: &Client,
+    credentials: &Credentials,
+    calendar_url: &Url,
+) -> Result<CalendarDefaults, MiniCaldavError> {
+    let root = propfind_get(
+        client,
+        credentials,
+        calendar_url,
+        CALENDAR_DEFAULTS_REQUEST.to_string(),
+        &[],
+        "0",
+    )
+    .await?
+    .1;
+    Ok(CalendarDefaults::from_xml(&root))
+}
+
+/// Store the given settings in the properties of the given calendar.
+/// Only `Some` values are written, the other properties are left unchanged.
+/// `duration` and `transparency` have no standard property and are not written.
+pub async fn set_calendar_defaults(
+    client: &Client,
+    credentials: &Credentials,
+    calendar_url: &Url,
+    defaults: &CalendarDefaults,
+) -> Result<(), MiniCaldavError> {
+    if defaults.alarm_offset.is_none() && defaults.schedule_transparency.is_none() {
+        return Ok(());
+    }
+    proppatch(
+        client,
+        credentials,
+        calendar_url,
+        build_calendar_defaults_xml(
+            defaults.alarm_offset.as_deref(),
+            defaults.schedule_transparency.as_deref(),
+        ),
+    )
+    .await
+}
+
+/// Show or hide the given calendar in clients by setting `oc:calendar-enabled`.
+/// The calendar and its events stay on the server either way.
+pub async fn set_calendar_enabled(
+    client: &Client,
+    credentials: &Credentials,
+    calendar_url: &Url,
+    enabled: bool,
+) -> Result<(), MiniCaldavError> {
+    proppatch(
+        client,
+        credentials,
+        calendar_url,
+        build_calendar_enabled_xml(enabled),
+    )
+    .await
+}
+
+/// Set the `calendar-color` of the given calendar to the given value, e.g. `#3a87ad`.
+pub async fn set_calendar_color(
+    client: &Client,
+    credentials: &Credentials,
+    calendar_url: &Url,
+    color: &str,
+) -> Result<(), MiniCaldavError> {
+    proppatch(
+        client,
+        credentials,
+        calendar_url,
+        build_calendar_color_xml(color),
+    )
+    .await
+}
+
+/// Set the position of the given calendar in calendar lists.
+pub async fn set_calendar_order(
+    client: &Client,
+    credentials: &Credentials,
+    calendar_url: &Url,
+    order: i32,
+) -> Result<(), MiniCaldavError> {
+    proppatch(
+        client,
+        credentials,
+        calendar_url,
+        build_calendar_order_xml(order),
+    )
+    .await
+}
+
+/// The name of a DAV property, e.g. an app-specific property stored on an event resource.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DavPropertyName {
+    /// The XML namespace, e.g. `http://example.com/ns/`.
+    pub namespace: String,
+    pub name: String,
+}
+
+impl DavPropertyName {
+    pub fn new(namespace: &str, name: &str) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+        }
+    }
+}
+
+/// Set custom DAV properties on the resource at the given url with a single PROPPATCH.
+/// Properties with value `None` are removed. Servers apply all or none of the changes,
+/// an error is returned if any property could not be set.
+pub async fn set_object_properties(
+    client: &Client,
+    credentials: &Credentials,
+    url: &Url,
+    properties: &[(DavPropertyName, Option<String>)],
+) -> Result<(), MiniCaldavError> {
+    if properties.is_empty() {
+        return Ok(());
+    }
+    let body = build_object_properties_xml(
+        &properties
+            .iter()
+            .map(|(p, value)| (p.namespace.as_str(), p.name.as_str(), value.as_deref()))
+            .collect::<Vec<_>>(),
+    );
+    proppatch(client, credentials, url, body).await
+}
+
+/// Get the given DAV properties of the resource at the given url.
+/// Properties that are not set are missing in the result.
+pub async fn get_object_properties(
+    client: &Client,
+    credentials: &Credentials,
+    url: &Url,
+    names: &[DavPropertyName],
+) -> Result<HashMap<DavPropertyName, String>, MiniCaldavError> {
+    if names.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let body = build_object_properties_request_xml(
+        &names
+            .iter()
+            .map(|p| (p.namespace.as_str(), p.name.as_str()))
+            .collect::<Vec<_>>(),
+    );
+    let root = propfind_get(client, credentials, url, body, &[], "0")
+        .await?
+        .1;
+    Ok(parse_object_properties(&root).0)
+}
+
+/// The properties with a successful status and the names of the properties with a failed status
+/// in the given multistatus document.
+fn parse_object_properties(
+    root: &xmltree::Element,
+) -> (HashMap<DavPropertyName, String>, Vec<DavPropertyName>) {
+    let mut found = HashMap::new();
+    let mut failed = Vec::new();
+    fn children(e: &xmltree::Element) -> impl Iterator<Item = &xmltree::Element> {
+        e.children.iter().filter_map(|c| c.as_element())
+    }
+    for response in children(root).filter(|e| e.name == "response") {
+        for propstat in children(response).filter(|e| e.name == "propstat") {
+            let success = propstat
+                .get_child("status")
+                .and_then(|s| s.get_text())
+                .and_then(|s| parse_status_code(&s))
+                .map(|s| (200..300).contains(&s))
+                .unwrap_or(true);
+            let Some(prop) = propstat.get_child("prop") else {
+                continue;
+            };
+            for property in children(prop) {
+                let name = DavPropertyName {
+                    namespace: property.namespace.clone().unwrap_or_default(),
+                    name: property.name.clone(),
+                };
+                if success {
+                    let value = property
+                        .get_text()
+                        .map(|t| t.trim().to_string())
+                        .unwrap_or_default();
+                    found.insert(name, value);
+                } else {
+                    failed.push(name);
+                }
+            }
+        }
+    }
+    (found, failed)
+}
+
+/// Send a PROPPATCH request. Servers apply all or none of the changes, an error is returned
+/// if the Multi-Status response reports a failed status for any property.
+async fn proppatch(
+    client: &Client,
+    credentials: &Credentials,
+    url: &Url,
+    body: String,
+) -> Result<(), MiniCaldavError> {
+    let proppatch = Method::from_bytes(b"PROPPATCH").unwrap();
+
+    let response = client
+        .request(proppatch, url.as_str())
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .authorization(credentials)
+        .body(body)
+        .send()
+        .await?
+        .check_status()
+        .await?;
+    if response.status() != reqwest::StatusCode::MULTI_STATUS {
+        return Ok(());
+    }
+    let text = response.text().await?;
+    trace!("CalDAV proppatch response: {:?}", text);
+    let root = xmltree::Element::parse(text.as_bytes())?;
+    let failed = parse_object_properties(&root).1;
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(RequestFailed(format!(
+            "Could not set properties of {}: {}",
+            url,
+            failed
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )))
+    }
+}
+
+/// Whether the sharee replied to a share invite.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InviteStatus {
+    NoResponse,
+    Accepted,
+    Declined,
+}
+
+/// An invitation to a calendar another user shared with the current user.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareInvite {
+    /// The url of the notification resource.
+    pub url: Url,
+    pub uid: String,
+    /// The calendar address of the invited user.
+    pub sharee: String,
+    /// The url of the shared calendar.
+    pub host_url: Url,
+    /// The calendar address of the user who shared the calendar.
+    pub organizer: Option<String>,
+    pub summary: Option<String>,
+    pub read_write: bool,
+    pub status: InviteStatus,
+}
+
+/// Parse a `CS:notification` XML document into a `ShareInvite`.
+/// Returns `None` if the notification is no invite notification.
+pub fn parse_invite_notification(
+    client: &Client,
+    url: &Url,
+    root: &xmltree::Element,
+) -> Option<ShareInvite> {
+    let invite = root.get_child("invite-notification")?;
+    let text =
+        |e: Option<&xmltree::Element>| e.and_then(|e| e.get_text()).map(|t| t.trim().to_string());
+    let status = if invite.get_child("invite-accepted").is_some() {
+        InviteStatus::Accepted
+    } else if invite.get_child("invite-declined").is_some() {
+        InviteStatus::Declined
+    } else {
+        InviteStatus::NoResponse
+    };
+    Some(ShareInvite {
+        url: url.clone(),
+        uid: text(invite.get_child("uid"))?,
+        sharee: text(invite.get_child("href"))?,
+        host_url: client
+            .resolve_href(
+                url,
+                &text(
+                    invite
+                        .get_child("hosturl")
+                        .and_then(|e| e.get_child("href")),
+                )?,
+            )
+            .ok()?,
+        organizer: text(
+            invite
+                .get_child("organizer")
+                .and_then(|e| e.get_child("href")),
+        ),
+        summary: text(invite.get_child("summary")),
+        read_write: invite
+            .get_child("access")
+            .map(|e| e.get_child("read-write").is_some())
+            .unwrap_or(false),
+        status,
+    })
+}
+
+/// Get all calendar share invites from the notification collection of the current user.
+pub async fn get_share_invites(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: Url,
+) -> Result<Vec<ShareInvite>, MiniCaldavError> {
+    let principal_url = discover_principal_url(client, credentials, &base_url).await?;
+    let notification_url = get_notification_url(client, credentials, principal_url).await?;
 
-    trace!("Read CalDAV events: {:?}", content);
-    let reader = content.as_bytes();
+    let root = propfind_get(
+        client,
+        credentials,
+        &notification_url,
+        r#"<d:propfind xmlns:d="DAV:"><d:prop><d:getetag /></d:prop></d:propfind>"#.to_string(),
+        &[],
+        "1",
+    )
+    .await?
+    .1;
 
-    let root = xmltree::Element::parse(reader)?;
-    let mut todos = Vec::new();
-    for c in &root.children {
-        if let Some(child) = c.as_element() {
-            let href = child.get_child("href").and_then(|e| e.get_text());
-            let etag = child
-                .get_child("propstat")
-                .and_then(|e| e.get_child("prop"))
-                .and_then(|e| e.get_child("getetag"))
-                .and_then(|e| e.get_text())
-                .map(|e| e.to_string());
-            let data = child
-                .get_child("propstat")
-                .and_then(|e| e.get_child("prop"))
-                .and_then(|e| e.get_child("calendar-data"))
-                .and_then(|e| e.get_text());
-            if href.is_none() || etag.is_none() || data.is_none() {
+    let mut invites = Vec::new();
+    for response in root.children.iter().filter_map(|c| c.as_element()) {
+        let url = match response
+            .get_child("href")
+            .and_then(|e| e.get_text())
+            .and_then(|href| client.resolve_href(&notification_url, &href).ok())
+        {
+            Some(url) if url != notification_url => url,
+            _ => continue,
+        };
+        // A notification can be removed in the meantime, the others are still returned.
+        let text = async {
+            client
+                .get(url.as_str())
+                .authorization(credentials)
+                .send()
+                .await?
+                .check_status()
+                .await?
+                .text()
+                .await
+                .map_err(MiniCaldavError::from)
+        };
+        let text = match text.await {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("Could not get notification {}: {}", url, e);
                 continue;
             }
-
-            if let Some((href, data)) = href.and_then(|href| data.map(|data| (href, data))) {
-                if let Ok(url) = base_url.join(&href) {
-                    todos.push(EventRef {
-                        url,
-                        data: data.to_string(),
-                        etag,
-                    })
-                } else {
-                    error!("Could not parse url {}/{}", base_url, href)
+        };
+        match xmltree::Element::parse(text.as_bytes()) {
+            Ok(notification) => {
+                if let Some(invite) = parse_invite_notification(client, &url, &notification) {
+                    invites.push(invite);
                 }
             }
+            Err(e) => warn!("Could not parse notification {}: {}", url, e),
         }
     }
-
-    Ok(todos)
+    Ok(invites)
 }
 
-/// Save the given event on the CalDAV server.
-/// If no event for the events url exist it will create a new event.
-/// Otherwise this is an update operation.
-pub async fn save_event(
+/// Accept or decline the given share invite.
+pub async fn reply_to_share_invite(
     client: &Client,
     credentials: &Credentials,
-    event_ref: EventRef,
-) -> Result<EventRef, MiniCaldavError> {
-    let auth = get_auth_header(credentials);
+    base_url: Url,
+    invite: &ShareInvite,
+    accept: bool,
+) -> Result<(), MiniCaldavError> {
+    let homeset_url = discover_home_set_url(client, credentials, &base_url).await?;
 
-    let EventRef { data, url, .. } = event_ref.clone();
+    post_xml(
+        client,
+        credentials,
+        &homeset_url,
+        build_invite_reply_xml(&invite.sharee, invite.host_url.path(), &invite.uid, accept),
+    )
+    .await
+}
 
-    let content_length = data.len();
+pub static PUSH_PROPERTIES_REQUEST: &str = r#"
+<d:propfind xmlns:d="DAV:" xmlns:p="https://bitfire.at/webdav-push">
+    <d:prop>
+        <p:transports />
+        <p:topic />
+    </d:prop>
+</d:propfind>
+"#;
 
-    let response = client
-        .put(url)
-        .header(USER_AGENT, "rust-minicaldav")
-        .header(CONTENT_TYPE, "text/calendar")
-        .header(CONTENT_LENGTH, content_length.to_string())
-        .header(AUTHORIZATION, &auth)
-        .body(data)
-        .send()
-        .await?;
+/// WebDAV-Push support of a collection as advertised by the server.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PushSupport {
+    /// Names of the advertised push transports, e.g. `web-push`.
+    pub transports: Vec<String>,
+    /// The push topic of the collection. Push messages carry this topic.
+    pub topic: Option<String>,
+}
 
-    let etag = response
-        .headers()
-        .get("ETag")
-        .map(|etag| etag.to_str().unwrap().to_string());
+impl PushSupport {
+    /// Whether the server supports push via the given transport.
+    pub fn supports(&self, transport: &str) -> bool {
+        self.transports.iter().any(|t| t == transport)
+    }
+}
 
-    let event_ref = EventRef { etag, ..event_ref };
+/// A Web Push subscription of the client that should be registered at the server.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebPushSubscription {
+    /// The push resource url from the push service.
+    pub push_resource: String,
+    /// The `p256dh` public key of the subscription (base64url encoded).
+    pub public_key: Option<String>,
+    /// The authentication secret of the subscription (base64url encoded).
+    pub auth_secret: Option<String>,
+}
 
-    Ok(event_ref)
+/// A push subscription registered at the server.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushRegistration {
+    /// The url of the registration. Send a DELETE to this url to unregister.
+    pub url: Url,
+    /// The expiration of the registration as sent by the server (HTTP date).
+    pub expires: Option<String>,
 }
 
-/// Delete the given event from the CalDAV server.
-pub async fn remove_event(
+/// Discover the WebDAV-Push transports and topic of the given collection.
+pub async fn get_push_support(
     client: &Client,
     credentials: &Credentials,
-    event_ref: EventRef,
-) -> Result<(), MiniCaldavError> {
-    let auth = get_auth_header(credentials);
+    url: &Url,
+) -> Result<PushSupport, MiniCaldavError> {
+    let root = propfind_get(
+        client,
+        credentials,
+        url,
+        PUSH_PROPERTIES_REQUEST.to_string(),
+        &[],
+        "0",
+    )
+    .await?
+    .1;
+
+    let prop = root
+        .get_child("response")
+        .and_then(|e| e.get_child("propstat"))
+        .and_then(|e| e.get_child("prop"));
+
+    let transports = prop
+        .and_then(|e| e.get_child("transports"))
+        .map(|e| {
+            e.children
+                .iter()
+                .filter_map(|c| c.as_element())
+                .map(|c| c.name.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    let topic = prop
+        .and_then(|e| e.get_child("topic"))
+        .and_then(|e| e.get_text())
+        .map(|t| t.trim().to_string());
+
+    Ok(PushSupport { transports, topic })
+}
+
+fn build_push_register_xml(subscription: &WebPushSubscription, expires: Option<&str>) -> String {
+    let mut inner = format!(
+        "<push-resource>{}</push-resource>",
+        escape_xml(&subscription.push_resource)
+    );
+    if let Some(key) = &subscription.public_key {
+        inner.push_str(&format!(
+            r#"<content-encoding>aes128gcm</content-encoding><subscription-public-key type="p256dh">{}</subscription-public-key>"#,
+            escape_xml(key)
+        ));
+    }
+    if let Some(secret) = &subscription.auth_secret {
+        inner.push_str(&format!(
+            "<auth-secret>{}</auth-secret>",
+            escape_xml(secret)
+        ));
+    }
+    let expires = expires
+        .map(|e| format!("<expires>{}</expires>", escape_xml(e)))
+        .unwrap_or_default();
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<push-register xmlns="https://bitfire.at/webdav-push">
+    <subscription>
+        <web-push-subscription>{}</web-push-subscription>
+    </subscription>
+    {}
+</push-register>
+"#,
+        inner, expires
+    )
+}
 
+/// Register a Web Push subscription for the given collection.
+/// `expires` is the requested expiration as HTTP date. The server may choose a different one.
+pub async fn register_push_subscription(
+    client: &Client,
+    credentials: &Credentials,
+    url: &Url,
+    subscription: &WebPushSubscription,
+    expires: Option<&str>,
+) -> Result<PushRegistration, MiniCaldavError> {
     let response = client
-        .delete(event_ref.url.as_str())
-        .header(USER_AGENT, "rust-minicaldav")
-        .header(AUTHORIZATION, &auth)
+        .post(url.as_str())
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .authorization(credentials)
+        .body(build_push_register_xml(subscription, expires))
         .send()
+        .await?
+        .check_status()
         .await?;
 
-    response.error_for_status()?;
+    let location = response
+        .headers()
+        .get("Location")
+        .and_then(|l| l.to_str().ok())
+        .ok_or_else(|| RequestFailed("Push registration without Location header".into()))?;
+    let expires = response
+        .headers()
+        .get("Expires")
+        .and_then(|e| e.to_str().ok())
+        .map(|e| e.to_string());
 
-    Ok(())
+    Ok(PushRegistration {
+        url: url.join(location)?,
+        expires,
+    })
 }
 
-/// Send a MKCOL request to create a new calendar collection
-pub async fn create_calendar(
+/// Remove the given push registration from the server.
+pub async fn unregister_push_subscription(
     client: &Client,
     credentials: &Credentials,
-    base_url: &Url,
-    calid: String,
-    name: String,
-    color: String,
+    registration: &PushRegistration,
 ) -> Result<(), MiniCaldavError> {
-    let auth = get_auth_header(credentials);
+    client
+        .delete(registration.url.as_str())
+        .authorization(credentials)
+        .send()
+        .await?
+        .check_status()
+        .await?;
 
-    let principal_url = get_principal_url(client, credentials, base_url.clone())
-        .await
-        .unwrap_or_else(|_| base_url.clone());
+    Ok(())
+}
 
-    let homeset_url = get_home_set_url(client, credentials, principal_url)
-        .await
-        .unwrap_or_else(|_| base_url.clone());
+/// Capabilities of a DAV server as announced in the response to an OPTIONS request.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerCapabilities {
+    /// All compliance classes of the `DAV` header, e.g. `1`, `access-control`, `calendar-access`.
+    pub dav: Vec<String>,
+    /// All methods of the `Allow` header in upper case.
+    pub allow: Vec<String>,
+}
 
-    let new_cal_url = homeset_url.join(&calid)?;
+impl ServerCapabilities {
+    /// Parse the values of the `DAV` and `Allow` headers.
+    /// Each of them may be given multiple times by the server.
+    pub fn from_headers<'a, D, A>(dav: D, allow: A) -> Self
+    where
+        D: IntoIterator<Item = &'a str>,
+        A: IntoIterator<Item = &'a str>,
+    {
+        let split = |value: &str| -> Vec<String> {
+            value
+                .split(',')
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string())
+                .collect()
+        };
+        Self {
+            dav: dav.into_iter().flat_map(split).collect(),
+            allow: allow
+                .into_iter()
+                .flat_map(split)
+                .map(|m| m.to_uppercase())
+                .collect(),
+        }
+    }
 
-    let mkcol = Method::from_bytes(b"MKCOL").unwrap();
+    /// Whether the `DAV` header contains the given compliance class.
+    pub fn has(&self, class: &str) -> bool {
+        self.dav.iter().any(|c| c.eq_ignore_ascii_case(class))
+    }
+
+    /// Whether the given method is allowed on the resource.
+    pub fn allows(&self, method: &str) -> bool {
+        self.allow.iter().any(|m| m.eq_ignore_ascii_case(method))
+    }
+
+    /// CalDAV (RFC 4791)
+    pub fn calendar_access(&self) -> bool {
+        self.has("calendar-access")
+    }
+
+    /// CalDAV scheduling (RFC 6638)
+    pub fn calendar_schedule(&self) -> bool {
+        self.has("calendar-schedule") || self.has("calendar-auto-schedule")
+    }
+
+    /// Server side scheduling (RFC 6638)
+    pub fn calendar_auto_schedule(&self) -> bool {
+        self.has("calendar-auto-schedule")
+    }
+
+    /// WebDAV collection synchronization (RFC 6578)
+    /// Not every server announces this in the `DAV` header even if it supports it.
+    pub fn sync_collection(&self) -> bool {
+        self.has("sync-collection")
+    }
+
+    /// Extended MKCOL (RFC 5689)
+    pub fn extended_mkcol(&self) -> bool {
+        self.has("extended-mkcol")
+    }
+
+    /// WebDAV access control (RFC 3744)
+    pub fn access_control(&self) -> bool {
+        self.has("access-control")
+    }
+
+    /// Calendar sharing of calendarserver.org
+    pub fn calendarserver_sharing(&self) -> bool {
+        self.has("calendarserver-sharing")
+    }
+
+    /// Managed attachments (RFC 8607)
+    pub fn calendar_managed_attachments(&self) -> bool {
+        self.has("calendar-managed-attachments")
+    }
+
+    /// Time zones by reference (RFC 7809): VTIMEZONE components can be omitted on upload.
+    pub fn calendar_no_timezone(&self) -> bool {
+        self.has("calendar-no-timezone")
+    }
 
-    let body = build_create_calendar_xml(name, color);
+    /// Whether MKCALENDAR can be used to create calendars.
+    pub fn mkcalendar(&self) -> bool {
+        self.allows("MKCALENDAR")
+    }
+}
 
+/// Send an OPTIONS request to the given url and parse the announced server capabilities.
+pub async fn get_capabilities(
+    client: &Client,
+    credentials: &Credentials,
+    url: &Url,
+) -> Result<ServerCapabilities, MiniCaldavError> {
     let response = client
-        .request(mkcol, new_cal_url)
-        .header(USER_AGENT, "rust-minicaldav")
-        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
-        .header(ACCEPT, "text/xml, text/calendar")
-        .header(AUTHORIZATION, auth)
-        .body(body)
+        .request(Method::OPTIONS, url.as_str())
+        .authorization(credentials)
         .send()
+        .await?
+        .check_status()
         .await?;
 
-    response.error_for_status()?;
+    let headers = response.headers();
+    let values = |name: &str| -> Vec<&str> {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .collect()
+    };
 
-    Ok(())
+    Ok(ServerCapabilities::from_headers(
+        values("DAV"),
+        values("Allow"),
+    ))
 }
 
-pub async fn remove_calendar(
-    client: &Client,
-    credentials: &Credentials,
-    base_url: &Url,
-    calid: String,
-) -> Result<(), MiniCaldavError> {
-    let auth = get_auth_header(credentials);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_windows() {
+        for value in ["19700101T000000Z", "20000229T123456Z", "21000105T000000Z"] {
+            assert_eq!(format_utc_seconds(parse_utc_seconds(value).unwrap()), value);
+        }
+        assert_eq!(parse_utc_seconds("19700102T000001Z"), Some(86401));
+        assert_eq!(format_utc_seconds(1704103200), "20240101T100000Z");
+        assert_eq!(parse_utc_seconds("20240101"), None);
+
+        let url = Url::parse("https://example.com/cal/a.ics").unwrap();
+        let event_ref = |data: &str| EventRef {
+            etag: None,
+            url: url.clone(),
+            data: data.to_string(),
+            schedule_tag: None,
+        };
+        let instance = |id: &str| {
+            format!(
+                "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nRECURRENCE-ID:{}\nEND:VEVENT\nEND:VCALENDAR\n",
+                id
+            )
+        };
+        let mut event_refs = vec![event_ref(&instance("20240101T100000Z"))];
+        merge_event_refs(
+            &mut event_refs,
+            vec![
+                event_ref(&instance("20240101T100000Z")),
+                event_ref(&instance("20240301T100000Z")),
+            ],
+        );
+        assert_eq!(event_refs.len(), 1);
+        assert_eq!(event_refs[0].data.matches("BEGIN:VEVENT").count(), 2);
+
+        let broken = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nRECURRENCE-ID;20240401\n";
+        merge_event_refs(&mut event_refs, vec![event_ref(broken)]);
+        assert_eq!(event_refs.len(), 2);
+        assert_eq!(event_refs[0].data.matches("BEGIN:VEVENT").count(), 2);
+        assert_eq!(event_refs[1].data, broken);
+    }
+
+    #[test]
+    fn test_write_outcome() {
+        let url = Url::parse("https://example.com/cal/event.ics").unwrap();
+        let stored = |etag: &str| {
+            Ok(EventRef {
+                etag: Some(etag.into()),
+                url: url.clone(),
+                data: String::new(),
+                schedule_tag: None,
+            })
+        };
+        let error = |status| {
+            Err(ResourceError {
+                url: url.clone(),
+                status: Some(status),
+                description: None,
+            })
+        };
+        let put = |etag: Option<&str>| ExpectedWrite::Put {
+            previous_etag: etag.map(|e| e.to_string()),
+        };
+
+        assert!(matches!(
+            write_outcome(&put(None), stored("\"1\"")),
+            Ok(WriteOutcome::Stored(e)) if e.etag.as_deref() == Some("\"1\"")
+        ));
+        assert!(matches!(
+            write_outcome(&put(Some("\"1\"")), stored("\"2\"")),
+            Ok(WriteOutcome::Stored(_))
+        ));
+        assert!(matches!(
+            write_outcome(&put(Some("\"1\"")), stored("\"1\"")),
+            Ok(WriteOutcome::NotApplied)
+        ));
+        assert!(matches!(
+            write_outcome(&put(None), error(404)),
+            Ok(WriteOutcome::NotApplied)
+        ));
+        assert!(matches!(
+            write_outcome(&ExpectedWrite::Delete, error(410)),
+            Ok(WriteOutcome::Deleted)
+        ));
+        assert!(matches!(
+            write_outcome(&ExpectedWrite::Delete, stored("\"1\"")),
+            Ok(WriteOutcome::NotApplied)
+        ));
+        assert!(matches!(
+            write_outcome(&ExpectedWrite::Delete, error(403)),
+            Err(Resource(e)) if e.is_forbidden()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_discover_home_set_url() {
+        use crate::test_server::{Response, TestServer};
+
+        let principal = Response::multistatus(
+            r#"<d:multistatus xmlns:d="DAV:"><d:response><d:href>/</d:href><d:propstat>
+                <d:prop><d:current-user-principal><d:href>/principals/foo/</d:href></d:current-user-principal></d:prop>
+                <d:status>HTTP/1.1 200 OK</d:status>
+            </d:propstat></d:response></d:multistatus>"#,
+        );
+        let server = TestServer::start(vec![
+            // No principal and no home set: fall back to the base url.
+            Response::new(404),
+            Response::multistatus(r#"<d:multistatus xmlns:d="DAV:" />"#),
+            // Authentication errors are returned.
+            Response::new(401),
+            principal.clone(),
+            Response::new(403),
+            // The home set is normalized to a trailing slash, its query is kept.
+            principal,
+            Response::multistatus(
+                r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav"><d:response>
+                <d:href>/principals/foo/</d:href>
+                <d:propstat><d:prop><c:calendar-home-set><d:href>/dav/calendars/foo?x=1</d:href></c:calendar-home-set></d:prop></d:propstat>
+            </d:response></d:multistatus>"#,
+            ),
+        ]);
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let base_url = server.join("/dav");
+        let discover = || discover_home_set_url(&client, &credentials, &base_url);
+        assert_eq!(discover().await.unwrap(), server.join("/dav/"));
+        let error = discover().await.unwrap_err();
+        assert!(matches!(error, Unauthorized(_)), "{:?}", error);
+        let error = discover().await.unwrap_err();
+        assert!(matches!(error, Forbidden(_)), "{:?}", error);
+        assert_eq!(
+            discover().await.unwrap(),
+            server.join("/dav/calendars/foo/?x=1")
+        );
+        let requests = server.requests();
+        assert_eq!(requests[1].url, "/dav");
+        assert_eq!(requests[4].url, "/principals/foo/");
+    }
+
+    #[tokio::test]
+    async fn test_progress() {
+        use crate::test_server::{Response, TestServer};
+
+        let body = |names: &[&str]| {
+            let responses = names
+                .iter()
+                .map(|name| {
+                    format!(
+                        r#"<d:response><d:href>/cal/{0}.ics</d:href><d:propstat><d:prop>
+                        <d:getetag>"1"</d:getetag>
+                        <c:calendar-data>BEGIN:VCALENDAR&#13;
+BEGIN:VEVENT&#13;
+UID:{0}&#13;
+END:VEVENT&#13;
+END:VCALENDAR</c:calendar-data>
+                        </d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>"#,
+                        name
+                    )
+                })
+                .collect::<String>();
+            format!(
+                r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">{}</d:multistatus>"#,
+                responses
+            )
+        };
+        let chunks = [body(&["a", "b"]), body(&["c", "d"]), body(&["e"])];
+        let query = body(&["a", "b", "c"]);
+        let server = TestServer::start(
+            chunks
+                .iter()
+                .chain([&query])
+                .map(|body| Response::multistatus(body))
+                .collect(),
+        );
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let calendar_url = server.join("/cal/");
+        let urls = ["a", "b", "c", "d", "e"]
+            .map(|name| calendar_url.join(&format!("{}.ics", name)).unwrap());
 
-    let principal_url = get_principal_url(client, credentials, base_url.clone())
+        // One request at a time, so the chunks are fetched in order.
+        let mut reported = Vec::new();
+        let items = calendar_multiget_parallel_with_progress(
+            &client,
+            &credentials,
+            server.url(),
+            &calendar_url,
+            &urls,
+            2,
+            1,
+            |progress| reported.push(progress),
+        )
         .await
-        .unwrap_or_else(|_| base_url.clone());
+        .unwrap();
+        assert_eq!(items.len(), 5);
+        let bytes = |n: usize| chunks[..n].iter().map(|c| c.len() as u64).sum::<u64>();
+        assert_eq!(
+            reported,
+            [
+                Progress {
+                    fetched: 2,
+                    total: Some(5),
+                    bytes: bytes(1)
+                },
+                Progress {
+                    fetched: 4,
+                    total: Some(5),
+                    bytes: bytes(2)
+                },
+                Progress {
+                    fetched: 5,
+                    total: Some(5),
+                    bytes: bytes(3)
+                },
+            ]
+        );
 
-    let homeset_url = get_home_set_url(client, credentials, principal_url)
+        // The download reports the bytes without total, the end the number of events.
+        let mut reported = Vec::new();
+        let (event_refs, _) = get_events_with_progress(
+            &client,
+            &credentials,
+            server.url().clone(),
+            calendar_url.clone(),
+            None,
+            None,
+            false,
+            |progress| reported.push(progress),
+        )
         .await
-        .unwrap_or_else(|_| base_url.clone());
+        .unwrap();
+        assert_eq!(event_refs.len(), 3);
+        let (last, downloading) = reported.split_last().unwrap();
+        assert!(!downloading.is_empty());
+        assert!(downloading
+            .iter()
+            .all(|p| p.fetched == 0 && p.total.is_none()));
+        assert!(downloading.windows(2).all(|w| w[0].bytes < w[1].bytes));
+        assert_eq!(downloading.last().unwrap().bytes, query.len() as u64);
+        assert_eq!(
+            *last,
+            Progress {
+                fetched: 3,
+                total: Some(3),
+                bytes: query.len() as u64
+            }
+        );
+    }
 
-    let cal_url = homeset_url.join(&calid)?;
+    #[tokio::test]
+    async fn test_save_event_schedule_tag() {
+        use crate::test_server::{Response, TestServer};
 
-    let response = client
-        .delete(cal_url)
-        .header(USER_AGENT, "rust-minicaldav")
-        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
-        .header(AUTHORIZATION, auth)
-        .send()
-        .await?;
+        let server = TestServer::start(vec![
+            Response::new(204)
+                .header("ETag", "\"2\"")
+                .header("Schedule-Tag", "\"s2\""),
+            Response::new(412),
+            Response::new(500),
+        ]);
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let event_ref = EventRef {
+            url: server.join("/cal/a.ics"),
+            etag: Some("\"1\"".into()),
+            data: "BEGIN:VCALENDAR\nEND:VCALENDAR\n".into(),
+            schedule_tag: Some("\"s1\"".into()),
+        };
+        let save = || save_event(&client, &credentials, event_ref.clone());
 
-    response.error_for_status()?;
+        let saved = save().await.unwrap();
+        assert_eq!(saved.etag.as_deref(), Some("\"2\""));
+        assert_eq!(saved.schedule_tag.as_deref(), Some("\"s2\""));
+        // The organizer changed the event since it was fetched.
+        let error = save().await.unwrap_err();
+        assert!(
+            matches!(&error, PreconditionFailed(url) if url == &event_ref.url),
+            "{:?}",
+            error
+        );
+        assert!(save().await.is_err());
 
-    Ok(())
+        let requests = server.requests();
+        assert_eq!(requests[1].method, "PUT");
+        assert_eq!(requests[1].header("If-Schedule-Tag-Match"), Some("\"s1\""));
+    }
+
+    #[tokio::test]
+    async fn test_report_error_status() {
+        use crate::test_server::{Response, TestServer};
+
+        let server = TestServer::start(vec![
+            Response::new(401),
+            Response::new(404)
+                .header("Content-Type", "text/html")
+                .body("<html><body>Not Found</body></html>"),
+        ]);
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let calendar = server.calendar("/cal/");
+        let calendar_ref = calendar.calendar_ref();
+
+        let error = get_todos(&client, &credentials, server.url(), calendar_ref)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Unauthorized(_)), "{:?}", error);
+        let error = get_ical_events(&client, &credentials, server.join("/cal.ics"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, NotFound(_)), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn test_get_events_in_windows() {
+        use crate::test_server::{Response, TestServer};
+
+        let server = TestServer::start(vec![
+            Response::multistatus(r#"<d:multistatus xmlns:d="DAV:" />"#),
+            Response::new(403).body("<error/>"),
+            Response::new(403).body("<error/>"),
+        ]);
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .max_report_size(10)
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let get = |start: &str, end: &str, expanded| {
+            get_events(
+                &client,
+                &credentials,
+                server.url().clone(),
+                server.join("/cal/"),
+                Some(start.into()),
+                Some(end.into()),
+                expanded,
+            )
+        };
+        // Not expanded: a single query without time range, the response is not limited.
+        let (event_refs, _) = get("20240101T000000Z", "20240103T000000Z", false)
+            .await
+            .unwrap();
+        assert!(event_refs.is_empty());
+        // Error responses are not parsed or split, whether the window can be split or not.
+        for end in ["20240101T120000Z", "20240103T000000Z"] {
+            let error = get("20240101T000000Z", end, true).await.unwrap_err();
+            assert!(matches!(error, Forbidden(_)), "{:?}", error);
+        }
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(!requests[0].body.contains("time-range"));
+        assert!(requests[2].body.contains(r#"end="20240103T000000Z""#));
+    }
+
+    #[test]
+    fn test_push_register_xml() {
+        let subscription = WebPushSubscription {
+            push_resource: "https://push.example.com/?a=1&b=<2>".into(),
+            public_key: Some("key&<".into()),
+            auth_secret: None,
+        };
+        let xml = build_push_register_xml(&subscription, Some("Wed, 20 Dec 2023 10:03:31 GMT"));
+        let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        let push = root
+            .get_child("subscription")
+            .and_then(|s| s.get_child("web-push-subscription"))
+            .unwrap();
+        let text = |name: &str| push.get_child(name).and_then(|c| c.get_text()).unwrap();
+        assert_eq!(text("push-resource"), "https://push.example.com/?a=1&b=<2>");
+        assert_eq!(text("subscription-public-key"), "key&<");
+        assert!(push.get_child("auth-secret").is_none());
+    }
+
+    #[test]
+    fn test_object_properties() {
+        let body = build_object_properties_xml(&[
+            ("http://example.com/ns/", "synced", Some("a<b")),
+            ("http://example.com/ns/", "flag", None),
+        ]);
+        assert!(body.contains(
+            "<D:set><D:prop><X0:synced xmlns:X0=\"http://example.com/ns/\">a&lt;b</X0:synced></D:prop></D:set>"
+        ));
+        assert!(body.contains(
+            "<D:remove><D:prop><X1:flag xmlns:X1=\"http://example.com/ns/\"/></D:prop></D:remove>"
+        ));
+
+        let root = xmltree::Element::parse(
+            r#"<d:multistatus xmlns:d="DAV:" xmlns:x="http://example.com/ns/">
+                <d:response>
+                    <d:href>/cal/a.ics</d:href>
+                    <d:propstat>
+                        <d:prop><x:synced> 2024-01-01 </x:synced></d:prop>
+                        <d:status>HTTP/1.1 200 OK</d:status>
+                    </d:propstat>
+                    <d:propstat>
+                        <d:prop><x:flag/></d:prop>
+                        <d:status>HTTP/1.1 404 Not Found</d:status>
+                    </d:propstat>
+                </d:response>
+            </d:multistatus>"#
+                .as_bytes(),
+        )
+        .unwrap();
+        let (found, failed) = parse_object_properties(&root);
+        assert_eq!(
+            found.get(&DavPropertyName::new("http://example.com/ns/", "synced")),
+            Some(&"2024-01-01".to_string())
+        );
+        assert_eq!(
+            failed,
+            [DavPropertyName::new("http://example.com/ns/", "flag")]
+        );
+    }
+
+    #[test]
+    fn test_parse_etags() {
+        let xml = r#"<d:multistatus xmlns:d="DAV:">
+            <d:response>
+                <d:href>/cal/work/</d:href>
+                <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop></d:propstat>
+            </d:response>
+            <d:response>
+                <d:href>/cal/work/a.ics</d:href>
+                <d:propstat><d:prop><d:getetag>"1"</d:getetag><d:resourcetype/></d:prop></d:propstat>
+            </d:response>
+            <d:response>
+                <d:href>/cal/work/nested/</d:href>
+                <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop></d:propstat>
+            </d:response>
+            <d:response>
+                <d:href>http://localhost:5232/cal/work/b.ics</d:href>
+                <d:propstat><d:prop><d:getetag>"2"</d:getetag><d:resourcetype/></d:prop></d:propstat>
+            </d:response>
+        </d:multistatus>"#;
+        let base_url = Url::parse("https://example.com/").unwrap();
+        let calendar_url = base_url.join("/cal/work/").unwrap();
+        let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        let client = Client::builder().force_same_origin(true).build().unwrap();
+        assert_eq!(
+            parse_etags(&client, &base_url, &calendar_url, &root),
+            [
+                ChangedResource {
+                    url: base_url.join("/cal/work/a.ics").unwrap(),
+                    etag: Some("\"1\"".into()),
+                },
+                ChangedResource {
+                    url: base_url.join("/cal/work/b.ics").unwrap(),
+                    etag: Some("\"2\"".into()),
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hrefs_resolved_alike() {
+        let xml = r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+            <d:response>
+                <d:href>http://localhost:5232/cal/work/a.ics</d:href>
+                <d:propstat>
+                    <d:prop><d:getetag>"1"</d:getetag><c:calendar-data>BEGIN:VCALENDAR</c:calendar-data></d:prop>
+                    <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat>
+            </d:response>
+        </d:multistatus>"#;
+        let base_url = Url::parse("https://example.com/").unwrap();
+        let calendar_url = base_url.join("/cal/work/").unwrap();
+        let expected = base_url.join("/cal/work/a.ics").unwrap();
+        let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        let client = Client::builder().force_same_origin(true).build().unwrap();
+
+        assert_eq!(
+            parse_etags(&client, &base_url, &calendar_url, &root)[0].url,
+            expected
+        );
+        assert_eq!(
+            parse_calendar_data_response(&client, &base_url, &root).0[0].url,
+            expected
+        );
+        assert_eq!(
+            SyncCollectionResult::from_xml(&client, &base_url, &calendar_url, &root).changed[0].url,
+            expected
+        );
+        assert_eq!(
+            Multistatus::from_xml(&client, &base_url, &root).responses[0].url,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_collection_url() {
+        let url = |url: &str| collection_url(Url::parse(url).unwrap()).to_string();
+        assert_eq!(url("https://example.com/cal"), "https://example.com/cal/");
+        assert_eq!(url("https://example.com/cal/"), "https://example.com/cal/");
+        assert_eq!(url("https://example.com"), "https://example.com/");
+        assert_eq!(
+            url("https://example.com/cal?x=1"),
+            "https://example.com/cal/?x=1"
+        );
+        assert_eq!(
+            url("https://example.com/cal/?x=1"),
+            "https://example.com/cal/?x=1"
+        );
+        assert_eq!(
+            url("https://example.com/cal#work"),
+            "https://example.com/cal/#work"
+        );
+
+        // Hrefs of a multistatus are resolved against the request url first.
+        let base_url = Url::parse("https://example.com/dav/").unwrap();
+        let client = Client::new();
+        let href = |href: &str| collection_url(client.resolve_href(&base_url, href).unwrap());
+        assert_eq!(href("/dav/cal"), base_url.join("/dav/cal/").unwrap());
+        assert_eq!(href("cal/"), base_url.join("/dav/cal/").unwrap());
+        assert_eq!(
+            href("/dav/cal?x=1"),
+            base_url.join("/dav/cal/?x=1").unwrap()
+        );
+        assert_eq!(
+            href("https://example.com/dav/cal?x=1"),
+            base_url.join("/dav/cal/?x=1").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_deep_discovery() {
+        let deep = CalendarDiscovery::Deep { max_responses: 3 };
+        assert_eq!(deep.attempts(), [deep]);
+
+        let client = Client::new();
+        let url = Url::parse("https://example.com/dav/principals/user/").unwrap();
+        let response = |href: &str| {
+            format!(
+                r#"<d:response>
+                <d:href>{}</d:href>
+                <d:propstat><d:prop>
+                    <d:displayname>Calendar</d:displayname>
+                    <d:resourcetype><d:collection/><c:calendar/></d:resourcetype>
+                    <c:supported-calendar-component-set><c:comp name="VEVENT"/></c:supported-calendar-component-set>
+                </d:prop></d:propstat>
+            </d:response>"#,
+                href
+            )
+        };
+        let xml = format!(
+            r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">{}</d:multistatus>"#,
+            [
+                "/dav/principals/user/calendars/work/",
+                "/dav/principals/user/calendars/work",
+                "/dav/principals/user/a/b/a/b/a/b/a/b/a/",
+                "/dav/principals/user/calendars/home/",
+                "/dav/principals/user/calendars/late/",
+            ]
+            .map(response)
+            .join("")
+        );
+        let mut root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        limit_deep_responses(&client, &url, &mut root, 4);
+        let calendars = parse_calendars(&client, &url, &root);
+        assert_eq!(
+            calendars.iter().map(|c| c.url.path()).collect::<Vec<_>>(),
+            [
+                "/dav/principals/user/calendars/work/",
+                "/dav/principals/user/calendars/home/"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_calendar_discovery() {
+        use crate::test_server::{Response, TestServer};
+
+        assert_eq!(
+            CalendarDiscovery::default().attempts(),
+            [CalendarDiscovery::Propfind, CalendarDiscovery::Query]
+        );
+        assert_eq!(
+            CalendarDiscovery::Query.attempts(),
+            [CalendarDiscovery::Query]
+        );
+
+        let home_set = || {
+            Response::multistatus(
+                r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav"><d:response>
+                <d:href>/dav</d:href>
+                <d:propstat><d:prop><c:calendar-home-set><d:href>/dav/calendars/user/</d:href></c:calendar-home-set></d:prop></d:propstat>
+            </d:response></d:multistatus>"#,
+            )
+        };
+        // PROPFIND on the home set: the home set itself is listed and skipped.
+        let propfind = Response::multistatus(
+            r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+            <d:response>
+                <d:href>/dav/calendars/user/</d:href>
+                <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop></d:propstat>
+            </d:response>
+            <d:response>
+                <d:href>/dav/calendars/user/work</d:href>
+                <d:propstat><d:prop>
+                    <d:displayname>Work</d:displayname>
+                    <d:resourcetype><d:collection/><c:calendar/></d:resourcetype>
+                    <c:supported-calendar-component-set><c:comp name="VEVENT"/></c:supported-calendar-component-set>
+                </d:prop></d:propstat>
+            </d:response>
+        </d:multistatus>"#,
+        );
+        // calendar-query on the base url: only calendars supporting events or todos are kept.
+        let query = || {
+            Response::multistatus(
+                r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+            <d:response>
+                <d:href>/dav/calendars/user/tasks/</d:href>
+                <d:propstat><d:prop>
+                    <d:displayname>Tasks</d:displayname>
+                    <d:resourcetype><d:collection/><c:calendar/></d:resourcetype>
+                    <c:supported-calendar-component-set><c:comp name="VTODO"/></c:supported-calendar-component-set>
+                </d:prop></d:propstat>
+            </d:response>
+            <d:response>
+                <d:href>/dav/calendars/user/journal/</d:href>
+                <d:propstat><d:prop>
+                    <d:displayname>Journal</d:displayname>
+                    <d:resourcetype><d:collection/><c:calendar/></d:resourcetype>
+                    <c:supported-calendar-component-set><c:comp name="VJOURNAL"/></c:supported-calendar-component-set>
+                </d:prop></d:propstat>
+            </d:response>
+        </d:multistatus>"#,
+            )
+        };
+        let server = TestServer::start(vec![
+            // Propfind: no principal, the home set of the base url is listed.
+            Response::new(404),
+            home_set(),
+            propfind,
+            // Query
+            query(),
+            // Both: PROPFIND on the home set is forbidden, the query succeeds.
+            Response::new(404),
+            home_set(),
+            Response::new(403),
+            query(),
+            // Propfind: PROPFIND on the home set is forbidden, no fallback.
+            Response::new(404),
+            home_set(),
+            Response::new(403),
+            // Both: both fail, the error of the query is returned.
+            Response::new(404),
+            home_set(),
+            Response::new(403),
+            Response::new(401),
+        ]);
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let discover = |discovery| {
+            get_calendars_with_discovery(&client, &credentials, server.join("/dav"), discovery)
+        };
+        let names =
+            |calendars: Vec<CalendarRef>| calendars.into_iter().map(|c| c.name).collect::<Vec<_>>();
+
+        let (calendars, used) = discover(CalendarDiscovery::Propfind).await.unwrap();
+        assert_eq!(used, CalendarDiscovery::Propfind);
+        assert_eq!(calendars[0].url, server.join("/dav/calendars/user/work/"));
+        assert_eq!(names(calendars), ["Work"]);
+
+        let (calendars, used) = discover(CalendarDiscovery::Query).await.unwrap();
+        assert_eq!(used, CalendarDiscovery::Query);
+        assert_eq!(names(calendars), ["Tasks"]);
+
+        let (calendars, used) = discover(CalendarDiscovery::Both).await.unwrap();
+        assert_eq!(used, CalendarDiscovery::Query);
+        assert_eq!(names(calendars), ["Tasks"]);
+
+        let error = discover(CalendarDiscovery::Propfind).await.unwrap_err();
+        assert!(matches!(error, Forbidden(_)), "{:?}", error);
+        let error = discover(CalendarDiscovery::Both).await.unwrap_err();
+        assert!(matches!(error, Unauthorized(_)), "{:?}", error);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 15);
+        let propfind = &requests[2];
+        assert_eq!(propfind.url, "/dav/calendars/user/");
+        assert_eq!(propfind.header("Depth"), Some("1"));
+        assert!(propfind.body.contains("<d:propfind"));
+        for query in [&requests[3], &requests[7]] {
+            assert_eq!(query.url, "/dav");
+            assert!(query.body.contains("<c:calendar-query"));
+        }
+    }
+
+    #[test]
+    fn test_calendar_enabled_and_order() {
+        let calendar = |props: &str| {
+            let xml = format!(
+                r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav"
+                    xmlns:a="http://apple.com/ns/ical/" xmlns:oc="http://owncloud.org/ns">
+                <d:response>
+                    <d:href>/dav/calendars/user/work/</d:href>
+                    <d:propstat><d:prop>
+                        <d:displayname>Work</d:displayname>
+                        <d:resourcetype><d:collection/><c:calendar/></d:resourcetype>
+                        <c:supported-calendar-component-set><c:comp name="VEVENT"/></c:supported-calendar-component-set>
+                        {}
+                    </d:prop></d:propstat>
+                </d:response>
+            </d:multistatus>"#,
+                props
+            );
+            let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+            let base_url = Url::parse("https://example.com/dav/").unwrap();
+            let mut calendars = parse_calendars(&Client::new(), &base_url, &root);
+            assert_eq!(calendars.len(), 1);
+            let calendar = calendars.remove(0);
+            (calendar.enabled, calendar.order)
+        };
+        assert_eq!(
+            calendar("<oc:calendar-enabled>0</oc:calendar-enabled><a:calendar-order>3</a:calendar-order>"),
+            (Some(false), Some(3))
+        );
+        assert_eq!(
+            calendar("<oc:calendar-enabled> true </oc:calendar-enabled><a:calendar-order>0</a:calendar-order>"),
+            (Some(true), Some(0))
+        );
+        assert_eq!(calendar(""), (None, None));
+        // Empty or invalid values are treated as absent.
+        assert_eq!(
+            calendar("<oc:calendar-enabled/><a:calendar-order>first</a:calendar-order>"),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn test_object_head_property() {
+        let head = ObjectHead {
+            url: Url::parse("https://example.com/cal/1.ics").unwrap(),
+            etag: None,
+            data: "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:1\r\nSUMMARY:A very long\r\n  summary\r\nDTSTART;TZID=Europe/Berlin:20240101T100000\r\n".into(),
+            complete: false,
+            size: Some(5_000_000),
+        };
+        assert_eq!(
+            head.property("SUMMARY").map(|p| p.value),
+            Some("A very long summary".to_string())
+        );
+        assert_eq!(
+            head.property("DTSTART").and_then(|p| p.tzid()),
+            Some("Europe/Berlin".to_string())
+        );
+        assert_eq!(head.property("ATTACH"), None);
+    }
+
+    #[test]
+    fn test_schedule_response() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8" ?>
+<C:schedule-response xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+  <C:response>
+    <C:recipient><D:href>mailto:bob@example.com</D:href></C:recipient>
+    <C:request-status>2.0;Success</C:request-status>
+    <C:calendar-data>BEGIN:VCALENDAR
+END:VCALENDAR
+</C:calendar-data>
+  </C:response>
+  <C:response>
+    <C:recipient>mailto:carol@example.com</C:recipient>
+    <C:request-status>3.7;Invalid calendar user</C:request-status>
+  </C:response>
+</C:schedule-response>"#;
+        let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        let responses = ScheduleResponse::from_xml(&root);
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].recipient, "mailto:bob@example.com");
+        assert!(responses[0].is_success());
+        assert!(responses[0].calendar_data.is_some());
+        assert_eq!(responses[1].recipient, "mailto:carol@example.com");
+        assert!(!responses[1].is_success());
+        assert_eq!(responses[1].calendar_data, None);
+    }
+
+    #[test]
+    fn test_capabilities_from_headers() {
+        let caps = ServerCapabilities::from_headers(
+            vec![
+                "1, 3, extended-mkcol, access-control, calendarserver-principal-property-search",
+                "calendar-access, calendar-auto-schedule",
+            ],
+            vec!["OPTIONS, GET, HEAD, DELETE, PROPFIND, PUT, PROPPATCH, COPY, MOVE, REPORT, mkcalendar"],
+        );
+        assert!(caps.calendar_access());
+        assert!(caps.calendar_schedule());
+        assert!(caps.calendar_auto_schedule());
+        assert!(caps.extended_mkcol());
+        assert!(caps.access_control());
+        assert!(caps.mkcalendar());
+        assert!(caps.allows("propfind"));
+        assert!(!caps.allows("MKCOL"));
+        assert!(!caps.calendarserver_sharing());
+    }
+
+    #[test]
+    fn test_parse_invite_notification() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<CS:notification xmlns:D="DAV:" xmlns:CS="http://calendarserver.org/ns/">
+  <CS:dtstamp>20240101T120000Z</CS:dtstamp>
+  <CS:invite-notification>
+    <CS:uid>a3b4c5</CS:uid>
+    <D:href>mailto:bob@example.com</D:href>
+    <CS:invite-noresponse />
+    <CS:hosturl>
+      <D:href>/calendars/alice/work/</D:href>
+    </CS:hosturl>
+    <CS:organizer>
+      <D:href>mailto:alice@example.com</D:href>
+      <CS:common-name>Alice</CS:common-name>
+    </CS:organizer>
+    <CS:access>
+      <CS:read-write />
+    </CS:access>
+    <CS:summary>Work</CS:summary>
+  </CS:invite-notification>
+</CS:notification>"#;
+        let url = Url::parse("https://example.com/notifications/bob/a3b4c5.xml").unwrap();
+        let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        let invite = parse_invite_notification(&Client::new(), &url, &root).unwrap();
+        assert_eq!(invite.uid, "a3b4c5");
+        assert_eq!(invite.sharee, "mailto:bob@example.com");
+        assert_eq!(
+            invite.host_url.as_str(),
+            "https://example.com/calendars/alice/work/"
+        );
+        assert_eq!(
+            invite.organizer.as_deref(),
+            Some("mailto:alice@example.com")
+        );
+        assert_eq!(invite.summary.as_deref(), Some("Work"));
+        assert!(invite.read_write);
+        assert_eq!(invite.status, InviteStatus::NoResponse);
+    }
+
+    #[tokio::test]
+    async fn test_get_share_invites_skips_failed_notifications() {
+        use crate::test_server::{Response, TestServer};
+
+        let href = |path: &str, prop: &str| {
+            Response::multistatus(&format!(
+                r#"<d:multistatus xmlns:d="DAV:" xmlns:cs="http://calendarserver.org/ns/">
+                <d:response><d:href>/</d:href><d:propstat>
+                <d:prop><{0}><d:href>{1}</d:href></{0}></d:prop>
+                <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat></d:response></d:multistatus>"#,
+                prop, path
+            ))
+        };
+        let invite = |uid: &str| {
+            Response::new(200).body(&format!(
+                r#"<CS:notification xmlns:D="DAV:" xmlns:CS="http://calendarserver.org/ns/">
+                <CS:invite-notification><CS:uid>{}</CS:uid><D:href>mailto:bob@example.com</D:href>
+                <CS:hosturl><D:href>/calendars/alice/{}/</D:href></CS:hosturl>
+                </CS:invite-notification></CS:notification>"#,
+                uid, uid
+            ))
+        };
+        let server = TestServer::start(vec![
+            href("/principals/bob/", "d:current-user-principal"),
+            href("/notifications/bob/", "cs:notification-URL"),
+            Response::multistatus(
+                r#"<d:multistatus xmlns:d="DAV:">
+                <d:response><d:href>/notifications/bob/</d:href></d:response>
+                <d:response><d:href>/notifications/bob/a.xml</d:href></d:response>
+                <d:response><d:href>/notifications/bob/gone.xml</d:href></d:response>
+                <d:response><d:href>/notifications/bob/c.xml</d:href></d:response>
+                </d:multistatus>"#,
+            ),
+            invite("a"),
+            Response::new(404),
+            invite("c"),
+        ]);
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("bob".into(), "bar".into());
+        let invites = get_share_invites(&client, &credentials, server.join("/dav/"))
+            .await
+            .unwrap();
+        assert_eq!(
+            invites.iter().map(|i| i.uid.as_str()).collect::<Vec<_>>(),
+            ["a", "c"]
+        );
+        assert_eq!(invites[1].host_url, server.join("/calendars/alice/c/"));
+        assert_eq!(server.requests()[4].url, "/notifications/bob/gone.xml");
+    }
+
+    #[test]
+    fn test_calendar_defaults_from_xml() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/calendars/user/work/</d:href>
+    <d:propstat>
+      <d:prop>
+        <c:default-alarm-vevent-datetime>BEGIN:VALARM
+ACTION:DISPLAY
+TRIGGER;RELATED=START:-PT15M
+END:VALARM
+</c:default-alarm-vevent-datetime>
+        <c:schedule-calendar-transp><c:transparent /></c:schedule-calendar-transp>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#;
+        let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        let defaults = CalendarDefaults::from_xml(&root);
+        assert_eq!(
+            defaults,
+            CalendarDefaults {
+                alarm_offset: Some("-PT15M".into()),
+                schedule_transparency: Some("TRANSPARENT".into()),
+                ..Default::default()
+            }
+        );
+
+        let xml = build_calendar_defaults_xml(Some("-PT15M"), None);
+        assert!(xml.contains("TRIGGER:-PT15M"));
+        assert!(!xml.contains("schedule-calendar-transp"));
+        assert!(!xml.contains("remove"));
+        let xml = build_calendar_defaults_xml(None, Some("transparent"));
+        assert!(xml.contains(
+            "<C:schedule-calendar-transp><C:transparent /></C:schedule-calendar-transp>"
+        ));
+        assert!(!xml.contains("default-alarm-vevent-datetime"));
+        assert!(xmltree::Element::parse(xml.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn test_calendar_query_xml() {
+        let query = CalendarQuery::events()
+            .time_range(Some("20240101T000000Z".into()), None)
+            .prop_filter(PropFilter::new("categories").contains("work"))
+            .prop_filter(
+                PropFilter::new("SUMMARY")
+                    .contains("stand<up>")
+                    .collation(Collation::Octet)
+                    .negate(),
+            )
+            .prop_filter(PropFilter::is_not_defined("RRULE"));
+        let xml = query.to_xml();
+        assert!(xml.contains(r#"<c:comp-filter name="VEVENT"><c:time-range start="20240101T000000Z" /><c:prop-filter name="CATEGORIES"><c:text-match collation="i;ascii-casemap" negate-condition="no">work</c:text-match></c:prop-filter>"#));
+        assert!(xml.contains(r#"<c:text-match collation="i;octet" negate-condition="yes">stand&lt;up&gt;</c:text-match>"#));
+        assert!(xml.contains(
+            r#"<c:prop-filter name="RRULE"><c:is-not-defined /></c:prop-filter></c:comp-filter>"#
+        ));
+        assert!(xmltree::Element::parse(xml.as_bytes()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_proppatch_failed_property() {
+        use crate::test_server::{Response, TestServer};
+
+        let propstat = |status: &str| {
+            Response::multistatus(&format!(
+                r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+                    <d:response><d:href>/inbox/</d:href><d:propstat>
+                        <d:prop><c:calendar-availability/></d:prop>
+                        <d:status>HTTP/1.1 {}</d:status>
+                    </d:propstat></d:response>
+                </d:multistatus>"#,
+                status
+            ))
+        };
+        let server = TestServer::start(vec![
+            propstat("200 OK"),
+            propstat("403 Forbidden"),
+            Response::new(200),
+        ]);
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let inbox_url = server.join("/inbox/");
+        let set = || set_calendar_availability(&client, &credentials, &inbox_url, "");
+        assert!(set().await.is_ok());
+        let error = set().await.unwrap_err();
+        assert!(
+            matches!(&error, RequestFailed(message) if message.contains("calendar-availability")),
+            "{:?}",
+            error
+        );
+        assert!(set().await.is_ok());
+        assert_eq!(server.requests()[0].method, "PROPPATCH");
+    }
+
+    #[tokio::test]
+    async fn test_calendar_availability() {
+        use crate::test_server::{Response, TestServer};
+
+        let data = "BEGIN:VCALENDAR\r\nBEGIN:VAVAILABILITY\r\nUID:work\r\nEND:VAVAILABILITY\r\nEND:VCALENDAR\r\n";
+        let availability = |data: &str| {
+            Response::multistatus(&format!(
+                r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+                    <d:response><d:href>/inbox/</d:href><d:propstat>
+                        <d:prop><c:calendar-availability>{}</c:calendar-availability></d:prop>
+                        <d:status>HTTP/1.1 200 OK</d:status>
+                    </d:propstat></d:response>
+                </d:multistatus>"#,
+                data
+            ))
+        };
+        let server = TestServer::start(vec![
+            availability(data),
+            availability(""),
+            Response::new(200),
+        ]);
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let inbox_url = server.join("/inbox/");
+
+        let get = || get_calendar_availability(&client, &credentials, &inbox_url);
+        assert_eq!(get().await.unwrap().as_deref(), Some(data));
+        assert_eq!(get().await.unwrap(), None);
+        set_calendar_availability(&client, &credentials, &inbox_url, "<&>")
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "PROPFIND");
+        assert_eq!(requests[0].header("Depth"), Some("0"));
+        assert!(requests[0].body.contains("calendar-availability"));
+        assert_eq!(requests[2].method, "PROPPATCH");
+        assert!(requests[2]
+            .body
+            .contains("<C:calendar-availability>&lt;&amp;&gt;</C:calendar-availability>"));
+    }
+
+    #[test]
+    fn test_parse_multiget_response() {
+        let xml = r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+            <d:response>
+                <d:href>/cal/a.ics</d:href>
+                <d:propstat>
+                    <d:prop><d:getetag>"1"</d:getetag><c:calendar-data>BEGIN:VCALENDAR</c:calendar-data></d:prop>
+                    <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat>
+            </d:response>
+            <d:response>
+                <d:href>/cal/deleted.ics</d:href>
+                <d:status>HTTP/1.1 404 Not Found</d:status>
+            </d:response>
+            <d:response>
+                <d:href>/cal/secret.ics</d:href>
+                <d:status>HTTP/1.1 403 Forbidden</d:status>
+            </d:response>
+            <d:response>
+                <d:href>/cal/removed.ics</d:href>
+                <d:status>HTTP/1.1 410 Gone</d:status>
+            </d:response>
+        </d:multistatus>"#;
+        let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        let base_url = Url::parse("https://example.com/").unwrap();
+        let items = parse_multiget_response(&Client::new(), &base_url, &root);
+        assert_eq!(items.len(), 4);
+        assert!(matches!(&items[0], MultigetItem::Found(e) if e.url.path() == "/cal/a.ics"));
+        assert!(
+            matches!(&items[1], MultigetItem::Gone(e) if e.url.path() == "/cal/deleted.ics" && e.status == Some(404))
+        );
+        assert!(matches!(&items[2], MultigetItem::Error(e) if e.is_forbidden()));
+        assert!(
+            matches!(&items[3], MultigetItem::Gone(e) if e.url.path() == "/cal/removed.ics" && e.status == Some(410))
+        );
+    }
+
+    #[test]
+    fn test_multistatus_from_xml() {
+        let xml = r#"<d:multistatus xmlns:d="DAV:" xmlns:x="http://example.com/ns">
+            <d:response>
+                <d:href>/cal/</d:href>
+                <d:propstat>
+                    <d:prop><d:displayname>Work</d:displayname><x:custom>42</x:custom></d:prop>
+                    <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat>
+                <d:propstat>
+                    <d:prop><x:missing/></d:prop>
+                    <d:status>HTTP/1.1 404 Not Found</d:status>
+                </d:propstat>
+            </d:response>
+            <d:response>
+                <d:href>/cal/gone.ics</d:href>
+                <d:status>HTTP/1.1 404 Not Found</d:status>
+            </d:response>
+            <d:sync-token>token-1</d:sync-token>
+        </d:multistatus>"#;
+        let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        let base_url = Url::parse("https://example.com/dav/").unwrap();
+        let multistatus = Multistatus::from_xml(&Client::new(), &base_url, &root);
+        assert_eq!(multistatus.sync_token.as_deref(), Some("token-1"));
+        assert_eq!(multistatus.responses.len(), 2);
+        let calendar = &multistatus.responses[0];
+        assert_eq!(calendar.url.as_str(), "https://example.com/cal/");
+        assert_eq!(calendar.prop_text("displayname").as_deref(), Some("Work"));
+        assert_eq!(calendar.prop_text("custom").as_deref(), Some("42"));
+        assert!(calendar.prop("missing").is_none());
+        assert_eq!(multistatus.responses[1].status, Some(404));
+    }
+
+    #[test]
+    fn test_parse_calendar_data_response_with_errors() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/cal/a.ics</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:getetag>"1"</d:getetag>
+        <c:calendar-data>BEGIN:VCALENDAR
+END:VCALENDAR</c:calendar-data>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+    <d:propstat>
+      <d:prop><c:schedule-tag /></d:prop>
+      <d:status>HTTP/1.1 404 Not Found</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/cal/b.ics</d:href>
+    <d:status>HTTP/1.1 404 Not Found</d:status>
+  </d:response>
+  <d:response>
+    <d:href>/cal/c.ics</d:href>
+    <d:propstat>
+      <d:prop><d:getetag /><c:calendar-data /></d:prop>
+      <d:status>HTTP/1.1 403 Forbidden</d:status>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#;
+        let base_url = Url::parse("https://example.com/").unwrap();
+        let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        let (events, errors) = parse_calendar_data_response(&Client::new(), &base_url, &root);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].url.as_str(), "https://example.com/cal/a.ics");
+        assert_eq!(events[0].schedule_tag, None);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].url.as_str(), "https://example.com/cal/b.ics");
+        assert!(errors[0].is_not_found());
+        assert_eq!(errors[1].status, Some(403));
+        assert!(errors[1].is_forbidden());
+    }
+
+    #[test]
+    fn test_sync_collection_result_from_xml() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response>
+    <d:href>/cal/a.ics</d:href>
+    <d:propstat>
+      <d:prop><d:getetag>"2"</d:getetag></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/cal/b.ics</d:href>
+    <d:status>HTTP/1.1 404 Not Found</d:status>
+  </d:response>
+  <d:response>
+    <d:href>/cal/</d:href>
+    <d:status>HTTP/1.1 507 Insufficient Storage</d:status>
+  </d:response>
+  <d:sync-token>http://example.com/ns/sync/1234</d:sync-token>
+</d:multistatus>"#;
+        let base_url = Url::parse("https://example.com/").unwrap();
+        let calendar_url = base_url.join("/cal/").unwrap();
+        let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        let result =
+            SyncCollectionResult::from_xml(&Client::new(), &base_url, &calendar_url, &root);
+        assert_eq!(
+            result.sync_token.as_deref(),
+            Some("http://example.com/ns/sync/1234")
+        );
+        assert_eq!(
+            result.changed,
+            vec![ChangedResource {
+                url: base_url.join("/cal/a.ics").unwrap(),
+                etag: Some("\"2\"".into()),
+            }]
+        );
+        assert_eq!(result.deleted, vec![base_url.join("/cal/b.ics").unwrap()]);
+        assert!(result.truncated);
+    }
 }
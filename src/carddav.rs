@@ -0,0 +1,515 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! CardDAV (RFC 6352) addressbooks and their vCard contacts.
+//!
+//! Addressbooks are found through the principal like calendars. To get the changes of an
+//! addressbook, use `caldav::sync_collection` with the url of the addressbook.
+//!
+//! ```rust,no_run
+//! # async fn run() -> Result<(), minicaldav::errors::MiniCaldavError> {
+//! let client = minicaldav::Client::new();
+//! let url = url::Url::parse("http://mycaldav.com/").unwrap();
+//! let credentials = minicaldav::Credentials::Basic("foo".into(), "s3cret!".into());
+//! for addressbook in minicaldav::carddav::get_addressbooks(&client, &credentials, url).await? {
+//!     let (contacts, _) =
+//!         minicaldav::carddav::get_contacts(&client, &credentials, &addressbook).await?;
+//!     for contact in contacts {
+//!         println!("{:?}", contact.get("FN"));
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use url::Url;
+
+use crate::caldav::{self, parse_object_data_response, propfind_get, EventRef};
+use crate::client::Client;
+use crate::credentials::Credentials;
+use crate::errors::MiniCaldavError::{self, *};
+use crate::errors::{CheckStatus, ResourceError};
+use crate::ical::{self, Ical, Property};
+use crate::privileges::Privileges;
+use crate::xml_templates::escape_xml;
+
+pub static ADDRESSBOOK_HOMESET_REQUEST: &str = r#"
+    <d:propfind xmlns:d="DAV:" xmlns:card="urn:ietf:params:xml:ns:carddav" >
+      <d:self/>
+      <d:prop>
+        <card:addressbook-home-set />
+      </d:prop>
+    </d:propfind>
+"#;
+
+pub static ADDRESSBOOKS_REQUEST: &str = r#"
+<d:propfind xmlns:d="DAV:" xmlns:card="urn:ietf:params:xml:ns:carddav" >
+    <d:prop>
+        <d:displayname />
+        <d:resourcetype />
+        <d:current-user-privilege-set/>
+        <card:addressbook-description />
+    </d:prop>
+</d:propfind>
+"#;
+
+pub static CONTACTS_REQUEST: &str = r#"
+    <card:addressbook-query xmlns:d="DAV:" xmlns:card="urn:ietf:params:xml:ns:carddav">
+        <d:prop>
+            <d:getetag />
+            <card:address-data />
+        </d:prop>
+        <card:filter />
+    </card:addressbook-query>
+"#;
+
+/// An addressbook collection of a CardDAV server.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Addressbook {
+    base_url: Url,
+    url: Url,
+    name: String,
+    description: Option<String>,
+    privileges: Vec<String>,
+}
+
+impl Addressbook {
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+    pub fn description(&self) -> Option<&String> {
+        self.description.as_ref()
+    }
+    pub fn writable(&self) -> bool {
+        self.privileges().can_write_content()
+    }
+    /// The normalized privileges of the current user.
+    pub fn privileges(&self) -> Privileges {
+        Privileges::from_names(&self.privileges)
+    }
+}
+
+/// A contact in a CardDAV addressbook.
+/// Corresponds to exactly one `.vcf` file
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Contact {
+    etag: Option<String>,
+    url: Url,
+    vcard: Ical,
+}
+
+impl Contact {
+    pub fn new(etag: Option<String>, url: Url, vcard: Ical) -> Self {
+        Self { etag, url, vcard }
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn etag(&self) -> Option<&String> {
+        self.etag.as_ref()
+    }
+
+    /// The VCARD component of the contact.
+    pub fn vcard(&self) -> &Ical {
+        &self.vcard
+    }
+
+    pub fn vcard_mut(&mut self) -> &mut Ical {
+        &mut self.vcard
+    }
+
+    /// The value of the first property with the given name, e.g. `FN` or `EMAIL`.
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.vcard.get_first_property(name).map(|p| &p.value)
+    }
+
+    /// The values of all properties with the given name, e.g. all `TEL` numbers.
+    pub fn get_all(&self, name: &str) -> Vec<&String> {
+        self.vcard
+            .properties
+            .iter()
+            .filter(|p| p.name == name)
+            .map(|p| &p.value)
+            .collect()
+    }
+
+    /// Set the value of the first property with the given name, adding it if it does not exist.
+    pub fn set(&mut self, name: &str, value: &str) {
+        match self.vcard.properties.iter_mut().find(|p| p.name == name) {
+            Some(property) => property.value = value.to_string(),
+            None => self.vcard.add_property(Property::new(name, value)),
+        }
+    }
+
+    /// The contact as vCard, e.g. to export it.
+    pub fn to_vcf(&self) -> String {
+        self.vcard.serialize()
+    }
+}
+
+/// Get the addressbook home set url of the given principal.
+pub async fn get_addressbook_home_set_url(
+    client: &Client,
+    credentials: &Credentials,
+    url: Url,
+) -> Result<Url, MiniCaldavError> {
+    let homeset_url = propfind_get(
+        client,
+        credentials,
+        &url,
+        ADDRESSBOOK_HOMESET_REQUEST.to_string(),
+        &[
+            "response",
+            "propstat",
+            "prop",
+            "addressbook-home-set",
+            "href",
+        ],
+        "0",
+    )
+    .await
+    .map_err(|e| match e {
+        PathNotExists(_) => HomeSetNotFound(url.clone()),
+        e => e,
+    })?
+    .0;
+
+    Ok(caldav::collection_url(
+        client.resolve_href(&url, &homeset_url)?,
+    ))
+}
+
+/// Get the addressbooks for the given credentials.
+pub async fn get_addressbooks(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: Url,
+) -> Result<Vec<Addressbook>, MiniCaldavError> {
+    let principal_url = caldav::discover_principal_url(client, credentials, &base_url).await?;
+    let homeset_url = match get_addressbook_home_set_url(client, credentials, principal_url).await {
+        Err(e) if caldav::is_not_provided(&e) => caldav::collection_url(base_url.clone()),
+        result => result?,
+    };
+
+    let root = propfind_get(
+        client,
+        credentials,
+        &homeset_url,
+        ADDRESSBOOKS_REQUEST.to_string(),
+        &[],
+        "1",
+    )
+    .await?
+    .1;
+    Ok(parse_addressbooks(client, &base_url, &root))
+}
+
+fn parse_addressbooks(
+    client: &Client,
+    base_url: &Url,
+    root: &xmltree::Element,
+) -> Vec<Addressbook> {
+    let mut addressbooks = Vec::new();
+    for response in root.children.iter().filter_map(|c| c.as_element()) {
+        let prop = response
+            .get_child("propstat")
+            .and_then(|e| e.get_child("prop"));
+        let is_addressbook = prop
+            .and_then(|e| e.get_child("resourcetype"))
+            .map(|e| e.get_child("addressbook").is_some())
+            .unwrap_or(false);
+        if !is_addressbook {
+            continue;
+        }
+        let Some(href) = response.get_child("href").and_then(|e| e.get_text()) else {
+            continue;
+        };
+        let Ok(url) = client
+            .resolve_href(base_url, &href)
+            .map(caldav::collection_url)
+        else {
+            error!("Could not parse url: {}/{}", base_url, href);
+            continue;
+        };
+        let name = prop
+            .and_then(|e| e.get_child("displayname"))
+            .and_then(|e| e.get_text())
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| {
+                url.path_segments()
+                    .and_then(|mut s| s.rfind(|s| !s.is_empty()))
+                    .unwrap_or_default()
+                    .to_string()
+            });
+        let description = prop
+            .and_then(|e| e.get_child("addressbook-description"))
+            .and_then(|e| e.get_text())
+            .map(|d| d.to_string());
+        let privileges = prop
+            .and_then(|e| e.get_child("current-user-privilege-set"))
+            .map(Privileges::names_from_xml)
+            .unwrap_or_default();
+        addressbooks.push(Addressbook {
+            base_url: base_url.clone(),
+            url,
+            name,
+            description,
+            privileges,
+        });
+    }
+    addressbooks
+}
+
+/// Get all contacts of the given addressbook.
+/// This function returns a tuple of all contacts that could be parsed and all contacts that couldn't.
+/// Contacts the server reported with an error status are returned as `Resource` errors.
+pub async fn get_contacts(
+    client: &Client,
+    credentials: &Credentials,
+    addressbook: &Addressbook,
+) -> Result<(Vec<Contact>, Vec<MiniCaldavError>), MiniCaldavError> {
+    let root =
+        caldav::raw_report(client, credentials, &addressbook.url, CONTACTS_REQUEST, "1").await?;
+    Ok(parse_contacts(client, &addressbook.base_url, &root))
+}
+
+/// Get the contacts with the given urls from the addressbook with an addressbook-multiget REPORT,
+/// e.g. the changes reported by `caldav::sync_collection`.
+pub async fn get_contacts_by_url(
+    client: &Client,
+    credentials: &Credentials,
+    addressbook: &Addressbook,
+    urls: &[Url],
+) -> Result<(Vec<Contact>, Vec<MiniCaldavError>), MiniCaldavError> {
+    if urls.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let root = caldav::raw_report(
+        client,
+        credentials,
+        &addressbook.url,
+        &build_multiget_xml(urls),
+        "1",
+    )
+    .await?;
+    Ok(parse_contacts(client, &addressbook.base_url, &root))
+}
+
+fn build_multiget_xml(urls: &[Url]) -> String {
+    let hrefs = urls
+        .iter()
+        .map(|u| format!("<d:href>{}</d:href>", escape_xml(u.path())))
+        .collect::<Vec<String>>()
+        .join("\n        ");
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<card:addressbook-multiget xmlns:d="DAV:" xmlns:card="urn:ietf:params:xml:ns:carddav">
+    <d:prop>
+        <d:getetag />
+        <card:address-data />
+    </d:prop>
+    {}
+</card:addressbook-multiget>
+"#,
+        hrefs
+    )
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+fn parse_contacts(
+    client: &Client,
+    base_url: &Url,
+    root: &xmltree::Element,
+) -> (Vec<Contact>, Vec<MiniCaldavError>) {
+    let (vcard_refs, resource_errors): (Vec<EventRef>, Vec<ResourceError>) =
+        parse_object_data_response(client, base_url, root, "address-data");
+    let mut contacts = Vec::new();
+    let mut errors = resource_errors
+        .into_iter()
+        .map(Resource)
+        .collect::<Vec<MiniCaldavError>>();
+    for vcard_ref in vcard_refs {
+        let mut lines = ical::LineIterator::new(&vcard_ref.data);
+        match Ical::parse(&mut lines) {
+            Ok(vcard) => contacts.push(Contact {
+                etag: vcard_ref.etag,
+                url: vcard_ref.url,
+                vcard,
+            }),
+            Err(e) => errors.push(CouldNotParseContact(vcard_ref.data, format!("{:?}", e))),
+        }
+    }
+    (contacts, errors)
+}
+
+/// Save the given contact on the CardDAV server.
+/// If no contact for the url exists it will be created, otherwise it is updated.
+pub async fn save_contact(
+    client: &Client,
+    credentials: &Credentials,
+    contact: Contact,
+) -> Result<Contact, MiniCaldavError> {
+    let data = contact.to_vcf();
+    let response = client
+        .put(contact.url.clone())
+        .header(CONTENT_TYPE, "text/vcard; charset=utf-8")
+        .header(CONTENT_LENGTH, data.len().to_string())
+        .authorization(credentials)
+        .body(data)
+        .send()
+        .await?
+        .check_status()
+        .await?;
+    let etag = response
+        .headers()
+        .get("ETag")
+        .and_then(|etag| etag.to_str().ok())
+        .map(|etag| etag.to_string());
+    Ok(Contact { etag, ..contact })
+}
+
+/// Delete the given contact from the CardDAV server.
+pub async fn remove_contact(
+    client: &Client,
+    credentials: &Credentials,
+    contact: Contact,
+) -> Result<(), MiniCaldavError> {
+    client
+        .delete(contact.url.as_str())
+        .authorization(credentials)
+        .send()
+        .await?
+        .check_status()
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_addressbooks() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:card="urn:ietf:params:xml:ns:carddav">
+  <d:response>
+    <d:href>/dav/addressbooks/foo/</d:href>
+    <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop></d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/dav/addressbooks/foo/contacts/</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:displayname>Contacts</d:displayname>
+        <d:resourcetype><d:collection/><card:addressbook/></d:resourcetype>
+        <d:current-user-privilege-set>
+          <d:privilege><d:read/></d:privilege>
+          <d:privilege><d:write/></d:privilege>
+        </d:current-user-privilege-set>
+        <card:addressbook-description>Private contacts</card:addressbook-description>
+      </d:prop>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/dav/addressbooks/foo/shared/</d:href>
+    <d:propstat>
+      <d:prop><d:resourcetype><d:collection/><card:addressbook/></d:resourcetype></d:prop>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#;
+        let base_url = Url::parse("https://example.com/").unwrap();
+        let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        let addressbooks = parse_addressbooks(&Client::new(), &base_url, &root);
+        assert_eq!(addressbooks.len(), 2);
+        assert_eq!(
+            addressbooks[0].url().as_str(),
+            "https://example.com/dav/addressbooks/foo/contacts/"
+        );
+        assert_eq!(addressbooks[0].name(), "Contacts");
+        assert_eq!(
+            addressbooks[0].description().map(|d| d.as_str()),
+            Some("Private contacts")
+        );
+        assert!(addressbooks[0].writable());
+        assert_eq!(addressbooks[1].name(), "shared");
+        assert!(!addressbooks[1].writable());
+    }
+
+    #[test]
+    fn test_parse_contacts() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:card="urn:ietf:params:xml:ns:carddav">
+  <d:response>
+    <d:href>/dav/addressbooks/foo/contacts/alice.vcf</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:getetag>"1"</d:getetag>
+        <card:address-data>BEGIN:VCARD
+VERSION:3.0
+UID:alice
+FN:Alice
+TEL:123
+TEL:456
+END:VCARD
+</card:address-data>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/dav/addressbooks/foo/contacts/bob.vcf</d:href>
+    <d:status>HTTP/1.1 404 Not Found</d:status>
+  </d:response>
+</d:multistatus>"#;
+        let base_url = Url::parse("https://example.com/").unwrap();
+        let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        #[cfg(feature = "tracing")]
+        let recorder = crate::test_server::SpanRecorder::default();
+        #[cfg(feature = "tracing")]
+        let guard = tracing::subscriber::set_default(recorder.clone());
+        let (mut contacts, errors) = parse_contacts(&Client::new(), &base_url, &root);
+        #[cfg(feature = "tracing")]
+        {
+            drop(guard);
+            assert_eq!(recorder.fields("parse_contacts").len(), 1);
+        }
+        assert_eq!(contacts.len(), 1);
+        assert!(matches!(&errors[..], [Resource(e)] if e.is_not_found()));
+
+        let contact = &mut contacts[0];
+        assert_eq!(contact.etag().map(|e| e.as_str()), Some("\"1\""));
+        assert_eq!(contact.get("FN").map(|v| v.as_str()), Some("Alice"));
+        assert_eq!(contact.get_all("TEL"), ["123", "456"]);
+        contact.set("FN", "Alice Liddell");
+        contact.set("NICKNAME", "Al");
+        assert!(contact.to_vcf().contains("FN:Alice Liddell\n"));
+        assert!(contact.to_vcf().contains("NICKNAME:Al\n"));
+    }
+}
@@ -0,0 +1,205 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Named colors used by servers and clients instead of hex values, with their RGB value.
+static NAMED_COLORS: &[(&str, (u8, u8, u8))] = &[
+    ("black", (0x00, 0x00, 0x00)),
+    ("white", (0xff, 0xff, 0xff)),
+    ("gray", (0x80, 0x80, 0x80)),
+    ("silver", (0xc0, 0xc0, 0xc0)),
+    ("red", (0xff, 0x00, 0x00)),
+    ("maroon", (0x80, 0x00, 0x00)),
+    ("orange", (0xff, 0xa5, 0x00)),
+    ("yellow", (0xff, 0xff, 0x00)),
+    ("olive", (0x80, 0x80, 0x00)),
+    ("lime", (0x00, 0xff, 0x00)),
+    ("green", (0x00, 0x80, 0x00)),
+    ("teal", (0x00, 0x80, 0x80)),
+    ("aqua", (0x00, 0xff, 0xff)),
+    ("cyan", (0x00, 0xff, 0xff)),
+    ("blue", (0x00, 0x00, 0xff)),
+    ("navy", (0x00, 0x00, 0x80)),
+    ("purple", (0x80, 0x00, 0x80)),
+    ("fuchsia", (0xff, 0x00, 0xff)),
+    ("magenta", (0xff, 0x00, 0xff)),
+    ("pink", (0xff, 0xc0, 0xcb)),
+    ("brown", (0xa5, 0x2a, 0x2a)),
+];
+
+/// The notation of a calendar color.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorFormat {
+    /// `#RRGGBB`
+    Hex,
+    /// `#RRGGBBAA` as written by Apple clients.
+    HexAlpha,
+    /// A color name, e.g. `red`.
+    Named,
+}
+
+/// A `calendar-color` as stored by the server, e.g. `#3a87ad`, `#3A87ADFF` or `blue`.
+/// Compare colors with `rgb` and write changes with `with_rgb`, which keeps the notation of the server.
+/// Otherwise clients using different notations keep rewriting the color of each other.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarColor {
+    raw: String,
+    format: Option<ColorFormat>,
+    rgb: Option<(u8, u8, u8)>,
+    alpha: u8,
+}
+
+impl CalendarColor {
+    /// Parse the given color. Unknown notations are kept, but have no RGB value.
+    pub fn parse(value: &str) -> Self {
+        let raw = value.to_string();
+        let value = value.trim();
+        let hex = |s: &str| u8::from_str_radix(s, 16).ok();
+        let parsed = match value.strip_prefix('#') {
+            Some(digits) if digits.is_ascii() && digits.len() == 6 => hex(&digits[0..2])
+                .zip(hex(&digits[2..4]))
+                .zip(hex(&digits[4..6]))
+                .map(|((r, g), b)| (ColorFormat::Hex, (r, g, b), 0xff)),
+            Some(digits) if digits.is_ascii() && digits.len() == 8 => hex(&digits[0..2])
+                .zip(hex(&digits[2..4]))
+                .zip(hex(&digits[4..6]))
+                .zip(hex(&digits[6..8]))
+                .map(|(((r, g), b), a)| (ColorFormat::HexAlpha, (r, g, b), a)),
+            Some(_) => None,
+            None => NAMED_COLORS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(value))
+                .map(|(_, rgb)| (ColorFormat::Named, *rgb, 0xff)),
+        };
+        match parsed {
+            Some((format, rgb, alpha)) => Self {
+                raw,
+                format: Some(format),
+                rgb: Some(rgb),
+                alpha,
+            },
+            None => Self {
+                raw,
+                format: None,
+                rgb: None,
+                alpha: 0xff,
+            },
+        }
+    }
+
+    /// The value as stored by the server.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// The notation of the value, `None` if it is not known.
+    pub fn format(&self) -> Option<ColorFormat> {
+        self.format
+    }
+
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        self.rgb
+    }
+
+    pub fn alpha(&self) -> u8 {
+        self.alpha
+    }
+
+    /// The canonical `#rrggbb` notation, e.g. for display.
+    pub fn to_hex(&self) -> Option<String> {
+        self.rgb
+            .map(|(r, g, b)| format!("#{:02x}{:02x}{:02x}", r, g, b))
+    }
+
+    /// This color changed to the given RGB value, written in the same notation.
+    /// The alpha value is kept. A named color without a name for the new value is written as `#rrggbb`.
+    pub fn with_rgb(&self, rgb: (u8, u8, u8)) -> Self {
+        if self.rgb == Some(rgb) {
+            return self.clone();
+        }
+        let (r, g, b) = rgb;
+        let upper = self.raw.chars().any(|c| c.is_ascii_uppercase());
+        let raw = match self.format {
+            Some(ColorFormat::HexAlpha) => {
+                format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, self.alpha)
+            }
+            Some(ColorFormat::Named) => NAMED_COLORS
+                .iter()
+                .find(|(_, named)| *named == rgb)
+                .map(|(name, _)| name.to_string())
+                .unwrap_or_else(|| format!("#{:02x}{:02x}{:02x}", r, g, b)),
+            _ => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        };
+        let raw = if upper && raw.starts_with('#') {
+            raw.to_ascii_uppercase()
+        } else {
+            raw
+        };
+        Self::parse(&raw)
+    }
+}
+
+impl std::fmt::Display for CalendarColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_colors() {
+        let color = CalendarColor::parse("#3A87ADFF");
+        assert_eq!(color.format(), Some(ColorFormat::HexAlpha));
+        assert_eq!(color.rgb(), Some((0x3a, 0x87, 0xad)));
+        assert_eq!(color.to_hex().as_deref(), Some("#3a87ad"));
+
+        let color = CalendarColor::parse("Blue");
+        assert_eq!(color.format(), Some(ColorFormat::Named));
+        assert_eq!(color.to_hex().as_deref(), Some("#0000ff"));
+
+        let color = CalendarColor::parse("#3a87ad");
+        assert_eq!(color.format(), Some(ColorFormat::Hex));
+        assert_eq!(color.alpha(), 0xff);
+
+        let color = CalendarColor::parse("rgb(1, 2, 3)");
+        assert_eq!(color.format(), None);
+        assert_eq!(color.rgb(), None);
+        assert_eq!(color.as_str(), "rgb(1, 2, 3)");
+    }
+
+    #[test]
+    fn test_with_rgb_keeps_format() {
+        let color = CalendarColor::parse("#3A87AD80");
+        assert_eq!(color.with_rgb((0x3a, 0x87, 0xad)).as_str(), "#3A87AD80");
+        assert_eq!(color.with_rgb((0xff, 0, 0)).as_str(), "#FF000080");
+        assert_eq!(
+            CalendarColor::parse("#3a87ad")
+                .with_rgb((0, 0x80, 0))
+                .as_str(),
+            "#008000"
+        );
+        let named = CalendarColor::parse("red");
+        assert_eq!(named.with_rgb((0, 0x80, 0)).as_str(), "green");
+        assert_eq!(named.with_rgb((1, 2, 3)).as_str(), "#010203");
+    }
+}
@@ -0,0 +1,460 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Checks which CalDAV features a server supports, e.g. to triage issues with a specific server.
+//!
+//! The probe creates a temporary calendar with one recurring event, runs the checks against it
+//! and removes it afterwards.
+//!
+//! ```rust,no_run
+//! # async fn run() {
+//! let client = minicaldav::Client::new();
+//! let url = url::Url::parse("http://mycaldav.com/").unwrap();
+//! let credentials = minicaldav::Credentials::Basic("foo".into(), "s3cret!".into());
+//! let report = minicaldav::conformance::probe(&client, &credentials, url).await;
+//! println!("{}", report);
+//! # }
+//! ```
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::caldav::{self, EventRef, MultigetItem, ServerCapabilities};
+use crate::client::Client;
+use crate::credentials::Credentials;
+use crate::errors::MiniCaldavError;
+
+/// The outcome of one check.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckResult {
+    Passed,
+    /// The server does not support the feature, with the reason.
+    Failed(String),
+    /// The check could not run, e.g. because a check it depends on failed.
+    Skipped(String),
+}
+
+/// One check of a [`Report`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: String,
+    pub result: CheckResult,
+}
+
+/// The results of all checks against a server.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub url: Url,
+    /// The capabilities announced by the server, if the OPTIONS request succeeded.
+    pub capabilities: Option<ServerCapabilities>,
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// The result of the check with the given name.
+    pub fn get(&self, name: &str) -> Option<&CheckResult> {
+        self.checks
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| &c.result)
+    }
+
+    /// The checks that failed.
+    pub fn failed(&self) -> impl Iterator<Item = &Check> {
+        self.checks
+            .iter()
+            .filter(|c| matches!(c.result, CheckResult::Failed(_)))
+    }
+
+    fn record<T>(&mut self, name: &str, result: Result<T, String>) -> Option<T> {
+        let (result, value) = match result {
+            Ok(value) => (CheckResult::Passed, Some(value)),
+            Err(reason) => (CheckResult::Failed(reason), None),
+        };
+        self.checks.push(Check {
+            name: name.to_string(),
+            result,
+        });
+        value
+    }
+
+    fn skip(&mut self, name: &str, reason: &str) {
+        self.checks.push(Check {
+            name: name.to_string(),
+            result: CheckResult::Skipped(reason.to_string()),
+        });
+    }
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "CalDAV conformance of {}", self.url)?;
+        for check in &self.checks {
+            match &check.result {
+                CheckResult::Passed => writeln!(f, "  PASS {}", check.name)?,
+                CheckResult::Failed(reason) => writeln!(f, "  FAIL {}: {}", check.name, reason)?,
+                CheckResult::Skipped(reason) => writeln!(f, "  SKIP {}: {}", check.name, reason)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+const EVENT: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//rust-minicaldav//EN\r
+BEGIN:VEVENT\r
+UID:minicaldav-conformance\r
+DTSTAMP:20240101T000000Z\r
+DTSTART:20240101T100000Z\r
+DTEND:20240101T110000Z\r
+RRULE:FREQ=DAILY;COUNT=3\r
+SUMMARY:minicaldav conformance probe\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+fn reason(e: MiniCaldavError) -> String {
+    format!("{:?}", e)
+}
+
+/// Run all checks against the server at the given url.
+/// Checks that fail do not stop the probe, see the returned report for the results.
+pub async fn probe(client: &Client, credentials: &Credentials, base_url: Url) -> Report {
+    let mut report = Report {
+        url: base_url.clone(),
+        capabilities: None,
+        checks: Vec::new(),
+    };
+
+    let principal_url = report.record(
+        "principal",
+        caldav::get_principal_url(client, credentials, base_url.clone())
+            .await
+            .map_err(reason),
+    );
+    let home_set_url = match principal_url.clone() {
+        Some(principal_url) => report.record(
+            "calendar-home-set",
+            caldav::get_home_set_url(client, credentials, principal_url)
+                .await
+                .map_err(reason),
+        ),
+        None => {
+            report.skip("calendar-home-set", "no principal");
+            None
+        }
+    };
+
+    report.capabilities = report.record(
+        "options",
+        caldav::get_capabilities(
+            client,
+            credentials,
+            home_set_url.as_ref().unwrap_or(&base_url),
+        )
+        .await
+        .map_err(reason)
+        .and_then(|c| {
+            if c.calendar_access() {
+                Ok(c)
+            } else {
+                Err("calendar-access is not announced in the DAV header".into())
+            }
+        }),
+    );
+
+    match &principal_url {
+        Some(principal_url) => {
+            report.record(
+                "scheduling",
+                caldav::get_schedule_inbox_url(client, credentials, principal_url.clone())
+                    .await
+                    .map_err(reason),
+            );
+        }
+        None => report.skip("scheduling", "no principal"),
+    }
+    let managed_attachments = report
+        .capabilities
+        .as_ref()
+        .map(|c| c.calendar_managed_attachments())
+        .unwrap_or(false);
+    report.record(
+        "managed-attachments",
+        if managed_attachments {
+            Ok(())
+        } else {
+            Err("calendar-managed-attachments is not announced".to_string())
+        },
+    );
+
+    let calid = format!(
+        "minicaldav-conformance-{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+    );
+    // Without the home set the url of the created calendar is unknown.
+    let calendar_url = report.record(
+        "mkcalendar",
+        match &home_set_url {
+            Some(home_set_url) => {
+                create_calendar(client, credentials, &base_url, home_set_url, &calid).await
+            }
+            None => Err("no calendar home set".to_string()),
+        },
+    );
+    let Some(calendar_url) = calendar_url else {
+        for name in [
+            "put",
+            "calendar-query",
+            "multiget",
+            "expand",
+            "sync-collection",
+        ] {
+            report.skip(name, "no test calendar");
+        }
+        return report;
+    };
+
+    let event_ref = report.record(
+        "put",
+        caldav::save_event(
+            client,
+            credentials,
+            EventRef {
+                etag: None,
+                url: calendar_url.join("minicaldav-conformance.ics").unwrap(),
+                data: EVENT.to_string(),
+                schedule_tag: None,
+            },
+        )
+        .await
+        .map_err(reason),
+    );
+    if let Some(event_ref) = event_ref {
+        run_event_checks(
+            &mut report,
+            client,
+            credentials,
+            &base_url,
+            &calendar_url,
+            &event_ref,
+        )
+        .await;
+    } else {
+        for name in ["calendar-query", "multiget", "expand", "sync-collection"] {
+            report.skip(name, "no test event");
+        }
+    }
+
+    if let Err(e) = caldav::remove_calendar(client, credentials, &base_url, calid).await {
+        warn!("Could not remove test calendar {}: {:?}", calendar_url, e);
+    }
+    report
+}
+
+/// Create the test calendar and return its url.
+async fn create_calendar(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    home_set_url: &Url,
+    calid: &str,
+) -> Result<Url, String> {
+    caldav::create_calendar(
+        client,
+        credentials,
+        base_url,
+        calid.to_string(),
+        "minicaldav conformance".to_string(),
+        "#808080".to_string(),
+    )
+    .await
+    .map_err(reason)?;
+    home_set_url
+        .join(&format!("{}/", calid))
+        .map_err(|e| e.to_string())
+}
+
+async fn run_event_checks(
+    report: &mut Report,
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+    calendar_url: &Url,
+    event_ref: &EventRef,
+) {
+    let found = |event_refs: &[EventRef]| {
+        if event_refs
+            .iter()
+            .any(|e| e.url.path() == event_ref.url.path())
+        {
+            Ok(())
+        } else {
+            Err("the test event is missing in the response".to_string())
+        }
+    };
+
+    let query = caldav::get_events(
+        client,
+        credentials,
+        base_url.clone(),
+        calendar_url.clone(),
+        None,
+        None,
+        false,
+    )
+    .await
+    .map_err(reason)
+    .and_then(|(event_refs, _)| found(&event_refs));
+    report.record("calendar-query", query);
+
+    let multiget = caldav::calendar_multiget(
+        client,
+        credentials,
+        base_url,
+        calendar_url,
+        std::slice::from_ref(&event_ref.url),
+    )
+    .await
+    .map_err(reason)
+    .and_then(|items| {
+        let event_refs = items
+            .into_iter()
+            .filter_map(|item| match item {
+                MultigetItem::Found(event_ref) => Some(event_ref),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        found(&event_refs)
+    });
+    report.record("multiget", multiget);
+
+    let expand = caldav::get_events(
+        client,
+        credentials,
+        base_url.clone(),
+        calendar_url.clone(),
+        Some("20240101T000000Z".into()),
+        Some("20240110T000000Z".into()),
+        true,
+    )
+    .await
+    .map_err(reason)
+    .and_then(|(event_refs, _)| {
+        let data = event_refs
+            .iter()
+            .map(|e| e.data.as_str())
+            .collect::<String>();
+        if data.matches("RECURRENCE-ID").count() == 3 && !data.contains("RRULE") {
+            Ok(())
+        } else {
+            Err("the recurring event was not expanded to 3 instances".to_string())
+        }
+    });
+    report.record("expand", expand);
+
+    let sync = caldav::sync_collection(client, credentials, base_url, calendar_url, None)
+        .await
+        .map_err(reason)
+        .and_then(|result| {
+            if result.sync_token.is_none() {
+                Err("no sync-token in the response".to_string())
+            } else if !result
+                .changed
+                .iter()
+                .any(|c| c.url.path() == event_ref.url.path())
+            {
+                Err("the test event is missing in the response".to_string())
+            } else {
+                Ok(())
+            }
+        });
+    report.record("sync-collection", sync);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let mut report = Report {
+            url: Url::parse("https://example.com/").unwrap(),
+            capabilities: None,
+            checks: Vec::new(),
+        };
+        assert_eq!(report.record("put", Ok::<_, String>(1)), Some(1));
+        assert_eq!(
+            report.record::<()>("expand", Err("not expanded".into())),
+            None
+        );
+        report.skip("multiget", "no test event");
+
+        assert_eq!(report.get("put"), Some(&CheckResult::Passed));
+        assert_eq!(
+            report.failed().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+            ["expand"]
+        );
+        assert_eq!(
+            report.to_string(),
+            "CalDAV conformance of https://example.com/\n  PASS put\n  FAIL expand: not expanded\n  SKIP multiget: no test event\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe_without_home_set() {
+        use crate::test_server::{Response, TestServer};
+
+        let server = TestServer::start(vec![
+            Response::new(404),
+            Response::new(200).header("DAV", "1, calendar-access"),
+        ]);
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let report = probe(&client, &credentials, server.join("/dav/")).await;
+        assert_eq!(report.get("options"), Some(&CheckResult::Passed));
+        assert_eq!(
+            report.get("mkcalendar"),
+            Some(&CheckResult::Failed("no calendar home set".into()))
+        );
+        assert_eq!(
+            report.get("put"),
+            Some(&CheckResult::Skipped("no test calendar".into()))
+        );
+        let requests = server.requests();
+        assert_eq!(
+            requests
+                .iter()
+                .map(|r| r.method.as_str())
+                .collect::<Vec<_>>(),
+            ["PROPFIND", "OPTIONS"]
+        );
+        assert_eq!(requests[1].url, "/dav/");
+    }
+}
@@ -6,4 +6,6 @@ use serde::{Deserialize, Serialize};
 pub enum Credentials {
     Basic(String, String),
     Bearer(String),
+    /// Send no Authorization header, e.g. if the client authenticates with a TLS client certificate.
+    None,
 }
@@ -8,6 +8,323 @@ pub enum MiniCaldavError {
     CouldNotParseXml(String),
     CouldNotParseTodo(String, String),
     CouldNotParseEvent(String, String),
+    CouldNotParseAvailability(String, String),
+    CouldNotParseContact(String, String),
+    CouldNotParseFreeBusy(String, String),
+    CouldNotParseJournal(String, String),
+    /// The time zone `String` is not known
+    UnknownTimezone(String),
+    /// The object contains a METHOD property with the value `String` and can not be stored
+    ContainsMethod(String),
+    /// The object is not valid, e.g. a required property is missing, and was not sent to the server
+    InvalidObject(String),
+    /// The server does not accept the sync token `String` anymore, a full sync is needed
+    InvalidSyncToken(String),
+    /// The server returned an error status for a single resource of a multistatus response
+    Resource(ResourceError),
+    /// The host name of the `Url` could not be resolved
+    DnsLookupFailed(url::Url),
+    /// The server does not provide `/.well-known/caldav`, the attempted `Url`
+    WellKnownNotFound(url::Url),
+    /// The server did not return a `current-user-principal` for the `Url`
+    PrincipalNotFound(url::Url),
+    /// The server did not return a `calendar-home-set` for the principal `Url`
+    HomeSetNotFound(url::Url),
+    /// An object with the `Url` exists already and was not overwritten
+    AlreadyExists(url::Url),
+    /// The object with the `Url` was changed on the server since it was fetched and was not overwritten
+    PreconditionFailed(url::Url),
+    /// The request timed out, see `TimeoutKind` for whether the server may have processed it
+    Timeout(TimeoutKind),
+    /// The connection was closed or reset after the request was sent, the server may have processed it
+    ConnectionLost(String),
+    /// No connection to the server could be established, the request was not sent
+    ConnectFailed(String),
+    /// The server answered the request with an error status without a dedicated variant
+    Http(HttpError),
+    /// The server rejected the credentials (401)
+    Unauthorized(HttpError),
+    /// The user may not access the resource (403)
+    Forbidden(HttpError),
+    /// The resource does not exist (404 or 410)
+    NotFound(HttpError),
+    /// The request conflicts with the state of the resource, e.g. a missing parent collection (409)
+    Conflict(HttpError),
+    /// The object is larger than the server accepts (413)
+    PayloadTooLarge(HttpError),
+    /// The quota of the user or the storage of the server is exhausted (507)
+    InsufficientStorage(HttpError),
+}
+
+/// When a request timed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    /// No connection could be established in time. The request was not sent, retrying is safe.
+    Connect,
+    /// The response did not arrive in time after the request was sent. The server may have
+    /// processed the request, e.g. check whether a PUT was stored with `verify_saved_event`.
+    Read,
+    /// The deadline of the request including retries expired, see `ClientBuilder::deadline`.
+    /// The server may have processed the request.
+    Deadline,
+}
+
+impl MiniCaldavError {
+    /// Whether the server may have processed the request although it failed,
+    /// i.e. it timed out or the connection was lost after the request was sent.
+    /// Check the outcome of such writes with `caldav::verify_write`.
+    pub fn is_unknown_outcome(&self) -> bool {
+        matches!(
+            self,
+            Self::Timeout(TimeoutKind::Read)
+                | Self::Timeout(TimeoutKind::Deadline)
+                | Self::ConnectionLost(_)
+        )
+    }
+
+    /// The status and body of the response, if the server answered with an error status.
+    pub fn http_error(&self) -> Option<&HttpError> {
+        match self {
+            Self::Http(e)
+            | Self::Unauthorized(e)
+            | Self::Forbidden(e)
+            | Self::NotFound(e)
+            | Self::Conflict(e)
+            | Self::PayloadTooLarge(e)
+            | Self::InsufficientStorage(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// Whether the server could not be reached, so the request was not sent.
+    /// Writes failing this way can be queued with `offline::OfflineQueue`.
+    pub fn is_offline(&self) -> bool {
+        matches!(
+            self,
+            Self::DnsLookupFailed(_) | Self::Timeout(TimeoutKind::Connect) | Self::ConnectFailed(_)
+        )
+    }
+}
+
+impl std::fmt::Display for MiniCaldavError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The parse errors also carry the data that could not be parsed, which is too long to show.
+        match self {
+            Self::PathNotExists(path) => write!(f, "Could not find {} in PROPFIND response", path),
+            Self::CouldNotJoinUrl(e) => write!(f, "Could not build url: {}", e),
+            Self::RequestFailed(e) => write!(f, "Request failed: {}", e),
+            Self::CouldNotParseXml(e) => write!(f, "Could not parse XML: {}", e),
+            Self::CouldNotParseTodo(_, e) => write!(f, "Could not parse todo: {}", e),
+            Self::CouldNotParseEvent(_, e) => write!(f, "Could not parse event: {}", e),
+            Self::CouldNotParseAvailability(_, e) => {
+                write!(f, "Could not parse availability: {}", e)
+            }
+            Self::CouldNotParseContact(_, e) => write!(f, "Could not parse contact: {}", e),
+            Self::CouldNotParseFreeBusy(_, e) => write!(f, "Could not parse free/busy: {}", e),
+            Self::CouldNotParseJournal(_, e) => write!(f, "Could not parse journal: {}", e),
+            Self::UnknownTimezone(tzid) => write!(f, "Unknown time zone {}", tzid),
+            Self::ContainsMethod(method) => {
+                write!(
+                    f,
+                    "The object contains METHOD:{} and can not be stored",
+                    method
+                )
+            }
+            Self::InvalidObject(e) => write!(f, "Invalid object: {}", e),
+            Self::InvalidSyncToken(token) => write!(f, "The sync token {} is not valid", token),
+            Self::Resource(e) => write!(f, "{}", e),
+            Self::DnsLookupFailed(url) => write!(f, "Could not resolve the host of {}", url),
+            Self::WellKnownNotFound(url) => write!(f, "{} is not provided", url),
+            Self::PrincipalNotFound(url) => write!(f, "No current-user-principal for {}", url),
+            Self::HomeSetNotFound(url) => write!(f, "No calendar-home-set for {}", url),
+            Self::AlreadyExists(url) => write!(f, "{} exists already", url),
+            Self::PreconditionFailed(url) => {
+                write!(f, "{} was changed on the server since it was fetched", url)
+            }
+            Self::Timeout(kind) => write!(f, "Request timed out: {}", kind),
+            Self::ConnectionLost(e) => write!(f, "Connection lost: {}", e),
+            Self::ConnectFailed(e) => write!(f, "Could not connect: {}", e),
+            Self::Http(e) => write!(f, "{}", e),
+            Self::Unauthorized(e) => write!(f, "Unauthorized, check the credentials: {}", e),
+            Self::Forbidden(e) => write!(f, "Forbidden: {}", e),
+            Self::NotFound(e) => write!(f, "Not found: {}", e),
+            Self::Conflict(e) => write!(f, "Conflict: {}", e),
+            Self::PayloadTooLarge(e) => write!(f, "Too large for the server: {}", e),
+            Self::InsufficientStorage(e) => write!(f, "Insufficient storage: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MiniCaldavError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Resource(e) => Some(e),
+            Self::Http(e)
+            | Self::Unauthorized(e)
+            | Self::Forbidden(e)
+            | Self::NotFound(e)
+            | Self::Conflict(e)
+            | Self::PayloadTooLarge(e)
+            | Self::InsufficientStorage(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connect => write!(f, "no connection established"),
+            Self::Read => write!(f, "no response received"),
+            Self::Deadline => write!(f, "deadline expired"),
+        }
+    }
+}
+
+/// A resource of a multistatus response that was reported with an error status instead of its data,
+/// e.g. because it was deleted (404) or is not accessible (403).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceError {
+    pub url: url::Url,
+    /// The HTTP status code, if the server returned one.
+    pub status: Option<u16>,
+    pub description: Option<String>,
+}
+
+impl ResourceError {
+    pub fn is_not_found(&self) -> bool {
+        self.status == Some(404)
+    }
+
+    /// Whether the resource does not exist (anymore), i.e. the status is 404 or 410.
+    pub fn is_gone(&self) -> bool {
+        matches!(self.status, Some(404) | Some(410))
+    }
+
+    pub fn is_forbidden(&self) -> bool {
+        self.status == Some(403)
+    }
+}
+
+impl std::fmt::Display for ResourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.status {
+            Some(status) => write!(f, "{} returned status {}", self.url, status)?,
+            None => write!(f, "{} returned an error", self.url)?,
+        }
+        if let Some(description) = &self.description {
+            write!(f, ": {}", description)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ResourceError {}
+
+/// Number of bytes of the response body kept in `HttpError`.
+const MAX_ERROR_BODY_LENGTH: usize = 1024;
+
+/// An error status the server answered a request with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpError {
+    pub url: url::Url,
+    pub status: u16,
+    /// The start of the response body, if the server sent one.
+    /// WebDAV servers often explain the error with a `DAV:error` element, see `precondition`.
+    pub body: Option<String>,
+}
+
+impl HttpError {
+    /// The credentials are missing or wrong.
+    pub fn is_unauthorized(&self) -> bool {
+        self.status == 401
+    }
+
+    pub fn is_forbidden(&self) -> bool {
+        self.status == 403
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.status == 404
+    }
+
+    /// The quota of the user or the storage of the server is exhausted.
+    pub fn is_insufficient_storage(&self) -> bool {
+        self.status == 507
+    }
+
+    /// The name of the violated precondition or postcondition of a `DAV:error` body
+    /// (RFC 4918, section 16), e.g. `no-uid-conflict` or `supported-calendar-data`.
+    pub fn precondition(&self) -> Option<String> {
+        let root = xmltree::Element::parse(self.body.as_deref()?.as_bytes()).ok()?;
+        if root.name != "error" {
+            return None;
+        }
+        root.children
+            .iter()
+            .find_map(|c| c.as_element())
+            .map(|e| e.name.clone())
+    }
+
+    /// The error for the given response body, keeping only its start.
+    pub(crate) fn new(url: url::Url, status: u16, body: &str) -> Self {
+        let mut end = body.len().min(MAX_ERROR_BODY_LENGTH);
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        let body = Some(body[..end].trim().to_string()).filter(|body| !body.is_empty());
+        Self { url, status, body }
+    }
+
+    pub(crate) async fn from_response(response: reqwest::Response) -> Self {
+        let url = response.url().clone();
+        let status = response.status().as_u16();
+        let body = response.text().await.unwrap_or_default();
+        Self::new(url, status, &body)
+    }
+}
+
+impl std::fmt::Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} returned status {}", self.url, self.status)?;
+        if let Some(precondition) = self.precondition() {
+            write!(f, ": {}", precondition)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+impl From<HttpError> for MiniCaldavError {
+    /// The dedicated variant for the status, `Http` for other statuses.
+    fn from(e: HttpError) -> Self {
+        match e.status {
+            401 => Self::Unauthorized(e),
+            403 => Self::Forbidden(e),
+            404 | 410 => Self::NotFound(e),
+            409 => Self::Conflict(e),
+            412 => Self::PreconditionFailed(e.url),
+            413 => Self::PayloadTooLarge(e),
+            507 => Self::InsufficientStorage(e),
+            _ => Self::Http(e),
+        }
+    }
+}
+
+/// Like `reqwest::Response::error_for_status`, but returning an `HttpError` with the body.
+pub(crate) trait CheckStatus {
+    async fn check_status(self) -> Result<reqwest::Response, MiniCaldavError>;
+}
+
+impl CheckStatus for reqwest::Response {
+    async fn check_status(self) -> Result<reqwest::Response, MiniCaldavError> {
+        if self.status().is_client_error() || self.status().is_server_error() {
+            Err(HttpError::from_response(self).await.into())
+        } else {
+            Ok(self)
+        }
+    }
 }
 
 impl From<url::ParseError> for MiniCaldavError {
@@ -18,12 +335,202 @@ impl From<url::ParseError> for MiniCaldavError {
 
 impl From<reqwest::Error> for MiniCaldavError {
     fn from(e: reqwest::Error) -> Self {
-        Self::RequestFailed(e.to_string())
+        match e.url() {
+            Some(url) if e.is_connect() && is_dns_error(&e) => Self::DnsLookupFailed(url.clone()),
+            Some(url) if e.is_status() => HttpError {
+                url: url.clone(),
+                status: e.status().map(|s| s.as_u16()).unwrap_or_default(),
+                body: None,
+            }
+            .into(),
+            _ if e.is_timeout() && e.is_connect() => Self::Timeout(TimeoutKind::Connect),
+            _ if e.is_timeout() => Self::Timeout(TimeoutKind::Read),
+            _ if !e.is_connect() && is_connection_lost(&e) => Self::ConnectionLost(e.to_string()),
+            _ if e.is_connect() => Self::ConnectFailed(e.to_string()),
+            _ => Self::RequestFailed(e.to_string()),
+        }
+    }
+}
+
+/// A failed lookup of a host name by the resolver of `Client`.
+#[derive(Debug)]
+pub(crate) struct DnsError(pub(crate) std::io::Error);
+
+impl std::fmt::Display for DnsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not resolve host name: {}", self.0)
+    }
+}
+
+impl std::error::Error for DnsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
     }
 }
 
+/// reqwest does not expose the kind of a connect error, look for the resolver error in its sources.
+/// Only clients built by `ClientBuilder` use the resolver returning `DnsError`.
+fn is_dns_error(e: &reqwest::Error) -> bool {
+    let mut source = std::error::Error::source(e);
+    while let Some(error) = source {
+        if error.is::<DnsError>() {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+/// Whether the connection broke down while the request or response was in flight.
+fn is_connection_lost(e: &reqwest::Error) -> bool {
+    let mut source = std::error::Error::source(e);
+    while let Some(error) = source {
+        if let Some(io) = error.downcast_ref::<std::io::Error>() {
+            if matches!(
+                io.kind(),
+                std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::UnexpectedEof
+            ) {
+                return true;
+            }
+        }
+        // The server closed the connection before the response was complete.
+        if let Some(hyper) = error.downcast_ref::<hyper::Error>() {
+            if hyper.is_incomplete_message() {
+                return true;
+            }
+        }
+        source = error.source();
+    }
+    false
+}
+
 impl From<xmltree::ParseError> for MiniCaldavError {
     fn from(e: xmltree::ParseError) -> Self {
         Self::CouldNotParseXml(e.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let url = url::Url::parse("https://example.com/cal/a.ics").unwrap();
+        let error = MiniCaldavError::Resource(ResourceError {
+            url: url.clone(),
+            status: Some(404),
+            description: Some("Not Found".into()),
+        });
+        assert_eq!(
+            error.to_string(),
+            "https://example.com/cal/a.ics returned status 404: Not Found"
+        );
+        assert!(std::error::Error::source(&error).is_some());
+
+        let error = MiniCaldavError::CouldNotParseEvent("BEGIN:VCALENDAR".into(), "no END".into());
+        assert_eq!(error.to_string(), "Could not parse event: no END");
+        assert_eq!(
+            MiniCaldavError::Timeout(TimeoutKind::Read).to_string(),
+            "Request timed out: no response received"
+        );
+        let boxed: Box<dyn std::error::Error> = Box::new(MiniCaldavError::PreconditionFailed(url));
+        assert!(boxed.source().is_none());
+    }
+
+    #[test]
+    fn test_http_error() {
+        let error = HttpError {
+            url: url::Url::parse("https://example.com/cal/a.ics").unwrap(),
+            status: 403,
+            body: Some(
+                r#"<d:error xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav"><c:no-uid-conflict><d:href>/cal/b.ics</d:href></c:no-uid-conflict></d:error>"#.into(),
+            ),
+        };
+        assert!(error.is_forbidden());
+        assert_eq!(error.precondition().as_deref(), Some("no-uid-conflict"));
+        assert_eq!(
+            MiniCaldavError::Http(error.clone()).to_string(),
+            "https://example.com/cal/a.ics returned status 403: no-uid-conflict"
+        );
+
+        let error = HttpError {
+            body: Some("<html>Forbidden</html>".into()),
+            ..error
+        };
+        assert_eq!(error.precondition(), None);
+    }
+
+    #[test]
+    fn test_status_variants() {
+        let error = |status: u16| {
+            MiniCaldavError::from(HttpError {
+                url: url::Url::parse("https://example.com/cal/a.ics").unwrap(),
+                status,
+                body: None,
+            })
+        };
+        assert!(matches!(error(401), MiniCaldavError::Unauthorized(_)));
+        assert!(matches!(error(410), MiniCaldavError::NotFound(_)));
+        assert!(matches!(error(412), MiniCaldavError::PreconditionFailed(_)));
+        assert!(matches!(
+            error(507),
+            MiniCaldavError::InsufficientStorage(_)
+        ));
+        assert!(matches!(error(500), MiniCaldavError::Http(_)));
+        assert_eq!(error(413).http_error().map(|e| e.status), Some(413));
+        assert_eq!(
+            error(401).to_string(),
+            "Unauthorized, check the credentials: https://example.com/cal/a.ics returned status 401"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_errors() {
+        use std::io::Read;
+
+        let client = crate::client::Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let get = |url: String| {
+            let client = client.clone();
+            async move { client.get(url.as_str()).send().await.unwrap_err() }
+        };
+
+        // The .invalid top-level domain never resolves (RFC 6761).
+        let error = get("http://minicaldav.invalid/".into()).await;
+        assert!(
+            matches!(error, MiniCaldavError::DnsLookupFailed(_)),
+            "{:?}",
+            error
+        );
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        // The server reads the request and closes the connection without answering.
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 1024]);
+        });
+        let error = get(url.clone()).await;
+        assert!(
+            matches!(error, MiniCaldavError::ConnectionLost(_)),
+            "{:?}",
+            error
+        );
+        server.join().unwrap();
+
+        // Nothing listens on the port anymore.
+        let error = get(url).await;
+        assert!(
+            matches!(error, MiniCaldavError::ConnectFailed(_)),
+            "{:?}",
+            error
+        );
+        assert!(error.is_offline());
+    }
+}
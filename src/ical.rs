@@ -97,10 +97,112 @@ impl Ical {
         self.add_property(prop);
     }
 
-    /// Parse the given lines to an ICAL container.
-    pub fn parse(lines: &LineIterator) -> Result<Self, Error> {
+    /// Get all TZIDs referenced by TZID parameters of this container and its children.
+    pub fn referenced_tzids(&self) -> Vec<String> {
+        let mut tzids = Vec::new();
+        self.collect_tzids(&mut tzids);
+        tzids
+    }
+
+    fn collect_tzids(&self, tzids: &mut Vec<String>) {
+        for prop in &self.properties {
+            if let Some(tzid) = prop.attributes.get("TZID") {
+                let tzid = tzid.trim_matches('"').to_string();
+                if !tzids.contains(&tzid) {
+                    tzids.push(tzid);
+                }
+            }
+        }
+        for child in self.children.iter().filter(|c| c.name != "VTIMEZONE") {
+            child.collect_tzids(tzids);
+        }
+    }
+
+    /// Get the TZIDs of all VTIMEZONE components of this container.
+    pub fn defined_tzids(&self) -> Vec<String> {
+        self.children
+            .iter()
+            .filter(|c| c.name == "VTIMEZONE")
+            .filter_map(|c| c.get_first_property("TZID"))
+            .map(|p| p.value.trim_matches('"').to_string())
+            .collect()
+    }
+
+    /// Rewrite all Windows time zone names (e.g. `W. Europe Standard Time`) used as TZID
+    /// in TZID parameters and VTIMEZONE components of this container and its children to IANA TZIDs.
+    pub fn normalize_tzids(&mut self) {
+        for prop in &mut self.properties {
+            if let Some(tzid) = prop.attributes.get_mut("TZID") {
+                if let Some(iana) = crate::windows_tz::windows_to_iana(tzid) {
+                    *tzid = iana.to_string();
+                }
+            }
+            if self.name == "VTIMEZONE" && prop.name == "TZID" {
+                if let Some(iana) = crate::windows_tz::windows_to_iana(&prop.value) {
+                    prop.value = iana.to_string();
+                }
+            }
+        }
+        for child in &mut self.children {
+            child.normalize_tzids();
+        }
+    }
+
+    /// Remove all VTIMEZONE components from this container and return them.
+    /// Servers that support time zones by reference (RFC 7809) do not need them.
+    pub fn strip_timezones(&mut self) -> Vec<Ical> {
+        let (timezones, children) = std::mem::take(&mut self.children)
+            .into_iter()
+            .partition(|c| c.name == "VTIMEZONE");
+        self.children = children;
+        timezones
+    }
+
+    /// Add VTIMEZONE components for all referenced TZIDs that are not defined in this container.
+    /// The `resolver` is asked for the definition of each missing TZID.
+    pub fn attach_timezones<F>(&mut self, mut resolver: F)
+    where
+        F: FnMut(&str) -> Option<Ical>,
+    {
+        let defined = self.defined_tzids();
+        let mut timezones = self
+            .referenced_tzids()
+            .iter()
+            .filter(|tzid| !defined.contains(tzid))
+            .filter_map(|tzid| resolver(tzid))
+            .collect::<Vec<Ical>>();
+        let position = self
+            .children
+            .iter()
+            .position(|c| c.name != "VTIMEZONE")
+            .unwrap_or(self.children.len());
+        for (i, tz) in timezones.drain(..).enumerate() {
+            self.children.insert(position + i, tz);
+        }
+    }
+
+    /// Parse the given text to an ICAL container.
+    /// The result does not borrow from the input, so the input can be dropped right away,
+    /// e.g. the body of a response in an async task.
+    pub fn parse_owned(input: String) -> Result<Self, Error> {
+        Self::parse(&mut LineIterator::new(&input))
+    }
+
+    /// Parse the next ICAL container from the given lines.
+    /// The iterator is advanced to the line after the `END` of the container.
+    pub fn parse(lines: &mut LineIterator) -> Result<Self, Error> {
+        Self::parse_with_buffer(lines, &mut String::new())
+    }
+
+    /// `parse` with the buffer for unfolding lines, which is reused for the nested components.
+    fn parse_with_buffer<'a>(
+        lines: &mut LineIterator<'a>,
+        line_buffer: &mut String,
+    ) -> Result<Self, Error> {
         let mut ical: Option<Ical> = None;
-        let mut line_buffer = String::new();
+        // The line of the last property of `ical`, continuation lines are appended to it.
+        let mut last_line: Option<&'a str> = None;
+        line_buffer.clear();
         while let Some(line) = lines.next() {
             if line.trim().is_empty() {
                 continue;
@@ -108,24 +210,28 @@ impl Ical {
 
             if let Some(line) = line.strip_prefix(' ') {
                 if line_buffer.is_empty() {
-                    if let Some(last_prop) = ical.as_mut().and_then(|ical| ical.properties.pop()) {
-                        line_buffer.push_str(&last_prop.serialize());
+                    if let Some(last_line) = last_line.take() {
+                        ical.as_mut().and_then(|ical| ical.properties.pop());
+                        line_buffer.push_str(last_line);
                     }
                 }
                 line_buffer.push_str(line);
                 continue;
-            } else if !line.contains(':') && line_buffer.is_empty() {
+            }
+            last_line = None;
+            let has_colon = memchr::memchr(b':', line.as_bytes()).is_some();
+            if !has_colon && line_buffer.is_empty() {
                 line_buffer.push_str(line);
                 continue;
             } else if !line_buffer.is_empty() {
-                let prop = Property::parse(&line_buffer)?;
+                let prop = Property::parse(line_buffer)?;
                 if let Some(ical) = ical.as_mut() {
                     ical.properties.push(prop);
                 }
-                line_buffer = String::new();
+                line_buffer.clear();
             }
 
-            if !line.contains(':') {
+            if !has_colon {
                 line_buffer.push_str(line);
                 continue;
             }
@@ -139,7 +245,8 @@ impl Ical {
                 continue;
             }
             if prop.is("BEGIN").is_some() {
-                let child = Ical::parse(lines.decrement())?;
+                lines.push_back(line);
+                let child = Ical::parse_with_buffer(lines, line_buffer)?;
                 if let Some(ical) = ical.as_mut() {
                     ical.children.push(child);
                 }
@@ -156,6 +263,7 @@ impl Ical {
             }
             if let Some(ical) = ical.as_mut() {
                 ical.properties.push(prop);
+                last_line = Some(line);
             } else {
                 warn!("minicaldav wants to add a property but it does not seem to collect properties right now.")
             }
@@ -186,32 +294,50 @@ impl Ical {
     }
 }
 
-/// A utility struct used during ical parsing.
+/// Formats the container as ICAL, same as `serialize`.
+impl std::fmt::Display for Ical {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.serialize())
+    }
+}
+
+impl std::str::FromStr for Ical {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(&mut LineIterator::new(s))
+    }
+}
+
+/// An iterator over the lines of an ICAL formatted text, used for parsing.
+///
+/// The iterator owns its position, so it can be cloned to parse the same text again
+/// and sent to other threads like any other iterator.
+#[derive(Debug, Clone)]
 pub struct LineIterator<'a> {
-    pos: std::cell::Cell<usize>,
-    lines: Vec<&'a str>,
+    lines: std::str::Lines<'a>,
+    /// A line that was read ahead and is returned again by the next call to `next`.
+    pushed_back: Option<&'a str>,
 }
 
 impl<'a> LineIterator<'a> {
     pub fn new(lines: &'a str) -> Self {
         Self {
-            pos: std::cell::Cell::new(0),
-            lines: lines.lines().collect(),
+            lines: lines.lines(),
+            pushed_back: None,
         }
     }
-    fn next(&self) -> Option<&'a str> {
-        let pos = self.pos.take();
-        if pos >= self.lines.len() {
-            self.pos.set(pos);
-            None
-        } else {
-            self.pos.set(pos + 1);
-            Some(self.lines[pos])
-        }
+
+    fn push_back(&mut self, line: &'a str) {
+        self.pushed_back = Some(line);
     }
-    fn decrement(&self) -> &Self {
-        self.pos.set(self.pos.take() - 1);
-        self
+}
+
+impl<'a> Iterator for LineIterator<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.pushed_back.take().or_else(|| self.lines.next())
     }
 }
 
@@ -248,41 +374,73 @@ impl Property {
     }
 
     /// Parses the given input to a Property instance.
+    ///
+    /// - An empty value (e.g. `URL;VALUE=URI:`) results in an empty `value`.
+    /// - Parameters without value (e.g. `X-FOO;BAR:value`) result in an empty attribute value.
+    /// - `;` and `:` inside quoted parameter values are part of the parameter value.
     pub fn parse(input: &str) -> Result<Self, Error> {
-        // Find position of the first colon (separation between name and value) that is not
-        // enclosed in `"`.
-        let mut name = String::new();
-        let mut value = String::new();
-        let mut read_value = false;
-        let mut ignore_colon = false;
-        for c in input.chars() {
-            if c == '"' {
-                ignore_colon = !ignore_colon;
-            } else if c == ':' && !ignore_colon && !read_value {
-                read_value = true;
-                continue;
-            }
-            if read_value {
-                value.push(c);
-            } else {
-                name.push(c);
-            }
+        if memchr::memchr(b'"', input.as_bytes()).is_none() {
+            Self::parse_unquoted(input)
+        } else {
+            Self::parse_quoted(input)
         }
-        let mut parts = name.split(';');
-        if let Some(next) = parts.next() {
-            let mut property = Property::new(next.trim(), &value);
-            for part in parts {
-                if let Some((k, v)) = part.split_once('=') {
-                    property.attributes.insert(k.into(), v.into());
+    }
+
+    /// Slow path of `parse` for lines with quoted parameter values.
+    fn parse_quoted(input: &str) -> Result<Self, Error> {
+        // Split at `;` and at the first `:` (separation between name and value) that are not
+        // enclosed in `"`. All three are ASCII, so the byte positions are char boundaries.
+        let mut segments = Vec::new();
+        let mut start = 0;
+        let mut end = input.len();
+        let mut quoted = false;
+        for (i, b) in input.bytes().enumerate() {
+            match b {
+                b'"' => quoted = !quoted,
+                b';' if !quoted => {
+                    segments.push(&input[start..i]);
+                    start = i + 1;
                 }
+                b':' if !quoted => {
+                    end = i;
+                    break;
+                }
+                _ => {}
             }
-            Ok(property)
-        } else {
-            Err(Error::new(format!(
-                "The property [{:?}, {:?}] did not contain any text after split(';')",
-                name, value
-            )))
         }
+        segments.push(&input[start..end]);
+        let value = input.get(end + 1..).unwrap_or_default();
+        Ok(Self::from_segments(segments.into_iter(), value))
+    }
+
+    /// Fast path of `parse` for the common case of lines without quoted parameter values.
+    fn parse_unquoted(input: &str) -> Result<Self, Error> {
+        let (name, value) = match memchr::memchr(b':', input.as_bytes()) {
+            Some(colon) => (&input[..colon], &input[colon + 1..]),
+            None => (input, ""),
+        };
+        Ok(Self::from_segments(name.split(';'), value))
+    }
+
+    /// The property with the given value from the `;` separated segments before the value:
+    /// the name followed by the parameters.
+    fn from_segments<'a>(mut segments: impl Iterator<Item = &'a str>, value: &str) -> Self {
+        let name = segments.next().unwrap_or_default().trim();
+        let mut property = Property::new(name, value);
+        for segment in segments.filter(|s| !s.trim().is_empty()) {
+            // Parameters without value (e.g. `X-FOO;BAR:value`) are kept with an empty value.
+            let (k, v) = segment.split_once('=').unwrap_or((segment, ""));
+            property.attributes.insert(k.trim().into(), v.into());
+        }
+        property
+    }
+
+    /// The IANA TZID of the TZID parameter of this property.
+    /// Windows time zone names are mapped to the corresponding IANA TZID.
+    pub fn tzid(&self) -> Option<String> {
+        self.attributes
+            .get("TZID")
+            .map(|tzid| crate::windows_tz::resolve_tzid(tzid))
     }
 
     /// Checks whether this property has the given name and if so, returns its value.
@@ -302,7 +460,7 @@ impl Property {
                 self.name,
                 self.attributes
                     .iter()
-                    .map(|(k, v)| format!("{}={}", k, v))
+                    .map(|(k, v)| format!("{}={}", k, serialize_param_value(v)))
                     .collect::<Vec<String>>()
                     .join(";"),
                 self.value
@@ -313,6 +471,183 @@ impl Property {
     }
 }
 
+/// Escape the given text for use as ICAL TEXT value (RFC 5545, 3.3.11).
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Convert an ICAL TEXT value to plain text, the inverse of `escape_text`.
+/// `\N` is accepted as line break as well, unknown escape sequences are kept.
+pub fn unescape_text(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => text.push('\n'),
+            Some(c @ ('\\' | ';' | ',')) => text.push(c),
+            Some(c) => {
+                text.push('\\');
+                text.push(c);
+            }
+            None => text.push('\\'),
+        }
+    }
+    text
+}
+
+/// Split a multi-valued TEXT value (e.g. of CATEGORIES) at unescaped commas and unescape the values.
+pub fn split_text_list(value: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push(c);
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            ',' => values.push(unescape_text(&std::mem::take(&mut current))),
+            c => current.push(c),
+        }
+    }
+    values.push(unescape_text(&current));
+    values.into_iter().filter(|v| !v.is_empty()).collect()
+}
+
+/// Join the given plain texts to a multi-valued TEXT value, the inverse of `split_text_list`.
+pub fn join_text_list<S: AsRef<str>>(values: &[S]) -> String {
+    values
+        .iter()
+        .map(|v| escape_text(v.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Prepare the given text for use as parameter value, quoting it if necessary (RFC 5545, 3.2).
+/// Quotes and line breaks are escaped as defined in RFC 6868.
+pub(crate) fn quote_param_value(value: &str) -> String {
+    let value = value
+        .replace('^', "^^")
+        .replace('"', "^'")
+        .replace("\r\n", "^n")
+        .replace(['\n', '\r'], "^n");
+    if value.contains([';', ':', ',']) {
+        format!("\"{}\"", value)
+    } else {
+        value
+    }
+}
+
+/// The text of the given parameter value, without quotes and with RFC 6868 escapes resolved.
+pub(crate) fn unquote_param_value(value: &str) -> String {
+    let mut text = String::new();
+    let mut chars = value.trim_matches('"').chars();
+    while let Some(c) = chars.next() {
+        if c != '^' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('^') => text.push('^'),
+            Some('\'') => text.push('"'),
+            // Unknown escapes are kept as they are.
+            Some(c) => {
+                text.push('^');
+                text.push(c);
+            }
+            None => text.push('^'),
+        }
+    }
+    text
+}
+
+/// The given parameter value as written in a property line.
+/// Values are kept as parsed, including their quotes, so values written by other clients are not changed.
+/// Unquoted values that can not be written as they are (e.g. set with a `;` or `:`) are quoted.
+fn serialize_param_value(value: &str) -> std::borrow::Cow<'_, str> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in value.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            ',' if !quoted => parts.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    parts.push(current);
+    let needs_quotes = |part: &str| {
+        let is_quoted = part.len() >= 2 && part.starts_with('"') && part.ends_with('"');
+        !is_quoted && part.contains([':', ';', '"', '\n', '\r'])
+    };
+    if !parts.iter().any(|p| needs_quotes(p)) {
+        return value.into();
+    }
+    parts
+        .iter()
+        .map(|part| {
+            if needs_quotes(part) {
+                let part = quote_param_value(part);
+                if part.starts_with('"') {
+                    part
+                } else {
+                    format!("\"{}\"", part)
+                }
+            } else {
+                part.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+        .into()
+}
+
+/// Split a (possibly quoted) multi-valued parameter value like `"mailto:a@b.c","mailto:d@e.f"`.
+pub(crate) fn split_param_values(value: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in value.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => values.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    values.push(current);
+    values.into_iter().filter(|v| !v.is_empty()).collect()
+}
+
+/// Join the given values to a multi-valued parameter value, quoting each value.
+pub(crate) fn join_param_values(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|v| format!("\"{}\"", v))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
 impl From<(String, String)> for Property {
     fn from((name, value): (String, String)) -> Self {
         Self {
@@ -339,6 +674,61 @@ impl Error {
 mod tests {
     use super::*;
 
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_types_are_send_and_sync() {
+        assert_send_sync::<Ical>();
+        assert_send_sync::<Property>();
+        assert_send_sync::<LineIterator>();
+        assert_send_sync::<Error>();
+    }
+
+    #[test]
+    fn test_parse_owned_and_from_str() {
+        let text = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:1\nEND:VEVENT\nEND:VCALENDAR\n";
+        let parsed: Ical = text.parse().unwrap();
+        assert_eq!(
+            parsed.children[0].get_first_property("UID").unwrap().value,
+            "1"
+        );
+        assert_eq!(Ical::parse_owned(text.to_string()).unwrap(), parsed);
+        assert!("BEGIN:VEVENT\nUID:1\n".parse::<Ical>().is_err());
+        assert_eq!(parsed.to_string(), text);
+    }
+
+    #[test]
+    fn test_parse_advances_lines() {
+        let text = "BEGIN:VEVENT\nUID:1\nEND:VEVENT\nBEGIN:VEVENT\nUID:2\nEND:VEVENT\n";
+        let mut lines = LineIterator::new(text);
+        let first = Ical::parse(&mut lines).unwrap();
+        let second = Ical::parse(&mut lines).unwrap();
+        assert_eq!(first.get_first_property("UID").unwrap().value, "1");
+        assert_eq!(second.get_first_property("UID").unwrap().value, "2");
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn test_unfold_lines() {
+        let text = "BEGIN:VEVENT\nATTENDEE;CN=\"Doe, Jane\";ROLE=CHAIR:mailto:ja\n ne@example.com\nBEGIN:VALARM\nTRIGGER:-PT5M\nDESCRIPTION:Al\n arm\nEND:VALARM\nDESCRIPTION:Line one\n  and two\nEND:VEVENT\n";
+        let ical = Ical::parse(&mut LineIterator::new(text)).unwrap();
+        assert_eq!(
+            ical.properties,
+            [
+                Property::parse("ATTENDEE;CN=\"Doe, Jane\";ROLE=CHAIR:mailto:jane@example.com")
+                    .unwrap(),
+                Property::new("DESCRIPTION", "Line one and two"),
+            ]
+        );
+        assert_eq!(
+            ical.children[0].properties,
+            [
+                Property::new("TRIGGER", "-PT5M"),
+                Property::new("DESCRIPTION", "Alarm")
+            ]
+        );
+    }
+
     #[test]
     fn test_ical_calendar_with_properties() {
         let ical = r#"BEGIN:VCALENDAR
@@ -348,7 +738,7 @@ CALSCALE:GREGORIAN
 METHOD:PUBLISH
 END:VCALENDAR"#;
         assert_eq!(
-            Ical::parse(&LineIterator::new(ical)),
+            Ical::parse(&mut LineIterator::new(ical)),
             Ok(Ical {
                 name: "VCALENDAR".to_string(),
                 properties: vec![
@@ -386,7 +776,7 @@ DESCRIPTION:Born February 12\, 1809\nSixteenth President (1861-1865)\n\n\n\nhttp
 URL:http://americanhistorycalendar.com/peoplecalendar/1,328-abraham-lincoln
 END:VEVENT
 END:VCALENDAR"#;
-        let parsed = Ical::parse(&LineIterator::new(ical));
+        let parsed = Ical::parse(&mut LineIterator::new(ical));
         // println!("{:#?}", parsed);
         assert_eq!(
             parsed,
@@ -450,7 +840,7 @@ URL:http://americanhistorycalendar.com/peoplecalendar/1,328-abraham-lincoln
 END:VEVENT
 END:VCALENDAR
         "#;
-        let parsed = Ical::parse(&LineIterator::new(ical));
+        let parsed = Ical::parse(&mut LineIterator::new(ical));
         // println!("{:#?}", parsed);
         assert_eq!(
             parsed,
@@ -552,7 +942,7 @@ END:VALARM
 END:VEVENT
 END:VCALENDAR"#;
 
-        let parsed = Ical::parse(&LineIterator::new(ical));
+        let parsed = Ical::parse(&mut LineIterator::new(ical));
         assert!(parsed.is_ok());
         let cal = parsed.unwrap();
         assert_eq!(
@@ -653,7 +1043,7 @@ UID:5dd6eacd-c03d-4a47-b2b4-327486fc000c
 END:VEVENT
 END:VCALENDAR"#;
 
-        let parsed = Ical::parse(&LineIterator::new(ical));
+        let parsed = Ical::parse(&mut LineIterator::new(ical));
         assert!(parsed.is_ok());
         let cal = parsed.unwrap();
         assert_eq!(
@@ -735,7 +1125,7 @@ END:VEVENT
 END:VCALENDAR
 "#;
 
-        let parsed = Ical::parse(&LineIterator::new(ical));
+        let parsed = Ical::parse(&mut LineIterator::new(ical));
         assert!(parsed.is_ok());
         let cal = parsed.unwrap();
 
@@ -743,4 +1133,207 @@ END:VCALENDAR
         assert_eq!(apple_location.name, "X-APPLE-STRUCTURED-LOCATION");
         assert_eq!(apple_location.value, "geo:47.599824,-122.315080");
     }
+
+    #[test]
+    fn test_quote_param_value() {
+        assert_eq!(quote_param_value("Doe, John"), "\"Doe, John\"");
+        assert_eq!(quote_param_value("John"), "John");
+        assert_eq!(
+            quote_param_value("Line 1\r\nLine 2\n^ \"x\""),
+            "Line 1^nLine 2^n^^ ^'x^'"
+        );
+        assert_eq!(
+            unquote_param_value("\"Line 1^nLine 2^n^^ ^'x^' ^a\""),
+            "Line 1\nLine 2\n^ \"x\" ^a"
+        );
+    }
+
+    #[test]
+    fn test_parse_paths_agree() {
+        for line in [
+            "SUMMARY:Meeting",
+            "DTSTART;TZID=Europe/Berlin:20240101T100000",
+            "X-FOO;BAR;BAZ=1:value",
+            "X-FOO;;BAR=;:value:with:colons",
+            "URL;VALUE=URI:",
+            "NO-COLON;X=1",
+            " ATTENDEE ; CN=Alice ;ROLE=CHAIR:mailto:a@example.com",
+        ] {
+            let unquoted = Property::parse_unquoted(line).unwrap();
+            assert_eq!(Property::parse_quoted(line).unwrap(), unquoted, "{}", line);
+            assert_eq!(Property::parse(line).unwrap(), unquoted, "{}", line);
+        }
+        let property = Property::parse_unquoted("X-FOO;;BAR;BAZ=:v").unwrap();
+        assert_eq!(property.attributes.len(), 2);
+        assert_eq!(property.attributes["BAR"], "");
+        assert_eq!(property.attributes["BAZ"], "");
+    }
+
+    #[test]
+    fn test_param_round_trip() {
+        let line = r#"ATTENDEE;CN="Rüd, Alice";MEMBER="mailto:a@example.com","mailto:b@example.com";ROLE=REQ-PARTICIPANT;X-URL="https://example.com/a;b":mailto:c@example.com"#;
+        let property = Property::parse(line).unwrap();
+        assert_eq!(Property::parse(&property.serialize()).unwrap(), property);
+        for param in [
+            r#"CN="Rüd, Alice""#,
+            r#"MEMBER="mailto:a@example.com","mailto:b@example.com""#,
+            r#"X-URL="https://example.com/a;b""#,
+        ] {
+            assert!(property.serialize().contains(param));
+        }
+
+        // Values set without quotes are quoted where needed.
+        let property = Property::new_with_attributes(
+            "ATTENDEE",
+            "mailto:c@example.com",
+            vec![
+                ("CN", "Doe; John"),
+                ("DELEGATED-TO", "mailto:a@x.org,\"mailto:b@x.org\""),
+            ],
+        );
+        let parsed = Property::parse(&property.serialize()).unwrap();
+        assert_eq!(parsed.attributes["CN"], "\"Doe; John\"");
+        assert_eq!(
+            parsed.attributes["DELEGATED-TO"],
+            "\"mailto:a@x.org\",\"mailto:b@x.org\""
+        );
+        assert_eq!(parsed.value, "mailto:c@example.com");
+    }
+
+    #[test]
+    fn test_multi_valued_param() {
+        let values = split_param_values("\"mailto:a@example.com\",\"mailto:b@example.com\"");
+        assert_eq!(values, vec!["mailto:a@example.com", "mailto:b@example.com"]);
+        assert_eq!(
+            join_param_values(&values),
+            "\"mailto:a@example.com\",\"mailto:b@example.com\""
+        );
+        assert_eq!(
+            split_param_values("mailto:a@example.com"),
+            vec!["mailto:a@example.com"]
+        );
+    }
+
+    #[test]
+    fn test_strip_and_attach_timezones() {
+        let ical = r#"BEGIN:VCALENDAR
+VERSION:2.0
+BEGIN:VTIMEZONE
+TZID:Europe/Berlin
+BEGIN:STANDARD
+TZOFFSETFROM:+0200
+TZOFFSETTO:+0100
+DTSTART:19701025T030000
+END:STANDARD
+END:VTIMEZONE
+BEGIN:VEVENT
+UID:1
+DTSTART;TZID=Europe/Berlin:20210808T200000
+DTEND;TZID="America/New_York":20210808T220000
+END:VEVENT
+END:VCALENDAR"#;
+        let mut cal = Ical::parse(&mut LineIterator::new(ical)).unwrap();
+        assert_eq!(
+            cal.referenced_tzids(),
+            vec!["Europe/Berlin", "America/New_York"]
+        );
+        assert_eq!(cal.defined_tzids(), vec!["Europe/Berlin"]);
+
+        let stripped = cal.strip_timezones();
+        assert_eq!(stripped.len(), 1);
+        assert_eq!(cal.children.len(), 1);
+
+        cal.attach_timezones(|tzid| {
+            let mut tz = Ical::new("VTIMEZONE".into());
+            tz.add_property(Property::new("TZID", tzid));
+            Some(tz)
+        });
+        assert_eq!(
+            cal.defined_tzids(),
+            vec!["Europe/Berlin", "America/New_York"]
+        );
+        assert_eq!(cal.children[2].name, "VEVENT");
+    }
+
+    #[test]
+    fn test_normalize_windows_tzids() {
+        let ical = r#"BEGIN:VCALENDAR
+VERSION:2.0
+BEGIN:VTIMEZONE
+TZID:W. Europe Standard Time
+END:VTIMEZONE
+BEGIN:VEVENT
+UID:1
+DTSTART;TZID="(UTC-08:00) Pacific Time (US & Canada)":20210808T200000
+DTEND;TZID=W. Europe Standard Time:20210808T220000
+END:VEVENT
+END:VCALENDAR"#;
+        let mut cal = Ical::parse(&mut LineIterator::new(ical)).unwrap();
+        let event = cal.get("VEVENT").unwrap();
+        assert_eq!(
+            event.get_first_property("DTSTART").unwrap().tzid().unwrap(),
+            "America/Los_Angeles"
+        );
+
+        cal.normalize_tzids();
+        assert_eq!(cal.defined_tzids(), vec!["Europe/Berlin"]);
+        assert_eq!(
+            cal.referenced_tzids(),
+            vec!["America/Los_Angeles", "Europe/Berlin"]
+        );
+    }
+
+    #[test]
+    fn test_escape_and_unescape_text() {
+        let text = "Room 1; Building A, 2nd floor\nC:\\temp";
+        let escaped = escape_text(text);
+        assert_eq!(escaped, "Room 1\\; Building A\\, 2nd floor\\nC:\\\\temp");
+        assert_eq!(unescape_text(&escaped), text);
+        assert_eq!(unescape_text("a\\Nb\\xc\\"), "a\nb\\xc\\");
+    }
+
+    #[test]
+    fn test_text_lists() {
+        assert_eq!(
+            split_text_list("Work,Project\\, Phase 2,,Back\\\\slash"),
+            ["Work", "Project, Phase 2", "Back\\slash"]
+        );
+        let values = ["a,b", "c;d"];
+        assert_eq!(join_text_list(&values), "a\\,b,c\\;d");
+        assert_eq!(split_text_list(&join_text_list(&values)), values);
+    }
+
+    #[test]
+    fn test_property_edge_cases() {
+        let prop = Property::parse("URL;VALUE=URI:").unwrap();
+        assert_eq!(prop.name, "URL");
+        assert_eq!(prop.value, "");
+        assert_eq!(prop.attributes.get("VALUE").unwrap(), "URI");
+        assert_eq!(prop.serialize(), "URL;VALUE=URI:");
+
+        let prop = Property::parse("X-FOO;BAR;;BAZ=:value").unwrap();
+        assert_eq!(prop.value, "value");
+        assert_eq!(prop.attributes.len(), 2);
+        assert_eq!(prop.attributes.get("BAR").unwrap(), "");
+        assert_eq!(prop.attributes.get("BAZ").unwrap(), "");
+
+        let prop = Property::parse(
+            r#"X-APPLE-STRUCTURED-LOCATION;X-TITLE="Foo; Bar: Baz";VALUE=URI:geo:1,2"#,
+        )
+        .unwrap();
+        assert_eq!(prop.value, "geo:1,2");
+        assert_eq!(
+            prop.attributes.get("X-TITLE").unwrap(),
+            r#""Foo; Bar: Baz""#
+        );
+        assert_eq!(prop.attributes.get("VALUE").unwrap(), "URI");
+
+        let ical = Ical::parse(&mut LineIterator::new(
+            "BEGIN:VEVENT\nUID:1\nURL;VALUE=URI:\nX-APPLE-TRAVEL-ADVISORY-BEHAVIOR:DISABLED\nEND:VEVENT",
+        ))
+        .unwrap();
+        let url = ical.get_first_property("URL").unwrap();
+        assert_eq!(url.value, "");
+        assert_eq!(url.attributes.get("VALUE").unwrap(), "URI");
+    }
 }
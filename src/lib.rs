@@ -46,6 +46,12 @@
 //!     }
 //! }
 //! ```
+//!
+//! # Thread safety
+//!
+//! All public types are `Send` and `Sync`. Parsed events can be moved to and shared between
+//! threads, e.g. to process them on a thread pool. A [`Client`] can be cloned cheaply and shared
+//! between tasks, clones use the same connection pool and rate limit.
 
 #[cfg(any(feature = "caldav", feature = "ical"))]
 #[macro_use]
@@ -60,10 +66,78 @@ pub mod caldav;
 #[cfg(feature = "caldav")]
 pub use api::*;
 
+#[cfg(feature = "caldav")]
+mod color;
+#[cfg(feature = "caldav")]
+pub use color::{CalendarColor, ColorFormat};
+
+#[cfg(feature = "caldav")]
+mod privileges;
+#[cfg(feature = "caldav")]
+pub use privileges::{Privilege, Privileges};
+
+#[cfg(feature = "caldav")]
+mod client;
+#[cfg(feature = "caldav")]
+pub use client::{
+    Client, ClientBuilder, Proxy, RedirectPolicy, RequestBuilder, RetryPolicy, DEFAULT_USER_AGENT,
+};
+
+#[cfg(feature = "pinning")]
+pub mod pinning;
+
 #[cfg(feature = "caldav")]
 mod xml_templates;
 
+#[cfg(feature = "caldav")]
+pub mod watch;
+
+#[cfg(feature = "caldav")]
+pub mod simple;
+
+#[cfg(feature = "caldav")]
+pub mod conformance;
+
+#[cfg(feature = "caldav")]
+pub mod transform;
+
+#[cfg(feature = "caldav")]
+pub mod mirror;
+
+#[cfg(feature = "caldav")]
+pub mod cache;
+
+#[cfg(feature = "caldav")]
+pub mod offline;
+
+#[cfg(feature = "caldav")]
+mod util;
+
+#[cfg(feature = "carddav")]
+pub mod carddav;
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
+#[cfg(feature = "stream")]
+pub mod stream;
+
+#[cfg(feature = "nextcloud")]
+pub mod nextcloud;
+
 #[cfg(feature = "ical")]
 pub mod ical;
 
+#[cfg(feature = "ical")]
+pub mod windows_tz;
+
+#[cfg(feature = "tz")]
+pub mod tz;
+
+#[cfg(feature = "tz")]
+pub mod recurrence;
+
 mod credentials;
+
+#[cfg(all(test, feature = "caldav"))]
+mod test_server;
@@ -0,0 +1,442 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! One-way mirroring of the events of a calendar into another calendar, e.g. to publish busy times.
+//!
+//! Mirrored events get a UID derived from the UID of the source event and are marked with
+//! `X-MINICALDAV-MIRROR-OF`, so later runs update and delete them. Other events of the target
+//! calendar are not touched.
+//!
+//! ```rust,no_run
+//! # async fn run(private: minicaldav::Calendar, shared: minicaldav::Calendar) {
+//! let client = minicaldav::Client::new();
+//! let credentials = minicaldav::Credentials::Basic("foo".into(), "s3cret!".into());
+//! let options = minicaldav::mirror::MirrorOptions::busy_only();
+//! let report = minicaldav::mirror::mirror(&client, &credentials, &private, &shared, &options)
+//!     .await
+//!     .unwrap();
+//! println!("{} created, {} updated, {} deleted", report.created, report.updated, report.deleted);
+//! # }
+//! ```
+
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::caldav;
+use crate::client::Client;
+use crate::errors::MiniCaldavError;
+use crate::ical::{Ical, Property};
+use crate::util::stable_hash;
+use crate::{Calendar, Credentials, Event};
+
+/// The property marking mirrored events with the key of their source event.
+pub const MIRROR_OF_PROPERTY: &str = "X-MINICALDAV-MIRROR-OF";
+
+/// The property holding the hash of the mirrored content, to skip unchanged events.
+const MIRROR_HASH_PROPERTY: &str = "X-MINICALDAV-MIRROR-HASH";
+
+/// The properties kept in busy blocks.
+const BUSY_PROPERTIES: &[&str] = &[
+    "DTSTAMP",
+    "DTSTART",
+    "DTEND",
+    "DURATION",
+    "RRULE",
+    "RDATE",
+    "EXDATE",
+    "RECURRENCE-ID",
+];
+
+/// How `mirror` copies events.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MirrorOptions {
+    /// Only copy the times of events, with `busy_summary` as SUMMARY.
+    /// Transparent and cancelled events are skipped, such instances of recurring events are
+    /// excluded with EXDATE.
+    pub busy_only: bool,
+    pub busy_summary: String,
+    /// Only mirror events with an instance in this time range, e.g. `20240101T000000Z`.
+    /// Mirrored events outside of the range are deleted.
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+impl Default for MirrorOptions {
+    fn default() -> Self {
+        Self {
+            busy_only: false,
+            busy_summary: "Busy".to_string(),
+            start: None,
+            end: None,
+        }
+    }
+}
+
+impl MirrorOptions {
+    /// Mirror events as busy blocks.
+    pub fn busy_only() -> Self {
+        Self {
+            busy_only: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// The changes made by `mirror`.
+#[derive(Debug, Default)]
+pub struct MirrorReport {
+    pub created: usize,
+    pub updated: usize,
+    pub deleted: usize,
+    pub unchanged: usize,
+    /// Events that could not be fetched, parsed or written. The other events are still mirrored.
+    pub errors: Vec<MiniCaldavError>,
+}
+
+/// Copy the events of `source` into `target`, updating the events mirrored by earlier runs.
+/// ORGANIZER and ATTENDEE are never copied, so the server does not send invitations for the copies.
+pub async fn mirror(
+    client: &Client,
+    credentials: &Credentials,
+    source: &Calendar,
+    target: &Calendar,
+    options: &MirrorOptions,
+) -> Result<MirrorReport, MiniCaldavError> {
+    let mut report = MirrorReport::default();
+    let (source_events, errors) = if options.start.is_some() || options.end.is_some() {
+        // get_events only applies a time range to expanded events.
+        let query =
+            caldav::CalendarQuery::events().time_range(options.start.clone(), options.end.clone());
+        let (event_refs, resource_errors) =
+            caldav::query(client, credentials, source.base_url(), source.url(), &query).await?;
+        crate::parse_event_refs(
+            client,
+            event_refs,
+            resource_errors,
+            MiniCaldavError::CouldNotParseEvent,
+        )
+    } else {
+        crate::get_events(client, credentials, source, None, None, false).await?
+    };
+    report.errors.extend(errors);
+    let (target_events, errors) =
+        crate::get_events(client, credentials, target, None, None, false).await?;
+    report.errors.extend(errors);
+
+    let mut mirrored: HashMap<String, Event> = target_events
+        .into_iter()
+        .filter_map(|event| Some((mirror_key(event.ical())?, event)))
+        .collect();
+
+    for source_event in source_events {
+        let Some((key, ical)) = mirror_ical(source_event.ical(), options) else {
+            continue;
+        };
+        match mirrored.remove(&key) {
+            Some(existing) if mirror_hash(existing.ical()) == mirror_hash(&ical) => {
+                report.unchanged += 1
+            }
+            Some(mut existing) => {
+                *existing.ical_mut() = ical;
+                match crate::save_event(client, credentials, existing).await {
+                    Ok(_) => report.updated += 1,
+                    Err(e) => report.errors.push(e),
+                }
+            }
+            None => {
+                let event = Event::new(None, target.url().clone(), ical);
+                match crate::create_event(client, credentials, target, event).await {
+                    Ok(_) => report.created += 1,
+                    Err(e) => report.errors.push(e),
+                }
+            }
+        }
+    }
+
+    for (_, event) in mirrored {
+        match crate::remove_event(client, credentials, event).await {
+            Ok(()) => report.deleted += 1,
+            Err(e) => report.errors.push(e),
+        }
+    }
+    Ok(report)
+}
+
+/// The key of the source event a mirrored event was copied from.
+fn mirror_key(ical: &Ical) -> Option<String> {
+    ical.children
+        .iter()
+        .find_map(|c| c.get_first_property(MIRROR_OF_PROPERTY))
+        .map(|p| p.value.clone())
+}
+
+fn mirror_hash(ical: &Ical) -> Option<String> {
+    ical.children
+        .iter()
+        .find_map(|c| c.get_first_property(MIRROR_HASH_PROPERTY))
+        .map(|p| p.value.clone())
+}
+
+/// The object to store in the target calendar for the given source object with the key of the
+/// source event, or `None` if it is not mirrored.
+fn mirror_ical(source: &Ical, options: &MirrorOptions) -> Option<(String, Ical)> {
+    let uid = source
+        .children
+        .iter()
+        .filter(|c| c.name == "VEVENT")
+        .find_map(|c| c.get_first_property("UID"))?
+        .value
+        .clone();
+    let key = stable_hash(&uid);
+
+    // Instances of recurring events that do not block time, excluded from the busy blocks.
+    let mut exdates = Vec::new();
+
+    let mut ical = Ical::new(source.name.clone());
+    ical.properties = source
+        .properties
+        .iter()
+        .filter(|p| p.name == "VERSION" || p.name == "CALSCALE")
+        .cloned()
+        .collect();
+    for component in &source.children {
+        match component.name.as_str() {
+            "VTIMEZONE" => ical.children.push(component.clone()),
+            "VEVENT" => {
+                let property = |name: &str| {
+                    component
+                        .get_first_property(name)
+                        .map(|p| p.value.trim().to_ascii_uppercase())
+                };
+                let blocks_time = property("TRANSP").as_deref() != Some("TRANSPARENT")
+                    && property("STATUS").as_deref() != Some("CANCELLED");
+                if options.busy_only && !blocks_time {
+                    if let Some(recurrence_id) = component.get_first_property("RECURRENCE-ID") {
+                        let mut exdate = recurrence_id.clone();
+                        exdate.name = "EXDATE".into();
+                        exdates.push(exdate);
+                    }
+                    continue;
+                }
+                let mut event = Ical::new(component.name.clone());
+                event
+                    .properties
+                    .push(Property::new("UID", &format!("{}@minicaldav-mirror", key)));
+                if options.busy_only {
+                    event.properties.extend(
+                        component
+                            .properties
+                            .iter()
+                            .filter(|p| BUSY_PROPERTIES.contains(&p.name.as_str()))
+                            .cloned(),
+                    );
+                    event
+                        .properties
+                        .push(Property::new("SUMMARY", &options.busy_summary));
+                    event.properties.push(Property::new("CLASS", "PRIVATE"));
+                } else {
+                    event.properties.extend(
+                        component
+                            .properties
+                            .iter()
+                            .filter(|p| {
+                                !matches!(p.name.as_str(), "UID" | "ORGANIZER" | "ATTENDEE")
+                            })
+                            .cloned(),
+                    );
+                    event.children = component.children.clone();
+                }
+                ical.children.push(event);
+            }
+            _ => {}
+        }
+    }
+    if !ical.children.iter().any(|c| c.name == "VEVENT") {
+        return None;
+    }
+    if let Some(master) = ical
+        .children
+        .iter_mut()
+        .find(|c| c.name == "VEVENT" && c.get_first_property("RECURRENCE-ID").is_none())
+    {
+        master.properties.extend(exdates);
+    }
+
+    let mut content = String::new();
+    canonical_form(&ical, &mut content);
+    let hash = stable_hash(&content);
+    for event in ical.children.iter_mut().filter(|c| c.name == "VEVENT") {
+        event
+            .properties
+            .push(Property::new(MIRROR_OF_PROPERTY, &key));
+        event
+            .properties
+            .push(Property::new(MIRROR_HASH_PROPERTY, &hash));
+    }
+    Some((key, ical))
+}
+
+/// Write the object without DTSTAMP and with sorted parameters, which are kept in a `HashMap`,
+/// so equal objects have the same form.
+fn canonical_form(ical: &Ical, out: &mut String) {
+    out.push_str(&format!("BEGIN:{}\n", ical.name));
+    for property in ical.properties.iter().filter(|p| p.name != "DTSTAMP") {
+        let mut params = property.attributes.iter().collect::<Vec<_>>();
+        params.sort();
+        out.push_str(&property.name);
+        for (name, value) in params {
+            out.push_str(&format!(";{}={}", name, value));
+        }
+        out.push_str(&format!(":{}\n", property.value));
+    }
+    for child in &ical.children {
+        canonical_form(child, out);
+    }
+    out.push_str(&format!("END:{}\n", ical.name));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(data: &str) -> Ical {
+        Ical::parse(&mut crate::ical::LineIterator::new(data)).unwrap()
+    }
+
+    #[test]
+    fn test_mirror_ical() {
+        let source = parse(
+            "BEGIN:VCALENDAR\nVERSION:2.0\nPRODID:other\nBEGIN:VEVENT\nUID:secret-meeting\nDTSTAMP:20240101T000000Z\nDTSTART:20240102T100000Z\nDTEND:20240102T110000Z\nSUMMARY:Secret\nATTENDEE:mailto:bob@example.com\nEND:VEVENT\nBEGIN:VEVENT\nUID:secret-meeting\nRECURRENCE-ID:20240103T100000Z\nDTSTART:20240103T100000Z\nTRANSP:TRANSPARENT\nEND:VEVENT\nEND:VCALENDAR\n",
+        );
+        let (key, busy) = mirror_ical(&source, &MirrorOptions::busy_only()).unwrap();
+        assert_eq!(busy.children.len(), 1);
+        let event = &busy.children[0];
+        assert_eq!(
+            event.get_first_property("UID").unwrap().value,
+            format!("{}@minicaldav-mirror", key)
+        );
+        assert_eq!(event.get_first_property("SUMMARY").unwrap().value, "Busy");
+        assert!(event.get_first_property("ATTENDEE").is_none());
+        assert!(!busy.serialize().contains("secret"));
+        assert_eq!(mirror_key(&busy), Some(key.clone()));
+
+        // The key and hash do not change between runs or with DTSTAMP.
+        let restamped = parse(
+            &source
+                .serialize()
+                .replace("DTSTAMP:20240101T000000Z", "DTSTAMP:20240201T000000Z"),
+        );
+        let (_, again) = mirror_ical(&restamped, &MirrorOptions::busy_only()).unwrap();
+        assert_eq!(mirror_hash(&again), mirror_hash(&busy));
+        assert_eq!(key, stable_hash("secret-meeting"));
+
+        let (_, full) = mirror_ical(&source, &MirrorOptions::default()).unwrap();
+        assert_eq!(full.children.len(), 2);
+        assert_eq!(
+            full.children[0]
+                .get_first_property("SUMMARY")
+                .unwrap()
+                .value,
+            "Secret"
+        );
+        assert!(full.children[0].get_first_property("ATTENDEE").is_none());
+        assert_ne!(mirror_hash(&full), mirror_hash(&busy));
+
+        let todo = parse("BEGIN:VCALENDAR\nBEGIN:VTODO\nUID:a\nEND:VTODO\nEND:VCALENDAR\n");
+        assert!(mirror_ical(&todo, &MirrorOptions::default()).is_none());
+    }
+
+    #[test]
+    fn test_mirror_recurring_busy() {
+        let source = parse(
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nDTSTART;TZID=Europe/Berlin:20240102T100000\nRRULE:FREQ=DAILY\nEND:VEVENT\nBEGIN:VEVENT\nUID:a\nRECURRENCE-ID;TZID=Europe/Berlin:20240103T100000\nDTSTART;TZID=Europe/Berlin:20240103T100000\nSTATUS:CANCELLED\nEND:VEVENT\nEND:VCALENDAR\n",
+        );
+        let (_, busy) = mirror_ical(&source, &MirrorOptions::busy_only()).unwrap();
+        assert_eq!(busy.children.len(), 1);
+        let exdate = busy.children[0].get_first_property("EXDATE").unwrap();
+        assert_eq!(exdate.value, "20240103T100000");
+        assert_eq!(exdate.attributes.get("TZID").unwrap(), "Europe/Berlin");
+    }
+
+    #[test]
+    fn test_mirror_hash_parameter_order() {
+        let event = |params: &str| {
+            parse(&format!(
+                "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nDTSTART{}:20240102T100000\nEND:VEVENT\nEND:VCALENDAR\n",
+                params
+            ))
+        };
+        let hash =
+            |ical: &Ical| mirror_hash(&mirror_ical(ical, &MirrorOptions::default()).unwrap().1);
+        let a = event(";TZID=Europe/Berlin;VALUE=DATE-TIME");
+        assert_eq!(
+            hash(&a),
+            hash(&event(";VALUE=DATE-TIME;TZID=Europe/Berlin"))
+        );
+        assert_ne!(hash(&a), hash(&event(";TZID=Europe/Paris;VALUE=DATE-TIME")));
+    }
+
+    #[tokio::test]
+    async fn test_mirror_time_range() {
+        use crate::test_server::{Response, TestServer};
+
+        let mirrored = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:x@minicaldav-mirror\nX-MINICALDAV-MIRROR-OF:x\nEND:VEVENT\nEND:VCALENDAR\n";
+        let server = TestServer::start(vec![
+            Response::multistatus(r#"<d:multistatus xmlns:d="DAV:"/>"#),
+            Response::multistatus(&format!(
+                r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+                    <d:response><d:href>/target/x.ics</d:href><d:propstat>
+                        <d:prop><d:getetag>"1"</d:getetag><c:calendar-data>{}</c:calendar-data></d:prop>
+                        <d:status>HTTP/1.1 200 OK</d:status>
+                    </d:propstat></d:response>
+                </d:multistatus>"#,
+                mirrored
+            )),
+            Response::new(204),
+        ]);
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let options = MirrorOptions {
+            start: Some("20240101T000000Z".into()),
+            end: Some("20240201T000000Z".into()),
+            ..MirrorOptions::busy_only()
+        };
+        let report = mirror(
+            &client,
+            &credentials,
+            &server.calendar("/source/"),
+            &server.calendar("/target/"),
+            &options,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.deleted, 1);
+        assert!(report.errors.is_empty());
+
+        let requests = server.requests();
+        assert!(requests[0]
+            .body
+            .contains(r#"<c:time-range start="20240101T000000Z" end="20240201T000000Z""#));
+        assert_eq!(requests[2].method, "DELETE");
+        assert_eq!(requests[2].url, "/target/x.ics");
+    }
+}
@@ -0,0 +1,334 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Nextcloud specific extensions: the calendar trashbin.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use reqwest::Method;
+
+use crate::caldav;
+use crate::client::Client;
+use crate::credentials::Credentials;
+use crate::errors::{CheckStatus, MiniCaldavError};
+
+pub static TRASHBIN_OBJECTS_REQUEST: &str = r#"
+<d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav" xmlns:nc="http://nextcloud.com/ns">
+    <d:prop>
+        <d:getetag />
+        <c:calendar-data />
+        <nc:trashbin-deleted-at />
+        <nc:calendar-uri />
+    </d:prop>
+</d:propfind>
+"#;
+
+pub static TRASHBIN_CALENDARS_REQUEST: &str = r#"
+<d:propfind xmlns:d="DAV:" xmlns:nc="http://nextcloud.com/ns">
+    <d:prop>
+        <d:displayname />
+        <d:resourcetype />
+        <nc:trashbin-deleted-at />
+    </d:prop>
+</d:propfind>
+"#;
+
+/// A calendar object in the Nextcloud trashbin.
+#[derive(Debug, Clone)]
+pub struct DeletedObjectRef {
+    pub event_ref: caldav::EventRef,
+    /// The uri of the calendar the object was deleted from.
+    pub calendar_uri: Option<String>,
+    /// The time of deletion as sent by the server.
+    pub deleted_at: Option<String>,
+}
+
+/// A calendar in the Nextcloud trashbin.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct DeletedCalendarRef {
+    pub url: Url,
+    pub name: Option<String>,
+    /// The time of deletion as sent by the server.
+    pub deleted_at: Option<String>,
+}
+
+/// Get the url of the trashbin collection of the calendar home of the given credentials.
+pub async fn get_trashbin_url(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: &Url,
+) -> Result<Url, MiniCaldavError> {
+    let homeset_url = caldav::discover_home_set_url(client, credentials, base_url).await?;
+
+    Ok(homeset_url.join("trashbin/")?)
+}
+
+/// List all deleted calendar objects in the trashbin.
+pub async fn get_deleted_objects(
+    client: &Client,
+    credentials: &Credentials,
+    trashbin_url: &Url,
+) -> Result<Vec<DeletedObjectRef>, MiniCaldavError> {
+    let objects_url = trashbin_url.join("objects/")?;
+    let root = caldav::propfind_get(
+        client,
+        credentials,
+        &objects_url,
+        TRASHBIN_OBJECTS_REQUEST.to_string(),
+        &[],
+        "1",
+    )
+    .await?
+    .1;
+
+    Ok(parse_deleted_objects(client, trashbin_url, &root))
+}
+
+/// List all deleted calendars in the trashbin.
+pub async fn get_deleted_calendars(
+    client: &Client,
+    credentials: &Credentials,
+    trashbin_url: &Url,
+) -> Result<Vec<DeletedCalendarRef>, MiniCaldavError> {
+    let calendars_url = trashbin_url.join("calendars/")?;
+    let root = caldav::propfind_get(
+        client,
+        credentials,
+        &calendars_url,
+        TRASHBIN_CALENDARS_REQUEST.to_string(),
+        &[],
+        "1",
+    )
+    .await?
+    .1;
+
+    Ok(parse_deleted_calendars(client, trashbin_url, &root))
+}
+
+/// Restore the deleted object or calendar at the given url by moving it to the restore collection of the trashbin.
+pub async fn restore(
+    client: &Client,
+    credentials: &Credentials,
+    trashbin_url: &Url,
+    url: &Url,
+) -> Result<(), MiniCaldavError> {
+    let name = url
+        .path_segments()
+        .and_then(|segments| segments.rev().find(|s| !s.is_empty()))
+        .ok_or_else(|| MiniCaldavError::CouldNotJoinUrl(url.to_string()))?;
+    let destination = trashbin_url.join(&format!("restore/{}", name))?;
+
+    client
+        .request(Method::from_bytes(b"MOVE").unwrap(), url.as_str())
+        .authorization(credentials)
+        .header("Destination", destination.as_str())
+        .send()
+        .await?
+        .check_status()
+        .await?;
+
+    Ok(())
+}
+
+/// The property `name` of the given response, from a propstat with a successful status.
+fn prop<'a>(response: &'a xmltree::Element, name: &str) -> Option<&'a xmltree::Element> {
+    response
+        .children
+        .iter()
+        .filter_map(|c| c.as_element())
+        .filter(|e| e.name == "propstat")
+        .filter(|p| {
+            p.get_child("status")
+                .and_then(|s| s.get_text())
+                .and_then(|s| caldav::parse_status_code(&s))
+                .map(|s| (200..300).contains(&s))
+                .unwrap_or(true)
+        })
+        .filter_map(|p| p.get_child("prop"))
+        .find_map(|p| p.get_child(name))
+}
+
+fn text(response: &xmltree::Element, name: &str) -> Option<String> {
+    prop(response, name)
+        .and_then(|e| e.get_text())
+        .map(|t| t.to_string())
+}
+
+/// Resolve the href of the given response, responses with an invalid href are skipped.
+fn response_url(client: &Client, trashbin_url: &Url, response: &xmltree::Element) -> Option<Url> {
+    let href = response.get_child("href").and_then(|e| e.get_text())?;
+    match client.resolve_href(trashbin_url, &href) {
+        Ok(url) => Some(url),
+        Err(_) => {
+            error!("Could not parse url {}/{}", trashbin_url, href);
+            None
+        }
+    }
+}
+
+fn parse_deleted_objects(
+    client: &Client,
+    trashbin_url: &Url,
+    root: &xmltree::Element,
+) -> Vec<DeletedObjectRef> {
+    let mut objects = Vec::new();
+    for response in root.children.iter().filter_map(|c| c.as_element()) {
+        let Some(data) = text(response, "calendar-data") else {
+            continue;
+        };
+        let Some(url) = response_url(client, trashbin_url, response) else {
+            continue;
+        };
+        objects.push(DeletedObjectRef {
+            event_ref: caldav::EventRef {
+                url,
+                etag: text(response, "getetag"),
+                data,
+                schedule_tag: None,
+            },
+            calendar_uri: text(response, "calendar-uri"),
+            deleted_at: text(response, "trashbin-deleted-at"),
+        });
+    }
+    objects
+}
+
+fn parse_deleted_calendars(
+    client: &Client,
+    trashbin_url: &Url,
+    root: &xmltree::Element,
+) -> Vec<DeletedCalendarRef> {
+    let mut calendars = Vec::new();
+    for response in root.children.iter().filter_map(|c| c.as_element()) {
+        let is_calendar = prop(response, "resourcetype")
+            .map(|e| e.get_child("calendar").is_some())
+            .unwrap_or(false);
+        if !is_calendar {
+            continue;
+        }
+        let Some(url) = response_url(client, trashbin_url, response) else {
+            continue;
+        };
+        calendars.push(DeletedCalendarRef {
+            url,
+            name: text(response, "displayname"),
+            deleted_at: text(response, "trashbin-deleted-at"),
+        });
+    }
+    calendars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_deleted_objects() {
+        let xml = r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav" xmlns:nc="http://nextcloud.com/ns">
+            <d:response>
+                <d:href>/remote.php/dav/calendars/foo/trashbin/objects/1-a.ics</d:href>
+                <d:propstat>
+                    <d:prop><nc:calendar-uri /></d:prop>
+                    <d:status>HTTP/1.1 404 Not Found</d:status>
+                </d:propstat>
+                <d:propstat>
+                    <d:prop>
+                        <d:getetag>"1"</d:getetag>
+                        <c:calendar-data>BEGIN:VCALENDAR</c:calendar-data>
+                        <nc:trashbin-deleted-at>2024-01-01T10:00:00+00:00</nc:trashbin-deleted-at>
+                    </d:prop>
+                    <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat>
+            </d:response>
+            <d:response>
+                <d:href>webcal://example.com/b.ics</d:href>
+                <d:propstat>
+                    <d:prop><c:calendar-data>BEGIN:VCALENDAR</c:calendar-data></d:prop>
+                    <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat>
+            </d:response>
+            <d:response>
+                <d:href>/remote.php/dav/calendars/foo/trashbin/objects/2-c.ics</d:href>
+                <d:propstat>
+                    <d:prop><c:calendar-data>BEGIN:VCALENDAR</c:calendar-data><nc:calendar-uri>work</nc:calendar-uri></d:prop>
+                    <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat>
+            </d:response>
+        </d:multistatus>"#;
+        let trashbin_url =
+            Url::parse("https://example.com/remote.php/dav/calendars/foo/trashbin/").unwrap();
+        let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        let objects = parse_deleted_objects(&Client::new(), &trashbin_url, &root);
+        assert_eq!(objects.len(), 2);
+        assert_eq!(
+            objects[0].event_ref.url,
+            trashbin_url.join("objects/1-a.ics").unwrap()
+        );
+        assert_eq!(objects[0].event_ref.etag.as_deref(), Some("\"1\""));
+        assert_eq!(objects[0].event_ref.data, "BEGIN:VCALENDAR");
+        assert_eq!(objects[0].calendar_uri, None);
+        assert_eq!(
+            objects[0].deleted_at.as_deref(),
+            Some("2024-01-01T10:00:00+00:00")
+        );
+        assert_eq!(
+            objects[1].event_ref.url,
+            trashbin_url.join("objects/2-c.ics").unwrap()
+        );
+        assert_eq!(objects[1].calendar_uri.as_deref(), Some("work"));
+    }
+
+    #[test]
+    fn test_parse_deleted_calendars() {
+        let xml = r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav" xmlns:nc="http://nextcloud.com/ns">
+            <d:response>
+                <d:href>/remote.php/dav/calendars/foo/trashbin/calendars/</d:href>
+                <d:propstat>
+                    <d:prop><d:resourcetype><d:collection /></d:resourcetype></d:prop>
+                    <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat>
+            </d:response>
+            <d:response>
+                <d:href>/remote.php/dav/calendars/foo/trashbin/calendars/work-deleted/</d:href>
+                <d:propstat>
+                    <d:prop><nc:trashbin-deleted-at /></d:prop>
+                    <d:status>HTTP/1.1 404 Not Found</d:status>
+                </d:propstat>
+                <d:propstat>
+                    <d:prop>
+                        <d:displayname>Work</d:displayname>
+                        <d:resourcetype><d:collection /><c:calendar /></d:resourcetype>
+                    </d:prop>
+                    <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat>
+            </d:response>
+        </d:multistatus>"#;
+        let trashbin_url =
+            Url::parse("https://example.com/remote.php/dav/calendars/foo/trashbin/").unwrap();
+        let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        let calendars = parse_deleted_calendars(&Client::new(), &trashbin_url, &root);
+        assert_eq!(calendars.len(), 1);
+        assert_eq!(
+            calendars[0].url,
+            trashbin_url.join("calendars/work-deleted/").unwrap()
+        );
+        assert_eq!(calendars[0].name.as_deref(), Some("Work"));
+        assert_eq!(calendars[0].deleted_at, None);
+    }
+}
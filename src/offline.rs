@@ -0,0 +1,380 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Queue of changes made while the server is unreachable, replayed once it is reachable again.
+//!
+//! Updates and deletions are replayed with `If-Match`, so changes made on the server in the
+//! meantime are reported as conflicts instead of being overwritten.
+//!
+//! ```rust,no_run
+//! # async fn run(event: minicaldav::Event) {
+//! use minicaldav::offline::OfflineQueue;
+//!
+//! let client = minicaldav::Client::new();
+//! let credentials = minicaldav::Credentials::Basic("foo".into(), "s3cret!".into());
+//! let queue = OfflineQueue::new();
+//! match minicaldav::save_event(&client, &credentials, event.clone()).await {
+//!     Err(e) if e.is_offline() => queue.update_event(event),
+//!     result => println!("{:?}", result),
+//! }
+//! // Later, when the server is reachable again:
+//! let report = queue.replay(&client, &credentials).await;
+//! for conflict in report.conflicts {
+//!     println!("Changed on the server in the meantime: {:?}", conflict);
+//! }
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::caldav::{self, EventRef};
+use crate::client::Client;
+use crate::errors::MiniCaldavError::{self, *};
+use crate::{Calendar, Credentials, Event, MethodHandling, ValidationIssue};
+
+/// A change waiting to be sent to the server.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub enum QueuedOperation {
+    /// Create the event in the calendar.
+    Create {
+        calendar: Box<Calendar>,
+        event: Event,
+    },
+    /// Save the event, if it was not changed on the server since it was fetched.
+    Update(Event),
+    /// Delete the event, if it was not changed on the server since it was fetched.
+    Delete(Event),
+}
+
+impl QueuedOperation {
+    pub fn event(&self) -> &Event {
+        match self {
+            Self::Create { event, .. } | Self::Update(event) | Self::Delete(event) => event,
+        }
+    }
+}
+
+/// The result of `OfflineQueue::replay`.
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    /// The created and updated events as stored on the server, with their new etags.
+    pub saved: Vec<Event>,
+    pub deleted: usize,
+    /// Operations that were not applied because the event was changed on the server, or an
+    /// event with the same url was created. Fetch the event again and decide how to merge.
+    pub conflicts: Vec<QueuedOperation>,
+    /// Operations the server rejected for other reasons.
+    pub errors: Vec<(QueuedOperation, MiniCaldavError)>,
+    /// Operations still queued because the server became unreachable during the replay.
+    pub pending: usize,
+}
+
+/// Changes recorded while offline, see the module documentation.
+/// Several changes of the same event are combined, e.g. an event created and deleted while offline
+/// is never sent to the server.
+#[derive(Debug, Default)]
+pub struct OfflineQueue {
+    operations: Mutex<VecDeque<QueuedOperation>>,
+}
+
+impl OfflineQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restore a queue from operations returned by `operations`, e.g. after a restart.
+    pub fn from_operations(operations: Vec<QueuedOperation>) -> Self {
+        Self {
+            operations: Mutex::new(operations.into()),
+        }
+    }
+
+    /// The queued operations in the order they are replayed.
+    pub fn operations(&self) -> Vec<QueuedOperation> {
+        self.operations.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.lock().unwrap().is_empty()
+    }
+
+    /// Queue the creation of the event, returning it with the url it will get on the server.
+    /// Pass the returned event to `update_event` and `remove_event` for further changes.
+    pub fn create_event(
+        &self,
+        calendar: &Calendar,
+        event: Event,
+    ) -> Result<Event, MiniCaldavError> {
+        let uid = event
+            .ical()
+            .children
+            .iter()
+            .find_map(|c| c.get_first_property("UID"))
+            .map(|p| p.value.clone())
+            .ok_or_else(|| {
+                InvalidObject(
+                    ValidationIssue::MissingProperty {
+                        component: "VEVENT".into(),
+                        property: "UID".into(),
+                    }
+                    .to_string(),
+                )
+            })?;
+        let url = crate::resource_url(calendar.url(), &uid)?;
+        let event = Event::new(None, url, event.ical().clone());
+        self.push(QueuedOperation::Create {
+            calendar: Box::new(calendar.clone()),
+            event: event.clone(),
+        });
+        Ok(event)
+    }
+
+    /// Queue saving the event, which keeps the etag it was fetched with.
+    pub fn update_event(&self, event: Event) {
+        self.push(QueuedOperation::Update(event));
+    }
+
+    /// Queue deleting the event.
+    pub fn remove_event(&self, event: Event) {
+        self.push(QueuedOperation::Delete(event));
+    }
+
+    fn push(&self, operation: QueuedOperation) {
+        push_combined(&mut self.operations.lock().unwrap(), operation);
+    }
+
+    /// Send the queued operations to the server in order.
+    /// Stops when the server is unreachable or the outcome of a request is unknown,
+    /// the remaining operations stay queued for the next replay.
+    pub async fn replay(&self, client: &Client, credentials: &Credentials) -> ReplayReport {
+        let mut report = ReplayReport::default();
+        loop {
+            // The lock is not held during the request, operations may be queued meanwhile.
+            let Some(operation) = self.operations.lock().unwrap().pop_front() else {
+                break;
+            };
+            let result = match &operation {
+                QueuedOperation::Create { calendar, event } => {
+                    crate::create_event(client, credentials, calendar, event.clone())
+                        .await
+                        .map(Some)
+                }
+                QueuedOperation::Update(event) => crate::put_event(
+                    client,
+                    credentials,
+                    event.clone(),
+                    MethodHandling::Strip,
+                    true,
+                )
+                .await
+                .map(Some),
+                QueuedOperation::Delete(event) => {
+                    let event_ref = EventRef {
+                        data: String::new(),
+                        etag: event.etag().cloned(),
+                        url: event.url().clone(),
+                        schedule_tag: None,
+                    };
+                    caldav::remove_event_if_match(client, credentials, event_ref)
+                        .await
+                        .map(|_| None)
+                }
+            };
+            match result {
+                Err(e) if e.is_offline() || e.is_unknown_outcome() => {
+                    debug!("Stopping replay of offline changes: {:?}", e);
+                    self.operations.lock().unwrap().push_front(operation);
+                    break;
+                }
+                Ok(Some(event)) => report.saved.push(event),
+                Ok(None) => report.deleted += 1,
+                Err(PreconditionFailed(_)) | Err(AlreadyExists(_)) => {
+                    report.conflicts.push(operation)
+                }
+                Err(e) => report.errors.push((operation, e)),
+            }
+        }
+        report.pending = self.len();
+        report
+    }
+}
+
+/// Add the operation to the queue, combining it with a queued operation on the same event.
+fn push_combined(operations: &mut VecDeque<QueuedOperation>, operation: QueuedOperation) {
+    let url = operation.event().url().clone();
+    let queued = operations
+        .iter()
+        .position(|o| o.event().url() == &url && !matches!(o, QueuedOperation::Delete(_)));
+    let Some(index) = queued else {
+        operations.push_back(operation);
+        return;
+    };
+    match (operations.remove(index).unwrap(), operation) {
+        // The server never saw the event.
+        (QueuedOperation::Create { .. }, QueuedOperation::Delete(_)) => {}
+        (QueuedOperation::Create { calendar, .. }, QueuedOperation::Update(event)) => {
+            operations.push_back(QueuedOperation::Create { calendar, event })
+        }
+        // Keep the etag of the first update, the server still has that version.
+        (QueuedOperation::Update(previous), QueuedOperation::Update(event)) => operations
+            .push_back(QueuedOperation::Update(Event::new(
+                previous.etag().cloned(),
+                url,
+                event.ical().clone(),
+            ))),
+        (QueuedOperation::Update(previous), QueuedOperation::Delete(_)) => {
+            operations.push_back(QueuedOperation::Delete(previous))
+        }
+        (previous, operation) => {
+            operations.push_back(previous);
+            operations.push_back(operation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::Url;
+
+    fn event(etag: Option<&str>, summary: &str) -> Event {
+        let ics = format!(
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nSUMMARY:{}\nEND:VEVENT\nEND:VCALENDAR\n",
+            summary
+        );
+        let url = Url::parse("https://example.com/cal/work/a.ics").unwrap();
+        Event::from_raw(url, etag.map(|e| e.to_string()), &ics).unwrap()
+    }
+
+    fn summaries(queue: &OfflineQueue) -> Vec<String> {
+        queue
+            .operations()
+            .iter()
+            .map(|o| {
+                let kind = match o {
+                    QueuedOperation::Create { .. } => "create",
+                    QueuedOperation::Update(_) => "update",
+                    QueuedOperation::Delete(_) => "delete",
+                };
+                let summary = &o.event().ical().children[0]
+                    .get_first_property("SUMMARY")
+                    .unwrap()
+                    .value;
+                format!("{} {} {:?}", kind, summary, o.event().etag())
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        use crate::test_server::{Response, TestServer};
+
+        let server = TestServer::start(vec![Response::new(412), Response::new(204)]);
+        let client = Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let at = |url: Url, etag: &str| {
+            let event = event(Some(etag), "1");
+            Event::new(event.etag().cloned(), url, event.ical().clone())
+        };
+        let queue = OfflineQueue::new();
+        queue.update_event(at(server.join("/cal/a.ics"), "\"1\""));
+        queue.remove_event(at(server.join("/cal/b.ics"), "\"2\""));
+        let report = queue.replay(&client, &credentials).await;
+        assert!(matches!(
+            report.conflicts.as_slice(),
+            [QueuedOperation::Update(_)]
+        ));
+        assert_eq!(report.deleted, 1);
+        assert!(report.errors.is_empty());
+        assert_eq!(report.pending, 0);
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].header("If-Match"), Some("\"1\""));
+        assert_eq!(requests[1].method, "DELETE");
+        assert_eq!(requests[1].header("If-Match"), Some("\"2\""));
+
+        // Nothing listens on the port anymore: the operations stay queued.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+        let url = Url::parse(&format!("http://127.0.0.1:{}/cal/a.ics", port)).unwrap();
+        queue.update_event(at(url.clone(), "\"1\""));
+        queue.remove_event(at(url.join("b.ics").unwrap(), "\"2\""));
+        let report = queue.replay(&client, &credentials).await;
+        assert!(report.conflicts.is_empty() && report.errors.is_empty());
+        assert_eq!(report.pending, 2);
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[test]
+    fn test_push_combined() {
+        let calendar = Calendar::from_parts(
+            Url::parse("https://example.com/").unwrap(),
+            caldav::CalendarRef {
+                url: Url::parse("https://example.com/cal/work/").unwrap(),
+                name: "Work".into(),
+                color: None,
+                privileges: Vec::new(),
+                components: vec!["VEVENT".into()],
+                is_subscription: false,
+                source_url: None,
+                enabled: None,
+                order: None,
+            },
+        );
+
+        let queue = OfflineQueue::new();
+        let created = queue.create_event(&calendar, event(None, "1")).unwrap();
+        assert_eq!(created.url().as_str(), "https://example.com/cal/work/a.ics");
+        queue.update_event(event(None, "2"));
+        assert_eq!(summaries(&queue), ["create 2 None"]);
+        queue.remove_event(created);
+        assert!(queue.is_empty());
+
+        let queue = OfflineQueue::new();
+        queue.update_event(event(Some("\"1\""), "1"));
+        queue.update_event(event(None, "2"));
+        assert_eq!(summaries(&queue), ["update 2 Some(\"\\\"1\\\"\")"]);
+        queue.remove_event(event(None, "2"));
+        assert_eq!(summaries(&queue), ["delete 2 Some(\"\\\"1\\\"\")"]);
+
+        // Recreating a deleted event is a separate operation.
+        queue.create_event(&calendar, event(None, "3")).unwrap();
+        assert_eq!(queue.len(), 2);
+
+        let mut no_uid = event(None, "4");
+        no_uid.remove_property("UID");
+        let error = queue.create_event(&calendar, no_uid).unwrap_err();
+        assert!(
+            matches!(&error, InvalidObject(reason) if reason == "VEVENT without UID"),
+            "{:?}",
+            error
+        );
+        assert_eq!(queue.len(), 2);
+    }
+}
@@ -0,0 +1,239 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! TLS certificate pinning.
+//!
+//! A pinned client only accepts servers presenting a certificate that matches one of the pins,
+//! regardless of the certificate authorities trusted by the system. Connections to servers with
+//! any other certificate fail before a request is sent.
+//!
+//! ```rust,no_run
+//! use minicaldav::pinning::CertificatePin;
+//!
+//! let client = minicaldav::Client::builder()
+//!     .pin_certificate(
+//!         CertificatePin::public_key_sha256("nn6953P6b357uEKYQEgz8Jaqonhx/F3y13gANkC/Z74=").unwrap(),
+//!     )
+//!     .build()
+//!     .unwrap();
+//! ```
+
+use std::sync::Arc;
+
+use ring::digest::{digest, SHA256};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+
+use crate::errors::MiniCaldavError;
+
+/// The SHA-256 hash of a server certificate or of its public key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertificatePin {
+    /// Hash of the whole DER encoded certificate. Changes whenever the certificate is renewed.
+    Certificate([u8; 32]),
+    /// Hash of the DER encoded SubjectPublicKeyInfo. Stays the same as long as the key is reused.
+    PublicKey([u8; 32]),
+}
+
+impl CertificatePin {
+    /// Pin a certificate by its SHA-256 fingerprint in hex, with or without colons,
+    /// as printed by `openssl x509 -noout -fingerprint -sha256`.
+    pub fn certificate_sha256(fingerprint: &str) -> Option<Self> {
+        let hex = fingerprint.replace(':', "");
+        if hex.len() != 64 || !hex.is_ascii() {
+            return None;
+        }
+        let mut hash = [0; 32];
+        for (i, byte) in hash.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()?;
+        }
+        Some(Self::Certificate(hash))
+    }
+
+    /// Pin a public key by the base64 encoded SHA-256 hash of its SubjectPublicKeyInfo,
+    /// optionally prefixed with `sha256//` like the pins of curl's `--pinnedpubkey`.
+    pub fn public_key_sha256(hash: &str) -> Option<Self> {
+        let hash = hash.trim().trim_start_matches("sha256//");
+        let hash = base64::decode(hash).ok()?;
+        Some(Self::PublicKey(hash.try_into().ok()?))
+    }
+
+    fn matches(&self, certificate: &[u8]) -> bool {
+        match self {
+            Self::Certificate(hash) => digest(&SHA256, certificate).as_ref() == hash,
+            Self::PublicKey(hash) => subject_public_key_info(certificate)
+                .map(|spki| digest(&SHA256, spki).as_ref() == hash)
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Accepts the server if its certificate matches one of the pins.
+/// The certificate chain is not validated, the pin replaces the trust in certificate authorities.
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    pins: Vec<CertificatePin>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if self.pins.iter().any(|pin| pin.matches(end_entity)) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            warn!(
+                "Certificate of {:?} does not match any pinned certificate",
+                server_name
+            );
+            Err(rustls::Error::InvalidCertificate(
+                rustls::CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+/// A TLS configuration that only accepts servers matching one of the given pins.
+pub(crate) fn client_config(pins: Vec<CertificatePin>) -> Result<ClientConfig, MiniCaldavError> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| MiniCaldavError::RequestFailed(e.to_string()))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(PinnedCertVerifier { pins, provider }))
+        .with_no_client_auth();
+    Ok(config)
+}
+
+/// Split the DER element at the start of `data` into the whole element, its content and the remaining data.
+fn read_der(data: &[u8]) -> Option<(&[u8], &[u8], &[u8])> {
+    let first = *data.get(1)? as usize;
+    let (len, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let count = first & 0x7f;
+        if count == 0 || count > 4 {
+            return None;
+        }
+        let mut len = 0;
+        for i in 0..count {
+            len = (len << 8) | *data.get(2 + i)? as usize;
+        }
+        (len, 2 + count)
+    };
+    let end = header.checked_add(len)?;
+    if end > data.len() {
+        return None;
+    }
+    Some((&data[..end], &data[header..end], &data[end..]))
+}
+
+/// The DER encoded SubjectPublicKeyInfo of a X.509 certificate (RFC 5280).
+fn subject_public_key_info(certificate: &[u8]) -> Option<&[u8]> {
+    let (_, certificate, _) = read_der(certificate)?;
+    let (_, mut tbs, _) = read_der(certificate)?;
+    // The version is optional and tagged with [0].
+    if tbs.first() == Some(&0xa0) {
+        tbs = read_der(tbs)?.2;
+    }
+    // Skip serialNumber, signature, issuer, validity and subject.
+    for _ in 0..5 {
+        tbs = read_der(tbs)?.2;
+    }
+    Some(read_der(tbs)?.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CERTIFICATE: &str = "MIIBkTCCATegAwIBAgIUJu7MZ7wCmWgHmnr/CyIU1u6dBkYwCgYIKoZIzj0EAwIw
+HTEbMBkGA1UEAwwSY2FsZGF2LmV4YW1wbGUuY29tMCAXDTI2MTAxNjE4NDQwM1oY
+DzIxMjYwOTIyMTg0NDAzWjAdMRswGQYDVQQDDBJjYWxkYXYuZXhhbXBsZS5jb20w
+WTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAQI8OxsV5z2f3riNOsFNS4zwO5GRQ80
+l2klqdZ4qrJI/ZMbUXpKX3cATk9M/iBSZQF/XZa/eDG6ISM98IYyxJIfo1MwUTAd
+BgNVHQ4EFgQU3u+nfdtPKOzxYf8bHpRuSJpZ4kcwHwYDVR0jBBgwFoAU3u+nfdtP
+KOzxYf8bHpRuSJpZ4kcwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBF
+AiEAsAtQCAxT6D1SpUIJOOaWs5XhtD6Xt6neZHLjBqP24KMCID6jKt0I3trI88Bk
+PDqHhhfNU6emQU6q8neimkW1bqcW";
+
+    #[test]
+    fn test_certificate_pins() {
+        let der = base64::decode(CERTIFICATE.replace('\n', "")).unwrap();
+
+        let pin = CertificatePin::certificate_sha256(
+            "F4:E2:A8:EF:5C:D5:F0:7D:C9:47:39:74:78:F6:CA:27:B0:3A:89:45:32:BB:65:10:A8:15:07:83:F5:4A:E3:2E",
+        )
+        .unwrap();
+        assert!(pin.matches(&der));
+
+        let pin = CertificatePin::public_key_sha256(
+            "sha256//nn6953P6b357uEKYQEgz8Jaqonhx/F3y13gANkC/Z74=",
+        )
+        .unwrap();
+        assert!(pin.matches(&der));
+
+        let pin = CertificatePin::public_key_sha256("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=")
+            .unwrap();
+        assert!(!pin.matches(&der));
+
+        assert!(CertificatePin::certificate_sha256("F4:E2").is_none());
+        assert!(CertificatePin::public_key_sha256("not base64").is_none());
+        assert!(subject_public_key_info(b"\x30\x05\x30").is_none());
+    }
+}
@@ -0,0 +1,241 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeSet;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A privilege of the current user on a collection (RFC 3744, section 3 and RFC 6638, section 6).
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Privilege {
+    Read,
+    ReadAcl,
+    ReadCurrentUserPrivilegeSet,
+    ReadFreeBusy,
+    Write,
+    WriteProperties,
+    WriteContent,
+    WriteAcl,
+    /// Create resources in the collection.
+    Bind,
+    /// Delete resources of the collection.
+    Unbind,
+    Unlock,
+    All,
+    ScheduleDeliver,
+    ScheduleDeliverInvite,
+    ScheduleDeliverReply,
+    ScheduleQueryFreebusy,
+    ScheduleSend,
+    ScheduleSendInvite,
+    ScheduleSendReply,
+    ScheduleSendFreebusy,
+    /// A privilege of another specification, with its name without namespace.
+    Other(String),
+}
+
+impl Privilege {
+    /// Parse the name of a privilege element, e.g. `write-content` or `d:write-content`.
+    pub fn parse(name: &str) -> Self {
+        let name = name.rsplit(':').next().unwrap_or(name).trim();
+        match name.to_ascii_lowercase().as_str() {
+            "read" => Self::Read,
+            "read-acl" => Self::ReadAcl,
+            "read-current-user-privilege-set" => Self::ReadCurrentUserPrivilegeSet,
+            "read-free-busy" => Self::ReadFreeBusy,
+            "write" => Self::Write,
+            "write-properties" => Self::WriteProperties,
+            "write-content" => Self::WriteContent,
+            "write-acl" => Self::WriteAcl,
+            "bind" => Self::Bind,
+            "unbind" => Self::Unbind,
+            "unlock" => Self::Unlock,
+            "all" => Self::All,
+            "schedule-deliver" => Self::ScheduleDeliver,
+            "schedule-deliver-invite" => Self::ScheduleDeliverInvite,
+            "schedule-deliver-reply" => Self::ScheduleDeliverReply,
+            "schedule-query-freebusy" => Self::ScheduleQueryFreebusy,
+            "schedule-send" => Self::ScheduleSend,
+            "schedule-send-invite" => Self::ScheduleSendInvite,
+            "schedule-send-reply" => Self::ScheduleSendReply,
+            "schedule-send-freebusy" => Self::ScheduleSendFreebusy,
+            _ => Self::Other(name.to_string()),
+        }
+    }
+
+    /// The privileges contained in this aggregate privilege.
+    fn contained(&self) -> &'static [Privilege] {
+        match self {
+            Self::All => &[
+                Self::Read,
+                Self::Write,
+                Self::WriteAcl,
+                Self::Unlock,
+                Self::ReadAcl,
+                Self::ScheduleDeliver,
+                Self::ScheduleSend,
+            ],
+            Self::Read => &[Self::ReadCurrentUserPrivilegeSet, Self::ReadFreeBusy],
+            Self::Write => &[
+                Self::WriteProperties,
+                Self::WriteContent,
+                Self::Bind,
+                Self::Unbind,
+            ],
+            Self::ScheduleDeliver => &[
+                Self::ScheduleDeliverInvite,
+                Self::ScheduleDeliverReply,
+                Self::ScheduleQueryFreebusy,
+            ],
+            Self::ScheduleSend => &[
+                Self::ScheduleSendInvite,
+                Self::ScheduleSendReply,
+                Self::ScheduleSendFreebusy,
+            ],
+            _ => &[],
+        }
+    }
+}
+
+/// The privileges of the current user with aggregate privileges expanded,
+/// e.g. `all` contains `write`, which contains `write-content`, `bind` and `unbind`.
+/// Servers report the same rights differently, use this instead of comparing privilege names.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Privileges {
+    privileges: BTreeSet<Privilege>,
+}
+
+impl Privileges {
+    /// Normalize the given privilege names as reported in a `current-user-privilege-set`.
+    pub fn from_names<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut privileges = BTreeSet::new();
+        let mut pending = names
+            .into_iter()
+            .map(|n| Privilege::parse(n.as_ref()))
+            .collect::<Vec<_>>();
+        while let Some(privilege) = pending.pop() {
+            pending.extend(privilege.contained().iter().cloned());
+            privileges.insert(privilege);
+        }
+        Self { privileges }
+    }
+
+    /// The names of all privileges in the given `current-user-privilege-set` element,
+    /// including privileges nested in aggregates.
+    pub(crate) fn names_from_xml(privilege_set: &xmltree::Element) -> Vec<String> {
+        fn collect(element: &xmltree::Element, names: &mut Vec<String>) {
+            for child in element.children.iter().filter_map(|c| c.as_element()) {
+                if child.name != "privilege" {
+                    names.push(child.name.clone());
+                }
+                collect(child, names);
+            }
+        }
+        let mut names = Vec::new();
+        collect(privilege_set, &mut names);
+        names
+    }
+
+    pub fn contains(&self, privilege: &Privilege) -> bool {
+        self.privileges.contains(privilege)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Privilege> {
+        self.privileges.iter()
+    }
+
+    pub fn can_read(&self) -> bool {
+        self.contains(&Privilege::Read)
+    }
+
+    /// Whether existing resources may be modified.
+    pub fn can_write_content(&self) -> bool {
+        self.contains(&Privilege::WriteContent)
+    }
+
+    /// Whether new resources may be created.
+    pub fn can_create(&self) -> bool {
+        self.contains(&Privilege::Bind)
+    }
+
+    /// Whether resources may be deleted.
+    pub fn can_delete(&self) -> bool {
+        self.contains(&Privilege::Unbind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(xml: &str) -> Privileges {
+        let root = xmltree::Element::parse(xml.as_bytes()).unwrap();
+        Privileges::from_names(Privileges::names_from_xml(&root))
+    }
+
+    #[test]
+    fn test_privileges_of_servers() {
+        // Radicale
+        let radicale = parse(
+            r#"<d:current-user-privilege-set xmlns:d="DAV:">
+                <d:privilege><d:read /></d:privilege>
+                <d:privilege><d:all /></d:privilege>
+            </d:current-user-privilege-set>"#,
+        );
+        assert!(radicale.can_write_content() && radicale.can_create() && radicale.can_delete());
+
+        // Nextcloud and other SabreDAV servers, read-only shared calendar
+        let sabre = parse(
+            r#"<d:current-user-privilege-set xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+                <d:privilege><d:read /></d:privilege>
+                <d:privilege><d:read-acl /></d:privilege>
+                <d:privilege><d:read-current-user-privilege-set /></d:privilege>
+                <d:privilege><cal:read-free-busy /></d:privilege>
+                <d:privilege><d:write-properties /></d:privilege>
+            </d:current-user-privilege-set>"#,
+        );
+        assert!(sabre.can_read());
+        assert!(!sabre.can_write_content() && !sabre.can_create());
+
+        // iCloud lists the contained privileges
+        let icloud = parse(
+            r#"<current-user-privilege-set xmlns="DAV:">
+                <privilege><read/></privilege>
+                <privilege><write-content/></privilege>
+                <privilege><bind/></privilege>
+            </current-user-privilege-set>"#,
+        );
+        assert!(icloud.can_write_content() && icloud.can_create() && !icloud.can_delete());
+
+        // Nested aggregates
+        let nested = parse(
+            r#"<d:current-user-privilege-set xmlns:d="DAV:">
+                <d:privilege><d:write><d:write-content/></d:write></d:privilege>
+                <d:privilege><d:x-custom/></d:privilege>
+            </d:current-user-privilege-set>"#,
+        );
+        assert!(nested.can_delete());
+        assert!(nested.contains(&Privilege::Other("x-custom".into())));
+        assert!(!nested.can_read());
+    }
+}
@@ -0,0 +1,1370 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Local expansion of recurring components (RRULE, RDATE and EXDATE of RFC 5545).
+
+use std::collections::VecDeque;
+
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+    Timelike, Utc, Weekday,
+};
+use chrono_tz::Tz;
+
+use crate::ical::{Error, Ical, Property};
+
+/// The FREQ of a recurrence rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Frequency {
+    Secondly,
+    Minutely,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A parsed RRULE value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RRule {
+    pub freq: Frequency,
+    pub interval: u32,
+    pub count: Option<u32>,
+    /// The raw UNTIL value, a DATE or DATE-TIME.
+    pub until: Option<String>,
+    pub by_second: Vec<u32>,
+    pub by_minute: Vec<u32>,
+    pub by_hour: Vec<u32>,
+    /// Weekdays with an optional ordinal, e.g. `-1SU` is `(Some(-1), Sun)`.
+    pub by_day: Vec<(Option<i32>, Weekday)>,
+    pub by_month_day: Vec<i32>,
+    pub by_year_day: Vec<i32>,
+    pub by_week_no: Vec<i32>,
+    pub by_month: Vec<u32>,
+    pub by_set_pos: Vec<i32>,
+    pub wkst: Weekday,
+}
+
+impl RRule {
+    /// Parse the given RRULE value, e.g. `FREQ=WEEKLY;BYDAY=MO,WE`.
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        let mut freq = None;
+        let mut rule = RRule {
+            freq: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_second: vec![],
+            by_minute: vec![],
+            by_hour: vec![],
+            by_day: vec![],
+            by_month_day: vec![],
+            by_year_day: vec![],
+            by_week_no: vec![],
+            by_month: vec![],
+            by_set_pos: vec![],
+            wkst: Weekday::Mon,
+        };
+        let invalid = |part: &str| Error::new(format!("Invalid RRULE part {:?}", part));
+        for part in value.split(';').filter(|p| !p.is_empty()) {
+            let (key, val) = part.split_once('=').ok_or_else(|| invalid(part))?;
+            match key.to_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match val.to_uppercase().as_str() {
+                        "SECONDLY" => Frequency::Secondly,
+                        "MINUTELY" => Frequency::Minutely,
+                        "HOURLY" => Frequency::Hourly,
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(invalid(part)),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = val.parse().map_err(|_| invalid(part))?;
+                    if rule.interval == 0 {
+                        return Err(invalid(part));
+                    }
+                }
+                "COUNT" => rule.count = Some(val.parse().map_err(|_| invalid(part))?),
+                "UNTIL" => rule.until = Some(val.to_string()),
+                "BYSECOND" => rule.by_second = parse_list(val).ok_or_else(|| invalid(part))?,
+                "BYMINUTE" => rule.by_minute = parse_list(val).ok_or_else(|| invalid(part))?,
+                "BYHOUR" => rule.by_hour = parse_list(val).ok_or_else(|| invalid(part))?,
+                "BYMONTHDAY" => rule.by_month_day = parse_list(val).ok_or_else(|| invalid(part))?,
+                "BYYEARDAY" => rule.by_year_day = parse_list(val).ok_or_else(|| invalid(part))?,
+                "BYWEEKNO" => rule.by_week_no = parse_list(val).ok_or_else(|| invalid(part))?,
+                "BYMONTH" => rule.by_month = parse_list(val).ok_or_else(|| invalid(part))?,
+                "BYSETPOS" => rule.by_set_pos = parse_list(val).ok_or_else(|| invalid(part))?,
+                "BYDAY" => {
+                    rule.by_day = val
+                        .split(',')
+                        .map(parse_weekday_num)
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(|| invalid(part))?
+                }
+                "WKST" => rule.wkst = parse_weekday(val).ok_or_else(|| invalid(part))?,
+                _ => {}
+            }
+        }
+        rule.freq = freq.ok_or_else(|| Error::new(format!("RRULE without FREQ: {}", value)))?;
+        Ok(rule)
+    }
+}
+
+fn parse_list<T: std::str::FromStr>(value: &str) -> Option<Vec<T>> {
+    value.split(',').map(|v| v.trim().parse().ok()).collect()
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    match value.trim().to_uppercase().as_str() {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn parse_weekday_num(value: &str) -> Option<(Option<i32>, Weekday)> {
+    let value = value.trim();
+    if value.len() < 2 {
+        return None;
+    }
+    let (num, day) = value.split_at(value.len() - 2);
+    let num = if num.is_empty() {
+        None
+    } else {
+        Some(num.trim_start_matches('+').parse().ok()?)
+    };
+    Some((num, parse_weekday(day)?))
+}
+
+/// Parse a DATE or DATE-TIME value (`20240101`, `20240101T100000` or `20240101T100000Z`).
+/// Dates are returned at midnight. The bool is true for UTC values.
+pub(crate) fn parse_naive(value: &str) -> Option<(NaiveDateTime, bool)> {
+    let value = value.trim();
+    if let Some(utc) = value.strip_suffix('Z') {
+        return NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .ok()
+            .map(|d| (d, true));
+    }
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y%m%d")
+                .ok()
+                .map(|d| d.and_time(NaiveTime::MIN))
+        })
+        .map(|d| (d, false))
+}
+
+/// Resolve the local time in the given zone.
+/// Times in a DST gap are moved forward by the length of the gap (RFC 5545, section 3.3.5).
+pub(crate) fn resolve_local(tz: &Tz, local: NaiveDateTime) -> DateTime<Tz> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(d) => d,
+        LocalResult::Ambiguous(earliest, _) => earliest,
+        LocalResult::None => (1..=4)
+            .find_map(|h| {
+                tz.from_local_datetime(&(local + Duration::minutes(30 * h)))
+                    .earliest()
+            })
+            .unwrap_or_else(|| tz.from_utc_datetime(&local)),
+    }
+}
+
+/// The time zone of the TZID parameter of the given property, if it is known.
+/// Windows time zone names are resolved as well.
+pub fn property_timezone(prop: &Property) -> Option<Tz> {
+    prop.tzid().and_then(|tzid| tzid.parse().ok())
+}
+
+/// The value of a DATE or DATE-TIME property like DTSTART, DTEND or DUE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateTimeValue {
+    /// An all-day date, e.g. `DTSTART;VALUE=DATE:20240101`.
+    Date(NaiveDate),
+    /// A local time without time zone, or with a TZID that is not known.
+    Floating(NaiveDateTime),
+    /// A time in UTC, e.g. `DTSTART:20240101T100000Z`.
+    Utc(DateTime<Utc>),
+    /// A local time in the time zone of the TZID parameter.
+    Zoned(DateTime<Tz>),
+}
+
+impl DateTimeValue {
+    /// Parse the first value of the given property, `None` if it is not a valid DATE or DATE-TIME.
+    pub fn parse(prop: &Property) -> Option<Self> {
+        Self::parse_value(prop, prop.value.split(',').next().unwrap_or_default())
+    }
+
+    /// Parse all values of the given property, e.g. of an EXDATE or RDATE with several values.
+    /// Periods (`RDATE;VALUE=PERIOD`) are parsed as their start.
+    pub fn parse_all(prop: &Property) -> Vec<Self> {
+        prop.value
+            .split(',')
+            .filter_map(|v| Self::parse_value(prop, v.split('/').next().unwrap_or(v)))
+            .collect()
+    }
+
+    fn parse_value(prop: &Property, value: &str) -> Option<Self> {
+        let value = value.trim();
+        let is_date = prop.attributes.get("VALUE").map(|v| v.as_str()) == Some("DATE")
+            || (value.len() == 8 && !value.contains('T'));
+        if is_date {
+            return NaiveDate::parse_from_str(value, "%Y%m%d")
+                .ok()
+                .map(Self::Date);
+        }
+        let (naive, utc) = parse_naive(value)?;
+        Some(if utc {
+            Self::Utc(Utc.from_utc_datetime(&naive))
+        } else if let Some(tz) = property_timezone(prop) {
+            Self::Zoned(resolve_local(&tz, naive))
+        } else {
+            Self::Floating(naive)
+        })
+    }
+
+    pub fn is_date(&self) -> bool {
+        matches!(self, Self::Date(_))
+    }
+
+    /// The value as written in a property, e.g. `20240101`, `20240101T100000` or `20240101T100000Z`.
+    pub fn value(&self) -> String {
+        match self {
+            Self::Date(date) => date.format("%Y%m%d").to_string(),
+            Self::Floating(naive) => naive.format("%Y%m%dT%H%M%S").to_string(),
+            Self::Utc(date) => date.format("%Y%m%dT%H%M%SZ").to_string(),
+            Self::Zoned(date) => date.format("%Y%m%dT%H%M%S").to_string(),
+        }
+    }
+
+    /// The TZID parameter of a property with this value.
+    pub fn tzid(&self) -> Option<&'static str> {
+        match self {
+            Self::Zoned(date) => Some(date.timezone().name()),
+            _ => None,
+        }
+    }
+
+    /// A property with the given name and this value, with `VALUE=DATE` or `TZID` if needed.
+    pub fn to_property(&self, name: &str) -> Property {
+        match (self, self.tzid()) {
+            (Self::Date(_), _) => {
+                Property::new_with_attributes(name, &self.value(), vec![("VALUE", "DATE")])
+            }
+            (_, Some(tzid)) => {
+                Property::new_with_attributes(name, &self.value(), vec![("TZID", tzid)])
+            }
+            _ => Property::new(name, &self.value()),
+        }
+    }
+
+    /// Whether both values denote the same date or point in time, e.g. in different time zones.
+    pub fn same_as(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Utc(_) | Self::Zoned(_), Self::Utc(_) | Self::Zoned(_)) => {
+                self.to_utc(Tz::UTC) == other.to_utc(Tz::UTC)
+            }
+            _ => self == other,
+        }
+    }
+
+    /// The point in time of this value. Dates (at midnight) and floating times are interpreted in `floating`.
+    pub fn to_utc(&self, floating: Tz) -> DateTime<Utc> {
+        match self {
+            Self::Date(date) => resolve_local(&floating, date.and_time(NaiveTime::MIN)),
+            Self::Floating(naive) => resolve_local(&floating, *naive),
+            Self::Utc(date) => return *date,
+            Self::Zoned(date) => return date.with_timezone(&Utc),
+        }
+        .with_timezone(&Utc)
+    }
+
+    /// This value written like `template`: as date, floating, UTC or in the time zone of `template`.
+    /// Floating times and dates are interpreted in the time zone of `template` (UTC if it has none).
+    pub fn like(&self, template: &Self) -> Self {
+        let tz = match template {
+            Self::Zoned(date) => date.timezone(),
+            _ => Tz::UTC,
+        };
+        match (template, self) {
+            (Self::Date(_), Self::Date(_)) | (Self::Floating(_), Self::Floating(_)) => *self,
+            (Self::Floating(_), Self::Date(date)) => Self::Floating(date.and_time(NaiveTime::MIN)),
+            (Self::Date(_), Self::Floating(naive)) => Self::Date(naive.date()),
+            (Self::Date(_), _) => Self::Date(self.to_utc(tz).with_timezone(&tz).date_naive()),
+            (Self::Floating(_), _) => Self::Floating(self.to_utc(tz).naive_utc()),
+            (Self::Utc(_), _) => Self::Utc(self.to_utc(tz)),
+            (Self::Zoned(_), _) => Self::Zoned(self.to_utc(tz).with_timezone(&tz)),
+        }
+    }
+
+    /// This value moved by `duration`, dates by whole days.
+    pub fn shifted(&self, duration: Duration) -> Self {
+        match self {
+            Self::Date(date) => Self::Date(*date + Duration::days(duration.num_days())),
+            Self::Floating(naive) => Self::Floating(*naive + duration),
+            Self::Utc(date) => Self::Utc(*date + duration),
+            Self::Zoned(date) => Self::Zoned(*date + duration),
+        }
+    }
+}
+
+/// Parse all values of the given DATE, DATE-TIME or PERIOD property (e.g. DTSTART, RDATE or EXDATE).
+/// Floating times and dates are interpreted in `floating`, UTC times are returned in `Tz::UTC`.
+pub fn parse_date_times(prop: &Property, floating: Tz) -> Vec<DateTime<Tz>> {
+    let tz = property_timezone(prop).unwrap_or(floating);
+    prop.value
+        .split(',')
+        .filter_map(|v| parse_naive(v.split('/').next().unwrap_or(v)))
+        .map(|(naive, utc)| {
+            if utc {
+                Utc.from_utc_datetime(&naive).with_timezone(&Tz::UTC)
+            } else {
+                resolve_local(&tz, naive)
+            }
+        })
+        .collect()
+}
+
+/// Parse the first value of the given DATE or DATE-TIME property.
+pub fn parse_date_time(prop: &Property, floating: Tz) -> Option<DateTime<Tz>> {
+    parse_date_times(prop, floating).into_iter().next()
+}
+
+/// The property the instances of the given component start at: DTSTART, or DUE for a VTODO without DTSTART.
+/// Recurring todos without DTSTART are anchored on DUE by most clients.
+pub fn anchor_property(component: &Ical) -> Option<&Property> {
+    component.get_first_property("DTSTART").or_else(|| {
+        if component.name == "VTODO" {
+            component.get_first_property("DUE")
+        } else {
+            None
+        }
+    })
+}
+
+/// Expand the start times of all instances of the given component (e.g. a VEVENT).
+/// The RRULE is expanded in the time zone of DTSTART, in UTC for UTC times, and floating times
+/// and dates in `floating`.
+/// Todos without DTSTART are expanded from their DUE, see `anchor_property`.
+/// Returns `None` if the component has no valid DTSTART.
+pub fn occurrences(component: &Ical, floating: Tz) -> Option<Occurrences> {
+    let dtstart_prop = anchor_property(component)?;
+    let (naive, utc) = parse_naive(&dtstart_prop.value)?;
+    let (tz, dtstart) = if utc {
+        (
+            Tz::UTC,
+            Utc.from_utc_datetime(&naive).with_timezone(&Tz::UTC),
+        )
+    } else {
+        let tz = property_timezone(dtstart_prop).unwrap_or(floating);
+        (tz, resolve_local(&tz, naive))
+    };
+
+    let rule = component
+        .get_first_property("RRULE")
+        .and_then(|p| RRule::parse(&p.value).ok());
+    let mut rdates = component
+        .properties
+        .iter()
+        .filter(|p| p.name == "RDATE")
+        .flat_map(|p| parse_date_times(p, tz))
+        .collect::<Vec<_>>();
+    rdates.sort();
+    let exdates = component
+        .properties
+        .iter()
+        .filter(|p| p.name == "EXDATE")
+        .flat_map(|p| parse_date_times(p, tz))
+        .collect::<Vec<_>>();
+
+    let rule = rule.map(|rule| RuleIter::new(rule, tz, dtstart));
+    let single = if rule.is_none() { Some(dtstart) } else { None };
+    Some(Occurrences {
+        rule,
+        single,
+        pending_rule: None,
+        rdates: rdates.into(),
+        exdates,
+        last: None,
+    })
+}
+
+/// Parse a duration value, e.g. `-PT15M` or `P1DT12H`.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (sign, value) = match value.as_bytes().first()? {
+        b'-' => (-1, &value[1..]),
+        b'+' => (1, &value[1..]),
+        _ => (1, value),
+    };
+    let value = value.strip_prefix('P')?;
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            unit => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                duration += match unit {
+                    'W' => Duration::weeks(n),
+                    'D' => Duration::days(n),
+                    'H' => Duration::hours(n),
+                    'M' => Duration::minutes(n),
+                    'S' => Duration::seconds(n),
+                    _ => return None,
+                };
+            }
+        }
+    }
+    Some(duration * sign)
+}
+
+/// Expand the VEVENTs and VTODOs of the given VCALENDAR into one component per instance
+/// overlapping `[start, end)`, like the `expand` element of a calendar-query (RFC 4791, section 9.6.5).
+/// Instances of recurring components get a RECURRENCE-ID, overridden instances replace the generated ones.
+/// Date-times are converted to UTC, floating times and dates are interpreted in `floating`.
+/// Returns `None` if no instance overlaps the range.
+pub fn expand(
+    calendar: &Ical,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    floating: Tz,
+) -> Option<Ical> {
+    // Stop at some point for rules without end and ranges far in the future.
+    const MAX_INSTANCES: usize = 10_000;
+    let components = calendar
+        .children
+        .iter()
+        .filter(|c| c.name == "VEVENT" || c.name == "VTODO");
+    let master = components
+        .clone()
+        .find(|c| c.get_first_property("RECURRENCE-ID").is_none());
+    let overrides = components
+        .filter(|c| c.get_first_property("RECURRENCE-ID").is_some())
+        .collect::<Vec<_>>();
+
+    let mut instances = Vec::new();
+    let overlaps = |component: &Ical| {
+        let Some(instance_start) =
+            anchor_property(component).and_then(|p| parse_date_time(p, floating))
+        else {
+            return false;
+        };
+        let instance_end = instance_start + component_length(component, floating);
+        instance_start < end
+            && (instance_end > start || (instance_end == instance_start && instance_start >= start))
+    };
+    for component in &overrides {
+        if overlaps(component) {
+            instances.push(component_to_utc(component, floating));
+        }
+    }
+    if let Some(master) = master {
+        let recurring = ["RRULE", "RDATE"]
+            .iter()
+            .any(|name| master.get_first_property(name).is_some());
+        if !recurring {
+            if overlaps(master) {
+                instances.push(component_to_utc(master, floating));
+            }
+        } else {
+            let overridden = overrides
+                .iter()
+                .filter_map(|o| o.get_first_property("RECURRENCE-ID"))
+                .filter_map(|p| parse_date_time(p, floating))
+                .collect::<Vec<_>>();
+            let length = component_length(master, floating);
+            let dtstart = anchor_property(master)?;
+            let anchor = dtstart.name.clone();
+            let dates = is_date(dtstart);
+            // Only instances in the range count towards the limit, long-running rules have many before it.
+            for instance_start in occurrences(master, floating)?
+                .take_while(|s| *s < end)
+                .filter(|s| *s + length > start || (length.is_zero() && *s >= start))
+                .take(MAX_INSTANCES)
+            {
+                if overridden.contains(&instance_start) {
+                    continue;
+                }
+                let instance_end = instance_start + length;
+                let mut instance = component_to_utc(master, floating);
+                let end_names = ["DTEND", "DUE"]
+                    .into_iter()
+                    .filter(|name| *name != anchor && instance.get_first_property(name).is_some())
+                    .collect::<Vec<_>>();
+                instance.properties.retain(|p| {
+                    !matches!(
+                        p.name.as_str(),
+                        "DTSTART" | "DTEND" | "DUE" | "RRULE" | "RDATE" | "EXDATE"
+                    )
+                });
+                instance.add_property(date_property(&anchor, instance_start, dates));
+                for name in end_names {
+                    instance.add_property(date_property(name, instance_end, dates));
+                }
+                instance.add_property(date_property("RECURRENCE-ID", instance_start, dates));
+                instances.push(instance);
+            }
+        }
+    }
+    if instances.is_empty() {
+        return None;
+    }
+    let mut expanded = Ical::new(calendar.name.clone());
+    expanded.properties = calendar.properties.clone();
+    expanded.children = instances;
+    Some(expanded)
+}
+
+/// One instance of an event or todo, see `occurrences_between`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    pub start: DateTime<Utc>,
+    /// The end of the instance, equal to `start` for instances without DTEND, DUE or DURATION,
+    /// one day after `start` for such all-day events.
+    pub end: DateTime<Utc>,
+    /// The RECURRENCE-ID of an instance of a recurring component, `None` for single components.
+    pub recurrence_id: Option<DateTime<Utc>>,
+    /// The component of the instance, with the properties of an override if the instance was changed.
+    pub component: Ical,
+}
+
+/// The instances of the VEVENTs and VTODOs of the given VCALENDAR overlapping `[start, end)`, sorted by start.
+/// RRULE, RDATE, EXDATE and overridden instances are expanded locally, see `expand`.
+pub fn occurrences_between(
+    calendar: &Ical,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    floating: Tz,
+) -> Vec<Occurrence> {
+    let Some(expanded) = expand(calendar, start, end, floating) else {
+        return Vec::new();
+    };
+    let time = |component: &Ical, name: &str| {
+        component
+            .get_first_property(name)
+            .and_then(|p| parse_date_time(p, floating))
+            .map(|d| d.with_timezone(&Utc))
+    };
+    let mut occurrences = expanded
+        .children
+        .into_iter()
+        .filter_map(|component| {
+            let start = anchor_property(&component)
+                .and_then(|p| parse_date_time(p, floating))?
+                .with_timezone(&Utc);
+            Some(Occurrence {
+                start,
+                end: start + component_length(&component, floating),
+                recurrence_id: time(&component, "RECURRENCE-ID"),
+                component,
+            })
+        })
+        .collect::<Vec<_>>();
+    occurrences.sort_by_key(|o| o.start);
+    occurrences
+}
+
+fn is_date(prop: &Property) -> bool {
+    prop.attributes.get("VALUE").map(|v| v.as_str()) == Some("DATE") || prop.value.trim().len() == 8
+}
+
+fn date_value(date: DateTime<Tz>, is_date: bool) -> String {
+    if is_date {
+        date.format("%Y%m%d").to_string()
+    } else {
+        date.with_timezone(&Utc)
+            .format("%Y%m%dT%H%M%SZ")
+            .to_string()
+    }
+}
+
+fn date_property(name: &str, date: DateTime<Tz>, is_date: bool) -> Property {
+    if is_date {
+        Property::new_with_attributes(name, &date_value(date, true), vec![("VALUE", "DATE")])
+    } else {
+        Property::new(name, &date_value(date, false))
+    }
+}
+
+/// The time between DTSTART and DTEND (or DUE) of the component, or its DURATION.
+/// All-day events without either last one day (RFC 5545, section 3.6.1).
+fn component_length(component: &Ical, floating: Tz) -> Duration {
+    let time = |name: &str| {
+        component
+            .get_first_property(name)
+            .and_then(|p| parse_date_time(p, floating))
+    };
+    match (time("DTSTART"), time("DTEND").or_else(|| time("DUE"))) {
+        (Some(start), Some(end)) => end - start,
+        _ => component
+            .get_first_property("DURATION")
+            .and_then(|p| parse_duration(&p.value))
+            .unwrap_or_else(|| {
+                let all_day = component.name == "VEVENT"
+                    && component.get_first_property("DTSTART").is_some_and(is_date);
+                if all_day {
+                    Duration::days(1)
+                } else {
+                    Duration::zero()
+                }
+            }),
+    }
+}
+
+/// Convert the DATE-TIME values of DTSTART, DTEND, DUE and RECURRENCE-ID to UTC.
+fn component_to_utc(component: &Ical, floating: Tz) -> Ical {
+    let mut component = component.clone();
+    for prop in component.properties.iter_mut() {
+        if !matches!(
+            prop.name.as_str(),
+            "DTSTART" | "DTEND" | "DUE" | "RECURRENCE-ID"
+        ) || is_date(prop)
+        {
+            continue;
+        }
+        if let Some(date) = parse_date_time(prop, floating) {
+            *prop = Property::new(&prop.name, &date_value(date, false));
+        }
+    }
+    component
+}
+
+/// Iterator over the start times of the instances of a recurring component, in ascending order.
+pub struct Occurrences {
+    rule: Option<RuleIter>,
+    single: Option<DateTime<Tz>>,
+    pending_rule: Option<DateTime<Tz>>,
+    rdates: VecDeque<DateTime<Tz>>,
+    exdates: Vec<DateTime<Tz>>,
+    last: Option<DateTime<Tz>>,
+}
+
+impl Iterator for Occurrences {
+    type Item = DateTime<Tz>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.pending_rule.is_none() {
+                self.pending_rule = match &mut self.rule {
+                    Some(rule) => rule.next(),
+                    None => self.single.take(),
+                };
+            }
+            let next = match (self.pending_rule, self.rdates.front()) {
+                (Some(r), Some(d)) if *d < r => self.rdates.pop_front(),
+                (Some(_), _) => self.pending_rule.take(),
+                (None, Some(_)) => self.rdates.pop_front(),
+                (None, None) => return None,
+            }?;
+            if self.last == Some(next) || self.exdates.contains(&next) {
+                continue;
+            }
+            self.last = Some(next);
+            return Some(next);
+        }
+    }
+}
+
+/// Expands a RRULE in local time.
+struct RuleIter {
+    rule: RRule,
+    tz: Tz,
+    dtstart: NaiveDateTime,
+    until: Option<NaiveDateTime>,
+    period: u32,
+    buffer: VecDeque<NaiveDateTime>,
+    emitted: u32,
+    finished: bool,
+}
+
+impl RuleIter {
+    fn new(mut rule: RRule, tz: Tz, dtstart: DateTime<Tz>) -> Self {
+        let dtstart = dtstart.naive_local();
+        let until = rule
+            .until
+            .as_deref()
+            .and_then(parse_naive)
+            .map(|(until, utc)| {
+                if utc {
+                    Utc.from_utc_datetime(&until)
+                        .with_timezone(&tz)
+                        .naive_local()
+                } else if until.time() == NaiveTime::MIN
+                    && !rule.until.iter().any(|u| u.contains('T'))
+                {
+                    // An UNTIL date includes the whole day.
+                    until + Duration::days(1) - Duration::seconds(1)
+                } else {
+                    until
+                }
+            });
+
+        // Like most implementations, derive missing parts of the rule from DTSTART.
+        if rule.by_week_no.is_empty()
+            && rule.by_year_day.is_empty()
+            && rule.by_month_day.is_empty()
+            && rule.by_day.is_empty()
+        {
+            match rule.freq {
+                Frequency::Yearly => {
+                    if rule.by_month.is_empty() {
+                        rule.by_month = vec![dtstart.month()];
+                    }
+                    rule.by_month_day = vec![dtstart.day() as i32];
+                }
+                Frequency::Monthly => rule.by_month_day = vec![dtstart.day() as i32],
+                Frequency::Weekly => rule.by_day = vec![(None, dtstart.weekday())],
+                _ => {}
+            }
+        }
+
+        // The DTSTART always counts as the first instance.
+        let mut buffer = VecDeque::new();
+        buffer.push_back(dtstart);
+
+        Self {
+            rule,
+            tz,
+            dtstart,
+            until,
+            period: 0,
+            buffer,
+            emitted: 0,
+            finished: false,
+        }
+    }
+
+    /// The first day and time of the n-th period since DTSTART.
+    fn period_start(&self, n: u32) -> Option<NaiveDateTime> {
+        let step = n as i64 * self.rule.interval as i64;
+        let start = self.dtstart;
+        match self.rule.freq {
+            Frequency::Yearly => {
+                let year = start.year() as i64 + step;
+                if year > 9999 {
+                    return None;
+                }
+                NaiveDate::from_ymd_opt(year as i32, 1, 1).map(|d| d.and_time(NaiveTime::MIN))
+            }
+            Frequency::Monthly => {
+                let month = start.year() as i64 * 12 + start.month0() as i64 + step;
+                if month / 12 > 9999 {
+                    return None;
+                }
+                NaiveDate::from_ymd_opt((month / 12) as i32, (month % 12) as u32 + 1, 1)
+                    .map(|d| d.and_time(NaiveTime::MIN))
+            }
+            Frequency::Weekly => {
+                let offset = (7 + start.weekday().num_days_from_monday()
+                    - self.rule.wkst.num_days_from_monday())
+                    % 7;
+                let week_start = start.date() - Duration::days(offset as i64);
+                week_start
+                    .checked_add_signed(Duration::weeks(step))
+                    .map(|d| d.and_time(NaiveTime::MIN))
+            }
+            Frequency::Daily => start
+                .date()
+                .checked_add_signed(Duration::days(step))
+                .map(|d| d.and_time(NaiveTime::MIN)),
+            Frequency::Hourly => start
+                .with_minute(0)
+                .and_then(|s| s.with_second(0))
+                .and_then(|s| s.checked_add_signed(Duration::hours(step))),
+            Frequency::Minutely => start
+                .with_second(0)
+                .and_then(|s| s.checked_add_signed(Duration::minutes(step))),
+            Frequency::Secondly => start.checked_add_signed(Duration::seconds(step)),
+        }
+        .filter(|d| d.year() <= 9999)
+    }
+
+    /// All instances in the period starting at `start`, sorted and limited by BYSETPOS.
+    fn expand_period(&self, start: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let days: Vec<NaiveDate> = match self.rule.freq {
+            Frequency::Yearly => start
+                .date()
+                .iter_days()
+                .take_while(|d| d.year() == start.year())
+                .collect(),
+            Frequency::Monthly => start
+                .date()
+                .iter_days()
+                .take_while(|d| d.month() == start.month())
+                .collect(),
+            Frequency::Weekly => start.date().iter_days().take(7).collect(),
+            _ => vec![start.date()],
+        };
+        let days = days.into_iter().filter(|d| self.day_matches(*d));
+
+        let hours = self.time_values(
+            &self.rule.by_hour,
+            self.dtstart.hour(),
+            Frequency::Hourly,
+            start.hour(),
+        );
+        let minutes = self.time_values(
+            &self.rule.by_minute,
+            self.dtstart.minute(),
+            Frequency::Minutely,
+            start.minute(),
+        );
+        let seconds = self.time_values(
+            &self.rule.by_second,
+            self.dtstart.second(),
+            Frequency::Secondly,
+            start.second(),
+        );
+
+        let mut instances = Vec::new();
+        for day in days {
+            for h in &hours {
+                for m in &minutes {
+                    for s in &seconds {
+                        if let Some(time) = NaiveTime::from_hms_opt(*h, *m, *s) {
+                            instances.push(day.and_time(time));
+                        }
+                    }
+                }
+            }
+        }
+        instances.sort();
+
+        if self.rule.by_set_pos.is_empty() {
+            return instances;
+        }
+        let len = instances.len() as i32;
+        let mut selected = self
+            .rule
+            .by_set_pos
+            .iter()
+            .filter_map(|pos| {
+                let index = if *pos > 0 { pos - 1 } else { len + pos };
+                if (0..len).contains(&index) {
+                    Some(instances[index as usize])
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        selected.sort();
+        selected.dedup();
+        selected
+    }
+
+    /// The values of a time part: fixed by the period if the frequency is at least as fine,
+    /// otherwise expanded by the BYxxx list or taken from DTSTART.
+    fn time_values(&self, by: &[u32], dtstart: u32, part: Frequency, period: u32) -> Vec<u32> {
+        if self.rule.freq <= part {
+            if by.is_empty() || by.contains(&period) {
+                vec![period]
+            } else {
+                vec![]
+            }
+        } else if by.is_empty() {
+            vec![dtstart]
+        } else {
+            let mut values = by.to_vec();
+            values.sort();
+            values
+        }
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let rule = &self.rule;
+        if !rule.by_month.is_empty() && !rule.by_month.contains(&date.month()) {
+            return false;
+        }
+        if !rule.by_week_no.is_empty() {
+            let (week, weeks) = week_number(date, rule.wkst);
+            if !rule
+                .by_week_no
+                .iter()
+                .any(|n| *n == week || *n == week - weeks - 1)
+            {
+                return false;
+            }
+        }
+        if !rule.by_year_day.is_empty() {
+            let day = date.ordinal() as i32;
+            let days = days_in_year(date.year()) as i32;
+            if !rule
+                .by_year_day
+                .iter()
+                .any(|n| *n == day || *n == day - days - 1)
+            {
+                return false;
+            }
+        }
+        if !rule.by_month_day.is_empty() {
+            let day = date.day() as i32;
+            let days = days_in_month(date.year(), date.month()) as i32;
+            if !rule
+                .by_month_day
+                .iter()
+                .any(|n| *n == day || *n == day - days - 1)
+            {
+                return false;
+            }
+        }
+        if !rule.by_day.is_empty() {
+            // Ordinals refer to the month or year, only for MONTHLY and YEARLY rules.
+            let in_year = rule.freq == Frequency::Yearly && rule.by_month.is_empty();
+            let (index, count) = if in_year {
+                (
+                    (date.ordinal0() / 7 + 1) as i32,
+                    ((days_in_year(date.year()) - date.ordinal0() - 1) / 7) as i32,
+                )
+            } else {
+                (
+                    (date.day0() / 7 + 1) as i32,
+                    ((days_in_month(date.year(), date.month()) - date.day0() - 1) / 7) as i32,
+                )
+            };
+            let ordinals = matches!(rule.freq, Frequency::Monthly | Frequency::Yearly);
+            if !rule.by_day.iter().any(|(n, weekday)| {
+                *weekday == date.weekday()
+                    && match n {
+                        Some(n) if ordinals => *n == index || *n == -(count + 1),
+                        _ => true,
+                    }
+            }) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Iterator for RuleIter {
+    type Item = DateTime<Tz>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut empty_periods = 0;
+        loop {
+            if let Some(count) = self.rule.count {
+                if self.emitted >= count {
+                    return None;
+                }
+            }
+            if let Some(next) = self.buffer.pop_front() {
+                if self.until.map(|u| next > u).unwrap_or(false) {
+                    self.finished = true;
+                    self.buffer.clear();
+                    return None;
+                }
+                self.emitted += 1;
+                return Some(resolve_local(&self.tz, next));
+            }
+            if self.finished || empty_periods > 100_000 {
+                return None;
+            }
+            let start = match self.period_start(self.period) {
+                Some(start) => start,
+                None => {
+                    self.finished = true;
+                    return None;
+                }
+            };
+            self.period += 1;
+            let dtstart = self.dtstart;
+            self.buffer = self
+                .expand_period(start)
+                .into_iter()
+                .filter(|d| *d > dtstart)
+                .collect();
+            if self.buffer.is_empty() {
+                empty_periods += 1;
+            }
+        }
+    }
+}
+
+fn days_in_year(year: i32) -> u32 {
+    if NaiveDate::from_ymd_opt(year, 2, 29).is_some() {
+        366
+    } else {
+        365
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|d| d.checked_add_months(chrono::Months::new(1)))
+        .map(|d| (d - Duration::days(1)).day())
+        .unwrap_or(31)
+}
+
+/// The week number of the given date (week 1 is the first week with at least four days in the year)
+/// and the number of weeks in its year.
+fn week_number(date: NaiveDate, wkst: Weekday) -> (i32, i32) {
+    let week1_start = |year: i32| {
+        let jan1 = NaiveDate::from_ymd_opt(year, 1, 1).unwrap_or_default();
+        let offset = (7 + jan1.weekday().num_days_from_monday() - wkst.num_days_from_monday()) % 7;
+        if offset <= 3 {
+            jan1 - Duration::days(offset as i64)
+        } else {
+            jan1 + Duration::days(7 - offset as i64)
+        }
+    };
+    let start = week1_start(date.year());
+    let weeks = ((week1_start(date.year() + 1) - start).num_days() / 7) as i32;
+    let week = if date < start {
+        0
+    } else {
+        ((date - start).num_days() / 7 + 1) as i32
+    };
+    (week, weeks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ical::LineIterator;
+
+    #[test]
+    fn test_date_time_value() {
+        let property = |line: &str| {
+            let ical = Ical::parse(&mut LineIterator::new(&format!(
+                "BEGIN:VEVENT\n{}\nEND:VEVENT\n",
+                line
+            )))
+            .unwrap();
+            ical.properties[0].clone()
+        };
+        let parse = |line: &str| DateTimeValue::parse(&property(line));
+        let date = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        assert_eq!(
+            parse("DTSTART;VALUE=DATE:20240331"),
+            Some(DateTimeValue::Date(date))
+        );
+        assert_eq!(
+            parse("DTSTART:20240331T100000"),
+            Some(DateTimeValue::Floating(date.and_hms_opt(10, 0, 0).unwrap()))
+        );
+        let utc = parse("DTSTART:20240331T100000Z").unwrap();
+        assert_eq!(
+            utc.to_utc(Tz::Europe__Berlin).to_rfc3339(),
+            "2024-03-31T10:00:00+00:00"
+        );
+        let zoned = parse("DTSTART;TZID=Europe/Berlin:20240331T100000").unwrap();
+        assert!(matches!(zoned, DateTimeValue::Zoned(_)));
+        assert_eq!(
+            zoned.to_utc(Tz::UTC).to_rfc3339(),
+            "2024-03-31T08:00:00+00:00"
+        );
+        assert_eq!(
+            parse("DTSTART;TZID=Unknown/Zone:20240331T100000"),
+            Some(DateTimeValue::Floating(date.and_hms_opt(10, 0, 0).unwrap()))
+        );
+        assert_eq!(parse("DTSTART:tomorrow"), None);
+
+        let exdates = DateTimeValue::parse_all(&property(
+            "EXDATE;TZID=Europe/Berlin:20240331T100000,20240401T100000",
+        ));
+        assert_eq!(exdates.len(), 2);
+        assert_eq!(exdates[1].value(), "20240401T100000");
+        assert_eq!(
+            exdates[0].to_property("EXDATE"),
+            property("EXDATE;TZID=Europe/Berlin:20240331T100000")
+        );
+        assert!(zoned.same_as(&parse("DTSTART:20240331T080000Z").unwrap()));
+        assert!(!zoned.same_as(&utc));
+    }
+
+    fn expand(event: &str, n: usize) -> Vec<String> {
+        expand_in(event, n, Tz::UTC)
+    }
+
+    fn expand_in(event: &str, n: usize, floating: Tz) -> Vec<String> {
+        let ical = Ical::parse(&mut LineIterator::new(event)).unwrap();
+        occurrences(&ical, floating)
+            .unwrap()
+            .take(n)
+            .map(|d| d.format("%Y%m%dT%H%M%S%z").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("-PT15M"), Some(Duration::minutes(-15)));
+        assert_eq!(
+            parse_duration("P1DT12H"),
+            Some(Duration::days(1) + Duration::hours(12))
+        );
+        assert_eq!(parse_duration("P2W"), Some(Duration::weeks(2)));
+        assert_eq!(parse_duration("1H"), None);
+    }
+
+    #[test]
+    fn test_expand_calendar() {
+        let calendar = Ical::parse(&mut LineIterator::new(
+            "BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:daily
+DTSTART;TZID=Europe/Berlin:20240101T100000
+DTEND;TZID=Europe/Berlin:20240101T110000
+RRULE:FREQ=DAILY;COUNT=5
+EXDATE;TZID=Europe/Berlin:20240103T100000
+END:VEVENT
+BEGIN:VEVENT
+UID:daily
+RECURRENCE-ID;TZID=Europe/Berlin:20240104T100000
+DTSTART;TZID=Europe/Berlin:20240104T150000
+DTEND;TZID=Europe/Berlin:20240104T160000
+END:VEVENT
+END:VCALENDAR",
+        ))
+        .unwrap();
+        let utc = |s: &str| Utc.from_utc_datetime(&parse_naive(s).unwrap().0);
+        let expanded = super::expand(
+            &calendar,
+            utc("20240102T000000Z"),
+            utc("20240110T000000Z"),
+            Tz::UTC,
+        )
+        .unwrap();
+        let value = |c: &Ical, name: &str| c.get_first_property(name).unwrap().value.clone();
+        let instances = expanded
+            .children
+            .iter()
+            .map(|c| {
+                (
+                    value(c, "RECURRENCE-ID"),
+                    value(c, "DTSTART"),
+                    value(c, "DTEND"),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            instances,
+            [
+                (
+                    "20240104T090000Z".into(),
+                    "20240104T140000Z".into(),
+                    "20240104T150000Z".into()
+                ),
+                (
+                    "20240102T090000Z".into(),
+                    "20240102T090000Z".into(),
+                    "20240102T100000Z".into()
+                ),
+                (
+                    "20240105T090000Z".into(),
+                    "20240105T090000Z".into(),
+                    "20240105T100000Z".into()
+                ),
+            ]
+        );
+        assert!(expanded
+            .children
+            .iter()
+            .all(|c| c.get_first_property("RRULE").is_none()));
+        assert!(super::expand(
+            &calendar,
+            utc("20240201T000000Z"),
+            utc("20240301T000000Z"),
+            Tz::UTC
+        )
+        .is_none());
+
+        let occurrences = occurrences_between(
+            &calendar,
+            utc("20240102T000000Z"),
+            utc("20240110T000000Z"),
+            Tz::UTC,
+        );
+        assert_eq!(
+            occurrences
+                .iter()
+                .map(|o| (o.start, o.end, o.recurrence_id))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    utc("20240102T090000Z"),
+                    utc("20240102T100000Z"),
+                    Some(utc("20240102T090000Z"))
+                ),
+                (
+                    utc("20240104T140000Z"),
+                    utc("20240104T150000Z"),
+                    Some(utc("20240104T090000Z"))
+                ),
+                (
+                    utc("20240105T090000Z"),
+                    utc("20240105T100000Z"),
+                    Some(utc("20240105T090000Z"))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_long_running_rule() {
+        let calendar = Ical::parse(&mut LineIterator::new(
+            "BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:hourly
+DTSTART:20200101T000000Z
+DTEND:20200101T003000Z
+RRULE:FREQ=HOURLY
+END:VEVENT
+END:VCALENDAR",
+        ))
+        .unwrap();
+        let utc = |s: &str| Utc.from_utc_datetime(&parse_naive(s).unwrap().0);
+        // Tens of thousands of instances lie before the range.
+        let occurrences = occurrences_between(
+            &calendar,
+            utc("20240301T100000Z"),
+            utc("20240301T130000Z"),
+            Tz::UTC,
+        );
+        assert_eq!(
+            occurrences.iter().map(|o| o.start).collect::<Vec<_>>(),
+            [
+                utc("20240301T100000Z"),
+                utc("20240301T110000Z"),
+                utc("20240301T120000Z")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_all_day_event_lasts_one_day() {
+        let calendar = Ical::parse(&mut LineIterator::new(
+            "BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:all-day
+DTSTART;VALUE=DATE:20240301
+END:VEVENT
+END:VCALENDAR",
+        ))
+        .unwrap();
+        let utc = |s: &str| Utc.from_utc_datetime(&parse_naive(s).unwrap().0);
+        let occurrences = occurrences_between(
+            &calendar,
+            utc("20240301T120000Z"),
+            utc("20240302T120000Z"),
+            Tz::UTC,
+        );
+        assert_eq!(occurrences.len(), 1);
+        assert_eq!(occurrences[0].end, utc("20240302T000000Z"));
+        assert!(occurrences_between(
+            &calendar,
+            utc("20240302T000000Z"),
+            utc("20240303T000000Z"),
+            Tz::UTC
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_rrule_parse() {
+        let rule = RRule::parse("FREQ=MONTHLY;INTERVAL=2;BYDAY=-1SU,+2MO;COUNT=3").unwrap();
+        assert_eq!(rule.freq, Frequency::Monthly);
+        assert_eq!(rule.interval, 2);
+        assert_eq!(rule.count, Some(3));
+        assert_eq!(
+            rule.by_day,
+            vec![(Some(-1), Weekday::Sun), (Some(2), Weekday::Mon)]
+        );
+        assert!(RRule::parse("INTERVAL=2").is_err());
+        assert!(RRule::parse("FREQ=DAILY;INTERVAL=0").is_err());
+    }
+
+    #[test]
+    fn test_expand_weekly_with_exdate_and_rdate() {
+        let event = "BEGIN:VEVENT
+DTSTART;TZID=Europe/Berlin:20240321T100000
+RRULE:FREQ=WEEKLY;BYDAY=TH;COUNT=4
+EXDATE;TZID=Europe/Berlin:20240328T100000
+RDATE;TZID=Europe/Berlin:20240330T120000
+END:VEVENT";
+        assert_eq!(
+            expand(event, 10),
+            vec![
+                "20240321T100000+0100",
+                "20240330T120000+0100",
+                "20240404T100000+0200",
+                "20240411T100000+0200",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_utc_across_dst() {
+        let event = "BEGIN:VEVENT
+DTSTART:20240321T090000Z
+RRULE:FREQ=WEEKLY;COUNT=3
+EXDATE:20240328T090000Z
+END:VEVENT";
+        assert_eq!(
+            expand_in(event, 10, Tz::Europe__Berlin),
+            vec!["20240321T090000+0000", "20240404T090000+0000"]
+        );
+    }
+
+    #[test]
+    fn test_expand_monthly_and_yearly() {
+        let event = "BEGIN:VEVENT
+DTSTART:20240131T090000Z
+RRULE:FREQ=MONTHLY;BYMONTHDAY=-1
+END:VEVENT";
+        assert_eq!(
+            expand(event, 3),
+            vec![
+                "20240131T090000+0000",
+                "20240229T090000+0000",
+                "20240331T090000+0000"
+            ]
+        );
+
+        let event = "BEGIN:VEVENT
+DTSTART;VALUE=DATE:20240229
+RRULE:FREQ=YEARLY;UNTIL=20320101
+END:VEVENT";
+        assert_eq!(
+            expand(event, 5),
+            vec!["20240229T000000+0000", "20280229T000000+0000",]
+        );
+
+        let event = "BEGIN:VEVENT
+DTSTART:20240101T080000
+RRULE:FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1
+END:VEVENT";
+        assert_eq!(
+            expand(event, 3),
+            vec![
+                "20240101T080000+0000",
+                "20240131T080000+0000",
+                "20240229T080000+0000"
+            ]
+        );
+    }
+}
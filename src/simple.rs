@@ -0,0 +1,142 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The shortest way to read and add events, e.g. for quick scripts.
+//!
+//! Every call creates its own `Client` and uses basic authentication.
+//! Use the functions of the crate root for anything else.
+//!
+//! ```rust,no_run
+//! # async fn run() -> Result<(), minicaldav::errors::MiniCaldavError> {
+//! let calendars = minicaldav::simple::list_calendars("https://mycaldav.com/", "foo", "s3cret!").await?;
+//! for calendar in calendars {
+//!     let events = minicaldav::simple::list_events(calendar.url().as_str(), "foo", "s3cret!").await?;
+//!     println!("{}: {} events", calendar.name(), events.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use url::Url;
+
+use crate::api::parse_event_refs;
+use crate::caldav;
+use crate::client::Client;
+use crate::errors::MiniCaldavError;
+use crate::{Calendar, Credentials, Event};
+
+fn credentials(user: &str, pass: &str) -> Credentials {
+    Credentials::Basic(user.into(), pass.into())
+}
+
+/// Parse the url of a collection, adding the trailing slash needed to join resources to it.
+fn collection_url(url: &str) -> Result<Url, MiniCaldavError> {
+    Ok(caldav::collection_url(Url::parse(url)?))
+}
+
+/// Get all calendars of the user, discovering them from the server url.
+pub async fn list_calendars(
+    url: &str,
+    user: &str,
+    pass: &str,
+) -> Result<Vec<Calendar>, MiniCaldavError> {
+    crate::get_calendars(&Client::new(), &credentials(user, pass), Url::parse(url)?).await
+}
+
+/// Get all events and todos of the calendar. Objects that can not be parsed are skipped.
+pub async fn list_events(
+    calendar_url: &str,
+    user: &str,
+    pass: &str,
+) -> Result<Vec<Event>, MiniCaldavError> {
+    let url = collection_url(calendar_url)?;
+    let client = Client::new();
+    let (event_refs, resource_errors) = caldav::get_events(
+        &client,
+        &credentials(user, pass),
+        url.clone(),
+        url,
+        None,
+        None,
+        false,
+    )
+    .await?;
+    let (events, errors) = parse_event_refs(
+        &client,
+        event_refs,
+        resource_errors,
+        MiniCaldavError::CouldNotParseEvent,
+    );
+    for error in errors {
+        warn!("Skipping event: {:?}", error);
+    }
+    Ok(events)
+}
+
+/// Add an event to the calendar and return it.
+/// `start` and `end` are ICAL date-times, e.g. `20240101T100000Z`, or dates, e.g. `20240101`.
+pub async fn add_event(
+    calendar_url: &str,
+    user: &str,
+    pass: &str,
+    summary: &str,
+    start: &str,
+    end: &str,
+) -> Result<Event, MiniCaldavError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let uid = format!("{:x}-{:x}@minicaldav", now.as_secs(), now.subsec_nanos());
+    let url = collection_url(calendar_url)?.join(&format!("{}.ics", uid))?;
+    let value_type = |value: &str| {
+        if value.len() == 8 {
+            vec![("VALUE", "DATE")]
+        } else {
+            vec![]
+        }
+    };
+    let event = Event::builder(url)
+        .uid(uid)
+        .timestamp(crate::caldav::format_utc_seconds(now.as_secs() as i64))
+        .summary(summary.into())
+        .start(start.into(), value_type(start))
+        .end(end.into(), value_type(end))
+        .build();
+    crate::save_event(&Client::new(), &credentials(user, pass), event).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collection_url() {
+        assert_eq!(
+            collection_url("https://example.com/cal/personal")
+                .unwrap()
+                .as_str(),
+            "https://example.com/cal/personal/"
+        );
+        assert_eq!(
+            collection_url("https://example.com/cal/personal/")
+                .unwrap()
+                .as_str(),
+            "https://example.com/cal/personal/"
+        );
+    }
+}
@@ -0,0 +1,517 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Streaming of large calendars.
+//!
+//! `minicaldav::get_events` reads the whole response before parsing it. The streams of this
+//! module yield every event as soon as it is received, so only the current event is held in
+//! memory, even for calendars with tens of thousands of events.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use quick_xml::events::Event as XmlEvent;
+use quick_xml::Reader;
+use reqwest::Response;
+use url::Url;
+
+use crate::api::parse_event_refs;
+use crate::caldav::{self, parse_status_code, EventRef};
+use crate::client::Client;
+use crate::errors::{MiniCaldavError, ResourceError};
+use crate::{Calendar, Credentials, Event};
+
+/// Get the events of the given calendar as a stream, see `minicaldav::get_events`.
+/// Objects that can not be parsed or were reported with an error status are yielded as `Err`,
+/// the stream continues after them. Subscriptions are not supported.
+pub async fn get_events(
+    client: &Client,
+    credentials: &Credentials,
+    calendar: &Calendar,
+    start: Option<String>,
+    end: Option<String>,
+    expanded: bool,
+) -> Result<EventStream, MiniCaldavError> {
+    let inner = get_event_refs(
+        client,
+        credentials,
+        calendar.base_url().clone(),
+        calendar.url().clone(),
+        start,
+        end,
+        expanded,
+    )
+    .await?;
+    Ok(EventStream {
+        client: client.clone(),
+        inner,
+    })
+}
+
+/// Get the objects of the given calendar as a stream, see `minicaldav::caldav::get_events`.
+pub async fn get_event_refs(
+    client: &Client,
+    credentials: &Credentials,
+    base_url: Url,
+    calendar_url: Url,
+    start: Option<String>,
+    end: Option<String>,
+    expanded: bool,
+) -> Result<EventRefStream, MiniCaldavError> {
+    let response =
+        caldav::send_events_report(client, credentials, calendar_url, start, end, expanded).await?;
+    Ok(EventRefStream {
+        client: client.clone(),
+        base_url,
+        buffer: ResponseBuffer::default(),
+        chunk: Some(next_chunk(response)),
+        finished: false,
+    })
+}
+
+/// The parsed events of a calendar-query response.
+pub struct EventStream {
+    client: Client,
+    inner: EventRefStream,
+}
+
+impl Stream for EventStream {
+    type Item = Result<Event, MiniCaldavError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let client = self.client.clone();
+        Pin::new(&mut self.inner).poll_next(cx).map(|item| {
+            item.map(|item| {
+                let (mut events, mut errors) = parse_event_refs(
+                    &client,
+                    vec![item?],
+                    vec![],
+                    MiniCaldavError::CouldNotParseEvent,
+                );
+                match events.pop() {
+                    Some(event) => Ok(event),
+                    None => Err(errors.remove(0)),
+                }
+            })
+        })
+    }
+}
+
+type Chunk = Pin<Box<dyn Future<Output = (Response, reqwest::Result<Option<Vec<u8>>>)> + Send>>;
+
+fn next_chunk(mut response: Response) -> Chunk {
+    Box::pin(async move {
+        let chunk = response.chunk().await.map(|c| c.map(|c| c.to_vec()));
+        (response, chunk)
+    })
+}
+
+/// The `response` elements of a multistatus response, parsed while the response is received.
+/// Responses with an error status are yielded as `MiniCaldavError::Resource`.
+pub struct EventRefStream {
+    client: Client,
+    base_url: Url,
+    buffer: ResponseBuffer,
+    /// The next chunk of the response body, `None` once the body is read completely.
+    chunk: Option<Chunk>,
+    finished: bool,
+}
+
+impl Stream for EventRefStream {
+    type Item = Result<EventRef, MiniCaldavError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if this.finished {
+                return Poll::Ready(None);
+            }
+            match this.buffer.next() {
+                Some(Parsed::Response(response)) => {
+                    return Poll::Ready(Some(
+                        response.into_event_ref(&this.client, &this.base_url),
+                    ));
+                }
+                Some(Parsed::End) => {
+                    this.finished = true;
+                    return Poll::Ready(None);
+                }
+                None => {}
+            }
+            let Some(chunk) = this.chunk.as_mut() else {
+                this.finished = true;
+                return Poll::Ready(Some(Err(MiniCaldavError::CouldNotParseXml(
+                    this.buffer.error(),
+                ))));
+            };
+            match chunk.as_mut().poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready((response, Ok(Some(data)))) => {
+                    this.buffer.push(&data);
+                    this.chunk = Some(next_chunk(response));
+                }
+                Poll::Ready((_, Ok(None))) => this.chunk = None,
+                Poll::Ready((_, Err(e))) => {
+                    this.finished = true;
+                    return Poll::Ready(Some(Err(e.into())));
+                }
+            }
+        }
+    }
+}
+
+/// The received part of a multistatus body.
+#[derive(Debug, Default)]
+struct ResponseBuffer {
+    data: Vec<u8>,
+    /// Start of the data that was not parsed yet.
+    position: usize,
+    /// End of the data that was searched for the end of an element.
+    scanned: usize,
+    in_multistatus: bool,
+    /// The parser error of the last attempt, reported if the body ends. Usually a tag cut off at
+    /// the end of the data, which is parsed again once the `>` closing it is received.
+    error: Option<String>,
+    #[cfg(test)]
+    parses: usize,
+}
+
+/// An element returned by `ResponseBuffer::next`.
+enum Parsed {
+    Response(ResponseData),
+    End,
+}
+
+impl ResponseBuffer {
+    fn push(&mut self, chunk: &[u8]) {
+        self.data.drain(..self.position);
+        self.scanned = self.scanned.saturating_sub(self.position);
+        self.position = 0;
+        self.data.extend_from_slice(chunk);
+    }
+
+    /// Parse the next `response` element or the end of the multistatus element, `None` if more
+    /// data is needed. The data is only parsed again once it may contain the end of an element,
+    /// so a response received in many chunks is parsed once instead of once per chunk.
+    fn next(&mut self) -> Option<Parsed> {
+        let scanned = self.position.max(self.scanned);
+        let closes_tag = self.error.is_some() && self.data[scanned..].contains(&b'>');
+        if !closes_tag && !may_end_element(&self.data, scanned) {
+            self.scanned = self.data.len();
+            return None;
+        }
+        #[cfg(test)]
+        {
+            self.parses += 1;
+        }
+        let next = next_response(&self.data[self.position..], &mut self.in_multistatus);
+        self.scanned = self.data.len();
+        self.error = None;
+        match next {
+            Ok(Next::Response(consumed, response)) => {
+                self.position += consumed;
+                // The rest of the data may contain further responses.
+                self.scanned = self.position;
+                Some(Parsed::Response(response))
+            }
+            Ok(Next::End) => Some(Parsed::End),
+            Ok(Next::NeedMore(consumed)) => {
+                self.position += consumed;
+                None
+            }
+            Err(e) => {
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+
+    fn error(&self) -> String {
+        self.error
+            .clone()
+            .unwrap_or_else(|| "Unexpected end of multistatus response".to_string())
+    }
+}
+
+/// Whether a `response` or `multistatus` name ends in the data after `scanned`. Element names
+/// can not occur elsewhere unescaped, only in text, e.g. of calendar data.
+fn may_end_element(data: &[u8], scanned: usize) -> bool {
+    [&b"response"[..], &b"multistatus"[..]].iter().any(|name| {
+        let start = scanned.saturating_sub(name.len() - 1);
+        data.get(start..)
+            .is_some_and(|data| data.windows(name.len()).any(|w| w == *name))
+    })
+}
+
+enum Next {
+    /// A complete `response` element and the number of bytes up to its end.
+    Response(usize, ResponseData),
+    /// The end of the multistatus element was reached.
+    End,
+    /// More data is needed. The given number of bytes is parsed and does not need to be read again.
+    NeedMore(usize),
+}
+
+#[derive(Debug, Default)]
+struct ResponseData {
+    href: String,
+    status: Option<String>,
+    description: Option<String>,
+    propstats: Vec<PropstatData>,
+}
+
+#[derive(Debug, Default)]
+struct PropstatData {
+    status: Option<String>,
+    props: Vec<(String, String)>,
+}
+
+/// Parse the next `response` element of a multistatus body from the given data.
+/// Only complete elements are returned, the data may end at any position.
+fn next_response(data: &[u8], in_multistatus: &mut bool) -> Result<Next, String> {
+    // On errors nothing is consumed, so the start of the multistatus element is read again.
+    let mut entered = *in_multistatus;
+    let next = parse_next_response(data, &mut entered)?;
+    *in_multistatus = entered;
+    Ok(next)
+}
+
+fn parse_next_response(data: &[u8], in_multistatus: &mut bool) -> Result<Next, String> {
+    let mut reader = Reader::from_reader(data);
+    // The data starts after the elements parsed before, e.g. inside of the multistatus element.
+    reader.config_mut().check_end_names = false;
+    reader.config_mut().allow_unmatched_ends = true;
+    let mut consumed = 0;
+    let mut path: Vec<String> = Vec::new();
+    let mut response: Option<ResponseData> = None;
+    loop {
+        let event = match reader.read_event() {
+            Ok(XmlEvent::Eof) => return Ok(Next::NeedMore(consumed)),
+            Ok(event) => event,
+            Err(e) => return Err(e.to_string()),
+        };
+        match event {
+            XmlEvent::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                if !*in_multistatus {
+                    if name == "multistatus" {
+                        *in_multistatus = true;
+                        consumed = reader.buffer_position() as usize;
+                    }
+                    continue;
+                }
+                match (path.as_slice(), name.as_str()) {
+                    ([], "response") => response = Some(ResponseData::default()),
+                    ([r], "propstat") if r == "response" => {
+                        if let Some(response) = response.as_mut() {
+                            response.propstats.push(PropstatData::default());
+                        }
+                    }
+                    _ => {}
+                }
+                path.push(name);
+            }
+            XmlEvent::End(_) => {
+                if path.pop().is_none() && *in_multistatus {
+                    return Ok(Next::End);
+                }
+                if path.is_empty() {
+                    consumed = reader.buffer_position() as usize;
+                    if let Some(response) = response.take() {
+                        return Ok(Next::Response(consumed, response));
+                    }
+                }
+            }
+            XmlEvent::Text(text) => {
+                if let Some(response) = response.as_mut() {
+                    let text = text.unescape().map_err(|e| e.to_string())?;
+                    response.add_text(&path, &text);
+                }
+            }
+            XmlEvent::CData(text) => {
+                if let Some(response) = response.as_mut() {
+                    response.add_text(&path, &String::from_utf8_lossy(&text));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl ResponseData {
+    /// Add text found at the given path of element names, starting with `response`.
+    fn add_text(&mut self, path: &[String], text: &str) {
+        let path = path.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+        let target = match path.as_slice() {
+            ["response", "href"] => &mut self.href,
+            ["response", "status"] => self.status.get_or_insert_with(String::new),
+            ["response", "responsedescription"] => self.description.get_or_insert_with(String::new),
+            ["response", "propstat", "status"] => match self.propstats.last_mut() {
+                Some(propstat) => propstat.status.get_or_insert_with(String::new),
+                None => return,
+            },
+            ["response", "propstat", "prop", name] => match self.propstats.last_mut() {
+                Some(propstat) => {
+                    if propstat.props.last().map(|(n, _)| n.as_str()) != Some(*name) {
+                        propstat.props.push((name.to_string(), String::new()));
+                    }
+                    &mut propstat.props.last_mut().unwrap().1
+                }
+                None => return,
+            },
+            _ => return,
+        };
+        target.push_str(text);
+    }
+
+    /// Same as `caldav::get_events`: responses without etag and calendar data are errors.
+    fn into_event_ref(self, client: &Client, base_url: &Url) -> Result<EventRef, MiniCaldavError> {
+        let url = client.resolve_href(base_url, &self.href)?;
+        let description = self.description.map(|d| d.trim().to_string());
+        let is_success = |status: &Option<String>| {
+            status
+                .as_deref()
+                .and_then(parse_status_code)
+                .map(|s| (200..300).contains(&s))
+        };
+        if is_success(&self.status) == Some(false) {
+            return Err(MiniCaldavError::Resource(ResourceError {
+                url,
+                status: self.status.as_deref().and_then(parse_status_code),
+                description,
+            }));
+        }
+        let prop = |name: &str| {
+            self.propstats
+                .iter()
+                .filter(|p| is_success(&p.status).unwrap_or(true))
+                .flat_map(|p| p.props.iter())
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| value.clone())
+        };
+        match (prop("getetag"), prop("calendar-data")) {
+            (Some(etag), Some(data)) => Ok(EventRef {
+                url,
+                data,
+                etag: Some(etag),
+                schedule_tag: prop("schedule-tag"),
+            }),
+            _ => Err(MiniCaldavError::Resource(ResourceError {
+                url,
+                status: self
+                    .propstats
+                    .iter()
+                    .filter_map(|p| p.status.as_deref().and_then(parse_status_code))
+                    .find(|s| !(200..300).contains(s)),
+                description,
+            })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESPONSE: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/cal/1.ics</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:getetag>"1"</d:getetag>
+        <cal:calendar-data>BEGIN:VCALENDAR&#13;
+SUMMARY:Fish &amp; Chips&#13;
+END:VCALENDAR</cal:calendar-data>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/cal/2.ics</d:href>
+    <d:status>HTTP/1.1 404 Not Found</d:status>
+  </d:response>
+</d:multistatus>"#;
+
+    /// Parse all responses, feeding the data in chunks of the given size.
+    /// Returns the responses and the number of parser runs.
+    fn parse(
+        client: &Client,
+        body: &str,
+        chunk_size: usize,
+    ) -> (Vec<Result<EventRef, MiniCaldavError>>, usize) {
+        let base_url = Url::parse("https://example.com/").unwrap();
+        let mut buffer = ResponseBuffer::default();
+        let mut items = Vec::new();
+        for chunk in body.as_bytes().chunks(chunk_size) {
+            buffer.push(chunk);
+            loop {
+                match buffer.next() {
+                    Some(Parsed::Response(response)) => {
+                        items.push(response.into_event_ref(client, &base_url))
+                    }
+                    Some(Parsed::End) => return (items, buffer.parses),
+                    None => break,
+                }
+            }
+        }
+        panic!("multistatus is not complete: {}", buffer.error());
+    }
+
+    #[test]
+    fn test_streaming_multistatus() {
+        for chunk_size in [1, 7, 64, RESPONSE.len()] {
+            let (items, parses) = parse(&Client::new(), RESPONSE, chunk_size);
+            // At most twice for each of the 6 element names, not once per chunk.
+            assert!(
+                parses <= 12,
+                "{} parses with chunks of {}",
+                parses,
+                chunk_size
+            );
+            assert_eq!(items.len(), 2);
+            let event = items[0].as_ref().unwrap();
+            assert_eq!(event.url.as_str(), "https://example.com/cal/1.ics");
+            assert_eq!(event.etag.as_deref(), Some("\"1\""));
+            assert_eq!(
+                event.data,
+                "BEGIN:VCALENDAR\r\nSUMMARY:Fish & Chips\r\nEND:VCALENDAR"
+            );
+            assert!(matches!(
+                &items[1],
+                Err(MiniCaldavError::Resource(e)) if e.status == Some(404)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_streaming_resolves_hrefs() {
+        let body = RESPONSE.replace("/cal/1.ics", "https://other.example.org/cal/1.ics");
+        let url = |client: &Client| {
+            let (items, _) = parse(client, &body, body.len());
+            items[0].as_ref().unwrap().url.to_string()
+        };
+        assert_eq!(url(&Client::new()), "https://other.example.org/cal/1.ics");
+        let client = Client::builder().force_same_origin(true).build().unwrap();
+        assert_eq!(url(&client), "https://example.com/cal/1.ics");
+
+        let body = RESPONSE.replace("/cal/1.ics", "mailto:foo@example.com");
+        let (items, _) = parse(&Client::new(), &body, body.len());
+        assert!(matches!(items[0], Err(MiniCaldavError::CouldNotJoinUrl(_))));
+    }
+}
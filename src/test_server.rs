@@ -0,0 +1,237 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! HTTP server for unit tests, answering requests with prepared responses.
+
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use url::Url;
+
+/// How long the server waits for the next request before it stops.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A response of the test server.
+#[derive(Debug, Clone)]
+pub struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn body(mut self, body: &str) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// A 207 Multi-Status response with the given XML body.
+    pub fn multistatus(body: &str) -> Self {
+        Self::new(207)
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(body)
+    }
+}
+
+/// A request received by the test server.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    /// The path and query of the request.
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A server on localhost answering one request with each response, in order.
+/// The server stops after the last response, or when no request arrives for a while.
+pub struct TestServer {
+    url: Url,
+    server: Arc<tiny_http::Server>,
+    thread: Option<JoinHandle<Vec<Request>>>,
+}
+
+impl TestServer {
+    pub fn start(responses: Vec<Response>) -> Self {
+        let mut test_server = Self::unresponsive();
+        let server = test_server.server.clone();
+        test_server.thread = Some(std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let Ok(Some(mut request)) = server.recv_timeout(REQUEST_TIMEOUT) else {
+                    break;
+                };
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                requests.push(Request {
+                    method: request.method().to_string(),
+                    url: request.url().to_string(),
+                    headers: request
+                        .headers()
+                        .iter()
+                        .map(|h| (h.field.to_string(), h.value.to_string()))
+                        .collect(),
+                    body,
+                });
+                let mut answer = tiny_http::Response::from_string(response.body)
+                    .with_status_code(response.status);
+                for (name, value) in response.headers {
+                    answer.add_header(
+                        tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap(),
+                    );
+                }
+                // The client may have given up already.
+                let _ = request.respond(answer);
+            }
+            requests
+        }));
+        test_server
+    }
+
+    /// A server accepting connections but never answering.
+    pub fn unresponsive() -> Self {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", server.server_addr())).unwrap();
+        Self {
+            url,
+            server: Arc::new(server),
+            thread: None,
+        }
+    }
+
+    /// The url of the server root.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The url of the given path on the server.
+    pub fn join(&self, path: &str) -> Url {
+        self.url.join(path).unwrap()
+    }
+
+    /// A calendar with the given path on the server.
+    pub fn calendar(&self, path: &str) -> crate::Calendar {
+        crate::Calendar::from_parts(
+            self.url.clone(),
+            crate::caldav::CalendarRef {
+                url: self.join(path),
+                name: "Test".into(),
+                color: None,
+                privileges: Vec::new(),
+                components: vec!["VEVENT".into()],
+                is_subscription: false,
+                source_url: None,
+                enabled: None,
+                order: None,
+            },
+        )
+    }
+
+    /// Wait for the server to stop and return the requests it received.
+    pub fn requests(mut self) -> Vec<Request> {
+        self.thread
+            .take()
+            .map(|thread| thread.join().unwrap())
+            .unwrap_or_default()
+    }
+}
+
+/// The name and the recorded fields of a span.
+#[cfg(feature = "tracing")]
+type RecordedSpan = (&'static str, Vec<(String, String)>);
+
+/// A tracing subscriber recording the fields of all spans, to check the instrumentation.
+#[cfg(feature = "tracing")]
+#[derive(Clone, Default)]
+pub struct SpanRecorder {
+    spans: Arc<std::sync::Mutex<Vec<RecordedSpan>>>,
+}
+
+#[cfg(feature = "tracing")]
+impl SpanRecorder {
+    /// The fields recorded for each span with the given name, in order of creation.
+    pub fn fields(&self, name: &str) -> Vec<Vec<(String, String)>> {
+        self.spans
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(n, _)| *n == name)
+            .map(|(_, fields)| fields.clone())
+            .collect()
+    }
+}
+
+#[cfg(feature = "tracing")]
+struct FieldVisitor<'a>(&'a mut Vec<(String, String)>);
+
+#[cfg(feature = "tracing")]
+impl tracing::field::Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        self.0.push((field.name().into(), format!("{:?}", value)));
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl tracing::Subscriber for SpanRecorder {
+    fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        let mut fields = Vec::new();
+        span.record(&mut FieldVisitor(&mut fields));
+        let mut spans = self.spans.lock().unwrap();
+        spans.push((span.metadata().name(), fields));
+        tracing::span::Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, span: &tracing::span::Id, values: &tracing::span::Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        let index = span.into_u64() as usize - 1;
+        values.record(&mut FieldVisitor(&mut spans[index].1));
+    }
+
+    fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+    fn event(&self, _: &tracing::Event<'_>) {}
+
+    fn enter(&self, _: &tracing::span::Id) {}
+
+    fn exit(&self, _: &tracing::span::Id) {}
+}
@@ -0,0 +1,131 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Hooks that change the iCalendar data of events when they are fetched or saved.
+//!
+//! Transformations are registered on the client with `ClientBuilder::transform` and applied by
+//! all functions of the client fetching or saving events, e.g. `get_events` and `save_event`.
+//!
+//! ```rust,no_run
+//! use minicaldav::ical::Ical;
+//! use minicaldav::transform::{IcsTransform, StripProperties};
+//!
+//! /// Only keep busy times, e.g. for a calendar shared with colleagues.
+//! struct BusyOnly;
+//!
+//! impl IcsTransform for BusyOnly {
+//!     fn on_save(&self, _url: &url::Url, ical: &mut Ical) {
+//!         for component in ical.children.iter_mut().filter(|c| c.name == "VEVENT") {
+//!             component.properties.retain(|p| p.name != "DESCRIPTION" && p.name != "LOCATION");
+//!         }
+//!     }
+//! }
+//!
+//! let client = minicaldav::Client::builder()
+//!     .transform(StripProperties::new(&["X-MYAPP-NOTES"]))
+//!     .transform(BusyOnly)
+//!     .build()
+//!     .unwrap();
+//! ```
+
+use url::Url;
+
+use crate::ical::Ical;
+
+/// A change of the iCalendar data of events, see the module documentation.
+/// Transformations are applied in the order they were registered.
+pub trait IcsTransform: Send + Sync {
+    /// Change an event fetched from the server before it is returned.
+    fn on_fetch(&self, _url: &Url, _ical: &mut Ical) {}
+
+    /// Change the data uploaded for an event.
+    /// The event returned by the save function keeps its data as it was before the change.
+    fn on_save(&self, _url: &Url, _ical: &mut Ical) {}
+}
+
+impl std::fmt::Debug for dyn IcsTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "IcsTransform")
+    }
+}
+
+/// Remove the given properties from all components before upload,
+/// e.g. `X-` properties that are private to the app.
+#[derive(Debug, Clone)]
+pub struct StripProperties {
+    names: Vec<String>,
+}
+
+impl StripProperties {
+    pub fn new(names: &[&str]) -> Self {
+        Self {
+            names: names.iter().map(|n| n.to_string()).collect(),
+        }
+    }
+}
+
+impl IcsTransform for StripProperties {
+    fn on_save(&self, _url: &Url, ical: &mut Ical) {
+        fn strip(ical: &mut Ical, names: &[String]) {
+            ical.properties.retain(|p| !names.contains(&p.name));
+            for child in &mut ical.children {
+                strip(child, names);
+            }
+        }
+        strip(ical, &self.names);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+
+    struct Tag;
+
+    impl IcsTransform for Tag {
+        fn on_fetch(&self, _url: &Url, ical: &mut Ical) {
+            ical.properties
+                .push(crate::ical::Property::new("X-FETCHED", "1"));
+        }
+    }
+
+    #[test]
+    fn test_transforms() {
+        let client = Client::builder()
+            .transform(StripProperties::new(&["X-PRIVATE"]))
+            .transform(Tag)
+            .build()
+            .unwrap();
+        let url = Url::parse("https://example.com/cal/a.ics").unwrap();
+        let ics =
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nX-PRIVATE:secret\nEND:VEVENT\nEND:VCALENDAR\n";
+        let mut ical = Ical::parse(&mut crate::ical::LineIterator::new(ics)).unwrap();
+
+        assert_eq!(
+            client.ics_for_upload(&url, &ical),
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nEND:VEVENT\nEND:VCALENDAR\n"
+        );
+        assert_eq!(Client::new().ics_for_upload(&url, &ical), ics);
+
+        client.transform_fetched(&url, &mut ical);
+        assert_eq!(
+            ical.get_first_property("X-FETCHED")
+                .map(|p| p.value.as_str()),
+            Some("1")
+        );
+    }
+}
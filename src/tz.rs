@@ -0,0 +1,398 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Time zone support based on the tz database embedded by chrono-tz.
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc,
+};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+
+use crate::ical::{Ical, Property};
+
+/// Generate a VTIMEZONE component for the given IANA time zone (e.g. `Europe/Berlin`).
+/// The observances describe the rules of the current year, repeated yearly since 1970.
+/// Windows time zone names (e.g. `W. Europe Standard Time`) are resolved to the corresponding IANA zone,
+/// the TZID of the component stays the given name.
+/// Returns `None` if the time zone is unknown.
+pub fn vtimezone_for(tzid: &str) -> Option<Ical> {
+    vtimezone_for_year(tzid, Utc::now().year())
+}
+
+/// Generate a VTIMEZONE component for the given IANA time zone using the rules of the given year.
+pub fn vtimezone_for_year(tzid: &str, year: i32) -> Option<Ical> {
+    let tzid = tzid.trim_matches('"');
+    let tz: Tz = crate::windows_tz::resolve_tzid(tzid).parse().ok()?;
+
+    let mut vtimezone = Ical::new("VTIMEZONE".into());
+    vtimezone.add_property(Property::new("TZID", tzid));
+
+    let transitions = transitions_in_year(&tz, year);
+    if transitions.is_empty() {
+        let offset = tz.offset_from_utc_datetime(&start_of_year(year));
+        let mut standard = Ical::new("STANDARD".into());
+        let utc_offset = format_offset(offset.fix().local_minus_utc());
+        standard.add_property(Property::new("TZOFFSETFROM", &utc_offset));
+        standard.add_property(Property::new("TZOFFSETTO", &utc_offset));
+        if let Some(name) = offset.abbreviation() {
+            standard.add_property(Property::new("TZNAME", name));
+        }
+        standard.add_property(Property::new("DTSTART", "19700101T000000"));
+        vtimezone.add_component(standard);
+        return Some(vtimezone);
+    }
+
+    for transition in transitions {
+        let before = tz.offset_from_utc_datetime(&(transition - Duration::minutes(1)));
+        let after = tz.offset_from_utc_datetime(&transition);
+        let from = before.fix().local_minus_utc();
+        let to = after.fix().local_minus_utc();
+
+        // The observance starts at the local wall clock time before the transition.
+        let local = transition + Duration::seconds(from as i64);
+        let by_day = by_day_rule(local.date());
+        let first = first_onset(local, &by_day);
+
+        let name = if after.dst_offset().num_seconds() != 0 {
+            "DAYLIGHT"
+        } else {
+            "STANDARD"
+        };
+        let mut observance = Ical::new(name.into());
+        observance.add_property(Property::new("TZOFFSETFROM", &format_offset(from)));
+        observance.add_property(Property::new("TZOFFSETTO", &format_offset(to)));
+        if let Some(name) = after.abbreviation() {
+            observance.add_property(Property::new("TZNAME", name));
+        }
+        observance.add_property(Property::new(
+            "DTSTART",
+            &first.format("%Y%m%dT%H%M%S").to_string(),
+        ));
+        observance.add_property(Property::new(
+            "RRULE",
+            &format!(
+                "FREQ=YEARLY;BYMONTH={};BYDAY={}{}",
+                local.month(),
+                by_day.0,
+                weekday_code(local.weekday())
+            ),
+        ));
+        vtimezone.add_component(observance);
+    }
+
+    Some(vtimezone)
+}
+
+/// Resolve the given TZID of a property in `calendar` to a time zone.
+/// IANA and Windows time zone names are resolved directly. Other TZIDs are looked up in the
+/// VTIMEZONE components of the calendar, using their `X-LIC-LOCATION` or an IANA name at the end
+/// of the TZID (e.g. `/mozilla.org/20050126_1/Europe/Berlin`).
+pub fn resolve_timezone(tzid: &str, calendar: &Ical) -> Option<Tz> {
+    if let Ok(tz) = crate::windows_tz::resolve_tzid(tzid).parse() {
+        return Some(tz);
+    }
+    let vtimezone = find_vtimezone(tzid, calendar)?;
+    if let Some(tz) = vtimezone
+        .get_first_property("X-LIC-LOCATION")
+        .and_then(|p| p.value.trim().parse().ok())
+    {
+        return Some(tz);
+    }
+    let tzid = tzid.trim_matches('"');
+    tzid.match_indices('/')
+        .find_map(|(i, _)| tzid[i + 1..].parse().ok())
+}
+
+/// The UTC offset of the given local time according to the observances of a VTIMEZONE component.
+/// Returns `None` if the component has no valid observance starting before `local`.
+pub fn vtimezone_offset(vtimezone: &Ical, local: NaiveDateTime) -> Option<FixedOffset> {
+    vtimezone
+        .children
+        .iter()
+        .filter(|c| c.name == "STANDARD" || c.name == "DAYLIGHT")
+        .filter_map(|observance| {
+            let offset = observance
+                .get_first_property("TZOFFSETTO")
+                .and_then(|p| parse_offset(&p.value))?;
+            // Onsets are local times, expanded without time zone.
+            let onset = crate::recurrence::occurrences(observance, Tz::UTC)?
+                .map(|onset| onset.naive_local())
+                .take_while(|onset| *onset <= local)
+                .last()?;
+            Some((onset, offset))
+        })
+        .max_by_key(|(onset, _)| *onset)
+        .map(|(_, offset)| offset)
+}
+
+/// Convert the first value of the given DATE or DATE-TIME property of `calendar` to UTC.
+/// The TZID is resolved with `resolve_timezone`, unknown time zones are evaluated with the
+/// embedded VTIMEZONE definition. Floating times and dates are interpreted in `floating`.
+pub fn to_utc(prop: &Property, calendar: &Ical, floating: Tz) -> Option<DateTime<Utc>> {
+    let value = prop.value.split(',').next().unwrap_or_default();
+    let (naive, utc) = crate::recurrence::parse_naive(value.split('/').next().unwrap_or(value))?;
+    if utc {
+        return Some(Utc.from_utc_datetime(&naive));
+    }
+    let Some(tzid) = prop.attributes.get("TZID") else {
+        return Some(crate::recurrence::resolve_local(&floating, naive).with_timezone(&Utc));
+    };
+    if let Some(tz) = resolve_timezone(tzid, calendar) {
+        return Some(crate::recurrence::resolve_local(&tz, naive).with_timezone(&Utc));
+    }
+    match find_vtimezone(tzid, calendar).and_then(|v| vtimezone_offset(v, naive)) {
+        Some(offset) => Some(
+            Utc.from_utc_datetime(&(naive - Duration::seconds(offset.local_minus_utc() as i64))),
+        ),
+        None => Some(crate::recurrence::resolve_local(&floating, naive).with_timezone(&Utc)),
+    }
+}
+
+/// Convert the first value of the given DATE or DATE-TIME property of `calendar` to the time zone `tz`,
+/// see `to_utc`.
+pub fn to_local(prop: &Property, calendar: &Ical, floating: Tz, tz: Tz) -> Option<DateTime<Tz>> {
+    to_utc(prop, calendar, floating).map(|t| t.with_timezone(&tz))
+}
+
+/// The VTIMEZONE component of `calendar` with the given TZID.
+fn find_vtimezone<'a>(tzid: &str, calendar: &'a Ical) -> Option<&'a Ical> {
+    let tzid = tzid.trim_matches('"');
+    calendar.children.iter().find(|c| {
+        c.name == "VTIMEZONE"
+            && c.get_first_property("TZID")
+                .map(|p| p.value.trim_matches('"') == tzid)
+                .unwrap_or(false)
+    })
+}
+
+/// Parse an UTC offset like `+0100`, `-0330` or `+013045`.
+fn parse_offset(value: &str) -> Option<FixedOffset> {
+    let value = value.trim();
+    let (sign, digits) = match value.as_bytes().first()? {
+        b'+' => (1, &value[1..]),
+        b'-' => (-1, &value[1..]),
+        _ => return None,
+    };
+    if !(digits.len() == 4 || digits.len() == 6) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let part = |i: usize| {
+        digits
+            .get(i..i + 2)
+            .and_then(|d| d.parse::<i32>().ok())
+            .unwrap_or(0)
+    };
+    FixedOffset::east_opt(sign * (part(0) * 3600 + part(2) * 60 + part(4)))
+}
+
+fn start_of_year(year: i32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(year, 1, 1)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .unwrap_or_default()
+}
+
+/// Find the UTC instants at which the UTC offset changes in the given year.
+fn transitions_in_year(tz: &Tz, year: i32) -> Vec<NaiveDateTime> {
+    let offset_at = |t: &NaiveDateTime| tz.offset_from_utc_datetime(t).fix().local_minus_utc();
+    let start = start_of_year(year);
+    let mut transitions = Vec::new();
+    let mut day = start;
+    while day.year() == year {
+        let next = day + Duration::days(1);
+        if offset_at(&day) != offset_at(&next) {
+            let (mut low, mut high) = (day, next);
+            while high - low > Duration::seconds(1) {
+                let mid = low + (high - low) / 2;
+                if offset_at(&mid) == offset_at(&low) {
+                    low = mid;
+                } else {
+                    high = mid;
+                }
+            }
+            transitions.push(high);
+        }
+        day = next;
+    }
+    transitions
+}
+
+/// The BYDAY ordinal of the given date within its month: 1 to 4, or -1 for the last one.
+fn by_day_rule(date: NaiveDate) -> (i32, chrono::Weekday) {
+    let days_in_month = NaiveDate::from_ymd_opt(date.year(), date.month(), 1)
+        .and_then(|d| d.checked_add_months(chrono::Months::new(1)))
+        .map(|d| (d - Duration::days(1)).day())
+        .unwrap_or(28);
+    let ordinal = if date.day() + 7 > days_in_month {
+        -1
+    } else {
+        ((date.day() - 1) / 7 + 1) as i32
+    };
+    (ordinal, date.weekday())
+}
+
+/// The date of the given BYDAY rule in 1970 at the time of `local`.
+fn first_onset(local: NaiveDateTime, by_day: &(i32, chrono::Weekday)) -> NaiveDateTime {
+    let (ordinal, weekday) = *by_day;
+    let month = local.month();
+    let date = if ordinal > 0 {
+        NaiveDate::from_weekday_of_month_opt(1970, month, weekday, ordinal as u8)
+    } else {
+        (1..=5)
+            .rev()
+            .find_map(|n| NaiveDate::from_weekday_of_month_opt(1970, month, weekday, n))
+    };
+    date.map(|d| d.and_time(local.time())).unwrap_or(local)
+}
+
+fn weekday_code(weekday: chrono::Weekday) -> &'static str {
+    match weekday {
+        chrono::Weekday::Mon => "MO",
+        chrono::Weekday::Tue => "TU",
+        chrono::Weekday::Wed => "WE",
+        chrono::Weekday::Thu => "TH",
+        chrono::Weekday::Fri => "FR",
+        chrono::Weekday::Sat => "SA",
+        chrono::Weekday::Sun => "SU",
+    }
+}
+
+/// Format an UTC offset in seconds as `+HHMM`.
+fn format_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    format!("{}{:02}{:02}", sign, seconds / 3600, (seconds % 3600) / 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vtimezone_europe_berlin() {
+        let tz = vtimezone_for_year("Europe/Berlin", 2024).unwrap();
+        assert_eq!(
+            tz.serialize(),
+            "BEGIN:VTIMEZONE
+TZID:Europe/Berlin
+BEGIN:DAYLIGHT
+TZOFFSETFROM:+0100
+TZOFFSETTO:+0200
+TZNAME:CEST
+DTSTART:19700329T020000
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU
+END:DAYLIGHT
+BEGIN:STANDARD
+TZOFFSETFROM:+0200
+TZOFFSETTO:+0100
+TZNAME:CET
+DTSTART:19701025T030000
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU
+END:STANDARD
+END:VTIMEZONE
+"
+        );
+    }
+
+    #[test]
+    fn test_vtimezone_new_york_and_tokyo() {
+        let tz = vtimezone_for_year("America/New_York", 2024).unwrap();
+        assert_eq!(
+            tz.children[0].get_first_property("RRULE").unwrap().value,
+            "FREQ=YEARLY;BYMONTH=3;BYDAY=2SU"
+        );
+        assert_eq!(
+            tz.children[1].get_first_property("RRULE").unwrap().value,
+            "FREQ=YEARLY;BYMONTH=11;BYDAY=1SU"
+        );
+
+        let tz = vtimezone_for_year("Asia/Tokyo", 2024).unwrap();
+        assert_eq!(tz.children.len(), 1);
+        assert_eq!(tz.children[0].name, "STANDARD");
+        assert_eq!(
+            tz.children[0]
+                .get_first_property("TZOFFSETTO")
+                .unwrap()
+                .value,
+            "+0900"
+        );
+
+        let tz = vtimezone_for_year("W. Europe Standard Time", 2024).unwrap();
+        assert_eq!(
+            tz.get_first_property("TZID").unwrap().value,
+            "W. Europe Standard Time"
+        );
+        assert_eq!(tz.children.len(), 2);
+
+        assert!(vtimezone_for("Not/AZone").is_none());
+    }
+
+    #[test]
+    fn test_embedded_vtimezone() {
+        let data = "BEGIN:VCALENDAR
+BEGIN:VTIMEZONE
+TZID:/mozilla.org/20050126_1/Europe/Berlin
+END:VTIMEZONE
+BEGIN:VTIMEZONE
+TZID:Custom Time Zone
+BEGIN:STANDARD
+TZOFFSETFROM:-0400
+TZOFFSETTO:-0500
+DTSTART:19701101T020000
+RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU
+END:STANDARD
+BEGIN:DAYLIGHT
+TZOFFSETFROM:-0500
+TZOFFSETTO:-0400
+DTSTART:19700308T020000
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU
+END:DAYLIGHT
+END:VTIMEZONE
+END:VCALENDAR
+";
+        let mut lines = crate::ical::LineIterator::new(data);
+        let calendar = Ical::parse(&mut lines).unwrap();
+        assert_eq!(
+            resolve_timezone("(UTC-08:00) Pacific Time (US & Canada)", &calendar),
+            Some(Tz::America__Los_Angeles)
+        );
+        assert_eq!(
+            resolve_timezone("/mozilla.org/20050126_1/Europe/Berlin", &calendar),
+            Some(Tz::Europe__Berlin)
+        );
+        assert_eq!(resolve_timezone("Custom Time Zone", &calendar), None);
+
+        let mut prop = Property::new("DTSTART", "20240701T100000");
+        prop.attributes
+            .insert("TZID".into(), "\"Custom Time Zone\"".into());
+        assert_eq!(
+            to_utc(&prop, &calendar, Tz::UTC).unwrap().to_rfc3339(),
+            "2024-07-01T14:00:00+00:00"
+        );
+        prop.value = "20240115T100000".into();
+        assert_eq!(
+            to_utc(&prop, &calendar, Tz::UTC).unwrap().to_rfc3339(),
+            "2024-01-15T15:00:00+00:00"
+        );
+        prop.attributes
+            .insert("TZID".into(), "W. Europe Standard Time".into());
+        assert_eq!(
+            to_local(&prop, &calendar, Tz::UTC, Tz::Asia__Tokyo)
+                .unwrap()
+                .to_rfc3339(),
+            "2024-01-15T18:00:00+09:00"
+        );
+    }
+}
@@ -0,0 +1,694 @@
+// minicaldav: Small and easy CalDAV client.
+// Copyright (C) 2022 Florian Loers
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Watch calendars for changes using sync-collection (RFC 6578).
+//!
+//! ```rust,no_run
+//! # async fn watch(calendars: Vec<minicaldav::Calendar>) {
+//! let client = minicaldav::Client::new();
+//! let credentials = minicaldav::Credentials::Basic("foo".into(), "s3cret!".into());
+//! let mut watcher = minicaldav::watch::Watcher::new();
+//! for calendar in calendars {
+//!     watcher.add_calendar(calendar, None);
+//! }
+//! loop {
+//!     for event in watcher.poll(&client, &credentials).await {
+//!         println!("{}: {:?}", event.calendar, event.kind);
+//!     }
+//!     // wait some time
+//! }
+//! # }
+//! ```
+//!
+//! A sync can be interrupted with a [`CancellationToken`]. The progress is kept in the watcher and
+//! can be saved with [`Watcher::checkpoint`], so the next run continues with the objects that were
+//! not fetched yet instead of starting over.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::client::Client;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::api::parse_event_refs;
+use crate::caldav::{self, MultigetItem, Progress, MAX_SYNC_ROUNDS};
+use crate::errors::MiniCaldavError;
+use crate::{Calendar, Credentials, Event};
+
+/// Number of objects fetched with one calendar-multiget.
+const MULTIGET_CHUNK_SIZE: usize = 100;
+
+/// Number of calendar-multiget requests running at the same time by default.
+const DEFAULT_PARALLELISM: usize = 4;
+
+/// A change in one of the watched calendars.
+#[derive(Debug)]
+pub struct WatchEvent {
+    /// The url of the calendar this event belongs to.
+    pub calendar: Url,
+    pub kind: WatchEventKind,
+}
+
+#[derive(Debug)]
+pub enum WatchEventKind {
+    /// The event was created or modified.
+    Updated(Event),
+    /// The event was modified and is cancelled now but still exists on the server,
+    /// see [`Event::cancellation`] for the reason.
+    Cancelled(Event),
+    /// The object with the given url was deleted.
+    Deleted(Url),
+    /// Syncing the calendar failed. The other calendars are not affected,
+    /// the failed changes are reported again with the next poll.
+    Error(MiniCaldavError),
+}
+
+/// Signals a running poll to stop after the current request.
+/// Clones share the same state, so the token can be cancelled from another task or thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// The changes of a sync-collection response that were not completely processed yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct PendingSync {
+    /// The token to use once all changes are processed.
+    sync_token: Option<String>,
+    /// Changed objects that were not fetched yet.
+    changed: Vec<Url>,
+    deleted: Vec<Url>,
+    truncated: bool,
+}
+
+/// The sync state of one calendar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CalendarCheckpoint {
+    pub calendar: Url,
+    pub sync_token: Option<String>,
+    pending: Option<PendingSync>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    known: HashSet<Url>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    listed: Option<HashSet<Url>>,
+}
+
+impl CalendarCheckpoint {
+    /// Whether the last sync of this calendar was interrupted.
+    pub fn is_interrupted(&self) -> bool {
+        self.pending.is_some()
+    }
+}
+
+/// The sync state of all watched calendars, including the progress of interrupted syncs.
+/// Store it (e.g. with the `serde` feature) and pass it to [`Watcher::restore`] to resume later.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Checkpoint {
+    pub calendars: Vec<CalendarCheckpoint>,
+}
+
+#[derive(Debug)]
+struct WatchedCalendar {
+    calendar: Calendar,
+    sync_token: Option<String>,
+    pending: Option<PendingSync>,
+    /// The objects reported so far, to find the deleted ones when the calendar is listed completely.
+    known: HashSet<Url>,
+    /// The objects listed so far while syncing without sync token, e.g. after it became invalid.
+    listed: Option<HashSet<Url>>,
+}
+
+/// Watches a set of calendars and merges their changes into one stream of `WatchEvent`s.
+/// Every calendar has its own sync token.
+#[derive(Debug)]
+pub struct Watcher {
+    calendars: Vec<WatchedCalendar>,
+    parallelism: usize,
+}
+
+impl Default for Watcher {
+    fn default() -> Self {
+        Self {
+            calendars: Vec::new(),
+            parallelism: DEFAULT_PARALLELISM,
+        }
+    }
+}
+
+impl Watcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fetch changed events with up to `parallelism` concurrent requests, 4 by default.
+    pub fn parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism.max(1);
+        self
+    }
+
+    /// Watch the given calendar. With a `sync_token` of an earlier run, only later changes are reported.
+    /// Without, the first poll reports all events of the calendar.
+    /// If the server does not accept the token anymore, the calendar is listed again and the objects
+    /// this watcher reported before (see `restore`) but missing in the list are reported as deleted.
+    pub fn add_calendar(&mut self, calendar: Calendar, sync_token: Option<String>) {
+        self.remove_calendar(&calendar.url().clone());
+        self.calendars.push(WatchedCalendar {
+            calendar,
+            sync_token,
+            pending: None,
+            known: HashSet::new(),
+            listed: None,
+        });
+    }
+
+    /// Stop watching the calendar with the given url.
+    pub fn remove_calendar(&mut self, calendar_url: &Url) {
+        self.calendars.retain(|c| c.calendar.url() != calendar_url);
+    }
+
+    pub fn calendars(&self) -> impl Iterator<Item = &Calendar> {
+        self.calendars.iter().map(|c| &c.calendar)
+    }
+
+    /// The current sync token of the given calendar. Store it to resume watching later.
+    pub fn sync_token(&self, calendar_url: &Url) -> Option<&String> {
+        self.calendars
+            .iter()
+            .find(|c| c.calendar.url() == calendar_url)
+            .and_then(|c| c.sync_token.as_ref())
+    }
+
+    /// The sync state of all watched calendars.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            calendars: self
+                .calendars
+                .iter()
+                .map(|c| CalendarCheckpoint {
+                    calendar: c.calendar.url().clone(),
+                    sync_token: c.sync_token.clone(),
+                    pending: c.pending.clone(),
+                    known: c.known.clone(),
+                    listed: c.listed.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Restore the sync state of the watched calendars from a checkpoint.
+    /// Calendars that are not watched are ignored, add them with `add_calendar` first.
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        for saved in checkpoint.calendars {
+            if let Some(watched) = self
+                .calendars
+                .iter_mut()
+                .find(|c| c.calendar.url() == &saved.calendar)
+            {
+                watched.sync_token = saved.sync_token;
+                watched.pending = saved.pending;
+                watched.known = saved.known;
+                watched.listed = saved.listed;
+            }
+        }
+    }
+
+    /// Get the changes of all watched calendars since the last poll.
+    pub async fn poll(&mut self, client: &Client, credentials: &Credentials) -> Vec<WatchEvent> {
+        self.poll_until_cancelled(client, credentials, &CancellationToken::new())
+            .await
+    }
+
+    /// Like `poll`, but stops after the current request once `cancel` is cancelled.
+    /// The changes reported so far are returned, the rest is fetched by the next poll.
+    pub async fn poll_until_cancelled(
+        &mut self,
+        client: &Client,
+        credentials: &Credentials,
+        cancel: &CancellationToken,
+    ) -> Vec<WatchEvent> {
+        self.poll_with_progress(client, credentials, cancel, |_, _| {})
+            .await
+    }
+
+    /// Like `poll_until_cancelled`, but calls `progress` with the url of the calendar
+    /// whenever a request for changed events is complete, e.g. to show the progress of an initial sync.
+    pub async fn poll_with_progress<F: FnMut(&Url, Progress)>(
+        &mut self,
+        client: &Client,
+        credentials: &Credentials,
+        cancel: &CancellationToken,
+        mut progress: F,
+    ) -> Vec<WatchEvent> {
+        let mut events = Vec::new();
+        for watched in &mut self.calendars {
+            if cancel.is_cancelled() {
+                break;
+            }
+            let calendar = watched.calendar.url().clone();
+            let mut push = |kind| {
+                events.push(WatchEvent {
+                    calendar: calendar.clone(),
+                    kind,
+                })
+            };
+            if let Err(e) = sync_calendar(
+                client,
+                credentials,
+                watched,
+                self.parallelism,
+                cancel,
+                &mut push,
+                &mut |p| progress(&calendar, p),
+            )
+            .await
+            {
+                push(WatchEventKind::Error(e));
+            }
+        }
+        events
+    }
+}
+
+/// Report the changes of the given calendar and update its sync token.
+/// The token is only updated once all changes were fetched, the progress until then is kept in `pending`.
+/// Without sync token the server lists all objects, the known objects missing in the list are reported
+/// as deleted, e.g. those deleted while the token was invalid.
+/// Stops after `MAX_SYNC_ROUNDS` parts of a truncated sync, the next poll continues with the token of the last part.
+async fn sync_calendar<F: FnMut(WatchEventKind), P: FnMut(Progress)>(
+    client: &Client,
+    credentials: &Credentials,
+    watched: &mut WatchedCalendar,
+    parallelism: usize,
+    cancel: &CancellationToken,
+    push: &mut F,
+    progress: &mut P,
+) -> Result<(), MiniCaldavError> {
+    let base_url = watched.calendar.base_url().clone();
+    let calendar_url = watched.calendar.url().clone();
+    let mut done = Progress::default();
+    let mut rounds = 0;
+    loop {
+        if watched.pending.is_none() {
+            if cancel.is_cancelled() || rounds == MAX_SYNC_ROUNDS {
+                return Ok(());
+            }
+            rounds += 1;
+            let full = watched.sync_token.is_none() || watched.listed.is_some();
+            let result = match caldav::sync_collection(
+                client,
+                credentials,
+                &base_url,
+                &calendar_url,
+                watched.sync_token.as_deref(),
+            )
+            .await
+            {
+                Err(MiniCaldavError::InvalidSyncToken(_)) if watched.sync_token.is_some() => {
+                    watched.sync_token = None;
+                    continue;
+                }
+                result => result?,
+            };
+            let changed: Vec<Url> = result.changed.into_iter().map(|c| c.url).collect();
+            if full {
+                watched
+                    .listed
+                    .get_or_insert_with(HashSet::new)
+                    .extend(changed.iter().cloned());
+            }
+            watched.known.extend(changed.iter().cloned());
+            watched.pending = Some(PendingSync {
+                sync_token: result.sync_token,
+                changed,
+                deleted: result.deleted,
+                truncated: result.truncated,
+            });
+        }
+        let Some(pending) = watched.pending.as_mut() else {
+            continue;
+        };
+
+        while !pending.changed.is_empty() {
+            if cancel.is_cancelled() {
+                return Ok(());
+            }
+            let count = pending.changed.len().min(MULTIGET_CHUNK_SIZE * parallelism);
+            let total = done.fetched + pending.changed.len();
+            let mut last = Progress::default();
+            let items = caldav::calendar_multiget_parallel_with_progress(
+                client,
+                credentials,
+                &base_url,
+                &calendar_url,
+                &pending.changed[..count],
+                MULTIGET_CHUNK_SIZE,
+                parallelism,
+                |batch| {
+                    last = batch;
+                    progress(Progress {
+                        fetched: done.fetched + batch.fetched,
+                        total: Some(total),
+                        bytes: done.bytes + batch.bytes,
+                    })
+                },
+            )
+            .await?;
+            done.fetched += last.fetched;
+            done.bytes += last.bytes;
+            pending.changed.drain(..count);
+            let mut event_refs = Vec::new();
+            let mut resource_errors = Vec::new();
+            for item in items {
+                match item {
+                    MultigetItem::Found(event_ref) => event_refs.push(event_ref),
+                    MultigetItem::Gone(e) => {
+                        watched.known.remove(&e.url);
+                        push(WatchEventKind::Deleted(e.url))
+                    }
+                    MultigetItem::Error(error) => resource_errors.push(error),
+                }
+            }
+            let (events, errors) = parse_event_refs(
+                client,
+                event_refs,
+                resource_errors,
+                MiniCaldavError::CouldNotParseEvent,
+            );
+            for event in events {
+                if event.is_cancelled() {
+                    push(WatchEventKind::Cancelled(event));
+                } else {
+                    push(WatchEventKind::Updated(event));
+                }
+            }
+            for error in errors {
+                push(WatchEventKind::Error(error));
+            }
+        }
+        for url in pending.deleted.drain(..) {
+            watched.known.remove(&url);
+            if let Some(listed) = watched.listed.as_mut() {
+                listed.remove(&url);
+            }
+            push(WatchEventKind::Deleted(url));
+        }
+
+        let truncated = pending.truncated;
+        if !truncated {
+            if let Some(listed) = watched.listed.take() {
+                let mut deleted: Vec<Url> = watched.known.difference(&listed).cloned().collect();
+                deleted.sort();
+                for url in deleted {
+                    watched.known.remove(&url);
+                    push(WatchEventKind::Deleted(url));
+                }
+            }
+        }
+        watched.sync_token = pending.sync_token.take();
+        watched.pending = None;
+        if !truncated {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Response, TestServer};
+
+    fn object(name: &str, data: &str) -> String {
+        format!(
+            r#"<d:response><d:href>/cal/{}</d:href><d:propstat>
+                <d:prop><d:getetag>"1"</d:getetag><c:calendar-data>{}</c:calendar-data></d:prop>
+                <d:status>HTTP/1.1 200 OK</d:status>
+            </d:propstat></d:response>"#,
+            name, data
+        )
+    }
+
+    fn changed(name: &str) -> String {
+        format!(
+            r#"<d:response><d:href>/cal/{}</d:href><d:propstat>
+                <d:prop><d:getetag>"1"</d:getetag></d:prop><d:status>HTTP/1.1 200 OK</d:status>
+            </d:propstat></d:response>"#,
+            name
+        )
+    }
+
+    fn deleted(name: &str) -> String {
+        format!(
+            r#"<d:response><d:href>/cal/{}</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>"#,
+            name
+        )
+    }
+
+    fn multistatus(responses: &[String], sync_token: Option<&str>) -> Response {
+        Response::multistatus(&format!(
+            r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">{}{}</d:multistatus>"#,
+            responses.concat(),
+            sync_token
+                .map(|t| format!("<d:sync-token>{}</d:sync-token>", t))
+                .unwrap_or_default()
+        ))
+    }
+
+    fn ics(uid: &str, status: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:{}\nDTSTART:20240101T100000Z\n{}END:VEVENT\nEND:VCALENDAR\n",
+            uid, status
+        )
+    }
+
+    fn client() -> Client {
+        Client::builder()
+            .retry_policy(crate::client::RetryPolicy::none())
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_events() {
+        let server = TestServer::start(vec![
+            multistatus(
+                &[changed("a.ics"), changed("b.ics"), deleted("c.ics")],
+                Some("token-1"),
+            ),
+            multistatus(
+                &[
+                    object("a.ics", &ics("a", "")),
+                    object("b.ics", &ics("b", "STATUS:CANCELLED\n")),
+                ],
+                None,
+            ),
+        ]);
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let mut watcher = Watcher::new();
+        watcher.add_calendar(server.calendar("/cal/"), None);
+        let events = watcher.poll(&client(), &credentials).await;
+        let kinds = events.iter().map(|e| &e.kind).collect::<Vec<_>>();
+        assert!(
+            matches!(
+                &kinds[..],
+                [
+                    WatchEventKind::Updated(a),
+                    WatchEventKind::Cancelled(b),
+                    WatchEventKind::Deleted(c),
+                ] if a.url().path() == "/cal/a.ics"
+                    && b.url().path() == "/cal/b.ics"
+                    && c.path() == "/cal/c.ics"
+            ),
+            "{:?}",
+            kinds
+        );
+        assert_eq!(
+            watcher.sync_token(&server.join("/cal/")),
+            Some(&"token-1".to_string())
+        );
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_and_resume() {
+        // One more object than fetched per round with parallelism 1.
+        let names = (0..=MULTIGET_CHUNK_SIZE)
+            .map(|i| format!("e{}.ics", i))
+            .collect::<Vec<_>>();
+        let mut sync = names.iter().map(|n| changed(n)).collect::<Vec<_>>();
+        sync.push(deleted("gone.ics"));
+        let objects = |names: &[String]| {
+            names
+                .iter()
+                .map(|n| object(n, &ics(n, "")))
+                .collect::<Vec<_>>()
+        };
+        let server = TestServer::start(vec![
+            multistatus(&sync, Some("token-1")),
+            multistatus(&objects(&names[..MULTIGET_CHUNK_SIZE]), None),
+            multistatus(&objects(&names[MULTIGET_CHUNK_SIZE..]), None),
+        ]);
+        let client = client();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let calendar_url = server.join("/cal/");
+        let watcher = || {
+            let mut watcher = Watcher::new().parallelism(1);
+            watcher.add_calendar(server.calendar("/cal/"), None);
+            watcher
+        };
+
+        // Cancelled before the poll: nothing is requested.
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let mut first = watcher();
+        assert!(first
+            .poll_until_cancelled(&client, &credentials, &cancel)
+            .await
+            .is_empty());
+        assert!(!first.checkpoint().calendars[0].is_interrupted());
+
+        // Cancelled while the first objects are fetched: the sync stops after the request.
+        let cancel = CancellationToken::new();
+        let events = first
+            .poll_with_progress(&client, &credentials, &cancel, |_, _| cancel.cancel())
+            .await;
+        assert_eq!(events.len(), MULTIGET_CHUNK_SIZE);
+        assert!(events
+            .iter()
+            .all(|e| matches!(e.kind, WatchEventKind::Updated(_))));
+        let checkpoint = first.checkpoint();
+        assert!(checkpoint.calendars[0].is_interrupted());
+        assert_eq!(checkpoint.calendars[0].sync_token, None);
+        assert_eq!(first.sync_token(&calendar_url), None);
+
+        // A new watcher resumes from the checkpoint without syncing the collection again.
+        let mut second = watcher();
+        second.restore(checkpoint);
+        let events = second.poll(&client, &credentials).await;
+        assert!(
+            matches!(
+                &events.iter().map(|e| &e.kind).collect::<Vec<_>>()[..],
+                [WatchEventKind::Updated(last), WatchEventKind::Deleted(gone)]
+                    if last.url().path() == format!("/cal/{}", names[MULTIGET_CHUNK_SIZE]) && gone.path() == "/cal/gone.ics"
+            ),
+            "{:?}",
+            events
+        );
+        assert_eq!(
+            second.sync_token(&calendar_url),
+            Some(&"token-1".to_string())
+        );
+        assert!(!second.checkpoint().calendars[0].is_interrupted());
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].body.contains("sync-collection"));
+        assert!(requests[2].body.contains(&names[MULTIGET_CHUNK_SIZE]));
+        assert!(!requests[2].body.contains("/cal/e0.ics"));
+    }
+
+    #[tokio::test]
+    async fn test_always_truncated() {
+        let truncated = |i: usize| {
+            multistatus(
+                &[r#"<d:response><d:href>/cal/</d:href><d:status>HTTP/1.1 507 Insufficient Storage</d:status></d:response>"#.to_string()],
+                Some(&format!("token-{}", i)),
+            )
+        };
+        let server = TestServer::start((1..=MAX_SYNC_ROUNDS).map(truncated).collect());
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let mut watcher = Watcher::new();
+        watcher.add_calendar(server.calendar("/cal/"), None);
+        let events = watcher.poll(&client(), &credentials).await;
+        assert!(events.is_empty(), "{:?}", events);
+        // The next poll continues with the token of the last part.
+        assert_eq!(
+            watcher.sync_token(&server.join("/cal/")),
+            Some(&format!("token-{}", MAX_SYNC_ROUNDS))
+        );
+        assert_eq!(server.requests().len(), MAX_SYNC_ROUNDS);
+    }
+
+    #[tokio::test]
+    async fn test_deletions_after_invalid_sync_token() {
+        let names = ["a.ics", "b.ics", "c.ics"];
+        let server = TestServer::start(vec![
+            multistatus(&names.map(changed), Some("token-1")),
+            multistatus(&names.map(|n| object(n, &ics(n, ""))), None),
+            Response::new(403)
+                .header("Content-Type", "application/xml; charset=utf-8")
+                .body(r#"<d:error xmlns:d="DAV:"><d:valid-sync-token/></d:error>"#),
+            // b.ics and c.ics were deleted while the token was invalid.
+            multistatus(&[changed("a.ics")], Some("token-2")),
+            multistatus(&[object("a.ics", &ics("a", ""))], None),
+        ]);
+        let client = client();
+        let credentials = Credentials::Basic("foo".into(), "bar".into());
+        let mut watcher = Watcher::new();
+        watcher.add_calendar(server.calendar("/cal/"), None);
+        assert_eq!(watcher.poll(&client, &credentials).await.len(), 3);
+
+        let events = watcher.poll(&client, &credentials).await;
+        let kinds = events.iter().map(|e| &e.kind).collect::<Vec<_>>();
+        assert!(
+            matches!(
+                &kinds[..],
+                [
+                    WatchEventKind::Updated(a),
+                    WatchEventKind::Deleted(b),
+                    WatchEventKind::Deleted(c),
+                ] if a.url().path() == "/cal/a.ics"
+                    && b.path() == "/cal/b.ics"
+                    && c.path() == "/cal/c.ics"
+            ),
+            "{:?}",
+            kinds
+        );
+        assert_eq!(
+            watcher.sync_token(&server.join("/cal/")),
+            Some(&"token-2".to_string())
+        );
+        let checkpoint = watcher.checkpoint();
+        assert_eq!(
+            checkpoint.calendars[0].known,
+            HashSet::from([server.join("/cal/a.ics")])
+        );
+        assert_eq!(checkpoint.calendars[0].listed, None);
+
+        let requests = server.requests();
+        assert!(requests[2]
+            .body
+            .contains("<d:sync-token>token-1</d:sync-token>"));
+        assert!(!requests[3].body.contains("token-1"));
+    }
+}
@@ -1,22 +1,5 @@
-pub fn build_create_calendar_xml(name: String, color: String) -> String {
-    format!(
-        r#"
-    <x0:mkcol xmlns:x0="DAV:">
-	<x0:set>
-		<x0:prop>
-			<x0:resourcetype>
-				<x0:collection/>
-				<x1:calendar xmlns:x1="urn:ietf:params:xml:ns:caldav"/>
-				</x0:resourcetype>
-				<x0:displayname>{name}</x0:displayname>
-				<x6:calendar-color
-					xmlns:x6="http://apple.com/ns/ical/">{color}
-				</x6:calendar-color>
-				<x4:calendar-enabled
-					xmlns:x4="http://owncloud.org/ns">1
-				</x4:calendar-enabled>
-				<x1:calendar-timezone
-					xmlns:x1="urn:ietf:params:xml:ns:caldav">BEGIN:VCALENDAR
+/// The calendar time zone used when creating calendars without an explicit time zone.
+pub const DEFAULT_CALENDAR_TIMEZONE: &str = "BEGIN:VCALENDAR
 PRODID:-//IDN nextcloud.com//Calendar app 5.2.2//EN
 CALSCALE:GREGORIAN
 VERSION:2.0
@@ -38,6 +21,28 @@ RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU
 END:STANDARD
 END:VTIMEZONE
 END:VCALENDAR
+";
+
+pub fn build_create_calendar_xml(name: String, color: String, timezone: &str) -> String {
+    let timezone = escape_xml(timezone.trim_end());
+    format!(
+        r#"
+    <x0:mkcol xmlns:x0="DAV:">
+	<x0:set>
+		<x0:prop>
+			<x0:resourcetype>
+				<x0:collection/>
+				<x1:calendar xmlns:x1="urn:ietf:params:xml:ns:caldav"/>
+				</x0:resourcetype>
+				<x0:displayname>{name}</x0:displayname>
+				<x6:calendar-color
+					xmlns:x6="http://apple.com/ns/ical/">{color}
+				</x6:calendar-color>
+				<x4:calendar-enabled
+					xmlns:x4="http://owncloud.org/ns">1
+				</x4:calendar-enabled>
+				<x1:calendar-timezone
+					xmlns:x1="urn:ietf:params:xml:ns:caldav">{timezone}
 				</x1:calendar-timezone>
 				<x1:supported-calendar-component-set
 					xmlns:x1="urn:ietf:params:xml:ns:caldav">
@@ -49,3 +54,219 @@ END:VCALENDAR
         "#
     )
 }
+
+/// Escape the given text for use in XML text content or attribute values.
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+pub fn build_share_xml(
+    sharee: &str,
+    common_name: Option<&str>,
+    summary: Option<&str>,
+    read_write: bool,
+) -> String {
+    let common_name = common_name
+        .map(|cn| format!("<CS:common-name>{}</CS:common-name>", escape_xml(cn)))
+        .unwrap_or_default();
+    let summary = summary
+        .map(|s| format!("<CS:summary>{}</CS:summary>", escape_xml(s)))
+        .unwrap_or_default();
+    let access = if read_write {
+        "<CS:read-write />"
+    } else {
+        "<CS:read />"
+    };
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<CS:share xmlns:D="DAV:" xmlns:CS="http://calendarserver.org/ns/">
+    <CS:set>
+        <D:href>{}</D:href>
+        {}
+        {}
+        {}
+    </CS:set>
+</CS:share>
+"#,
+        escape_xml(sharee),
+        common_name,
+        summary,
+        access
+    )
+}
+
+pub fn build_unshare_xml(sharee: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<CS:share xmlns:D="DAV:" xmlns:CS="http://calendarserver.org/ns/">
+    <CS:remove>
+        <D:href>{}</D:href>
+    </CS:remove>
+</CS:share>
+"#,
+        escape_xml(sharee)
+    )
+}
+
+pub fn build_invite_reply_xml(
+    sharee: &str,
+    host_url: &str,
+    in_reply_to: &str,
+    accept: bool,
+) -> String {
+    let reply = if accept {
+        "<CS:invite-accepted />"
+    } else {
+        "<CS:invite-declined />"
+    };
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<CS:invite-reply xmlns:D="DAV:" xmlns:CS="http://calendarserver.org/ns/">
+    <D:href>{}</D:href>
+    {}
+    <CS:hosturl>
+        <D:href>{}</D:href>
+    </CS:hosturl>
+    <CS:in-reply-to>{}</CS:in-reply-to>
+</CS:invite-reply>
+"#,
+        escape_xml(sharee),
+        reply,
+        escape_xml(host_url),
+        escape_xml(in_reply_to)
+    )
+}
+
+pub fn build_calendar_availability_xml(data: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<D:propertyupdate xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+    <D:set>
+        <D:prop>
+            <C:calendar-availability>{}</C:calendar-availability>
+        </D:prop>
+    </D:set>
+</D:propertyupdate>
+"#,
+        escape_xml(data)
+    )
+}
+
+/// Set the given calendar properties, the properties passed as `None` are not changed.
+pub fn build_calendar_defaults_xml(
+    alarm_offset: Option<&str>,
+    schedule_transparency: Option<&str>,
+) -> String {
+    let mut set = String::new();
+    if let Some(trigger) = alarm_offset {
+        set.push_str(&format!(
+            "<C:default-alarm-vevent-datetime>BEGIN:VALARM\r\nACTION:DISPLAY\r\nTRIGGER:{}\r\nDESCRIPTION:Reminder\r\nEND:VALARM\r\n</C:default-alarm-vevent-datetime>",
+            escape_xml(trigger)
+        ));
+    }
+    match schedule_transparency {
+        Some(transp) if transp.eq_ignore_ascii_case("TRANSPARENT") => set
+            .push_str("<C:schedule-calendar-transp><C:transparent /></C:schedule-calendar-transp>"),
+        Some(_) => {
+            set.push_str("<C:schedule-calendar-transp><C:opaque /></C:schedule-calendar-transp>")
+        }
+        None => {}
+    }
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<D:propertyupdate xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+    <D:set><D:prop>{}</D:prop></D:set>
+</D:propertyupdate>
+"#,
+        set
+    )
+}
+
+pub fn build_calendar_enabled_xml(enabled: bool) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<D:propertyupdate xmlns:D="DAV:" xmlns:O="http://owncloud.org/ns">
+    <D:set>
+        <D:prop>
+            <O:calendar-enabled>{}</O:calendar-enabled>
+        </D:prop>
+    </D:set>
+</D:propertyupdate>
+"#,
+        if enabled { "1" } else { "0" }
+    )
+}
+
+pub fn build_calendar_color_xml(color: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<D:propertyupdate xmlns:D="DAV:" xmlns:A="http://apple.com/ns/ical/">
+    <D:set>
+        <D:prop>
+            <A:calendar-color>{}</A:calendar-color>
+        </D:prop>
+    </D:set>
+</D:propertyupdate>
+"#,
+        escape_xml(color)
+    )
+}
+
+/// Build a PROPPATCH body for the given `(namespace, name, value)` properties.
+/// Properties without value are removed.
+pub fn build_object_properties_xml(properties: &[(&str, &str, Option<&str>)]) -> String {
+    let prop = |i: usize, namespace: &str, name: &str, value: Option<&str>| match value {
+        Some(value) => format!(
+            "<X{i}:{name} xmlns:X{i}=\"{}\">{}</X{i}:{name}>",
+            escape_xml(namespace),
+            escape_xml(value)
+        ),
+        None => format!("<X{i}:{name} xmlns:X{i}=\"{}\"/>", escape_xml(namespace)),
+    };
+    let mut body = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\" ?>\n<D:propertyupdate xmlns:D=\"DAV:\">\n",
+    );
+    for (i, (namespace, name, value)) in properties.iter().enumerate() {
+        let action = if value.is_some() { "set" } else { "remove" };
+        body.push_str(&format!(
+            "    <D:{action}><D:prop>{}</D:prop></D:{action}>\n",
+            prop(i, namespace, name, *value)
+        ));
+    }
+    body.push_str("</D:propertyupdate>\n");
+    body
+}
+
+/// Build a PROPFIND body requesting the given `(namespace, name)` properties.
+pub fn build_object_properties_request_xml(properties: &[(&str, &str)]) -> String {
+    let props = properties
+        .iter()
+        .enumerate()
+        .map(|(i, (namespace, name))| {
+            format!("<X{i}:{name} xmlns:X{i}=\"{}\"/>", escape_xml(namespace))
+        })
+        .collect::<String>();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\" ?>\n<D:propfind xmlns:D=\"DAV:\">\n    <D:prop>{}</D:prop>\n</D:propfind>\n",
+        props
+    )
+}
+
+pub fn build_calendar_order_xml(order: i32) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<D:propertyupdate xmlns:D="DAV:" xmlns:A="http://apple.com/ns/ical/">
+    <D:set>
+        <D:prop>
+            <A:calendar-order>{}</A:calendar-order>
+        </D:prop>
+    </D:set>
+</D:propertyupdate>
+"#,
+        order
+    )
+}
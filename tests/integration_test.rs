@@ -0,0 +1,303 @@
+//! Integration tests against real CalDAV servers.
+//!
+//! The tests are skipped unless `MINICALDAV_IT` is set:
+//! - `MINICALDAV_IT=radicale` starts a Radicale container with docker and tests against it.
+//! - `MINICALDAV_IT=url` tests against a running server, e.g. a configured Baïkal, given by
+//!   `MINICALDAV_IT_URL`, `MINICALDAV_IT_USER` and `MINICALDAV_IT_PASSWORD`.
+//!
+//! Run with `make it`. Every test works in its own calendar with a unique id, which is removed
+//! afterwards.
+
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use minicaldav::errors::MiniCaldavError;
+use minicaldav::watch::{WatchEventKind, Watcher};
+use minicaldav::{Calendar, Client, Credentials, Event, Todo, TodoFilter};
+use url::Url;
+
+const RADICALE_IMAGE: &str = "tomsquest/docker-radicale:3.2.3.0";
+const RADICALE_CONFIG: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/radicale/config");
+
+struct Server {
+    url: Url,
+    credentials: Credentials,
+    container: Option<String>,
+}
+
+impl Server {
+    /// The server configured by the environment or `None` if the integration tests are disabled.
+    async fn start(name: &str) -> Option<Self> {
+        match std::env::var("MINICALDAV_IT").ok()?.as_str() {
+            "radicale" => Some(Self::radicale(name).await),
+            "url" => Some(Self {
+                url: Url::parse(&env("MINICALDAV_IT_URL")).unwrap(),
+                credentials: Credentials::Basic(
+                    env("MINICALDAV_IT_USER"),
+                    env("MINICALDAV_IT_PASSWORD"),
+                ),
+                container: None,
+            }),
+            other => panic!("Unknown MINICALDAV_IT value {}", other),
+        }
+    }
+
+    async fn radicale(name: &str) -> Self {
+        let container = format!("minicaldav-it-{}-{}", name, std::process::id());
+        let output = Command::new("docker")
+            .args(["run", "--rm", "--detach", "--name", &container])
+            .args(["--publish", "127.0.0.1::5232"])
+            .args([
+                "--volume",
+                &format!("{}:/config/config:ro", RADICALE_CONFIG),
+            ])
+            .arg(RADICALE_IMAGE)
+            .output()
+            .expect("Could not run docker");
+        assert!(
+            output.status.success(),
+            "Could not start Radicale: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let output = Command::new("docker")
+            .args(["port", &container, "5232"])
+            .output()
+            .expect("Could not run docker");
+        let address = String::from_utf8_lossy(&output.stdout);
+        let address = address
+            .lines()
+            .next()
+            .expect("Radicale port is not published");
+        let server = Self {
+            url: Url::parse(&format!("http://{}/", address.trim())).unwrap(),
+            credentials: Credentials::Basic("minicaldav".into(), "minicaldav".into()),
+            container: Some(container),
+        };
+        server.wait_until_ready().await;
+        server
+    }
+
+    async fn wait_until_ready(&self) {
+        let client = Client::new();
+        for _ in 0..50 {
+            if minicaldav::check_connection(&client, &self.credentials, &self.url)
+                .await
+                .is_ok()
+            {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        panic!("Server at {} did not get ready", self.url);
+    }
+
+    /// Create a new calendar with an id starting with `name`, unique across test runs, so runs
+    /// against a shared server do not see each other's calendars or leftovers of failed runs.
+    async fn calendar(&self, client: &Client, name: &str) -> (Calendar, String) {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let calid = format!("it-{}-{}-{}", name, std::process::id(), nanos);
+        minicaldav::create_calendar(
+            client,
+            &self.credentials,
+            &self.url,
+            calid.clone(),
+            calid.clone(),
+            "#ff0000".into(),
+        )
+        .await
+        .expect("Could not create calendar");
+        let calendar = minicaldav::get_calendars(client, &self.credentials, self.url.clone())
+            .await
+            .expect("Could not get calendars")
+            .into_iter()
+            .find(|c| c.url().path().trim_end_matches('/').ends_with(&calid))
+            .expect("Created calendar is not listed");
+        (calendar, calid)
+    }
+
+    async fn remove_calendar(&self, client: &Client, calid: String) {
+        minicaldav::remove_calendar(client, &self.credentials, &self.url, calid)
+            .await
+            .expect("Could not remove calendar");
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        if let Some(container) = &self.container {
+            let _ = Command::new("docker").args(["stop", container]).output();
+        }
+    }
+}
+
+fn env(name: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| panic!("{} is not set", name))
+}
+
+fn event(calendar: &Calendar, uid: &str, summary: &str) -> Event {
+    Event::builder(calendar.url().join(&format!("{}.ics", uid)).unwrap())
+        .uid(uid.into())
+        .timestamp("20240101T090000Z".into())
+        .summary(summary.into())
+        .start("20240101T100000Z".into(), vec![])
+        .end("20240101T110000Z".into(), vec![])
+        .build()
+}
+
+fn summary(event: &Event) -> Option<String> {
+    event.get("SUMMARY").cloned()
+}
+
+#[tokio::test]
+async fn test_event_lifecycle() {
+    let Some(server) = Server::start("lifecycle").await else {
+        return;
+    };
+    let client = Client::new();
+    let credentials = &server.credentials;
+    let (calendar, calid) = server.calendar(&client, "lifecycle").await;
+
+    // Create
+    let created = minicaldav::create_event(
+        &client,
+        credentials,
+        &calendar,
+        event(&calendar, "lifecycle-1", "Created"),
+    )
+    .await
+    .unwrap();
+    assert!(created.etag().is_some());
+    let error = minicaldav::create_event(
+        &client,
+        credentials,
+        &calendar,
+        event(&calendar, "lifecycle-1", "Duplicate"),
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        matches!(error, MiniCaldavError::AlreadyExists(_)),
+        "{:?}",
+        error
+    );
+    let saved = minicaldav::save_event(
+        &client,
+        credentials,
+        event(&calendar, "lifecycle-2", "Saved"),
+    )
+    .await
+    .unwrap();
+    assert!(saved.etag().is_some());
+
+    // Read
+    let (events, errors) =
+        minicaldav::get_events(&client, credentials, &calendar, None, None, false)
+            .await
+            .unwrap();
+    assert!(errors.is_empty(), "{:?}", errors);
+    let mut summaries: Vec<_> = events.iter().filter_map(summary).collect();
+    summaries.sort();
+    assert_eq!(summaries, ["Created", "Saved"]);
+    let (events, errors) =
+        minicaldav::get_events_by_url(&client, credentials, &calendar, &[created.url().clone()], 2)
+            .await
+            .unwrap();
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].etag(), created.etag());
+
+    // Update
+    let mut updated = events[0].clone();
+    updated.set("SUMMARY", "Updated");
+    let updated = minicaldav::save_event(&client, credentials, updated)
+        .await
+        .unwrap();
+    assert_ne!(updated.etag(), created.etag());
+    let (events, _) =
+        minicaldav::get_events_by_url(&client, credentials, &calendar, &[created.url().clone()], 2)
+            .await
+            .unwrap();
+    assert_eq!(summary(&events[0]).as_deref(), Some("Updated"));
+
+    // Delete
+    minicaldav::remove_event(&client, credentials, updated)
+        .await
+        .unwrap();
+    minicaldav::remove_event(&client, credentials, saved)
+        .await
+        .unwrap();
+    let (events, _) = minicaldav::get_events(&client, credentials, &calendar, None, None, false)
+        .await
+        .unwrap();
+    assert!(events.is_empty());
+
+    server.remove_calendar(&client, calid).await;
+}
+
+#[tokio::test]
+async fn test_todo_lifecycle() {
+    let Some(server) = Server::start("todo").await else {
+        return;
+    };
+    let client = Client::new();
+    let credentials = &server.credentials;
+    let (calendar, calid) = server.calendar(&client, "todo").await;
+
+    let url = calendar.url().join("todo-1.ics").unwrap();
+    let ics = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//minicaldav//it//EN\r\nBEGIN:VTODO\r\nUID:todo-1\r\nDTSTAMP:20240101T090000Z\r\nSUMMARY:Write tests\r\nEND:VTODO\r\nEND:VCALENDAR\r\n";
+    let todo = Todo::from(Event::from_raw(url, None, ics).unwrap());
+    let todo = minicaldav::save_event(&client, credentials, todo.into_event())
+        .await
+        .map(Todo::from)
+        .unwrap();
+    let open = |filter| minicaldav::get_todos(&client, credentials, &calendar, filter);
+    assert_eq!(open(TodoFilter::Open).await.unwrap().0.len(), 1);
+
+    minicaldav::complete_todo(&client, credentials, todo)
+        .await
+        .unwrap();
+    assert!(open(TodoFilter::Open).await.unwrap().0.is_empty());
+    let (todos, _) = open(TodoFilter::Completed).await.unwrap();
+    assert_eq!(todos.len(), 1);
+    assert!(todos[0].is_completed());
+
+    server.remove_calendar(&client, calid).await;
+}
+
+#[tokio::test]
+async fn test_watch_changes() {
+    let Some(server) = Server::start("watch").await else {
+        return;
+    };
+    let client = Client::new();
+    let credentials = &server.credentials;
+    let (calendar, calid) = server.calendar(&client, "watch").await;
+
+    let mut watcher = Watcher::new().parallelism(2);
+    watcher.add_calendar(calendar.clone(), None);
+    assert!(watcher.poll(&client, credentials).await.is_empty());
+
+    let saved =
+        minicaldav::save_event(&client, credentials, event(&calendar, "watch-1", "Watched"))
+            .await
+            .unwrap();
+    let changes = watcher.poll(&client, credentials).await;
+    assert!(matches!(
+        changes.as_slice(),
+        [change] if matches!(&change.kind, WatchEventKind::Updated(e) if e.url() == saved.url())
+    ));
+
+    minicaldav::remove_event(&client, credentials, saved.clone())
+        .await
+        .unwrap();
+    let changes = watcher.poll(&client, credentials).await;
+    assert!(matches!(
+        changes.as_slice(),
+        [change] if matches!(&change.kind, WatchEventKind::Deleted(url) if url == saved.url())
+    ));
+
+    server.remove_calendar(&client, calid).await;
+}